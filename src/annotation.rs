@@ -0,0 +1,344 @@
+use attribute::attribute_info;
+use attribute::attribute_info_Data::*;
+use attribute::element_value_data::*;
+use attribute::{annotation, element_value};
+use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+use constant_pool::ConstantPool;
+
+#[derive(Debug, Clone, PartialEq)]
+/// An annotation on a class, field, method or parameter, with its elements resolved from the
+/// constant pool
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.16>
+pub struct Annotation<'a> {
+    type_descriptor: &'a str,
+    visible: bool,
+    elements: Vec<(&'a str, AnnotationValue<'a>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The value of an annotation element
+pub enum AnnotationValue<'a> {
+    Byte(i8),
+    /// A UTF-16 code unit, as a Java `char`
+    Char(u16),
+    Double(f64),
+    Float(f32),
+    Int(i32),
+    Long(i64),
+    Short(i16),
+    Boolean(bool),
+    String(&'a str),
+    /// An enum constant, given by the field descriptor of its enum class and its name
+    Enum {
+        type_descriptor: &'a str,
+        const_name: &'a str,
+    },
+    /// A class literal, given by its return descriptor, eg `Ljava/lang/String;`, `I` or `V`
+    Class(&'a str),
+    Annotation(Annotation<'a>),
+    Array(Vec<AnnotationValue<'a>>),
+}
+
+impl<'a> Annotation<'a> {
+    /// The field descriptor of the annotation interface, eg `Lorg/junit/Test;`
+    pub fn get_type(&self) -> &'a str {
+        self.type_descriptor
+    }
+
+    /// Whether the annotation is retained at run time, coming from a RuntimeVisible attribute
+    /// rather than a RuntimeInvisible one
+    ///
+    /// Nested annotations take the retention of the annotation they are a value of
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// The elements given values by the annotation, by name
+    ///
+    /// Elements that are left to their default aren't included, their values are in the
+    /// AnnotationDefault attributes of the annotation interface's methods
+    pub fn get_elements(&self) -> &[(&'a str, AnnotationValue<'a>)] {
+        &self.elements
+    }
+
+    pub fn get_element(&self, name: &str) -> Option<&AnnotationValue<'a>> {
+        self.elements.iter()
+            .find(|(element_name, _)| *element_name == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Resolves the annotations of both retention policies from an attributes table, the visible
+/// ones first
+pub fn read_annotations<'a>(
+    attributes: &[attribute_info],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Vec<Annotation<'a>>, ClassLoadingError> {
+    let mut resolved = Vec::new();
+    for visible in &[true, false] {
+        for info in attributes {
+            let annotations = match info.decode()? {
+                RuntimeVisibleAnnotations_attribute { annotations, .. } if *visible => {
+                    annotations
+                }
+                RuntimeInvisibleAnnotations_attribute { annotations, .. } if !*visible => {
+                    annotations
+                }
+                _ => continue,
+            };
+            for annotation in annotations {
+                resolved.push(
+                    resolve_annotation(annotation, *visible, constant_pool)
+                        .map_err(|error| error.at_offset(info.get_offset()))?,
+                );
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// Resolves the annotations of each formal parameter of a method, from the parameter
+/// annotation attributes of both retention policies
+///
+/// The attributes needn't have an entry for every parameter in the method descriptor, and a
+/// compiler may leave out synthetic parameters, so the result has as many entries as the
+/// longer of the two attributes
+pub fn read_parameter_annotations<'a>(
+    attributes: &[attribute_info],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Vec<Vec<Annotation<'a>>>, ClassLoadingError> {
+    let mut resolved: Vec<Vec<Annotation<'a>>> = Vec::new();
+    for visible in &[true, false] {
+        for info in attributes {
+            let parameter_annotations = match info.decode()? {
+                RuntimeVisibleParameterAnnotations_attribute { parameter_annotations, .. }
+                    if *visible => parameter_annotations,
+                RuntimeInvisibleParameterAnnotations_attribute { parameter_annotations, .. }
+                    if !*visible => parameter_annotations,
+                _ => continue,
+            };
+            if resolved.len() < parameter_annotations.len() {
+                resolved.resize(parameter_annotations.len(), Vec::new());
+            }
+            for (index, list) in parameter_annotations.iter().enumerate() {
+                for annotation in list.get_annotations() {
+                    resolved[index].push(
+                        resolve_annotation(annotation, *visible, constant_pool)
+                            .map_err(|error| error.at_offset(info.get_offset()))?,
+                    );
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// Resolves the default value of an annotation interface element from its AnnotationDefault
+/// attribute
+pub fn read_annotation_default<'a>(
+    attributes: &[attribute_info],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Option<AnnotationValue<'a>>, ClassLoadingError> {
+    for info in attributes {
+        if let AnnotationDefault_attribute { default_value } = info.decode()? {
+            return resolve_value(default_value, true, constant_pool)
+                .map(Some)
+                .map_err(|error| error.at_offset(info.get_offset()));
+        }
+    }
+    Ok(None)
+}
+
+/// The first annotation of an interface, given by its field descriptor, among `annotations`
+pub fn find_annotation<'a>(
+    annotations: Vec<Annotation<'a>>,
+    type_descriptor: &str,
+) -> Option<Annotation<'a>> {
+    annotations.into_iter().find(|annotation| annotation.type_descriptor == type_descriptor)
+}
+
+fn resolve_annotation<'a>(
+    annotation: &annotation,
+    visible: bool,
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Annotation<'a>, ClassLoadingError> {
+    let type_descriptor = constant_pool.get_string_entry(annotation.get_type_index())?;
+    let elements = annotation.get_element_value_pairs().iter().map(|pair| {
+        let name = constant_pool.get_string_entry(pair.get_element_name_index())?;
+        let value = resolve_value(pair.get_value(), visible, constant_pool)?;
+        Ok((name, value))
+    }).collect::<Result<Vec<_>, ClassLoadingError>>()?;
+    Ok(Annotation {
+        type_descriptor,
+        visible,
+        elements,
+    })
+}
+
+/// Resolves an element value, narrowing the `CONSTANT_Integer_info` of a `byte`, `char`,
+/// `short` or `boolean` to its type
+fn resolve_value<'a>(
+    value: &element_value,
+    visible: bool,
+    constant_pool: &ConstantPool<'a>,
+) -> Result<AnnotationValue<'a>, ClassLoadingError> {
+    Ok(match value.get_value() {
+        const_value_index(index) => {
+            let index = *index;
+            match value.get_tag() {
+                b'B' => AnnotationValue::Byte(constant_pool.integer(index)? as i8),
+                b'C' => AnnotationValue::Char(constant_pool.integer(index)? as u16),
+                b'D' => AnnotationValue::Double(constant_pool.double(index)?),
+                b'F' => AnnotationValue::Float(constant_pool.float(index)?),
+                b'I' => AnnotationValue::Int(constant_pool.integer(index)?),
+                b'J' => AnnotationValue::Long(constant_pool.long(index)?),
+                b'S' => AnnotationValue::Short(constant_pool.integer(index)? as i16),
+                b'Z' => AnnotationValue::Boolean(constant_pool.integer(index)? != 0),
+                b's' => AnnotationValue::String(constant_pool.get_string_entry(index)?),
+                tag => {
+                    return Err(ClassFormatError(format!(
+                        "Illegal element_value#tag for a constant: {}",
+                        tag
+                    )))
+                }
+            }
+        }
+        enum_const_value { type_name_index, const_name_index } => AnnotationValue::Enum {
+            type_descriptor: constant_pool.get_string_entry(*type_name_index)?,
+            const_name: constant_pool.get_string_entry(*const_name_index)?,
+        },
+        class_info_index(index) => {
+            AnnotationValue::Class(constant_pool.get_string_entry(*index)?)
+        }
+        annotation_value(annotation) => {
+            AnnotationValue::Annotation(resolve_annotation(annotation, visible, constant_pool)?)
+        }
+        array_value { values, .. } => AnnotationValue::Array(
+            values.iter()
+                .map(|value| resolve_value(value, visible, constant_pool))
+                .collect::<Result<_, _>>()?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Annotation, AnnotationValue};
+    use assembler::assemble_class;
+    use class_file::ClassLoadingError::ClassFormatError;
+    use typed_arena::Arena;
+
+    const SOURCE: &str = "public abstract class p.A
+  minor version: 0
+  major version: 61
+Constant pool:
+   #1 = Utf8               Lp/Marker;
+   #2 = Utf8               value
+   #3 = Utf8               text
+   #4 = Integer            200
+   #5 = Utf8               Lp/Color;
+   #6 = Utf8               RED
+   #7 = Utf8               Ljava/lang/String;
+   #8 = Utf8               Lp/Nested;
+   #9 = Double             1.5d
+  #11 = Utf8               Lp/Hidden;
+  #12 = Integer            1
+  #13 = Utf8               RuntimeVisibleAnnotations
+  #14 = Utf8               RuntimeInvisibleAnnotations
+  #15 = Utf8               RuntimeVisibleParameterAnnotations
+  #16 = Utf8               RuntimeInvisibleParameterAnnotations
+  #17 = Utf8               AnnotationDefault
+{
+  public abstract void m(int, int);
+    descriptor: (II)V
+    RuntimeVisibleParameterAnnotations:
+      parameter 0:
+        0: #1()
+    RuntimeInvisibleParameterAnnotations:
+      parameter 0:
+      parameter 1:
+        0: #11()
+
+  public abstract int n();
+    descriptor: ()I
+    AnnotationDefault:
+      default_value: I#12
+    RuntimeVisibleAnnotations:
+      0: #1()
+}
+RuntimeInvisibleAnnotations:
+  0: #11()
+RuntimeVisibleAnnotations:
+  0: #1(#2=s#3,#3=[B#4,Z#12,C#12],#6=e#5.#6,#7=c#7,#8=@#8(#2=D#9))
+";
+
+    fn marker(visible: bool) -> Annotation<'static> {
+        Annotation { type_descriptor: "Lp/Marker;", visible, elements: Vec::new() }
+    }
+
+    fn hidden() -> Annotation<'static> {
+        Annotation { type_descriptor: "Lp/Hidden;", visible: false, elements: Vec::new() }
+    }
+
+    #[test]
+    fn resolves_annotations_visible_ones_first() {
+        let strings = Arena::new();
+        let class = assemble_class(SOURCE, &strings).unwrap();
+        let annotations = class.get_annotations().unwrap();
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[1], hidden());
+        let annotation = &annotations[0];
+        assert_eq!(annotation.get_type(), "Lp/Marker;");
+        assert!(annotation.is_visible());
+        assert_eq!(annotation.get_element("value"), Some(&AnnotationValue::String("text")));
+        assert_eq!(annotation.get_element("text"), Some(&AnnotationValue::Array(vec![
+            // Narrowed from the CONSTANT_Integer_info to their element types
+            AnnotationValue::Byte(-56),
+            AnnotationValue::Boolean(true),
+            AnnotationValue::Char(1),
+        ])));
+        assert_eq!(annotation.get_element("RED"), Some(&AnnotationValue::Enum {
+            type_descriptor: "Lp/Color;",
+            const_name: "RED",
+        }));
+        assert_eq!(
+            annotation.get_element("Ljava/lang/String;"),
+            Some(&AnnotationValue::Class("Ljava/lang/String;"))
+        );
+        assert_eq!(annotation.get_element("Lp/Nested;"), Some(&AnnotationValue::Annotation(
+            Annotation {
+                type_descriptor: "Lp/Nested;",
+                visible: true,
+                elements: vec![("value", AnnotationValue::Double(1.5))],
+            }
+        )));
+        assert_eq!(annotation.get_element("missing"), None);
+        assert_eq!(class.find_annotation("Lp/Hidden;").unwrap(), Some(hidden()));
+        assert_eq!(class.find_annotation("Lp/Missing;").unwrap(), None);
+    }
+
+    #[test]
+    fn parameter_annotations_cover_the_longer_attribute() {
+        let strings = Arena::new();
+        let class = assemble_class(SOURCE, &strings).unwrap();
+        let method = &class.get_methods()[0];
+        let parameters = method.get_parameter_annotations().unwrap();
+        assert_eq!(parameters, [vec![marker(true)], vec![hidden()]]);
+        assert_eq!(method.get_annotation_default().unwrap(), None);
+        let method = &class.get_methods()[1];
+        assert!(method.get_parameter_annotations().unwrap().is_empty());
+        assert_eq!(method.get_annotation_default().unwrap(), Some(AnnotationValue::Int(1)));
+        assert_eq!(method.find_annotation("Lp/Marker;").unwrap(), Some(marker(true)));
+        assert_eq!(class.get_methods()[0].find_annotation("Lp/Marker;").unwrap(), None);
+    }
+
+    #[test]
+    fn constants_of_the_wrong_kind_are_rejected() {
+        let strings = Arena::new();
+        let source = SOURCE.replace("#2=s#3", "#2=s#4");
+        let class = assemble_class(&source, &strings).unwrap();
+        assert!(matches!(class.get_annotations(), Err(ClassFormatError(_))));
+    }
+}
@@ -0,0 +1,2154 @@
+use class_file::ClassFile;
+use class_file::ClassLoadingError;
+use class_writer::ClassWriter;
+use constant_pool::cp_info;
+use constant_pool::cp_info::*;
+use constant_pool::{encode_modified_utf8_units, ReferenceKind};
+use instruction::Instruction::*;
+use instruction::{Instruction, InstructionIter, WideInstruction};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use typed_arena::Arena;
+
+/// Attributes the disassembler prints, so lines starting with their name are attribute headers
+const ATTRIBUTE_NAMES: &[&str] = &[
+    "ConstantValue",
+    "Code",
+    "StackMapTable",
+    "Exceptions",
+    "InnerClasses",
+    "EnclosingMethod",
+    "Synthetic",
+    "Signature",
+    "SourceFile",
+    "SourceDebugExtension",
+    "LineNumberTable",
+    "LocalVariableTable",
+    "LocalVariableTypeTable",
+    "Deprecated",
+    "RuntimeVisibleAnnotations",
+    "RuntimeInvisibleAnnotations",
+    "RuntimeVisibleParameterAnnotations",
+    "RuntimeInvisibleParameterAnnotations",
+    "RuntimeVisibleTypeAnnotations",
+    "RuntimeInvisibleTypeAnnotations",
+    "AnnotationDefault",
+    "BootstrapMethods",
+    "Module",
+    "ModulePackages",
+    "ModuleMainClass",
+    "NestHost",
+    "NestMembers",
+    "Record",
+    "PermittedSubclasses",
+    "MethodParameters",
+];
+
+const CLASS_MODIFIERS: &[(&str, u16)] = &[
+    ("public", 0x0001),
+    ("final", 0x0010),
+    ("abstract", 0x0400),
+];
+
+const FIELD_MODIFIERS: &[(&str, u16)] = &[
+    ("public", 0x0001),
+    ("private", 0x0002),
+    ("protected", 0x0004),
+    ("static", 0x0008),
+    ("final", 0x0010),
+    ("volatile", 0x0040),
+    ("transient", 0x0080),
+];
+
+const METHOD_MODIFIERS: &[(&str, u16)] = &[
+    ("public", 0x0001),
+    ("private", 0x0002),
+    ("protected", 0x0004),
+    ("static", 0x0008),
+    ("final", 0x0010),
+    ("synchronized", 0x0020),
+    ("native", 0x0100),
+    ("abstract", 0x0400),
+    ("strictfp", 0x0800),
+];
+
+/// The names of the inner class flags the disassembler prints after the raw flags, which can
+/// be written by hand instead of them
+const INNER_CLASS_MODIFIERS: &[(&str, u16)] = &[
+    ("public", 0x0001),
+    ("private", 0x0002),
+    ("protected", 0x0004),
+    ("static", 0x0008),
+    ("final", 0x0010),
+    ("interface", 0x0200),
+    ("abstract", 0x0400),
+    ("synthetic", 0x1000),
+    ("annotation", 0x2000),
+    ("enum", 0x4000),
+];
+
+const METHOD_PARAMETER_FLAGS: &[(&str, u16)] = &[
+    ("final", 0x0010),
+    ("synthetic", 0x1000),
+    ("mandated", 0x8000),
+];
+
+const TYPE_ANNOTATION_TARGETS: &[(&str, u8)] = &[
+    ("CLASS_TYPE_PARAMETER", 0x00),
+    ("METHOD_TYPE_PARAMETER", 0x01),
+    ("CLASS_EXTENDS", 0x10),
+    ("CLASS_TYPE_PARAMETER_BOUND", 0x11),
+    ("METHOD_TYPE_PARAMETER_BOUND", 0x12),
+    ("FIELD", 0x13),
+    ("METHOD_RETURN", 0x14),
+    ("METHOD_RECEIVER", 0x15),
+    ("METHOD_FORMAL_PARAMETER", 0x16),
+    ("THROWS", 0x17),
+    ("LOCAL_VARIABLE", 0x40),
+    ("RESOURCE_VARIABLE", 0x41),
+    ("EXCEPTION_PARAMETER", 0x42),
+    ("INSTANCEOF", 0x43),
+    ("NEW", 0x44),
+    ("CONSTRUCTOR_REFERENCE", 0x45),
+    ("METHOD_REFERENCE", 0x46),
+    ("CAST", 0x47),
+    ("CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT", 0x48),
+    ("METHOD_INVOCATION_TYPE_ARGUMENT", 0x49),
+    ("CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT", 0x4A),
+    ("METHOD_REFERENCE_TYPE_ARGUMENT", 0x4B),
+];
+
+const ARRAY_TYPES: &[(&str, u8)] = &[
+    ("boolean", 4),
+    ("char", 5),
+    ("float", 6),
+    ("double", 7),
+    ("byte", 8),
+    ("short", 9),
+    ("int", 10),
+    ("long", 11),
+];
+
+const ACC_SUPER: u16 = 0x0020;
+const ACC_VARARGS: u16 = 0x0080;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_MODULE: u16 = 0x8000;
+
+#[derive(Debug)]
+pub enum AssemblyError {
+    /// The line number of the source that couldn't be assembled, and why
+    SyntaxError(usize, String),
+    /// The assembled class couldn't be loaded
+    ClassLoadingError(ClassLoadingError),
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblyError::SyntaxError(line, message) => write!(f, "line {}: {}", line, message),
+            AssemblyError::ClassLoadingError(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<ClassLoadingError> for AssemblyError {
+    fn from(error: ClassLoadingError) -> Self {
+        AssemblyError::ClassLoadingError(error)
+    }
+}
+
+/// Assembles a class from the format the disassembler prints, giving the bytes of the class
+/// file
+///
+/// Assembling the disassembly of a class reproduces it, and the disassembly of an assembled
+/// class reproduces its source. Hand-written classes can leave out what the disassembler only
+/// prints for reference:
+///
+/// * The constant pool can be left out, with operands given by value rather than by index, eg
+///   `invokevirtual Method java/io/PrintStream.println:(Ljava/lang/String;)V` or
+///   `ldc String hello`. Constants are added to the pool as they are needed
+/// * Instructions can be labelled by any name rather than their pc, and branches, exception
+///   tables and local variable tables refer to the labels. A label on its own line marks the
+///   end of the code
+/// * Access flags default to those of the modifiers of a declaration, and the superclass and
+///   interfaces to those it names
+/// * Lines starting with `//` are ignored
+///
+/// The class isn't checked, so that malformed classes can be assembled too
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssemblyError> {
+    let lines = parse_lines(source);
+    let strings = Arena::new();
+    let mut assembler = Assembler {
+        strings: &strings,
+        pool: vec![None],
+        pool_indices: HashMap::new(),
+        this_class_index: 0,
+        this_class: String::new(),
+        templates: instruction_templates(),
+    };
+    assembler.write_class(&lines)
+}
+
+/// Assembles a class like `assemble`, and loads it
+pub fn assemble_class<'a>(
+    source: &str,
+    string_allocator: &'a Arena<String>,
+) -> Result<ClassFile<'a>, AssemblyError> {
+    let bytes = assemble(source)?;
+    Ok(ClassFile::new(&mut bytes.as_slice(), string_allocator)?)
+}
+
+/// A line of source, with the more indented lines that follow it as its children
+struct Line<'s> {
+    number: usize,
+    text: &'s str,
+    children: Vec<Line<'s>>,
+}
+
+fn parse_lines<'s>(source: &'s str) -> Vec<Line<'s>> {
+    let mut roots = Vec::new();
+    let mut open: Vec<(usize, Line)> = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        let trimmed = trim(text);
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            continue;
+        }
+        let indent = text.find(trimmed).unwrap_or(0);
+        close_lines(&mut open, &mut roots, indent);
+        open.push((indent, Line { number: index + 1, text: trimmed, children: Vec::new() }));
+    }
+    close_lines(&mut open, &mut roots, 0);
+    roots
+}
+
+/// Finishes the open lines indented at least as much as `indent`
+fn close_lines<'s>(open: &mut Vec<(usize, Line<'s>)>, roots: &mut Vec<Line<'s>>, indent: usize) {
+    while open.last().is_some_and(|(open_indent, _)| *open_indent >= indent) {
+        let (_, line) = open.pop().unwrap();
+        match open.last_mut() {
+            Some((_, parent)) => parent.children.push(line),
+            None => roots.push(line),
+        }
+    }
+}
+
+fn syntax_error<T>(line: &Line, message: String) -> Result<T, AssemblyError> {
+    Err(AssemblyError::SyntaxError(line.number, message))
+}
+
+/// Finds each instruction by its mnemonic, with its operands zeroed
+///
+/// These are found by decoding every opcode, so that they always agree with the decoder
+fn instruction_templates() -> HashMap<String, Instruction> {
+    let mut templates = HashMap::new();
+    let mut code = [0u8; 20];
+    for opcode in 0..=255u8 {
+        code[0] = opcode;
+        if opcode == 0xc4 {
+            // wide, which takes the opcode it modifies
+            for modified in 0..=255u8 {
+                code[1] = modified;
+                if let Some(Ok((_, Wide(instruction)))) = InstructionIter::new(&code).next() {
+                    templates.insert(format!("{}_w", instruction.get_mnemonic()), Wide(instruction));
+                }
+            }
+            code[1] = 0;
+        } else if let Some(Ok((_, instruction))) = InstructionIter::new(&code).next() {
+            templates.insert(String::from(instruction.get_mnemonic()), instruction);
+        }
+    }
+    templates
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// What identifies a constant pool entry, so that each constant is only added once
+enum ConstantKey {
+    Utf8(String, Option<Vec<u8>>),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    Class(u16),
+    String(u16),
+    Fieldref(u16, u16),
+    Methodref(u16, u16),
+    InterfaceMethodref(u16, u16),
+    NameAndType(u16, u16),
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    Dynamic(u16, u16),
+    InvokeDynamic(u16, u16),
+    Module(u16),
+    Package(u16),
+}
+
+impl ConstantKey {
+    fn new(entry: &cp_info) -> ConstantKey {
+        match entry {
+            CONSTANT_Utf8_info { bytes, raw } => ConstantKey::Utf8(String::from(*bytes), raw.clone()),
+            CONSTANT_Integer_info { bytes } => ConstantKey::Integer(*bytes),
+            CONSTANT_Float_info { bytes } => ConstantKey::Float(bytes.to_bits()),
+            CONSTANT_Long_info { value } => ConstantKey::Long(*value),
+            CONSTANT_Double_info { value } => ConstantKey::Double(value.to_bits()),
+            CONSTANT_Class_info { name_index } => ConstantKey::Class(*name_index),
+            CONSTANT_String_info { string_index } => ConstantKey::String(*string_index),
+            CONSTANT_Fieldref_info { class_index, name_and_type_index } => {
+                ConstantKey::Fieldref(*class_index, *name_and_type_index)
+            }
+            CONSTANT_Methodref_info { class_index, name_and_type_index } => {
+                ConstantKey::Methodref(*class_index, *name_and_type_index)
+            }
+            CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index } => {
+                ConstantKey::InterfaceMethodref(*class_index, *name_and_type_index)
+            }
+            CONSTANT_NameAndType_info { name_index, descriptor_index } => {
+                ConstantKey::NameAndType(*name_index, *descriptor_index)
+            }
+            CONSTANT_MethodHandle_info { reference_kind, reference_index } => {
+                ConstantKey::MethodHandle(*reference_kind as u8, *reference_index)
+            }
+            CONSTANT_MethodType_info { descriptor_index } => ConstantKey::MethodType(*descriptor_index),
+            CONSTANT_Dynamic_info { bootstrap_method_attr_index, name_and_type_index } => {
+                ConstantKey::Dynamic(*bootstrap_method_attr_index, *name_and_type_index)
+            }
+            CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, name_and_type_index } => {
+                ConstantKey::InvokeDynamic(*bootstrap_method_attr_index, *name_and_type_index)
+            }
+            CONSTANT_Module_info { name_index } => ConstantKey::Module(*name_index),
+            CONSTANT_Package_info { name_index } => ConstantKey::Package(*name_index),
+        }
+    }
+}
+
+/// The pcs of the labels of a method's code
+type Labels = HashMap<String, u32>;
+
+struct Assembler<'s> {
+    strings: &'s Arena<String>,
+    /// Indexed by constant pool index, with `None` for index 0 and the slots after longs and
+    /// doubles
+    pool: Vec<Option<cp_info<'s>>>,
+    /// The first index of each constant in the pool
+    pool_indices: HashMap<ConstantKey, u16>,
+    this_class_index: u16,
+    /// The binary name of the class, eg `java/lang/Object`
+    this_class: String,
+    templates: HashMap<String, Instruction>,
+}
+
+impl<'s> Assembler<'s> {
+    fn write_class(&mut self, lines: &[Line]) -> Result<Vec<u8>, AssemblyError> {
+        // Indices are right-aligned, so entries from #1000 on aren't indented under the pool
+        let mut pool = Vec::new();
+        let mut in_pool = false;
+        for line in lines {
+            if line.text == "Constant pool:" {
+                in_pool = true;
+            } else if in_pool && line.text.starts_with('#') {
+                pool.push(line);
+            } else {
+                in_pool = false;
+                continue;
+            }
+            flatten(&line.children, &mut pool);
+        }
+        self.read_constant_pool(&pool)?;
+        let mut header = None;
+        let mut members = None;
+        let mut attributes = Vec::new();
+        for line in lines {
+            match line.text {
+                "Constant pool:" | "}" => {}
+                text if text.starts_with('#') => {}
+                "{" => members = Some(line),
+                text if text.starts_with("Compiled from ") || text.starts_with("Classfile ") => {}
+                _ if members.is_some() => attributes.push(line),
+                _ if header.is_none() => header = Some(line),
+                _ => return syntax_error(line, format!("Expected `{{` to start the members, found {:?}", line.text)),
+            }
+        }
+        let header = match header {
+            Some(header) => header,
+            None => return Err(AssemblyError::SyntaxError(1, String::from("Missing class declaration"))),
+        };
+
+        let declaration = ClassDeclaration::new(header)?;
+        let mut minor_version = None;
+        let mut major_version = None;
+        let mut access_flags = None;
+        let mut this_class = None;
+        let mut super_class = None;
+        for line in &header.children {
+            let (key, value) = key_value(line)?;
+            match key {
+                "minor version" => minor_version = Some(parse(value, line)?),
+                "major version" => major_version = Some(parse(value, line)?),
+                "flags" => access_flags = Some(parse_flags(value, line)?),
+                "this_class" => this_class = Some(self.class_operand(value, line)?),
+                "super_class" => super_class = Some(self.class_operand(value, line)?),
+                "interfaces" => {}
+                _ => return syntax_error(line, format!("Unknown class header {:?}", key)),
+            }
+        }
+        let minor_version = match minor_version {
+            Some(version) => version,
+            None => return syntax_error(header, String::from("Missing minor version")),
+        };
+        let major_version = match major_version {
+            Some(version) => version,
+            None => return syntax_error(header, String::from("Missing major version")),
+        };
+        let access_flags = match access_flags {
+            Some(flags) => flags,
+            None => declaration.access_flags,
+        };
+        self.this_class = declaration.name.replace('.', "/");
+        self.this_class_index = match this_class {
+            Some(index) => index,
+            None => {
+                let this_class = self.this_class.clone();
+                self.class(&this_class)?
+            }
+        };
+        let super_class = match super_class {
+            Some(index) => index,
+            None if declaration.kind == "module" => 0,
+            None => {
+                let super_class = declaration.super_class.clone()
+                    .unwrap_or_else(|| String::from("java/lang/Object"));
+                self.class(&super_class)?
+            }
+        };
+        let mut interfaces = Vec::new();
+        for interface in &declaration.interfaces {
+            interfaces.push(self.class(interface)?);
+        }
+
+        let mut body = ClassWriter::new();
+        body.write_u16(access_flags);
+        body.write_u16(self.this_class_index);
+        body.write_u16(super_class);
+        body.write_u16_table(&interfaces);
+        let members: &[Line] = members.map_or(&[], |line| &line.children);
+        let (methods, fields): (Vec<&Line>, Vec<&Line>) = members.iter()
+            .partition(|line| line.text.contains('(') || line.text == "static {};");
+        body.write_u16(fields.len() as u16);
+        for field in fields {
+            self.write_member(field, &declaration.name, false, &mut body)?;
+        }
+        body.write_u16(methods.len() as u16);
+        for method in methods {
+            self.write_member(method, &declaration.name, true, &mut body)?;
+        }
+        self.write_attributes(&attributes, None, &mut body)?;
+
+        // The constant pool is written last, as constants are added while assembling the rest
+        let mut output = ClassWriter::new();
+        output.write_u32(0xCAFEBABE);
+        output.write_u16(minor_version);
+        output.write_u16(major_version);
+        self.write_constant_pool(header, &mut output)?;
+        output.write_bytes(&body.into_bytes());
+        Ok(output.into_bytes())
+    }
+
+    fn read_constant_pool(&mut self, pool: &[&Line]) -> Result<(), AssemblyError> {
+        for line in pool {
+            let (number, rest) = match line.text.find('=') {
+                Some(equals) => (line.text[..equals].trim(), line.text[equals + 1..].trim_start()),
+                None => return syntax_error(line, format!("Expected a constant pool entry, found {:?}", line.text)),
+            };
+            let index: u16 = parse_index(number, line)?;
+            let tag_length = rest.find(' ').unwrap_or(rest.len());
+            let tag = &rest[..tag_length];
+            let rest = &rest[tag_length..];
+            let entry = if tag == "Utf8" {
+                // The value is aligned after the padded tag, and may itself start with spaces
+                let padding = rest.len() - rest.trim_start_matches(' ').len();
+                let value = &rest[padding.min(15)..];
+                self.utf8_entry(&unescape_units(value, line)?)
+            } else {
+                let value = split_comment(rest).0.trim();
+                match tag {
+                    "Integer" => CONSTANT_Integer_info { bytes: parse(value, line)? },
+                    "Float" => CONSTANT_Float_info { bytes: parse_float(value.trim_end_matches('f'), line)? as f32 },
+                    "Long" => CONSTANT_Long_info { value: parse(value.trim_end_matches('l'), line)? },
+                    "Double" => CONSTANT_Double_info { value: parse_float(value.trim_end_matches('d'), line)? },
+                    "Class" => CONSTANT_Class_info { name_index: parse_index(value, line)? },
+                    "String" => CONSTANT_String_info { string_index: parse_index(value, line)? },
+                    "Module" => CONSTANT_Module_info { name_index: parse_index(value, line)? },
+                    "Package" => CONSTANT_Package_info { name_index: parse_index(value, line)? },
+                    "MethodType" => CONSTANT_MethodType_info { descriptor_index: parse_index(value, line)? },
+                    "Fieldref" | "Methodref" | "InterfaceMethodref" => {
+                        let (class_index, name_and_type_index) = parse_index_pair(value, '.', line)?;
+                        match tag {
+                            "Fieldref" => CONSTANT_Fieldref_info { class_index, name_and_type_index },
+                            "Methodref" => CONSTANT_Methodref_info { class_index, name_and_type_index },
+                            _ => CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index },
+                        }
+                    }
+                    "NameAndType" => {
+                        let (name_index, descriptor_index) = parse_index_pair(value, ':', line)?;
+                        CONSTANT_NameAndType_info { name_index, descriptor_index }
+                    }
+                    "Dynamic" | "InvokeDynamic" => {
+                        let (bootstrap_method_attr_index, name_and_type_index) = parse_index_pair(value, ':', line)?;
+                        if tag == "Dynamic" {
+                            CONSTANT_Dynamic_info { bootstrap_method_attr_index, name_and_type_index }
+                        } else {
+                            CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, name_and_type_index }
+                        }
+                    }
+                    "MethodHandle" => {
+                        let (kind, reference) = split_pair(value, ':', line)?;
+                        let reference_kind = match ReferenceKind::from_u8(parse(kind, line)?) {
+                            Some(kind) => kind,
+                            None => return syntax_error(line, format!("Invalid reference kind {}", kind)),
+                        };
+                        CONSTANT_MethodHandle_info { reference_kind, reference_index: parse_index(reference, line)? }
+                    }
+                    _ => return syntax_error(line, format!("Unknown constant pool tag {:?}", tag)),
+                }
+            };
+            self.set_constant(index, entry, line)?;
+        }
+        Ok(())
+    }
+
+    fn write_constant_pool(&self, header: &Line, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        if self.pool.len() > 0xFFFF {
+            return syntax_error(header, format!("The constant pool has {} entries, more than fit in a class file", self.pool.len() - 1));
+        }
+        output.write_u16(self.pool.len() as u16);
+        for (index, entry) in self.pool.iter().enumerate().skip(1) {
+            match entry {
+                Some(entry) => entry.write(output),
+                None => match self.pool[index - 1] {
+                    Some(CONSTANT_Long_info { .. }) | Some(CONSTANT_Double_info { .. }) => {}
+                    _ => return syntax_error(header, format!("Constant pool entry #{} is missing", index)),
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn set_constant(&mut self, index: u16, entry: cp_info<'s>, line: &Line) -> Result<(), AssemblyError> {
+        let index = index as usize;
+        let wide = matches!(entry, CONSTANT_Long_info { .. } | CONSTANT_Double_info { .. });
+        let end = if wide { index + 2 } else { index + 1 };
+        if index == 0 || end > 0x10000 {
+            return syntax_error(line, format!("Invalid constant pool index #{}", index));
+        }
+        if self.pool.len() < end {
+            self.pool.resize_with(end, || None);
+        }
+        if self.pool[index].is_some() || (wide && self.pool[index + 1].is_some()) {
+            return syntax_error(line, format!("Constant pool entry #{} is defined twice", index));
+        }
+        self.pool_indices.entry(ConstantKey::new(&entry)).or_insert(index as u16);
+        self.pool[index] = Some(entry);
+        Ok(())
+    }
+
+    /// Finds a constant in the pool, adding it if it isn't there yet
+    fn constant(&mut self, entry: cp_info<'s>) -> Result<u16, AssemblyError> {
+        let key = ConstantKey::new(&entry);
+        if let Some(index) = self.pool_indices.get(&key) {
+            return Ok(*index);
+        }
+        let index = self.pool.len();
+        let wide = matches!(entry, CONSTANT_Long_info { .. } | CONSTANT_Double_info { .. });
+        if index + if wide { 2 } else { 1 } > 0x10000 {
+            return Err(AssemblyError::SyntaxError(0, String::from("The constant pool is full")));
+        }
+        self.pool.push(Some(entry));
+        if wide {
+            self.pool.push(None);
+        }
+        self.pool_indices.insert(key, index as u16);
+        Ok(index as u16)
+    }
+
+    fn string(&self, string: String) -> &'s str {
+        self.strings.alloc(string).as_str()
+    }
+
+    /// Builds a `CONSTANT_Utf8_info`, keeping the raw modified UTF-8 if the string has unpaired
+    /// surrogates
+    fn utf8_entry(&self, units: &[u16]) -> cp_info<'s> {
+        match String::from_utf16(units) {
+            Ok(string) => CONSTANT_Utf8_info { bytes: self.string(string), raw: None },
+            Err(_) => CONSTANT_Utf8_info {
+                bytes: self.string(String::from_utf16_lossy(units)),
+                raw: Some(encode_modified_utf8_units(units)),
+            },
+        }
+    }
+
+    fn utf8(&mut self, string: &str) -> Result<u16, AssemblyError> {
+        let bytes = self.string(String::from(string));
+        self.constant(CONSTANT_Utf8_info { bytes, raw: None })
+    }
+
+    fn class(&mut self, name: &str) -> Result<u16, AssemblyError> {
+        let name_index = self.utf8(name)?;
+        self.constant(CONSTANT_Class_info { name_index })
+    }
+
+    fn name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, AssemblyError> {
+        let name_index = self.utf8(name)?;
+        let descriptor_index = self.utf8(descriptor)?;
+        self.constant(CONSTANT_NameAndType_info { name_index, descriptor_index })
+    }
+
+    /// Finds the constant an operand refers to, either by index, eg `#12`, or by value as the
+    /// disassembler shows it, eg `Method java/lang/Object."<init>":()V`
+    ///
+    /// Members of this class can leave out the class name
+    fn operand(&mut self, text: &str, line: &Line) -> Result<u16, AssemblyError> {
+        let text = trim(text);
+        if text.starts_with('#') {
+            return parse_index(split_comment(text).0, line);
+        }
+        let (kind, raw_value) = match text.find(' ') {
+            Some(space) => (&text[..space], &text[space + 1..]),
+            None if text == "String" => (text, ""),
+            None => return syntax_error(line, format!("Expected a constant, found {:?}", text)),
+        };
+        // Unquoted strings run to the end of the line, but anything else may be followed by a
+        // comment
+        let value = if kind == "String" { raw_value } else { split_comment(raw_value).0 };
+        let value = value.trim();
+        match kind {
+            "class" => {
+                let name = unquote(value, line)?;
+                self.class(&name)
+            }
+            "String" => {
+                // Leading spaces are part of an unquoted string
+                let string = if value.starts_with('"') && value.len() >= 2 && value.ends_with('"') {
+                    unescape_units(&value[1..value.len() - 1], line)?
+                } else {
+                    unescape_units(raw_value, line)?
+                };
+                let entry = self.utf8_entry(&string);
+                let string_index = self.constant(entry)?;
+                self.constant(CONSTANT_String_info { string_index })
+            }
+            "int" => self.constant(CONSTANT_Integer_info { bytes: parse(value, line)? }),
+            "float" => {
+                let value = parse_float(value.trim_end_matches('f'), line)? as f32;
+                self.constant(CONSTANT_Float_info { bytes: value })
+            }
+            "long" => self.constant(CONSTANT_Long_info { value: parse(value.trim_end_matches('l'), line)? }),
+            "double" => {
+                let value = parse_float(value.trim_end_matches('d'), line)?;
+                self.constant(CONSTANT_Double_info { value })
+            }
+            "Field" | "Method" | "InterfaceMethod" => self.member_ref(kind, value, line),
+            "MethodType" => {
+                let descriptor_index = self.utf8(value)?;
+                self.constant(CONSTANT_MethodType_info { descriptor_index })
+            }
+            "MethodHandle" => {
+                let (kind, reference) = match value.find(' ') {
+                    Some(space) => (&value[..space], value[space + 1..].trim_start()),
+                    None => return syntax_error(line, format!("Expected a method handle, found {:?}", value)),
+                };
+                let reference_kind = match (1..=9).filter_map(ReferenceKind::from_u8)
+                    .find(|reference_kind| format!("{:?}", reference_kind) == kind)
+                {
+                    Some(reference_kind) => reference_kind,
+                    None => return syntax_error(line, format!("Unknown reference kind {:?}", kind)),
+                };
+                let member_kind = match reference_kind {
+                    _ if reference_kind.is_field_access() => "Field",
+                    ReferenceKind::REF_invokeInterface => "InterfaceMethod",
+                    _ => "Method",
+                };
+                let reference_index = self.member_ref(member_kind, reference, line)?;
+                self.constant(CONSTANT_MethodHandle_info { reference_kind, reference_index })
+            }
+            "InvokeDynamic" | "Dynamic" => {
+                let (bootstrap_method, name_and_type) = split_pair(value, ':', line)?;
+                let bootstrap_method_attr_index = parse_index(bootstrap_method, line)?;
+                let (name, descriptor) = parse_name_and_type(name_and_type, line)?;
+                let name_and_type_index = self.name_and_type(&name, &descriptor)?;
+                if kind == "Dynamic" {
+                    self.constant(CONSTANT_Dynamic_info { bootstrap_method_attr_index, name_and_type_index })
+                } else {
+                    self.constant(CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, name_and_type_index })
+                }
+            }
+            _ => syntax_error(line, format!("Unknown kind of constant {:?}", kind)),
+        }
+    }
+
+    /// Finds a field or method reference, eg `java/lang/Object."<init>":()V`
+    fn member_ref(&mut self, kind: &str, text: &str, line: &Line) -> Result<u16, AssemblyError> {
+        let (first, rest) = take_name(text, &['.', ':'], line)?;
+        let (class_index, name, rest) = if let Some(rest) = rest.strip_prefix('.') {
+            let (name, rest) = take_name(rest, &[':'], line)?;
+            (self.class(&first)?, name, rest)
+        } else {
+            (self.this_class_index, first, rest)
+        };
+        if !rest.starts_with(':') {
+            return syntax_error(line, format!("Expected a member reference, found {:?}", text));
+        }
+        let name_and_type_index = self.name_and_type(&name, &rest[1..])?;
+        self.constant(match kind {
+            "Field" => CONSTANT_Fieldref_info { class_index, name_and_type_index },
+            "Method" => CONSTANT_Methodref_info { class_index, name_and_type_index },
+            _ => CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index },
+        })
+    }
+
+    /// Finds a class given either by index, eg `#7  // java/lang/Object`, or by name
+    fn class_operand(&mut self, text: &str, line: &Line) -> Result<u16, AssemblyError> {
+        let (code, comment) = split_comment(text);
+        let code = code.trim();
+        if code.starts_with('#') {
+            return parse_index(code, line);
+        }
+        let name = if code.is_empty() { comment.unwrap_or("").trim() } else { code };
+        let name = unquote(name, line)?;
+        self.class(&name)
+    }
+
+    /// Finds a `CONSTANT_Utf8_info` given either by index, eg `#20  // ()V`, or by value
+    fn utf8_operand(&mut self, text: &str, line: &Line) -> Result<u16, AssemblyError> {
+        let (code, _) = split_comment(text);
+        let code = code.trim();
+        if code.starts_with('#') {
+            return parse_index(code, line);
+        }
+        self.utf8(&unescape(code, line)?)
+    }
+
+    fn write_member(
+        &mut self,
+        line: &Line,
+        class_name: &str,
+        is_method: bool,
+        output: &mut ClassWriter,
+    ) -> Result<(), AssemblyError> {
+        let declaration = line.text.trim_end_matches(';');
+        let (modifiers, name) = if is_method {
+            if declaration == "static {}" {
+                (0x0008, String::from("<clinit>"))
+            } else {
+                let parameters = declaration.find('(').unwrap_or(declaration.len());
+                let name = declaration[..parameters].rsplit(' ').next().unwrap_or("");
+                let name = if name == class_name { "<init>" } else { name };
+                let mut modifiers = parse_modifiers(declaration, METHOD_MODIFIERS);
+                if declaration[parameters..].contains("...") {
+                    modifiers |= ACC_VARARGS;
+                }
+                (modifiers, String::from(name))
+            }
+        } else {
+            let name = declaration.rsplit(' ').next().unwrap_or("");
+            (parse_modifiers(declaration, FIELD_MODIFIERS), String::from(name))
+        };
+        let mut descriptor = None;
+        let mut access_flags = modifiers;
+        let mut attributes = Vec::new();
+        for child in &line.children {
+            if child.text.starts_with("descriptor:") {
+                descriptor = Some(key_value(child)?.1);
+            } else if child.text.starts_with("flags:") {
+                access_flags = parse_flags(key_value(child)?.1, child)?;
+            } else {
+                attributes.push(child);
+            }
+        }
+        let descriptor = match descriptor {
+            Some(descriptor) => descriptor,
+            None => return syntax_error(line, format!("Missing descriptor of {}", name)),
+        };
+        output.write_u16(access_flags);
+        output.write_u16(self.utf8(&name)?);
+        output.write_u16(self.utf8(descriptor)?);
+        self.write_attributes(&attributes, None, output)
+    }
+
+    fn write_attributes(
+        &mut self,
+        lines: &[&Line],
+        labels: Option<&Labels>,
+        output: &mut ClassWriter,
+    ) -> Result<(), AssemblyError> {
+        output.write_u16(lines.len() as u16);
+        for line in lines {
+            self.write_attribute(line, labels, output)?;
+        }
+        Ok(())
+    }
+
+    /// Writes an attribute, with `labels` being those of the code it belongs to, if any
+    fn write_attribute(&mut self, line: &Line, labels: Option<&Labels>, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        let (name, value) = match line.text.find(':') {
+            Some(colon) => (&line.text[..colon], trim(&line.text[colon + 1..])),
+            None => return syntax_error(line, format!("Expected an attribute, found {:?}", line.text)),
+        };
+        let children = &line.children;
+        let mut info = ClassWriter::new();
+        match name {
+            // Unknown and malformed attributes are given by their bytes, whatever their name
+            _ if is_raw_attribute(value) => {
+                for child in children {
+                    for byte in child.text.split_whitespace() {
+                        match u8::from_str_radix(byte, 16) {
+                            Ok(byte) => info.write_u8(byte),
+                            Err(_) => return syntax_error(child, format!("Invalid byte {:?}", byte)),
+                        }
+                    }
+                }
+            }
+            "ConstantValue" => info.write_u16(self.operand(value, line)?),
+            "Code" => self.write_code(line, &mut info)?,
+            "StackMapTable" => {
+                info.write_u16(children.len() as u16);
+                for frame in children {
+                    self.write_frame(frame, labels, &mut info)?;
+                }
+            }
+            "Exceptions" => {
+                let mut exceptions = Vec::new();
+                for child in children {
+                    let names = match child.text.find("throws ") {
+                        Some(0) => &child.text[7..],
+                        _ => return syntax_error(child, format!("Expected `throws`, found {:?}", child.text)),
+                    };
+                    for name in names.split(',') {
+                        exceptions.push(self.class(&name.trim().replace('.', "/"))?);
+                    }
+                }
+                info.write_u16_table(&exceptions);
+            }
+            "InnerClasses" => {
+                info.write_u16(children.len() as u16);
+                for child in children {
+                    self.write_inner_class(child, &mut info)?;
+                }
+            }
+            "EnclosingMethod" => {
+                let (class, method) = split_pair(split_comment(value).0.trim(), '.', line)?;
+                info.write_u16(parse_index(class, line)?);
+                info.write_u16(parse_index(method, line)?);
+            }
+            "Synthetic" | "Deprecated" => {}
+            "Signature" => info.write_u16(self.utf8_operand(value, line)?),
+            "SourceFile" => {
+                let source_file = unquote(value, line)?;
+                info.write_u16(self.utf8(&source_file)?);
+            }
+            "SourceDebugExtension" => {
+                for child in children {
+                    info.write_bytes(child.text.as_bytes());
+                    info.write_u8(b'\n');
+                }
+            }
+            "LineNumberTable" => {
+                info.write_u16(children.len() as u16);
+                for child in children {
+                    let (line_number, pc) = match child.text.find("line ") {
+                        Some(0) => split_pair(&child.text[5..], ':', child)?,
+                        _ => return syntax_error(child, format!("Expected `line`, found {:?}", child.text)),
+                    };
+                    info.write_u16(pc_value(pc, labels, child)?);
+                    info.write_u16(parse(line_number, child)?);
+                }
+            }
+            "LocalVariableTable" | "LocalVariableTypeTable" => {
+                let mut rows = Vec::new();
+                flatten(children, &mut rows);
+                rows.retain(|row| !row.text.starts_with("Start"));
+                info.write_u16(rows.len() as u16);
+                for row in rows {
+                    let columns: Vec<&str> = row.text.split_whitespace().collect();
+                    if columns.len() != 5 {
+                        return syntax_error(row, format!("Expected a local variable, found {:?}", row.text));
+                    }
+                    info.write_u16(pc_value(columns[0], labels, row)?);
+                    info.write_u16(parse(columns[1], row)?);
+                    info.write_u16(self.utf8(&unescape(columns[3], row)?)?);
+                    info.write_u16(self.utf8(&unescape(columns[4], row)?)?);
+                    info.write_u16(parse(columns[2], row)?);
+                }
+            }
+            "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                self.write_annotations(children, &mut info)?;
+            }
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                info.write_u8(children.len() as u8);
+                for parameter in children {
+                    if !parameter.text.starts_with("parameter ") {
+                        return syntax_error(parameter, format!("Expected `parameter`, found {:?}", parameter.text));
+                    }
+                    self.write_annotations(&parameter.children, &mut info)?;
+                }
+            }
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                info.write_u16(children.len() as u16);
+                for child in children {
+                    self.write_type_annotation(child, labels, &mut info)?;
+                }
+            }
+            "AnnotationDefault" => match children.first() {
+                Some(child) if child.text.starts_with("default_value:") => {
+                    let mut scanner = Scanner::new(key_value(child)?.1, child);
+                    self.write_element_value(&mut scanner, &mut info)?;
+                    scanner.finish()?;
+                }
+                _ => return syntax_error(line, String::from("Expected `default_value:`")),
+            },
+            "BootstrapMethods" => {
+                info.write_u16(children.len() as u16);
+                for child in children {
+                    let method_ref = match child.text.split_whitespace().nth(1) {
+                        Some(method_ref) => parse_index(method_ref, child)?,
+                        None => return syntax_error(child, format!("Expected a bootstrap method, found {:?}", child.text)),
+                    };
+                    let mut arguments = Vec::new();
+                    for argument_list in &child.children {
+                        for argument in &argument_list.children {
+                            let index = argument.text.split_whitespace().next().unwrap_or("");
+                            arguments.push(parse_index(index, argument)?);
+                        }
+                    }
+                    info.write_u16(method_ref);
+                    info.write_u16_table(&arguments);
+                }
+            }
+            "Module" => self.write_module(line, &mut info)?,
+            "ModulePackages" => {
+                let mut packages = Vec::new();
+                for child in children {
+                    packages.push(parse_index(split_comment(child.text).0, child)?);
+                }
+                info.write_u16_table(&packages);
+            }
+            "ModuleMainClass" | "NestHost" => info.write_u16(self.operand(value, line)?),
+            "NestMembers" | "PermittedSubclasses" => {
+                let mut classes = Vec::new();
+                for child in children {
+                    let name = unquote(child.text, child)?;
+                    classes.push(self.class(&name)?);
+                }
+                info.write_u16_table(&classes);
+            }
+            "Record" => {
+                info.write_u16(children.len() as u16);
+                for component in children {
+                    self.write_record_component(component, &mut info)?;
+                }
+            }
+            "MethodParameters" => {
+                let mut rows = Vec::new();
+                flatten(children, &mut rows);
+                let flags_column = rows.iter()
+                    .find(|row| row.text.starts_with("Name"))
+                    .and_then(|header| header.text.find("Flags"));
+                rows.retain(|row| !row.text.starts_with("Name"));
+                info.write_u8(rows.len() as u8);
+                for row in rows {
+                    let mut words: Vec<&str> = row.text.split_whitespace().collect();
+                    let mut flags = Vec::new();
+                    match flags_column {
+                        // Names are padded out to the Flags column of the header, so a name such
+                        // as `final` is only a flag when it is in that column
+                        Some(column) if row.text.get(..column).is_some_and(|name| name.ends_with(' ')) => {
+                            words = row.text[..column].split_whitespace().collect();
+                            flags = row.text[column..].split_whitespace().collect();
+                        }
+                        // Otherwise the flags are the words after the name, which can't be left
+                        // out
+                        _ => {
+                            while words.len() > 1 && words.last().is_some_and(|word| flag_value(word, METHOD_PARAMETER_FLAGS).is_some()) {
+                                flags.push(words.pop().unwrap());
+                            }
+                        }
+                    }
+                    let mut access_flags = 0;
+                    for flag in flags {
+                        match flag_value(flag, METHOD_PARAMETER_FLAGS) {
+                            Some(flag) => access_flags |= flag,
+                            None => return syntax_error(row, format!("Unknown parameter flag {:?}", flag)),
+                        }
+                    }
+                    let name = words.join(" ");
+                    let name_index = if name == "<no name>" { 0 } else { self.utf8(&unescape(&name, row)?)? };
+                    info.write_u16(name_index);
+                    info.write_u16(access_flags);
+                }
+            }
+            _ => return syntax_error(line, format!("Unknown attribute {:?}", name)),
+        }
+        output.write_u16(self.utf8(name)?);
+        let info = info.into_bytes();
+        output.write_with_length(|output| output.write_bytes(&info));
+        Ok(())
+    }
+
+    fn write_code(&mut self, line: &Line, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        let (sizes, rest) = match line.children.split_first() {
+            Some((sizes, rest)) if sizes.text.starts_with("stack=") => (sizes, rest),
+            _ => return syntax_error(line, String::from("Expected `stack=`, `locals=` and `args_size=`")),
+        };
+        let mut max_stack = None;
+        let mut max_locals = None;
+        for size in sizes.text.split(',') {
+            let (key, value) = split_pair(size, '=', sizes)?;
+            match key.trim() {
+                "stack" => max_stack = Some(parse(value, sizes)?),
+                "locals" => max_locals = Some(parse(value, sizes)?),
+                "args_size" => {}
+                key => return syntax_error(sizes, format!("Unknown code size {:?}", key)),
+            }
+        }
+        let (max_stack, max_locals): (u16, u16) = match (max_stack, max_locals) {
+            (Some(max_stack), Some(max_locals)) => (max_stack, max_locals),
+            _ => return syntax_error(sizes, String::from("Missing `stack=` or `locals=`")),
+        };
+
+        // pcs are right-aligned, so the indentation of instructions can't be relied on
+        let mut lines = Vec::new();
+        flatten(&sizes.children, &mut lines);
+        let mut exception_table = None;
+        let mut attributes = Vec::new();
+        for child in rest {
+            if child.text == "Exception table:" {
+                exception_table = Some(child);
+            } else if is_attribute(child.text) {
+                attributes.push(child);
+            } else if exception_table.is_some() || !attributes.is_empty() {
+                return syntax_error(child, format!("Expected an attribute, found {:?}", child.text));
+            } else {
+                lines.push(child);
+                flatten(&child.children, &mut lines);
+            }
+        }
+        let mut instructions = Vec::new();
+        let mut lines = lines.into_iter();
+        while let Some(line) = lines.next() {
+            let mut cases = Vec::new();
+            if split_comment(line.text).0.trim_end().ends_with('{') {
+                loop {
+                    match lines.next() {
+                        Some(case) if case.text == "}" => break,
+                        Some(case) => cases.push(case),
+                        None => return syntax_error(line, String::from("Missing `}` at the end of the switch")),
+                    }
+                }
+            }
+            instructions.push((line, cases));
+        }
+
+        // The first pass lays out the code to find the pc of each label, the second encodes it
+        let mut labels = Labels::new();
+        let mut pc = 0;
+        for (line, cases) in &instructions {
+            let (label, instruction) = split_label(line.text);
+            if let Some(label) = label {
+                if labels.insert(String::from(label), pc).is_some() {
+                    return syntax_error(line, format!("Label {:?} is defined twice", label));
+                }
+            }
+            if let Some((mnemonic, operands)) = instruction {
+                let instruction = self.parse_instruction(mnemonic, operands, cases, line, pc, None)?;
+                let mut scratch = ClassWriter::new();
+                instruction.write(&mut scratch, pc);
+                pc += scratch.into_bytes().len() as u32;
+            }
+        }
+        let mut code = ClassWriter::new();
+        let mut pc = 0;
+        for (line, cases) in &instructions {
+            if let (_, Some((mnemonic, operands))) = split_label(line.text) {
+                let instruction = self.parse_instruction(mnemonic, operands, cases, line, pc, Some(&labels))?;
+                let mut bytes = ClassWriter::new();
+                instruction.write(&mut bytes, pc);
+                let bytes = bytes.into_bytes();
+                code.write_bytes(&bytes);
+                pc += bytes.len() as u32;
+            }
+        }
+
+        output.write_u16(max_stack);
+        output.write_u16(max_locals);
+        let code = code.into_bytes();
+        output.write_u32(code.len() as u32);
+        output.write_bytes(&code);
+        let mut rows = Vec::new();
+        if let Some(table) = exception_table {
+            flatten(&table.children, &mut rows);
+        }
+        rows.retain(|row| !row.text.starts_with("from"));
+        output.write_u16(rows.len() as u16);
+        for row in rows {
+            let columns: Vec<&str> = row.text.split_whitespace().collect();
+            let catch_type = match columns.get(3..) {
+                Some(["any"]) => 0,
+                Some(["Class", name]) => {
+                    let name = unquote(name, row)?;
+                    self.class(&name)?
+                }
+                _ => return syntax_error(row, format!("Expected an exception handler, found {:?}", row.text)),
+            };
+            output.write_u16(pc_value(columns[0], Some(&labels), row)?);
+            output.write_u16(pc_value(columns[1], Some(&labels), row)?);
+            output.write_u16(pc_value(columns[2], Some(&labels), row)?);
+            output.write_u16(catch_type);
+        }
+        self.write_attributes(&attributes, Some(&labels), output)
+    }
+
+    /// Parses an instruction at `pc`, leaving branch offsets as 0 if `labels` aren't known yet
+    ///
+    /// `cases` are the lines of a switch between its `{` and `}`
+    fn parse_instruction(
+        &mut self,
+        mnemonic: &str,
+        operands: &str,
+        cases: &[&Line],
+        line: &Line,
+        pc: u32,
+        labels: Option<&Labels>,
+    ) -> Result<Instruction, AssemblyError> {
+        let template = match self.templates.get(mnemonic) {
+            Some(template) => template.clone(),
+            None => return syntax_error(line, format!("Unknown instruction {:?}", mnemonic)),
+        };
+        let branch = |target: &str| -> Result<i32, AssemblyError> {
+            match labels {
+                Some(labels) => Ok(pc_value::<u32>(target, Some(labels), line)? as i32 - pc as i32),
+                None => Ok(0),
+            }
+        };
+        let short_branch = |target: &str| -> Result<i16, AssemblyError> {
+            let offset = branch(target)?;
+            if offset < i16::MIN as i32 || offset > i16::MAX as i32 {
+                return syntax_error(line, format!("Branch to {} is too far for {}", target, mnemonic));
+            }
+            Ok(offset as i16)
+        };
+        Ok(match template {
+            Bipush(_) => Bipush(parse(operands, line)?),
+            Sipush(_) => Sipush(parse(operands, line)?),
+            Ldc(_) => {
+                let index = self.operand(operands, line)?;
+                if index > 0xFF {
+                    return syntax_error(line, format!("Constant #{} is out of range of ldc, use ldc_w", index));
+                }
+                Ldc(index as u8)
+            }
+            LdcW(_) => LdcW(self.operand(operands, line)?),
+            Ldc2W(_) => Ldc2W(self.operand(operands, line)?),
+            Getstatic(_) => Getstatic(self.operand(operands, line)?),
+            Putstatic(_) => Putstatic(self.operand(operands, line)?),
+            Getfield(_) => Getfield(self.operand(operands, line)?),
+            Putfield(_) => Putfield(self.operand(operands, line)?),
+            Invokevirtual(_) => Invokevirtual(self.operand(operands, line)?),
+            Invokespecial(_) => Invokespecial(self.operand(operands, line)?),
+            Invokestatic(_) => Invokestatic(self.operand(operands, line)?),
+            New(_) => New(self.operand(operands, line)?),
+            Anewarray(_) => Anewarray(self.operand(operands, line)?),
+            Checkcast(_) => Checkcast(self.operand(operands, line)?),
+            Instanceof(_) => Instanceof(self.operand(operands, line)?),
+            Iload(_) => Iload(parse(operands, line)?),
+            Lload(_) => Lload(parse(operands, line)?),
+            Fload(_) => Fload(parse(operands, line)?),
+            Dload(_) => Dload(parse(operands, line)?),
+            Aload(_) => Aload(parse(operands, line)?),
+            Istore(_) => Istore(parse(operands, line)?),
+            Lstore(_) => Lstore(parse(operands, line)?),
+            Fstore(_) => Fstore(parse(operands, line)?),
+            Dstore(_) => Dstore(parse(operands, line)?),
+            Astore(_) => Astore(parse(operands, line)?),
+            Ret(_) => Ret(parse(operands, line)?),
+            Iinc { .. } => {
+                let (index, constant) = split_pair(operands, ',', line)?;
+                Iinc { index: parse(index, line)?, constant: parse(constant, line)? }
+            }
+            Ifeq(_) => Ifeq(short_branch(operands)?),
+            Ifne(_) => Ifne(short_branch(operands)?),
+            Iflt(_) => Iflt(short_branch(operands)?),
+            Ifge(_) => Ifge(short_branch(operands)?),
+            Ifgt(_) => Ifgt(short_branch(operands)?),
+            Ifle(_) => Ifle(short_branch(operands)?),
+            IfIcmpeq(_) => IfIcmpeq(short_branch(operands)?),
+            IfIcmpne(_) => IfIcmpne(short_branch(operands)?),
+            IfIcmplt(_) => IfIcmplt(short_branch(operands)?),
+            IfIcmpge(_) => IfIcmpge(short_branch(operands)?),
+            IfIcmpgt(_) => IfIcmpgt(short_branch(operands)?),
+            IfIcmple(_) => IfIcmple(short_branch(operands)?),
+            IfAcmpeq(_) => IfAcmpeq(short_branch(operands)?),
+            IfAcmpne(_) => IfAcmpne(short_branch(operands)?),
+            Goto(_) => Goto(short_branch(operands)?),
+            Jsr(_) => Jsr(short_branch(operands)?),
+            Ifnull(_) => Ifnull(short_branch(operands)?),
+            Ifnonnull(_) => Ifnonnull(short_branch(operands)?),
+            GotoW(_) => GotoW(branch(operands)?),
+            JsrW(_) => JsrW(branch(operands)?),
+            Tableswitch { .. } | Lookupswitch { .. } => {
+                let mut default = None;
+                let mut pairs = Vec::new();
+                for case in cases {
+                    let (key, target) = split_pair(case.text, ':', case)?;
+                    if key.trim() == "default" {
+                        default = Some(branch(target)?);
+                    } else {
+                        pairs.push((parse::<i32>(key, case)?, branch(target)?));
+                    }
+                }
+                let default = match default {
+                    Some(default) => default,
+                    None => return syntax_error(line, format!("{} is missing its default", mnemonic)),
+                };
+                if mnemonic == "lookupswitch" {
+                    Lookupswitch { default, pairs }
+                } else {
+                    let low = match pairs.first() {
+                        Some((low, _)) => *low,
+                        None => return syntax_error(line, String::from("tableswitch needs at least one case")),
+                    };
+                    if pairs.iter().enumerate().any(|(index, (key, _))| *key as i64 != low as i64 + index as i64) {
+                        return syntax_error(line, String::from("The cases of a tableswitch must be consecutive"));
+                    }
+                    let high = low + (pairs.len() - 1) as i32;
+                    let offsets = pairs.into_iter().map(|(_, offset)| offset).collect();
+                    Tableswitch { default, low, high, offsets }
+                }
+            }
+            Invokeinterface { .. } => {
+                let (reference, count) = match operands.rfind(',') {
+                    Some(comma) => (&operands[..comma], Some(&operands[comma + 1..])),
+                    None => (operands, None),
+                };
+                let index = self.operand(reference, line)?;
+                let count = match count {
+                    Some(count) => parse(count, line)?,
+                    None => return syntax_error(line, String::from("invokeinterface is missing its argument count")),
+                };
+                Invokeinterface { index, count }
+            }
+            Invokedynamic(_) => {
+                let reference = match operands.rfind(',') {
+                    Some(comma) => &operands[..comma],
+                    None => operands,
+                };
+                Invokedynamic(self.operand(reference, line)?)
+            }
+            Multianewarray { .. } => {
+                let (reference, dimensions) = match operands.rfind(',') {
+                    Some(comma) => (&operands[..comma], &operands[comma + 1..]),
+                    None => return syntax_error(line, String::from("multianewarray is missing its dimensions")),
+                };
+                Multianewarray { index: self.operand(reference, line)?, dimensions: parse(dimensions, line)? }
+            }
+            Newarray(_) => {
+                let atype = operands.trim();
+                match ARRAY_TYPES.iter().find(|(name, _)| *name == atype) {
+                    Some((_, atype)) => Newarray(*atype),
+                    None => Newarray(parse(atype.trim_end_matches("(unknown)"), line)?),
+                }
+            }
+            Wide(instruction) => Wide(match instruction {
+                WideInstruction::Iinc { .. } => {
+                    let (index, constant) = split_pair(operands, ',', line)?;
+                    WideInstruction::Iinc { index: parse(index, line)?, constant: parse(constant, line)? }
+                }
+                WideInstruction::Iload(_) => WideInstruction::Iload(parse(operands, line)?),
+                WideInstruction::Lload(_) => WideInstruction::Lload(parse(operands, line)?),
+                WideInstruction::Fload(_) => WideInstruction::Fload(parse(operands, line)?),
+                WideInstruction::Dload(_) => WideInstruction::Dload(parse(operands, line)?),
+                WideInstruction::Aload(_) => WideInstruction::Aload(parse(operands, line)?),
+                WideInstruction::Istore(_) => WideInstruction::Istore(parse(operands, line)?),
+                WideInstruction::Lstore(_) => WideInstruction::Lstore(parse(operands, line)?),
+                WideInstruction::Fstore(_) => WideInstruction::Fstore(parse(operands, line)?),
+                WideInstruction::Dstore(_) => WideInstruction::Dstore(parse(operands, line)?),
+                WideInstruction::Astore(_) => WideInstruction::Astore(parse(operands, line)?),
+                WideInstruction::Ret(_) => WideInstruction::Ret(parse(operands, line)?),
+            }),
+            instruction => {
+                if !operands.is_empty() {
+                    return syntax_error(line, format!("{} takes no operands", mnemonic));
+                }
+                instruction
+            }
+        })
+    }
+
+    fn write_frame(&mut self, line: &Line, labels: Option<&Labels>, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        let frame_type: u8 = match line.text.find("frame_type = ") {
+            Some(0) => parse(line.text[13..].split_whitespace().next().unwrap_or(""), line)?,
+            _ => return syntax_error(line, format!("Expected `frame_type =`, found {:?}", line.text)),
+        };
+        let mut offset_delta = None;
+        let mut locals = None;
+        let mut stack = None;
+        for child in &line.children {
+            let (key, value) = split_pair(child.text, '=', child)?;
+            match key.trim() {
+                "offset_delta" => offset_delta = Some(parse::<u16>(value, child)?),
+                "locals" => locals = Some((child, value.trim())),
+                "stack" => stack = Some((child, value.trim())),
+                key => return syntax_error(child, format!("Unknown frame field {:?}", key)),
+            }
+        }
+        output.write_u8(frame_type);
+        if frame_type >= 247 {
+            match offset_delta {
+                Some(offset_delta) => output.write_u16(offset_delta),
+                None => return syntax_error(line, String::from("Missing offset_delta")),
+            }
+        }
+        let locals = match locals {
+            Some((child, locals)) => self.verification_types(locals, labels, child)?,
+            None => Vec::new(),
+        };
+        let stack = match stack {
+            Some((child, stack)) => self.verification_types(stack, labels, child)?,
+            None => Vec::new(),
+        };
+        match frame_type {
+            0..=63 | 248..=251 => {}
+            64..=127 | 247 if stack.len() == 1 => output.write_bytes(&stack[0]),
+            252..=254 if locals.len() == frame_type as usize - 251 => {
+                for local in locals {
+                    output.write_bytes(&local);
+                }
+            }
+            255 => {
+                output.write_u16(locals.len() as u16);
+                for local in locals {
+                    output.write_bytes(&local);
+                }
+                output.write_u16(stack.len() as u16);
+                for item in stack {
+                    output.write_bytes(&item);
+                }
+            }
+            128..=246 => return syntax_error(line, format!("Reserved frame type {}", frame_type)),
+            _ => return syntax_error(line, format!("Wrong number of locals or stack items for frame type {}", frame_type)),
+        }
+        Ok(())
+    }
+
+    /// Encodes a list of verification types, eg `[ int, class java/lang/String ]`
+    fn verification_types(&mut self, text: &str, labels: Option<&Labels>, line: &Line) -> Result<Vec<Vec<u8>>, AssemblyError> {
+        if !text.starts_with('[') || !text.ends_with(']') {
+            return syntax_error(line, format!("Expected a list of types, found {:?}", text));
+        }
+        let mut types = Vec::new();
+        for verification_type in text[1..text.len() - 1].split(',').map(str::trim).filter(|text| !text.is_empty()) {
+            let mut output = ClassWriter::new();
+            match verification_type {
+                "top" => output.write_u8(0),
+                "int" => output.write_u8(1),
+                "float" => output.write_u8(2),
+                "double" => output.write_u8(3),
+                "long" => output.write_u8(4),
+                "null" => output.write_u8(5),
+                "this" => output.write_u8(6),
+                _ if verification_type.starts_with("class ") => {
+                    let name = unquote(&verification_type[6..], line)?;
+                    output.write_u8(7);
+                    output.write_u16(self.class(&name)?);
+                }
+                _ if verification_type.starts_with("uninitialized ") => {
+                    output.write_u8(8);
+                    output.write_u16(pc_value(&verification_type[14..], labels, line)?);
+                }
+                _ => return syntax_error(line, format!("Unknown verification type {:?}", verification_type)),
+            }
+            types.push(output.into_bytes());
+        }
+        Ok(types)
+    }
+
+    /// Writes a row of an InnerClasses attribute, eg `public static #12= #5 of #3;`
+    fn write_inner_class(&mut self, line: &Line, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        let code = split_comment(line.text).0.trim().trim_end_matches(';');
+        let mut access_flags = 0;
+        let mut raw_access_flags = None;
+        let mut inner_name_index = 0;
+        let mut inner_class_index = None;
+        let mut outer_class_index = 0;
+        let mut words = code.split_whitespace();
+        while let Some(word) = words.next() {
+            if word.starts_with("(0x") {
+                raw_access_flags = Some(parse_flags(word, line)?);
+            } else if let Some(flag) = flag_value(word, INNER_CLASS_MODIFIERS) {
+                access_flags |= flag;
+            } else if word.ends_with('=') {
+                inner_name_index = parse_index(word.trim_end_matches('='), line)?;
+            } else if word == "of" {
+                outer_class_index = parse_index(words.next().unwrap_or(""), line)?;
+            } else {
+                inner_class_index = Some(parse_index(word, line)?);
+            }
+        }
+        let inner_class_index = match inner_class_index {
+            Some(index) => index,
+            None => return syntax_error(line, String::from("Missing inner class")),
+        };
+        output.write_u16(inner_class_index);
+        output.write_u16(outer_class_index);
+        output.write_u16(inner_name_index);
+        // The raw flags are complete, the modifiers that follow them only describe them
+        output.write_u16(raw_access_flags.unwrap_or(access_flags));
+        Ok(())
+    }
+
+    fn write_record_component(&mut self, line: &Line, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        let name = line.text.trim_end_matches(';').rsplit(' ').next().unwrap_or("");
+        let mut descriptor = None;
+        let mut attributes = Vec::new();
+        for child in &line.children {
+            if child.text.starts_with("descriptor:") {
+                descriptor = Some(key_value(child)?.1);
+            } else {
+                attributes.push(child);
+            }
+        }
+        let descriptor = match descriptor {
+            Some(descriptor) => descriptor,
+            None => return syntax_error(line, format!("Missing descriptor of {}", name)),
+        };
+        output.write_u16(self.utf8(name)?);
+        output.write_u16(self.utf8(descriptor)?);
+        self.write_attributes(&attributes, None, output)
+    }
+
+    fn write_module(&mut self, line: &Line, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        let children = &line.children;
+        if children.len() != 7 {
+            return syntax_error(line, String::from(
+                "Expected the module, its version, and its requires, exports, opens, uses and provides"
+            ));
+        }
+        let (name, flags) = split_pair(split_comment(children[0].text).0.trim(), ',', &children[0])?;
+        output.write_u16(parse_index(name, &children[0])?);
+        output.write_u16(parse_hex(flags, &children[0])?);
+        output.write_u16(parse_index(split_comment(children[1].text).0, &children[1])?);
+
+        let requires = &children[2].children;
+        if !requires.len().is_multiple_of(2) {
+            return syntax_error(&children[2], String::from("Each requires must be followed by its version"));
+        }
+        output.write_u16((requires.len() / 2) as u16);
+        for pair in requires.chunks(2) {
+            let (module, flags) = split_pair(split_comment(pair[0].text).0.trim(), ',', &pair[0])?;
+            output.write_u16(parse_index(module, &pair[0])?);
+            output.write_u16(parse_hex(flags, &pair[0])?);
+            output.write_u16(parse_index(split_comment(pair[1].text).0, &pair[1])?);
+        }
+
+        for directives in &children[3..5] {
+            output.write_u16(directives.children.len() as u16);
+            for directive in &directives.children {
+                let (package, flags) = split_pair(split_comment(directive.text).0.trim(), ',', directive)?;
+                output.write_u16(parse_index(package, directive)?);
+                output.write_u16(parse_hex(flags, directive)?);
+                let mut modules = Vec::new();
+                for module in &directive.children {
+                    modules.push(parse_index(split_comment(module.text).0, module)?);
+                }
+                output.write_u16_table(&modules);
+            }
+        }
+
+        let mut uses = Vec::new();
+        for service in &children[5].children {
+            uses.push(parse_index(split_comment(service.text).0, service)?);
+        }
+        output.write_u16_table(&uses);
+
+        output.write_u16(children[6].children.len() as u16);
+        for provides in &children[6].children {
+            output.write_u16(parse_index(split_comment(provides.text).0, provides)?);
+            let mut implementations = Vec::new();
+            for implementation in &provides.children {
+                implementations.push(parse_index(split_comment(implementation.text).0, implementation)?);
+            }
+            output.write_u16_table(&implementations);
+        }
+        Ok(())
+    }
+
+    /// Writes the annotations of an attribute, each in the form `0: #12(#13=s#14)`
+    fn write_annotations(&mut self, lines: &[Line], output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        output.write_u16(lines.len() as u16);
+        for line in lines {
+            let mut scanner = Scanner::new(strip_list_index(line)?, line);
+            self.write_annotation(&mut scanner, output)?;
+            scanner.finish()?;
+        }
+        Ok(())
+    }
+
+    fn write_annotation(&mut self, scanner: &mut Scanner, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        output.write_u16(scanner.index()?);
+        scanner.expect('(')?;
+        let mut pairs = ClassWriter::new();
+        let mut count = 0u16;
+        if !scanner.accept(')') {
+            loop {
+                pairs.write_u16(scanner.index()?);
+                scanner.expect('=')?;
+                self.write_element_value(scanner, &mut pairs)?;
+                count += 1;
+                if scanner.accept(')') {
+                    break;
+                }
+                scanner.expect(',')?;
+            }
+        }
+        output.write_u16(count);
+        output.write_bytes(&pairs.into_bytes());
+        Ok(())
+    }
+
+    fn write_element_value(&mut self, scanner: &mut Scanner, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        let tag = scanner.next()?;
+        if !tag.is_ascii() {
+            return scanner.error(format!("Invalid element value tag {:?}", tag));
+        }
+        output.write_u8(tag as u8);
+        match tag {
+            'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' | 'c' => output.write_u16(scanner.index()?),
+            'e' => {
+                output.write_u16(scanner.index()?);
+                scanner.expect('.')?;
+                output.write_u16(scanner.index()?);
+            }
+            '@' => self.write_annotation(scanner, output)?,
+            '[' => {
+                let mut values = ClassWriter::new();
+                let mut count = 0u16;
+                if !scanner.accept(']') {
+                    loop {
+                        self.write_element_value(scanner, &mut values)?;
+                        count += 1;
+                        if scanner.accept(']') {
+                            break;
+                        }
+                        scanner.expect(',')?;
+                    }
+                }
+                output.write_u16(count);
+                output.write_bytes(&values.into_bytes());
+            }
+            tag => return scanner.error(format!("Invalid element value tag {:?}", tag)),
+        }
+        Ok(())
+    }
+
+    /// Writes a type annotation, eg `0: #12(): CAST, offset=9, type_index=0`
+    fn write_type_annotation(&mut self, line: &Line, labels: Option<&Labels>, output: &mut ClassWriter) -> Result<(), AssemblyError> {
+        let mut scanner = Scanner::new(strip_list_index(line)?, line);
+        let mut annotation = ClassWriter::new();
+        self.write_annotation(&mut scanner, &mut annotation)?;
+        let mut target = scanner.rest();
+        if !target.starts_with(':') {
+            return syntax_error(line, String::from("Expected the target of the type annotation"));
+        }
+        target = target[1..].trim();
+
+        let mut path = "";
+        if let Some(location) = target.find(", location=[") {
+            path = target[location + 12..].trim_end_matches(']');
+            target = &target[..location];
+        }
+        let mut table = None;
+        if let Some(start) = target.find(", {") {
+            table = Some(target[start + 3..].trim_end_matches('}'));
+            target = &target[..start];
+        }
+        let mut parts = target.split(", ");
+        let name = parts.next().unwrap_or("");
+        let target_type = match TYPE_ANNOTATION_TARGETS.iter().find(|(target, _)| *target == name) {
+            Some((_, target_type)) => *target_type,
+            None => return syntax_error(line, format!("Unknown type annotation target {:?}", name)),
+        };
+        let mut values = HashMap::new();
+        for part in parts {
+            let (key, value) = split_pair(part, '=', line)?;
+            values.insert(key.trim(), value.trim());
+        }
+        let value = |key: &str| -> Result<&str, AssemblyError> {
+            match values.get(key) {
+                Some(value) => Ok(*value),
+                None => syntax_error(line, format!("{} is missing {}", name, key)),
+            }
+        };
+
+        output.write_u8(target_type);
+        match target_type {
+            0x00 | 0x01 | 0x16 => output.write_u8(parse(value("param_index")?, line)?),
+            0x10 | 0x17 => output.write_u16(parse(value("type_index")?, line)?),
+            0x11 | 0x12 => {
+                output.write_u8(parse(value("param_index")?, line)?);
+                output.write_u8(parse(value("bound_index")?, line)?);
+            }
+            0x40 | 0x41 => {
+                let ranges: Vec<&str> = table.map_or(Vec::new(), |table| {
+                    table.split(';').map(str::trim).filter(|range| !range.is_empty()).collect()
+                });
+                output.write_u16(ranges.len() as u16);
+                for range in ranges {
+                    let mut entry = HashMap::new();
+                    for part in range.split(',') {
+                        let (key, value) = split_pair(part, '=', line)?;
+                        entry.insert(key.trim(), value.trim());
+                    }
+                    match (entry.get("start_pc"), entry.get("length"), entry.get("index")) {
+                        (Some(start_pc), Some(length), Some(index)) => {
+                            output.write_u16(pc_value(start_pc, labels, line)?);
+                            output.write_u16(parse(length, line)?);
+                            output.write_u16(parse(index, line)?);
+                        }
+                        _ => return syntax_error(line, format!("Invalid local variable range {:?}", range)),
+                    }
+                }
+            }
+            0x42 => output.write_u16(parse(value("exception_index")?, line)?),
+            0x43..=0x46 => output.write_u16(pc_value(value("offset")?, labels, line)?),
+            0x47..=0x4B => {
+                output.write_u16(pc_value(value("offset")?, labels, line)?);
+                output.write_u8(parse(value("type_index")?, line)?);
+            }
+            _ => {}
+        }
+
+        let path: Vec<&str> = path.split(',').map(str::trim).filter(|entry| !entry.is_empty()).collect();
+        output.write_u8(path.len() as u8);
+        for entry in path {
+            match entry {
+                "ARRAY" => output.write_bytes(&[0, 0]),
+                "INNER_TYPE" => output.write_bytes(&[1, 0]),
+                "WILDCARD" => output.write_bytes(&[2, 0]),
+                _ if entry.starts_with("TYPE_ARGUMENT(") => {
+                    output.write_u8(3);
+                    output.write_u8(parse(entry[14..].trim_end_matches(')'), line)?);
+                }
+                _ => return syntax_error(line, format!("Unknown type path {:?}", entry)),
+            }
+        }
+        output.write_bytes(&annotation.into_bytes());
+        Ok(())
+    }
+}
+
+/// The parts of a class declaration, eg `public class Foo extends Bar implements Baz`
+struct ClassDeclaration {
+    /// `class`, `interface` or `module`
+    kind: String,
+    /// The name as the disassembler shows it, eg `java.lang.Object`
+    name: String,
+    access_flags: u16,
+    /// The binary names of the classes named by the declaration
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+}
+
+impl ClassDeclaration {
+    fn new(line: &Line) -> Result<ClassDeclaration, AssemblyError> {
+        // Type arguments don't appear in the class file outside the Signature attribute
+        let mut text = String::new();
+        let mut depth = 0;
+        for character in line.text.chars() {
+            match character {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ if depth == 0 => text.push(character),
+                _ => {}
+            }
+        }
+        let mut words = text.split_whitespace();
+        let mut access_flags = 0;
+        let kind = loop {
+            match words.next() {
+                Some(kind @ "class") | Some(kind @ "interface") | Some(kind @ "module") => break kind,
+                Some(word) => match flag_value(word, CLASS_MODIFIERS) {
+                    Some(flag) => access_flags |= flag,
+                    None => return syntax_error(line, format!("Unknown class modifier {:?}", word)),
+                },
+                None => return syntax_error(line, String::from("Expected `class`, `interface` or `module`")),
+            }
+        };
+        access_flags |= match kind {
+            "class" => ACC_SUPER,
+            "interface" => ACC_INTERFACE | ACC_ABSTRACT,
+            _ => ACC_MODULE,
+        };
+        let name = match words.next() {
+            Some(name) => String::from(name),
+            None => return syntax_error(line, String::from("Missing class name")),
+        };
+
+        let mut extends = Vec::new();
+        let mut implements = Vec::new();
+        let mut list = None;
+        for word in words {
+            match word {
+                "extends" => list = Some(&mut extends),
+                "implements" => list = Some(&mut implements),
+                _ => match list {
+                    Some(ref mut list) => list.extend(
+                        word.split(',').filter(|name| !name.is_empty()).map(|name| name.replace('.', "/"))
+                    ),
+                    None => return syntax_error(line, format!("Unexpected {:?} in class declaration", word)),
+                },
+            }
+        }
+        let (super_class, interfaces) = if kind == "interface" {
+            (None, extends)
+        } else {
+            (extends.into_iter().next(), implements)
+        };
+        Ok(ClassDeclaration {
+            kind: String::from(kind),
+            name,
+            access_flags,
+            super_class,
+            interfaces,
+        })
+    }
+}
+
+/// Reads through the raw form of annotations, eg `#12(#13=s#14,#15=[I#16])`
+struct Scanner<'t, 'l: 't> {
+    text: &'t str,
+    position: usize,
+    line: &'t Line<'l>,
+}
+
+impl<'t, 'l> Scanner<'t, 'l> {
+    fn new(text: &'t str, line: &'t Line<'l>) -> Scanner<'t, 'l> {
+        Scanner { text, position: 0, line }
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, AssemblyError> {
+        syntax_error(self.line, format!("{} at column {} of {:?}", message, self.position, self.text))
+    }
+
+    fn next(&mut self) -> Result<char, AssemblyError> {
+        match self.text[self.position..].chars().next() {
+            Some(character) => {
+                self.position += character.len_utf8();
+                Ok(character)
+            }
+            None => self.error(String::from("Unexpected end")),
+        }
+    }
+
+    /// Skips over `expected` if it comes next
+    fn accept(&mut self, expected: char) -> bool {
+        if self.text[self.position..].starts_with(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), AssemblyError> {
+        if self.accept(expected) {
+            Ok(())
+        } else {
+            self.error(format!("Expected {:?}", expected))
+        }
+    }
+
+    /// Reads a constant pool index, eg `#12`
+    fn index(&mut self) -> Result<u16, AssemblyError> {
+        self.expect('#')?;
+        let rest = &self.text[self.position..];
+        let length = rest.find(|character: char| !character.is_ascii_digit()).unwrap_or(rest.len());
+        match rest[..length].parse() {
+            Ok(index) => {
+                self.position += length;
+                Ok(index)
+            }
+            Err(_) => self.error(String::from("Expected a constant pool index")),
+        }
+    }
+
+    fn rest(&self) -> &'t str {
+        &self.text[self.position..]
+    }
+
+    fn finish(&self) -> Result<(), AssemblyError> {
+        if self.rest().trim().is_empty() {
+            Ok(())
+        } else {
+            self.error(String::from("Unexpected text"))
+        }
+    }
+}
+
+/// Collects lines and all of their descendants in order, for sections whose indentation
+/// doesn't reflect their structure
+fn flatten<'l, 's>(lines: &'l [Line<'s>], output: &mut Vec<&'l Line<'s>>) {
+    for line in lines {
+        output.push(line);
+        flatten(&line.children, output);
+    }
+}
+
+/// Whether a line of a Code attribute starts one of its attributes rather than being an
+/// instruction
+fn is_attribute(text: &str) -> bool {
+    match text.find(':') {
+        Some(colon) => {
+            let value = text[colon + 1..].trim();
+            ATTRIBUTE_NAMES.contains(&&text[..colon]) || is_raw_attribute(value)
+        }
+        None => false,
+    }
+}
+
+/// Whether the value of an attribute header, eg `length = 0x4 (unknown attribute)`, introduces
+/// the bytes of an attribute the disassembler couldn't decode
+fn is_raw_attribute(value: &str) -> bool {
+    value.starts_with("length = 0x")
+        && (value.ends_with("(unknown attribute)") || value.ends_with("(malformed attribute)"))
+}
+
+/// Splits the label off an instruction, eg `12: iload_1`, giving the mnemonic and operands of
+/// the instruction if there is one
+fn split_label(text: &str) -> (Option<&str>, Option<(&str, &str)>) {
+    let code = split_comment(text).0.trim();
+    let (label, rest) = match code.find(':') {
+        Some(colon) if !code[..colon].contains(char::is_whitespace) && colon > 0 => {
+            (Some(&code[..colon]), code[colon + 1..].trim())
+        }
+        _ => (None, code),
+    };
+    if rest.is_empty() {
+        return (label, None);
+    }
+    let instruction = match rest.find(char::is_whitespace) {
+        Some(space) => (&rest[..space], rest[space..].trim()),
+        None => (rest, ""),
+    };
+    (label, Some(instruction))
+}
+
+/// Trims spaces and tabs, but not other whitespace, which can end a string
+fn trim(text: &str) -> &str {
+    text.trim_matches(|character| character == ' ' || character == '\t')
+}
+
+/// Splits the text from a `//` comment off a line, ignoring any inside quotes
+fn split_comment(text: &str) -> (&str, Option<&str>) {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, character) in text.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '/' if !in_quotes && text[index..].starts_with("//") => {
+                return (&text[..index], Some(&text[index + 2..]));
+            }
+            _ => {}
+        }
+    }
+    (text, None)
+}
+
+/// Splits a line such as `major version: 52` into its key and value
+fn key_value<'t>(line: &Line<'t>) -> Result<(&'t str, &'t str), AssemblyError> {
+    match line.text.find(':') {
+        Some(colon) => Ok((line.text[..colon].trim(), line.text[colon + 1..].trim())),
+        None => syntax_error(line, format!("Expected `key: value`, found {:?}", line.text)),
+    }
+}
+
+/// Strips the index off an item of a list, eg `0: #12()`
+fn strip_list_index<'t>(line: &Line<'t>) -> Result<&'t str, AssemblyError> {
+    match line.text.find(": ") {
+        Some(colon) => Ok(&line.text[colon + 2..]),
+        None => syntax_error(line, format!("Expected `index: value`, found {:?}", line.text)),
+    }
+}
+
+fn split_pair<'t>(text: &'t str, separator: char, line: &Line) -> Result<(&'t str, &'t str), AssemblyError> {
+    match text.find(separator) {
+        Some(position) => Ok((&text[..position], &text[position + separator.len_utf8()..])),
+        None => syntax_error(line, format!("Expected {:?} in {:?}", separator, text)),
+    }
+}
+
+fn parse<T: FromStr>(text: &str, line: &Line) -> Result<T, AssemblyError> {
+    match text.trim().parse() {
+        Ok(value) => Ok(value),
+        Err(_) => syntax_error(line, format!("Invalid number {:?}", text.trim())),
+    }
+}
+
+/// Parses a floating point number as the disassembler writes it, including `NaN` and
+/// `Infinity`
+fn parse_float(text: &str, line: &Line) -> Result<f64, AssemblyError> {
+    match text.trim() {
+        "NaN" => Ok(f64::NAN),
+        "Infinity" => Ok(f64::INFINITY),
+        "-Infinity" => Ok(f64::NEG_INFINITY),
+        text => parse(text, line),
+    }
+}
+
+fn parse_hex(text: &str, line: &Line) -> Result<u16, AssemblyError> {
+    let text = text.trim();
+    match u16::from_str_radix(text.trim_start_matches("0x"), 16) {
+        Ok(value) => Ok(value),
+        Err(_) => syntax_error(line, format!("Invalid hexadecimal number {:?}", text)),
+    }
+}
+
+/// Parses access flags as the disassembler writes them, eg `(0x0021) ACC_PUBLIC, ACC_SUPER`
+fn parse_flags(text: &str, line: &Line) -> Result<u16, AssemblyError> {
+    match (text.find("(0x"), text.find(')')) {
+        (Some(start), Some(end)) if start < end => parse_hex(&text[start + 3..end], line),
+        _ => syntax_error(line, format!("Expected flags such as `(0x0001)`, found {:?}", text)),
+    }
+}
+
+/// Parses a constant pool index, eg `#12`
+fn parse_index(text: &str, line: &Line) -> Result<u16, AssemblyError> {
+    let text = text.trim();
+    if !text.starts_with('#') {
+        return syntax_error(line, format!("Expected a constant pool index, found {:?}", text));
+    }
+    parse(&text[1..], line)
+}
+
+fn parse_index_pair(text: &str, separator: char, line: &Line) -> Result<(u16, u16), AssemblyError> {
+    let (first, second) = split_pair(text, separator, line)?;
+    Ok((parse_index(first, line)?, parse_index(second, line)?))
+}
+
+/// Parses a name and descriptor, eg `"<init>":()V`
+fn parse_name_and_type(text: &str, line: &Line) -> Result<(String, String), AssemblyError> {
+    let (name, rest) = take_name(text, &[':'], line)?;
+    if !rest.starts_with(':') {
+        return syntax_error(line, format!("Expected a name and type, found {:?}", text));
+    }
+    Ok((name, String::from(&rest[1..])))
+}
+
+/// Reads a name, either quoted or up to one of `terminators`, giving the rest of the text
+fn take_name<'t>(text: &'t str, terminators: &[char], line: &Line) -> Result<(String, &'t str), AssemblyError> {
+    if text.starts_with('"') {
+        let mut escaped = false;
+        for (index, character) in text.char_indices().skip(1) {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => return Ok((unescape(&text[1..index], line)?, &text[index + 1..])),
+                _ => {}
+            }
+        }
+        return syntax_error(line, format!("Unterminated quote in {:?}", text));
+    }
+    let end = text.find(terminators).unwrap_or(text.len());
+    Ok((String::from(&text[..end]), &text[end..]))
+}
+
+/// Finds the pc a label or number refers to
+fn pc_value<T: FromStr>(text: &str, labels: Option<&Labels>, line: &Line) -> Result<T, AssemblyError> {
+    let text = text.trim();
+    match labels.and_then(|labels| labels.get(text)) {
+        Some(pc) => parse(&pc.to_string(), line),
+        None => match text.parse() {
+            Ok(pc) => Ok(pc),
+            Err(_) => syntax_error(line, format!("Unknown label {:?}", text)),
+        },
+    }
+}
+
+fn flag_value(word: &str, flags: &[(&str, u16)]) -> Option<u16> {
+    flags.iter().find(|(name, _)| *name == word).map(|(_, flag)| *flag)
+}
+
+/// The access flags given by the modifiers at the start of a declaration
+fn parse_modifiers(declaration: &str, flags: &[(&str, u16)]) -> u16 {
+    declaration.split_whitespace()
+        .map_while(|word| flag_value(word, flags))
+        .fold(0, |access_flags, flag| access_flags | flag)
+}
+
+/// Removes the quotes from a name that the disassembler quoted, eg `"[I"`
+fn unquote(text: &str, line: &Line) -> Result<String, AssemblyError> {
+    let text = text.trim();
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        unescape(&text[1..text.len() - 1], line)
+    } else {
+        unescape(text, line)
+    }
+}
+
+/// Undoes the escaping of a Java string literal
+fn unescape(text: &str, line: &Line) -> Result<String, AssemblyError> {
+    match String::from_utf16(&unescape_units(text, line)?) {
+        Ok(string) => Ok(string),
+        Err(_) => syntax_error(line, format!("Unpaired surrogate in {:?}", text)),
+    }
+}
+
+/// Undoes the escaping of a Java string literal, giving its UTF-16 code units so that escaped
+/// unpaired surrogates are kept
+fn unescape_units(text: &str, line: &Line) -> Result<Vec<u16>, AssemblyError> {
+    let mut units = Vec::with_capacity(text.len());
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        let unescaped = match character {
+            '\\' => match characters.next() {
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('b') => '\u{8}',
+                Some('f') => '\u{c}',
+                Some('"') => '"',
+                Some('\'') => '\'',
+                Some('\\') => '\\',
+                Some('u') => {
+                    let digits: String = characters.by_ref().take(4).collect();
+                    match u16::from_str_radix(&digits, 16) {
+                        Ok(unit) if digits.len() == 4 => {
+                            units.push(unit);
+                            continue;
+                        }
+                        _ => return syntax_error(line, format!("Invalid escape \\u{}", digits)),
+                    }
+                }
+                other => {
+                    let other = other.map_or(String::new(), String::from);
+                    return syntax_error(line, format!("Invalid escape \\{}", other));
+                }
+            },
+            character => character,
+        };
+        let mut buffer = [0; 2];
+        units.extend_from_slice(unescaped.encode_utf16(&mut buffer));
+    }
+    Ok(units)
+}
+
+#[cfg(test)]
+mod tests {
+    use assembler::assemble;
+    use class_file::ClassFile;
+    use disassembler::disassemble;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use typed_arena::Arena;
+
+    /// Collects the javap listings under a directory, each beside the class file it describes
+    fn listings(dir: &Path, found: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                listings(&path, found);
+            } else if path.extension().is_some_and(|extension| extension == "javap") {
+                found.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixtures_are_assembled_from_their_listings() {
+        let mut found = Vec::new();
+        listings(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"), &mut found);
+        assert!(!found.is_empty());
+        for listing in found {
+            let source = fs::read_to_string(&listing).unwrap();
+            let bytes = fs::read(listing.with_extension("class")).unwrap();
+            assert_eq!(assemble(&source).unwrap(), bytes, "{}", listing.display());
+        }
+    }
+
+    /// Disassembles a class file and assembles it again, checking that both give the same bytes
+    fn round_trip(bytes: &[u8]) -> String {
+        let strings = Arena::new();
+        let class = ClassFile::from_bytes(bytes, &strings).unwrap();
+        let mut output = Vec::new();
+        disassemble(&class, &mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(assemble(&text).unwrap(), bytes, "{}", text);
+        text
+    }
+
+    #[test]
+    fn round_trips_nested_interfaces_and_enums() {
+        let bytes = assemble("public class p.Outer
+  minor version: 0
+  major version: 61
+Constant pool:
+   #1 = Class              #2             // p/Outer
+   #2 = Utf8               p/Outer
+   #3 = Class              #4             // java/lang/Object
+   #4 = Utf8               java/lang/Object
+   #5 = Class              #6             // p/Outer$Listener
+   #6 = Utf8               p/Outer$Listener
+   #7 = Utf8               Listener
+   #8 = Class              #9             // p/Outer$Color
+   #9 = Utf8               p/Outer$Color
+  #10 = Utf8               Color
+  #11 = Class              #12            // p/Outer$Marker
+  #12 = Utf8               p/Outer$Marker
+  #13 = Utf8               Marker
+  #14 = Class              #15            // p/Outer$1
+  #15 = Utf8               p/Outer$1
+  #16 = Utf8               InnerClasses
+{
+}
+InnerClasses:
+  public static interface abstract #7= #5 of #1;
+  public static final enum #10= #8 of #1;
+  static interface abstract annotation #13= #11 of #1;
+  (0x0820) #14;
+").unwrap();
+        let text = round_trip(&bytes);
+        assert!(text.contains("(0x0609) public static interface abstract #7= #5 of #1;"));
+        assert!(text.contains("(0x4019) public static final enum #10= #8 of #1;"));
+        assert!(text.contains("(0x2608) static interface abstract annotation #13= #11 of #1;"));
+        assert!(text.contains("(0x0820) #14;"));
+    }
+
+    #[test]
+    fn round_trips_parameter_names_that_look_like_flags() {
+        let bytes = assemble("public abstract class p.A
+  minor version: 0
+  major version: 61
+{
+  public abstract void m(int, int, int, int);
+    descriptor: (IIII)V
+    MethodParameters:
+      Name                           Flags
+      final                          synthetic
+      synthetic
+      aParameterNameLongerThanTheFlagsColumn final
+      <no name>                      mandated
+}
+").unwrap();
+        let strings = Arena::new();
+        let class = ClassFile::from_bytes(&bytes, &strings).unwrap();
+        let parameters = class.get_methods()[0].get_parameters().unwrap();
+        let names: Vec<_> = parameters.iter().map(|parameter| parameter.get_name()).collect();
+        assert_eq!(names, [
+            Some("final"),
+            Some("synthetic"),
+            Some("aParameterNameLongerThanTheFlagsColumn"),
+            None,
+        ]);
+        round_trip(&bytes);
+    }
+}
@@ -1,23 +1,63 @@
 use attribute::attribute_info_Data::*;
 use attribute::element_value_data::*;
+use attribute::target_info::*;
 use attribute::stack_map_frame_data::*;
 use attribute::verification_type_info_data::*;
-use byteorder::BigEndian;
-use byteorder::ReadBytesExt;
 use class_file::ClassLoadingError;
-use constant_pool::cp_info::*;
+use class_file::ClassLoadingError::ClassFormatError;
+use class_reader::ClassReader;
+use class_writer::ClassWriter;
 use constant_pool::ConstantPool;
-use std::io::Read;
+use module;
+use module::{module_exports, module_opens, module_provides, module_requires};
+use record::record_component_info;
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::fmt;
+use std::rc::Rc;
 
-#[derive(Debug)]
-pub struct attribute_info {
+#[derive(Debug, Clone)]
+pub struct attribute_info<'a> {
     attribute_name_index: u16,
     attribute_length: u32,
-    info: attribute_info_Data,
+    /// Empty until first accessed if the attribute was parsed lazily
+    info: OnceCell<attribute_info_Data<'a>>,
+    /// The class file a lazily parsed attribute is decoded from
+    source: Option<Rc<AttributeSource<'a>>>,
+    /// byte offset of the attribute within the class file, for reporting errors found later
+    offset: usize,
 }
 
-#[derive(Debug)]
-pub enum attribute_info_Data {
+/// The bytes and constant pool of a class file, kept so that its attributes can be decoded
+/// when they are first accessed rather than when the class is parsed
+pub struct AttributeSource<'a> {
+    bytes: &'a [u8],
+    constant_pool: Rc<ConstantPool<'a>>,
+    major_version: u16,
+}
+
+impl<'a> AttributeSource<'a> {
+    pub fn new(
+        bytes: &'a [u8],
+        constant_pool: Rc<ConstantPool<'a>>,
+        major_version: u16,
+    ) -> AttributeSource<'a> {
+        AttributeSource {
+            bytes,
+            constant_pool,
+            major_version,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for AttributeSource<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AttributeSource({} bytes)", self.bytes.len())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum attribute_info_Data<'a> {
     ConstantValue_attribute {
         constantvalue_index: u16,
     },
@@ -30,7 +70,7 @@ pub enum attribute_info_Data {
         exception_table_length: u16,
         exception_table: Vec<exception_info>,
         attributes_count: u16,
-        attributes: Vec<attribute_info>,
+        attributes: Vec<attribute_info<'a>>,
     },
 
     StackMapTable_attribute {
@@ -113,29 +153,109 @@ pub enum attribute_info_Data {
         bootstrap_methods: Vec<bootstrap_method>,
     },
 
-    RuntimeVisibleTypeAnnotations {},
+    Module_attribute {
+        module_name_index: u16,
+        module_flags: u16,
+        module_version_index: u16,
+        requires_count: u16,
+        requires: Vec<module_requires>,
+        exports_count: u16,
+        exports: Vec<module_exports>,
+        opens_count: u16,
+        opens: Vec<module_opens>,
+        uses_count: u16,
+        uses_index: Vec<u16>,
+        provides_count: u16,
+        provides: Vec<module_provides>,
+    },
+
+    ModulePackages_attribute {
+        package_count: u16,
+        package_index: Vec<u16>,
+    },
+
+    ModuleMainClass_attribute {
+        main_class_index: u16,
+    },
+
+    NestHost_attribute {
+        host_class_index: u16,
+    },
+
+    NestMembers_attribute {
+        number_of_classes: u16,
+        classes: Vec<u16>,
+    },
+
+    Record_attribute {
+        components_count: u16,
+        components: Vec<record_component_info<'a>>,
+    },
 
-    RuntimeInvisibleTypeAnnotations {},
+    PermittedSubclasses_attribute {
+        number_of_classes: u16,
+        classes: Vec<u16>,
+    },
+
+    MethodParameters_attribute {
+        parameters_count: u8,
+        parameters: Vec<method_parameter>,
+    },
+
+    RuntimeVisibleTypeAnnotations_attribute {
+        num_annotations: u16,
+        annotations: Vec<type_annotation>,
+    },
+
+    RuntimeInvisibleTypeAnnotations_attribute {
+        num_annotations: u16,
+        annotations: Vec<type_annotation>,
+    },
 
     Unknown_attribute {
         info: Vec<u8>,
     },
+
+    /// A lazily parsed attribute whose contents turned out to be malformed when first accessed
+    /// through `attribute_info::get_data`, kept as they were read along with the reason
+    Malformed_attribute {
+        info: Vec<u8>,
+        error: ClassLoadingError,
+    },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct bootstrap_method {
     bootstrap_method_ref: u16,
     num_bootstrap_arguments: u16,
     bootstrap_arguments: Vec<u16>,
 }
 
-#[derive(Debug)]
+impl bootstrap_method {
+    /// constant_pool index of the `CONSTANT_MethodHandle_info` used as the bootstrap method
+    pub fn get_method_ref(&self) -> u16 {
+        self.bootstrap_method_ref
+    }
+
+    /// constant_pool indices of the static arguments passed to the bootstrap method
+    pub fn get_arguments(&self) -> &[u16] {
+        &self.bootstrap_arguments
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct annotation_list {
     num_annotations: u16,
     annotations: Vec<annotation>,
 }
 
-#[derive(Debug)]
+impl annotation_list {
+    pub fn get_annotations(&self) -> &[annotation] {
+        &self.annotations
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct annotation {
     type_index: u16,
     num_element_value_pairs: u16,
@@ -143,12 +263,12 @@ pub struct annotation {
 }
 
 impl annotation {
-    pub fn new(input: &mut Read) -> Result<annotation, ClassLoadingError> {
-        let type_index = input.read_u16::<BigEndian>()?;
-        let num_element_value_pairs = input.read_u16::<BigEndian>()?;
+    pub fn new(input: &mut ClassReader) -> Result<annotation, ClassLoadingError> {
+        let type_index = input.read_u16()?;
+        let num_element_value_pairs = input.read_u16()?;
         let mut element_value_pairs = Vec::with_capacity(num_element_value_pairs as usize);
         for _ in 0..num_element_value_pairs {
-            let element_name_index = input.read_u16::<BigEndian>()?;
+            let element_name_index = input.read_u16()?;
             let value = element_value::new(input)?;
             element_value_pairs.push(element_value_pair {
                 element_name_index,
@@ -161,52 +281,360 @@ impl annotation {
             element_value_pairs,
         })
     }
+
+    /// constant_pool index of a `CONSTANT_Utf8_info` that is the field descriptor of the
+    /// annotation interface
+    pub fn get_type_index(&self) -> u16 {
+        self.type_index
+    }
+
+    pub fn get_element_value_pairs(&self) -> &[element_value_pair] {
+        &self.element_value_pairs
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.type_index);
+        output.write_u16(self.element_value_pairs.len() as u16);
+        for pair in self.element_value_pairs.iter() {
+            output.write_u16(pair.element_name_index);
+            pair.value.write(output);
+        }
+    }
+}
+
+/// An annotation on a use of a type
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.20>
+#[derive(Debug, Clone)]
+pub struct type_annotation {
+    target_type: u8,
+    target_info: target_info,
+    target_path: type_path,
+    annotation: annotation,
+}
+
+impl type_annotation {
+    pub fn new(input: &mut ClassReader) -> Result<type_annotation, ClassLoadingError> {
+        let target_type = input.read_u8()?;
+        let target_info = match target_type {
+            0x00 | 0x01 => type_parameter_target {
+                type_parameter_index: input.read_u8()?,
+            },
+            0x10 => supertype_target {
+                supertype_index: input.read_u16()?,
+            },
+            0x11 | 0x12 => {
+                let type_parameter_index = input.read_u8()?;
+                let bound_index = input.read_u8()?;
+                type_parameter_bound_target {
+                    type_parameter_index,
+                    bound_index,
+                }
+            }
+            0x13..=0x15 => empty_target,
+            0x16 => formal_parameter_target {
+                formal_parameter_index: input.read_u8()?,
+            },
+            0x17 => throws_target {
+                throws_type_index: input.read_u16()?,
+            },
+            0x40 | 0x41 => {
+                let table_length = input.read_u16()?;
+                let mut table = Vec::with_capacity(table_length as usize);
+                for _ in 0..table_length {
+                    let start_pc = input.read_u16()?;
+                    let length = input.read_u16()?;
+                    let index = input.read_u16()?;
+                    table.push(localvar_target_entry {
+                        start_pc,
+                        length,
+                        index,
+                    })
+                }
+                localvar_target {
+                    table_length,
+                    table,
+                }
+            }
+            0x42 => catch_target {
+                exception_table_index: input.read_u16()?,
+            },
+            0x43..=0x46 => offset_target {
+                offset: input.read_u16()?,
+            },
+            0x47..=0x4B => {
+                let offset = input.read_u16()?;
+                let type_argument_index = input.read_u8()?;
+                type_argument_target {
+                    offset,
+                    type_argument_index,
+                }
+            }
+            _ => {
+                return Err(ClassFormatError(format!(
+                    "Parsed illegal type_annotation#target_type: {:#04x}",
+                    target_type
+                )))
+            }
+        };
+        let path_length = input.read_u8()?;
+        let mut path = Vec::with_capacity(path_length as usize);
+        for _ in 0..path_length {
+            let type_path_kind = input.read_u8()?;
+            let type_argument_index = input.read_u8()?;
+            path.push(type_path_entry {
+                type_path_kind,
+                type_argument_index,
+            })
+        }
+        let target_path = type_path { path_length, path };
+        let annotation = annotation::new(input)?;
+        Ok(type_annotation {
+            target_type,
+            target_info,
+            target_path,
+            annotation,
+        })
+    }
+
+    /// The kind of target the annotation appears on, which determines the `target_info`
+    pub fn get_target_type(&self) -> u8 {
+        self.target_type
+    }
+
+    pub fn get_target_info(&self) -> &target_info {
+        &self.target_info
+    }
+
+    /// The part of the type at the target that is annotated
+    pub fn get_target_path(&self) -> &[type_path_entry] {
+        &self.target_path.path
+    }
+
+    pub fn get_annotation(&self) -> &annotation {
+        &self.annotation
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u8(self.target_type);
+        match &self.target_info {
+            type_parameter_target { type_parameter_index } => {
+                output.write_u8(*type_parameter_index)
+            }
+            supertype_target { supertype_index } => output.write_u16(*supertype_index),
+            type_parameter_bound_target { type_parameter_index, bound_index } => {
+                output.write_u8(*type_parameter_index);
+                output.write_u8(*bound_index);
+            }
+            empty_target => {}
+            formal_parameter_target { formal_parameter_index } => {
+                output.write_u8(*formal_parameter_index)
+            }
+            throws_target { throws_type_index } => output.write_u16(*throws_type_index),
+            localvar_target { table, .. } => {
+                output.write_u16(table.len() as u16);
+                for entry in table {
+                    output.write_u16(entry.start_pc);
+                    output.write_u16(entry.length);
+                    output.write_u16(entry.index);
+                }
+            }
+            catch_target { exception_table_index } => output.write_u16(*exception_table_index),
+            offset_target { offset } => output.write_u16(*offset),
+            type_argument_target { offset, type_argument_index } => {
+                output.write_u16(*offset);
+                output.write_u8(*type_argument_index);
+            }
+        }
+        output.write_u8(self.target_path.path.len() as u8);
+        for entry in self.target_path.path.iter() {
+            output.write_u8(entry.type_path_kind);
+            output.write_u8(entry.type_argument_index);
+        }
+        self.annotation.write(output);
+    }
+}
+
+/// Identifies which type in a declaration or expression a `type_annotation` applies to
+#[derive(Debug, Clone)]
+pub enum target_info {
+    /// A type parameter declaration of a generic class, interface, method or constructor
+    type_parameter_target { type_parameter_index: u8 },
+
+    /// A type in the extends or implements clause of a class, 65535 being the superclass
+    supertype_target { supertype_index: u16 },
+
+    /// A bound of a type parameter declaration
+    type_parameter_bound_target {
+        type_parameter_index: u8,
+        bound_index: u8,
+    },
+
+    /// The type of a field or record component, the return type of a method, or the receiver
+    empty_target,
+
+    /// The type of a formal parameter of a method, constructor or lambda expression
+    formal_parameter_target { formal_parameter_index: u8 },
+
+    /// A type in the throws clause of a method or constructor, indexing Exceptions_attribute
+    throws_target { throws_type_index: u16 },
+
+    /// The type of a local variable, or a resource variable in a try-with-resources
+    localvar_target {
+        table_length: u16,
+        table: Vec<localvar_target_entry>,
+    },
+
+    /// The type in an exception parameter declaration, indexing the Code exception_table
+    catch_target { exception_table_index: u16 },
+
+    /// The type in an instanceof, new, or method reference expression, at a bytecode offset
+    offset_target { offset: u16 },
+
+    /// A type argument in a cast, constructor or method invocation, or method reference
+    type_argument_target {
+        offset: u16,
+        type_argument_index: u8,
+    },
+}
+
+/// The range of code in which a local variable has a value, and the slot it occupies
+#[derive(Debug, Clone)]
+pub struct localvar_target_entry {
+    start_pc: u16,
+    length: u16,
+    index: u16,
 }
 
-#[derive(Debug)]
-struct element_value_pair {
+impl localvar_target_entry {
+    pub fn get_start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    pub fn get_length(&self) -> u16 {
+        self.length
+    }
+
+    pub fn get_index(&self) -> u16 {
+        self.index
+    }
+}
+
+#[derive(Debug, Clone)]
+struct type_path {
+    path_length: u8,
+    path: Vec<type_path_entry>,
+}
+
+/// One step from a type to the part of it that is annotated
+#[derive(Debug, Clone)]
+pub struct type_path_entry {
+    type_path_kind: u8,
+    type_argument_index: u8,
+}
+
+impl type_path_entry {
+    /// 0 -> deeper in an array type, 1 -> deeper in a nested type,
+    /// 2 -> on the bound of a wildcard type argument, 3 -> on a type argument
+    pub fn get_type_path_kind(&self) -> u8 {
+        self.type_path_kind
+    }
+
+    /// Which type argument of a parameterized type is annotated, when the kind is 3
+    pub fn get_type_argument_index(&self) -> u8 {
+        self.type_argument_index
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct element_value_pair {
     element_name_index: u16,
     value: element_value,
 }
 
-#[derive(Debug)]
+impl element_value_pair {
+    /// constant_pool index of a `CONSTANT_Utf8_info` that is the name of the element
+    pub fn get_element_name_index(&self) -> u16 {
+        self.element_name_index
+    }
+
+    pub fn get_value(&self) -> &element_value {
+        &self.value
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct element_value {
     tag: u8,
     value: element_value_data,
 }
 
 impl element_value {
-    pub fn new(input: &mut Read) -> Result<element_value, ClassLoadingError> {
+    pub fn new(input: &mut ClassReader) -> Result<element_value, ClassLoadingError> {
+        let offset = input.get_offset();
         let tag = input.read_u8()?;
         let value = match tag as char {
             'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => {
-                const_value_index(input.read_u16::<BigEndian>()?)
+                const_value_index(input.read_u16()?)
             }
             'e' => {
-                let type_name_index = input.read_u16::<BigEndian>()?;
-                let const_name_index = input.read_u16::<BigEndian>()?;
+                let type_name_index = input.read_u16()?;
+                let const_name_index = input.read_u16()?;
                 enum_const_value {
                     type_name_index,
                     const_name_index,
                 }
             }
-            'c' => class_info_index(input.read_u16::<BigEndian>()?),
+            'c' => class_info_index(input.read_u16()?),
             '@' => annotation_value(annotation::new(input)?),
             '[' => {
-                let num_values = input.read_u16::<BigEndian>()?;
+                let num_values = input.read_u16()?;
                 let mut values = Vec::with_capacity(num_values as usize);
                 for _ in 0..num_values {
                     values.push(element_value::new(input)?);
                 }
                 array_value { num_values, values }
             }
-            _ => panic!("Parsed illegal element_value#tag: {}", tag),
+            _ => {
+                return Err(ClassFormatError(format!(
+                    "Parsed illegal element_value#tag: {}",
+                    tag
+                )).at_offset(offset))
+            }
         };
         Ok(element_value { tag, value })
     }
+
+    /// The type of the value as a descriptor character, or `s` for String, `e` for an enum
+    /// constant, `c` for a class literal, `@` for an annotation and `[` for an array
+    pub fn get_tag(&self) -> u8 {
+        self.tag
+    }
+
+    pub fn get_value(&self) -> &element_value_data {
+        &self.value
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u8(self.tag);
+        match &self.value {
+            const_value_index(index) | class_info_index(index) => output.write_u16(*index),
+            enum_const_value { type_name_index, const_name_index } => {
+                output.write_u16(*type_name_index);
+                output.write_u16(*const_name_index);
+            }
+            annotation_value(annotation) => annotation.write(output),
+            array_value { values, .. } => {
+                output.write_u16(values.len() as u16);
+                for value in values {
+                    value.write(output);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum element_value_data {
     const_value_index(u16),
     enum_const_value {
@@ -221,7 +649,7 @@ pub enum element_value_data {
     },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct local_variable_table_entry {
     start_pc: u16,
     length: u16,
@@ -230,7 +658,48 @@ pub struct local_variable_table_entry {
     index: u16,
 }
 
-#[derive(Debug)]
+impl local_variable_table_entry {
+    pub fn get_start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    pub fn get_length(&self) -> u16 {
+        self.length
+    }
+
+    pub fn get_name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn get_descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    /// The local variable array slot the variable occupies
+    pub fn get_index(&self) -> u16 {
+        self.index
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct method_parameter {
+    name_index: u16,
+    access_flags: u16,
+}
+
+impl method_parameter {
+    /// constant_pool index of a `CONSTANT_Utf8_info` that is the parameter name, or 0 if the
+    /// parameter is unnamed
+    pub fn get_name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn get_access_flags(&self) -> u16 {
+        self.access_flags
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct local_variable_type_table_entry {
     start_pc: u16,
     length: u16,
@@ -239,13 +708,47 @@ pub struct local_variable_type_table_entry {
     index: u16,
 }
 
-#[derive(Debug)]
+impl local_variable_type_table_entry {
+    pub fn get_start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    pub fn get_length(&self) -> u16 {
+        self.length
+    }
+
+    pub fn get_name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    /// constant_pool index of a `CONSTANT_Utf8_info` that is the generic signature of the
+    /// variable
+    pub fn get_signature_index(&self) -> u16 {
+        self.signature_index
+    }
+
+    pub fn get_index(&self) -> u16 {
+        self.index
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct line_number_table_entry {
     start_pc: u16,
     line_number: u16,
 }
 
-#[derive(Debug)]
+impl line_number_table_entry {
+    pub fn get_start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    pub fn get_line_number(&self) -> u16 {
+        self.line_number
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct inner_class {
     inner_class_info_index: u16,
     outer_class_info_index: u16,
@@ -253,14 +756,35 @@ pub struct inner_class {
     inner_class_access_flags: u16,
 }
 
-#[derive(Debug)]
+impl inner_class {
+    pub fn get_inner_class_info_index(&self) -> u16 {
+        self.inner_class_info_index
+    }
+
+    /// 0 if the class is not a member of another class
+    pub fn get_outer_class_info_index(&self) -> u16 {
+        self.outer_class_info_index
+    }
+
+    /// 0 if the class is anonymous
+    pub fn get_inner_name_index(&self) -> u16 {
+        self.inner_name_index
+    }
+
+    pub fn get_inner_class_access_flags(&self) -> u16 {
+        self.inner_class_access_flags
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct stack_map_frame {
     frame_type: u8,
     frame_data: stack_map_frame_data,
 }
 
 impl stack_map_frame {
-    fn new(input: &mut Read) -> Result<stack_map_frame, ClassLoadingError> {
+    fn new(input: &mut ClassReader) -> Result<stack_map_frame, ClassLoadingError> {
+        let offset = input.get_offset();
         let frame_type = input.read_u8()?;
         let frame_data = match frame_type {
             0..=63 => same_frame,
@@ -269,7 +793,7 @@ impl stack_map_frame {
                 same_locals_1_stack_item_frame { stack }
             }
             247 => {
-                let offset_delta = input.read_u16::<BigEndian>()?;
+                let offset_delta = input.read_u16()?;
                 let stack = verification_type_info::new(input)?;
                 same_locals_1_stack_item_frame_extended {
                     offset_delta,
@@ -277,15 +801,15 @@ impl stack_map_frame {
                 }
             }
             248..=250 => {
-                let offset_delta = input.read_u16::<BigEndian>()?;
+                let offset_delta = input.read_u16()?;
                 chop_frame { offset_delta }
             }
             251 => {
-                let offset_delta = input.read_u16::<BigEndian>()?;
+                let offset_delta = input.read_u16()?;
                 same_frame_extended { offset_delta }
             }
             252..=254 => {
-                let offset_delta = input.read_u16::<BigEndian>()?;
+                let offset_delta = input.read_u16()?;
                 let mut locals = Vec::with_capacity((frame_type - 251) as usize);
                 for _ in 0..(frame_type - 251) {
                     locals.push(verification_type_info::new(input)?);
@@ -296,16 +820,16 @@ impl stack_map_frame {
                 }
             }
             255 => {
-                let offset_delta = input.read_u16::<BigEndian>()?;
-                let number_of_locals = input.read_u16::<BigEndian>()?;
+                let offset_delta = input.read_u16()?;
+                let number_of_locals = input.read_u16()?;
                 let mut locals = Vec::with_capacity(number_of_locals as usize);
                 for _ in 0..number_of_locals {
                     locals.push(verification_type_info::new(input)?);
                 }
-                let number_of_stack_items = input.read_u16::<BigEndian>()?;
+                let number_of_stack_items = input.read_u16()?;
                 let mut stack = Vec::with_capacity(number_of_stack_items as usize);
                 for _ in 0..number_of_stack_items {
-                    locals.push(verification_type_info::new(input)?);
+                    stack.push(verification_type_info::new(input)?);
                 }
                 full_frame {
                     offset_delta,
@@ -316,10 +840,10 @@ impl stack_map_frame {
                 }
             }
             _ => {
-                panic!(
+                return Err(ClassFormatError(format!(
                     "Parsed stack_map_frame#frame_type reserved for future use: {}",
                     frame_type
-                );
+                )).at_offset(offset))
             }
         };
         Ok(stack_map_frame {
@@ -327,10 +851,50 @@ impl stack_map_frame {
             frame_data,
         })
     }
+
+    pub fn get_frame_type(&self) -> u8 {
+        self.frame_type
+    }
+
+    pub fn get_frame_data(&self) -> &stack_map_frame_data {
+        &self.frame_data
+    }
+
+    fn write(&self, output: &mut ClassWriter) {
+        output.write_u8(self.frame_type);
+        match &self.frame_data {
+            same_frame => {}
+            same_locals_1_stack_item_frame { stack } => stack.write(output),
+            same_locals_1_stack_item_frame_extended { offset_delta, stack } => {
+                output.write_u16(*offset_delta);
+                stack.write(output);
+            }
+            chop_frame { offset_delta } | same_frame_extended { offset_delta } => {
+                output.write_u16(*offset_delta)
+            }
+            append_frame { offset_delta, locals } => {
+                output.write_u16(*offset_delta);
+                for local in locals {
+                    local.write(output);
+                }
+            }
+            full_frame { offset_delta, locals, stack, .. } => {
+                output.write_u16(*offset_delta);
+                output.write_u16(locals.len() as u16);
+                for local in locals {
+                    local.write(output);
+                }
+                output.write_u16(stack.len() as u16);
+                for item in stack {
+                    item.write(output);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug)]
-enum stack_map_frame_data {
+#[derive(Debug, Clone)]
+pub enum stack_map_frame_data {
     same_frame,
 
     same_locals_1_stack_item_frame {
@@ -364,14 +928,14 @@ enum stack_map_frame_data {
     },
 }
 
-#[derive(Debug)]
-struct verification_type_info {
+#[derive(Debug, Clone)]
+pub struct verification_type_info {
     tag: u8,
     data: verification_type_info_data,
 }
 
-#[derive(Debug)]
-enum verification_type_info_data {
+#[derive(Debug, Clone)]
+pub enum verification_type_info_data {
     Top_variable_info,
     Integer_variable_info,
     Float_variable_info,
@@ -384,7 +948,8 @@ enum verification_type_info_data {
 }
 
 impl verification_type_info {
-    pub fn new(input: &mut Read) -> Result<verification_type_info, ClassLoadingError> {
+    pub fn new(input: &mut ClassReader) -> Result<verification_type_info, ClassLoadingError> {
+        let offset = input.get_offset();
         let tag = input.read_u8()?;
         let data = match tag {
             0 => Top_variable_info,
@@ -395,85 +960,481 @@ impl verification_type_info {
             5 => Null_variable_info,
             6 => UninitializedThis_variable_info,
             7 => Object_variable_info {
-                cpool_index: input.read_u16::<BigEndian>()?,
+                cpool_index: input.read_u16()?,
             },
             8 => Uninitialized_variable_info {
-                offset: input.read_u16::<BigEndian>()?,
+                offset: input.read_u16()?,
             },
-            _ => panic!("Unsupported verification_type_info#tag parsed: {}", tag),
+            _ => {
+                return Err(ClassFormatError(format!(
+                    "Unsupported verification_type_info#tag parsed: {}",
+                    tag
+                )).at_offset(offset))
+            }
         };
         Ok(verification_type_info { tag, data })
     }
+
+    pub fn get_tag(&self) -> u8 {
+        self.tag
+    }
+
+    pub fn get_data(&self) -> &verification_type_info_data {
+        &self.data
+    }
+
+    fn write(&self, output: &mut ClassWriter) {
+        output.write_u8(self.tag);
+        match self.data {
+            Object_variable_info { cpool_index } => output.write_u16(cpool_index),
+            Uninitialized_variable_info { offset } => output.write_u16(offset),
+            _ => {}
+        }
+    }
+}
+
+/// Collects the type annotations of both retention policies from an attributes table
+pub fn get_type_annotations<'b>(attributes: &'b [attribute_info]) -> Vec<&'b type_annotation> {
+    attributes.iter().flat_map(|info| {
+        match info.get_data() {
+            RuntimeVisibleTypeAnnotations_attribute { annotations, .. }
+            | RuntimeInvisibleTypeAnnotations_attribute { annotations, .. } => annotations.iter(),
+            _ => [].iter(),
+        }
+    }).collect()
+}
+
+/// The first class file major version in which a predefined attribute is recognized
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7-320>
+pub fn attribute_version(name: &str) -> Option<u16> {
+    Some(match name {
+        "ConstantValue" | "Code" | "Exceptions" | "SourceFile" | "LineNumberTable"
+        | "LocalVariableTable" | "InnerClasses" | "Synthetic" | "Deprecated" => 45,
+        "EnclosingMethod" | "Signature" | "SourceDebugExtension" | "LocalVariableTypeTable"
+        | "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations"
+        | "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations"
+        | "AnnotationDefault" => 49,
+        "StackMapTable" => 50,
+        "BootstrapMethods" => 51,
+        "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations"
+        | "MethodParameters" => 52,
+        "Module" | "ModulePackages" | "ModuleMainClass" => 53,
+        "NestHost" | "NestMembers" => 55,
+        "Record" => 60,
+        "PermittedSubclasses" => 61,
+        _ => return None,
+    })
+}
+
+impl<'a> attribute_info_Data<'a> {
+    fn write(&self, output: &mut ClassWriter) {
+        match self {
+            ConstantValue_attribute { constantvalue_index } => {
+                output.write_u16(*constantvalue_index)
+            }
+            Code_attribute {
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                output.write_u16(*max_stack);
+                output.write_u16(*max_locals);
+                output.write_u32(code.len() as u32);
+                output.write_bytes(code);
+                output.write_u16(exception_table.len() as u16);
+                for exception in exception_table {
+                    exception.write(output);
+                }
+                write_attributes(output, attributes);
+            }
+            StackMapTable_attribute { entries, .. } => {
+                output.write_u16(entries.len() as u16);
+                for entry in entries {
+                    entry.write(output);
+                }
+            }
+            Exceptions_attribute { exception_index_table, .. } => {
+                output.write_u16_table(exception_index_table)
+            }
+            InnerClasses_attribute { classes, .. } => {
+                output.write_u16(classes.len() as u16);
+                for class in classes {
+                    output.write_u16(class.inner_class_info_index);
+                    output.write_u16(class.outer_class_info_index);
+                    output.write_u16(class.inner_name_index);
+                    output.write_u16(class.inner_class_access_flags);
+                }
+            }
+            EnclosingMethod_attribute { class_index, method_index } => {
+                output.write_u16(*class_index);
+                output.write_u16(*method_index);
+            }
+            Synthetic_attribute | Deprecated_attribute => {}
+            Signature_attribute { signature_index } => output.write_u16(*signature_index),
+            SourceFile_attribute { sourcefile_index } => output.write_u16(*sourcefile_index),
+            SourceDebugExtension { debug_extension } => output.write_bytes(debug_extension),
+            LineNumberTable_attribute { line_number_table, .. } => {
+                output.write_u16(line_number_table.len() as u16);
+                for entry in line_number_table {
+                    output.write_u16(entry.start_pc);
+                    output.write_u16(entry.line_number);
+                }
+            }
+            LocalVariableTable_attribute { local_variable_table, .. } => {
+                output.write_u16(local_variable_table.len() as u16);
+                for entry in local_variable_table {
+                    output.write_u16(entry.start_pc);
+                    output.write_u16(entry.length);
+                    output.write_u16(entry.name_index);
+                    output.write_u16(entry.descriptor_index);
+                    output.write_u16(entry.index);
+                }
+            }
+            LocalVariableTypeTable_attribute { local_variable_type_table, .. } => {
+                output.write_u16(local_variable_type_table.len() as u16);
+                for entry in local_variable_type_table {
+                    output.write_u16(entry.start_pc);
+                    output.write_u16(entry.length);
+                    output.write_u16(entry.name_index);
+                    output.write_u16(entry.signature_index);
+                    output.write_u16(entry.index);
+                }
+            }
+            RuntimeVisibleAnnotations_attribute { annotations, .. }
+            | RuntimeInvisibleAnnotations_attribute { annotations, .. } => {
+                output.write_u16(annotations.len() as u16);
+                for annotation in annotations {
+                    annotation.write(output);
+                }
+            }
+            RuntimeVisibleParameterAnnotations_attribute { parameter_annotations, .. }
+            | RuntimeInvisibleParameterAnnotations_attribute { parameter_annotations, .. } => {
+                output.write_u8(parameter_annotations.len() as u8);
+                for parameter in parameter_annotations {
+                    output.write_u16(parameter.annotations.len() as u16);
+                    for annotation in parameter.annotations.iter() {
+                        annotation.write(output);
+                    }
+                }
+            }
+            AnnotationDefault_attribute { default_value } => default_value.write(output),
+            BootstrapMethods_attribute { bootstrap_methods, .. } => {
+                output.write_u16(bootstrap_methods.len() as u16);
+                for method in bootstrap_methods {
+                    output.write_u16(method.bootstrap_method_ref);
+                    output.write_u16_table(&method.bootstrap_arguments);
+                }
+            }
+            Module_attribute {
+                module_name_index,
+                module_flags,
+                module_version_index,
+                requires,
+                exports,
+                opens,
+                uses_index,
+                provides,
+                ..
+            } => {
+                output.write_u16(*module_name_index);
+                output.write_u16(*module_flags);
+                output.write_u16(*module_version_index);
+                output.write_u16(requires.len() as u16);
+                for requires in requires {
+                    requires.write(output);
+                }
+                output.write_u16(exports.len() as u16);
+                for exports in exports {
+                    exports.write(output);
+                }
+                output.write_u16(opens.len() as u16);
+                for opens in opens {
+                    opens.write(output);
+                }
+                output.write_u16_table(uses_index);
+                output.write_u16(provides.len() as u16);
+                for provides in provides {
+                    provides.write(output);
+                }
+            }
+            ModulePackages_attribute { package_index, .. } => {
+                output.write_u16_table(package_index)
+            }
+            ModuleMainClass_attribute { main_class_index } => output.write_u16(*main_class_index),
+            NestHost_attribute { host_class_index } => output.write_u16(*host_class_index),
+            NestMembers_attribute { classes, .. }
+            | PermittedSubclasses_attribute { classes, .. } => output.write_u16_table(classes),
+            Record_attribute { components, .. } => {
+                output.write_u16(components.len() as u16);
+                for component in components {
+                    component.write(output);
+                }
+            }
+            MethodParameters_attribute { parameters, .. } => {
+                output.write_u8(parameters.len() as u8);
+                for parameter in parameters {
+                    output.write_u16(parameter.name_index);
+                    output.write_u16(parameter.access_flags);
+                }
+            }
+            RuntimeVisibleTypeAnnotations_attribute { annotations, .. }
+            | RuntimeInvisibleTypeAnnotations_attribute { annotations, .. } => {
+                output.write_u16(annotations.len() as u16);
+                for annotation in annotations {
+                    annotation.write(output);
+                }
+            }
+            Unknown_attribute { info } | Malformed_attribute { info, .. } => {
+                output.write_bytes(info)
+            }
+        }
+    }
 }
 
-pub fn read_attributes(
-    input: &mut Read,
+/// Reads an attributes table
+///
+/// With a `source` the attributes are only located, their contents are decoded when first
+/// accessed
+pub fn read_attributes<'a>(
+    input: &mut ClassReader,
     length: u16,
-    constant_pool: &ConstantPool,
-) -> Result<Vec<attribute_info>, ClassLoadingError> {
+    constant_pool: &ConstantPool<'a>,
+    major_version: u16,
+    source: Option<&Rc<AttributeSource<'a>>>,
+) -> Result<Vec<attribute_info<'a>>, ClassLoadingError> {
     let mut vector = Vec::with_capacity(length as usize);
     for _ in 0..length {
-        vector.push(attribute_info::new(input, constant_pool)?);
+        vector.push(match source {
+            Some(source) => attribute_info::new_lazy(input, source)?,
+            None => attribute_info::new(input, constant_pool, major_version)?,
+        });
     }
     Ok(vector)
 }
 
-impl attribute_info {
-    pub fn new(
-        input: &mut Read,
-        constant_pool: &ConstantPool,
-    ) -> Result<attribute_info, ClassLoadingError> {
-        let attribute_name_index = input.read_u16::<BigEndian>()?;
-        let attribute_length = input.read_u32::<BigEndian>()?;
-        let item = constant_pool.get_entry(attribute_name_index);
-        let attribute_name = match item {
-            CONSTANT_Utf8_info { bytes, .. } => *bytes,
-            _ => panic!(
-                "attribute_name pointed to {:#?}, not CONSTANT_Utf8_info",
-                item
-            ),
-        };
+/// Writes `attributes_count` followed by the attributes
+pub fn write_attributes(output: &mut ClassWriter, attributes: &[attribute_info]) {
+    output.write_u16(attributes.len() as u16);
+    for info in attributes {
+        info.write(output);
+    }
+}
 
-        let info =
-            attribute_info::parse_info(input, constant_pool, attribute_length, attribute_name)?;
+impl<'a> attribute_info<'a> {
+    pub fn new(
+        input: &mut ClassReader,
+        constant_pool: &ConstantPool<'a>,
+        major_version: u16,
+    ) -> Result<attribute_info<'a>, ClassLoadingError> {
+        let offset = input.get_offset();
+        let attribute_name_index = input.read_u16()?;
+        let attribute_length = input.read_u32()?;
+        // The attribute is parsed from its own reader so it can't run into the next structure
+        let mut input = input.sub_reader(attribute_length as usize)?;
+        let info = attribute_info::read_info(
+            &mut input,
+            constant_pool,
+            attribute_name_index,
+            offset,
+            major_version,
+        )?;
+        Ok(attribute_info {
+            attribute_name_index,
+            attribute_length,
+            info: OnceCell::from(info),
+            source: None,
+            offset,
+        })
+    }
 
+    /// Locates an attribute without decoding its contents, which are read from `source` when
+    /// first accessed
+    pub fn new_lazy(
+        input: &mut ClassReader,
+        source: &Rc<AttributeSource<'a>>,
+    ) -> Result<attribute_info<'a>, ClassLoadingError> {
+        let offset = input.get_offset();
+        let attribute_name_index = input.read_u16()?;
+        let attribute_length = input.read_u32()?;
+        source.constant_pool
+            .get_string_entry(attribute_name_index)
+            .map_err(|error| error.at_offset(offset))?;
+        input.read_bytes(attribute_length as usize)?;
         Ok(attribute_info {
             attribute_name_index,
             attribute_length,
-            info,
+            info: OnceCell::new(),
+            source: Some(source.clone()),
+            offset,
         })
     }
 
-    pub fn get_data(&self) -> &attribute_info_Data {
-        &self.info
+    /// Decodes the contents of an attribute, given a reader over exactly those bytes
+    fn read_info(
+        input: &mut ClassReader,
+        constant_pool: &ConstantPool<'a>,
+        attribute_name_index: u16,
+        offset: usize,
+        major_version: u16,
+    ) -> Result<attribute_info_Data<'a>, ClassLoadingError> {
+        let attribute_name = constant_pool
+            .get_string_entry(attribute_name_index)
+            .map_err(|error| error.at_offset(offset))?;
+        let attribute_length = input.remaining() as u32;
+
+        // Attributes introduced after this class file's version are not recognized
+        let recognized = attribute_version(attribute_name)
+            .is_none_or(|version| major_version >= version);
+        let info = if recognized {
+            attribute_info::parse_info(
+                input,
+                constant_pool,
+                attribute_length,
+                attribute_name,
+                major_version,
+            )?
+        } else {
+            let info = input.read_bytes(attribute_length as usize)?.to_vec();
+            Unknown_attribute { info }
+        };
+        if input.remaining() != 0 {
+            return Err(input.error(format!(
+                "{} attribute is {} bytes shorter than its attribute_length",
+                attribute_name,
+                input.remaining()
+            )));
+        }
+        Ok(info)
+    }
+
+    /// The undecoded contents of a lazily parsed attribute
+    fn raw_info(&self) -> Option<&'a [u8]> {
+        self.source.as_ref().map(|source| {
+            let start = self.offset + 6;
+            &source.bytes[start..start + self.attribute_length as usize]
+        })
+    }
+
+    /// The contents of the attribute, decoding them first if it was parsed lazily
+    ///
+    /// A lazily parsed attribute that turns out to be malformed is a `Malformed_attribute`,
+    /// which matches none of the variants a caller looks for. Anything that relies on an
+    /// attribute being well formed, such as checking or verifying the class, should use
+    /// `decode` or `read` instead, so that a lazily parsed class is rejected as it would be when
+    /// parsed eagerly.
+    pub fn get_data(&self) -> &attribute_info_Data<'a> {
+        match self.decode() {
+            Ok(info) => info,
+            Err(error) => self.info.get_or_init(|| Malformed_attribute {
+                info: self.raw_info().unwrap_or_default().to_vec(),
+                error,
+            }),
+        }
+    }
+
+    /// The contents of the attribute, decoding them first if it was parsed lazily, or the
+    /// `ClassFormatError` they are rejected with
+    pub fn decode(&self) -> Result<&attribute_info_Data<'a>, ClassLoadingError> {
+        match self.info.get() {
+            Some(Malformed_attribute { error, .. }) => return Err(error.clone()),
+            Some(info) => return Ok(info),
+            None => {}
+        }
+        let info = self.read_lazy_info()?;
+        Ok(self.info.get_or_init(|| info))
+    }
+
+    /// The contents of the attribute like `decode`, but without keeping them decoded if they
+    /// weren't already, for a caller that only needs them for a while
+    pub fn read(&self) -> Result<Cow<'_, attribute_info_Data<'a>>, ClassLoadingError> {
+        match self.info.get() {
+            Some(Malformed_attribute { error, .. }) => Err(error.clone()),
+            Some(info) => Ok(Cow::Borrowed(info)),
+            None => self.read_lazy_info().map(Cow::Owned),
+        }
+    }
+
+    /// Checks that the contents of the attribute are well formed, like `decode`, without
+    /// keeping them decoded if they weren't already
+    pub fn check(&self) -> Result<(), ClassLoadingError> {
+        self.read().map(|_| ())
+    }
+
+    /// Decodes the contents of a lazily parsed attribute from its class file
+    fn read_lazy_info(&self) -> Result<attribute_info_Data<'a>, ClassLoadingError> {
+        let source = match self.source.as_ref() {
+            Some(source) => source,
+            None => unreachable!("attribute_info without a source is always decoded"),
+        };
+        let mut input = ClassReader::new_at(self.raw_info().unwrap_or_default(), self.offset + 6);
+        attribute_info::read_info(
+            &mut input,
+            &source.constant_pool,
+            self.attribute_name_index,
+            self.offset,
+            source.major_version,
+        )
+    }
+
+    /// Whether the contents of the attribute have been decoded
+    pub fn is_decoded(&self) -> bool {
+        self.info.get().is_some()
+    }
+
+    /// constant_pool index of the `CONSTANT_Utf8_info` that is the name of the attribute
+    pub fn get_name_index(&self) -> u16 {
+        self.attribute_name_index
+    }
+
+    /// The byte offset of the attribute within the class file
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Writes the attribute, recomputing `attribute_length` from its contents
+    ///
+    /// Attributes that were never decoded are copied as they were read
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.attribute_name_index);
+        match (self.info.get(), self.raw_info()) {
+            (None, Some(raw_info)) => {
+                output.write_u32(self.attribute_length);
+                output.write_bytes(raw_info);
+            }
+            _ => output.write_with_length(|output| self.get_data().write(output)),
+        }
     }
 
     fn parse_info(
-        input: &mut Read,
-        constant_pool: &ConstantPool,
+        input: &mut ClassReader,
+        constant_pool: &ConstantPool<'a>,
         attribute_length: u32,
         name: &str,
-    ) -> Result<attribute_info_Data, ClassLoadingError> {
+        major_version: u16,
+    ) -> Result<attribute_info_Data<'a>, ClassLoadingError> {
         Ok(match name {
             "ConstantValue" => {
-                let constantvalue_index = input.read_u16::<BigEndian>()?;
+                let constantvalue_index = input.read_u16()?;
                 ConstantValue_attribute {
                     constantvalue_index,
                 }
             }
             "Code" => {
-                let max_stack = input.read_u16::<BigEndian>()?;
-                let max_locals = input.read_u16::<BigEndian>()?;
-                let code_length = input.read_u32::<BigEndian>()?;
-                let mut code = vec![0u8; code_length as usize];
-                input.read_exact(&mut code)?;
-                let exception_table_length = input.read_u16::<BigEndian>()?;
+                let max_stack = input.read_u16()?;
+                let max_locals = input.read_u16()?;
+                let code_length = input.read_u32()?;
+                let code = input.read_bytes(code_length as usize)?.to_vec();
+                let exception_table_length = input.read_u16()?;
                 let mut exception_table = Vec::with_capacity(exception_table_length as usize);
                 for _ in 0..exception_table_length {
-                    let start_pc = input.read_u16::<BigEndian>()?;
-                    let end_pc = input.read_u16::<BigEndian>()?;
-                    let handler_pc = input.read_u16::<BigEndian>()?;
-                    let catch_type = input.read_u16::<BigEndian>()?;
+                    let start_pc = input.read_u16()?;
+                    let end_pc = input.read_u16()?;
+                    let handler_pc = input.read_u16()?;
+                    let catch_type = input.read_u16()?;
                     exception_table.push(exception_info {
                         start_pc,
                         end_pc,
@@ -481,10 +1442,10 @@ impl attribute_info {
                         catch_type,
                     })
                 }
-                let attributes_count = input.read_u16::<BigEndian>()?;
+                let attributes_count = input.read_u16()?;
                 let mut attributes = Vec::with_capacity(attributes_count as usize);
                 for _ in 0..attributes_count {
-                    attributes.push(attribute_info::new(input, constant_pool)?);
+                    attributes.push(attribute_info::new(input, constant_pool, major_version)?);
                 }
                 Code_attribute {
                     max_stack,
@@ -498,7 +1459,7 @@ impl attribute_info {
                 }
             }
             "StackMapTable" => {
-                let number_of_entries = input.read_u16::<BigEndian>()?;
+                let number_of_entries = input.read_u16()?;
                 let mut entries = Vec::with_capacity(number_of_entries as usize);
                 for _ in 0..number_of_entries {
                     entries.push(stack_map_frame::new(input)?);
@@ -509,10 +1470,10 @@ impl attribute_info {
                 }
             }
             "Exceptions" => {
-                let number_of_exceptions = input.read_u16::<BigEndian>()?;
+                let number_of_exceptions = input.read_u16()?;
                 let mut exception_index_table = Vec::with_capacity(number_of_exceptions as usize);
                 for _ in 0..number_of_exceptions {
-                    exception_index_table.push(input.read_u16::<BigEndian>()?);
+                    exception_index_table.push(input.read_u16()?);
                 }
                 Exceptions_attribute {
                     number_of_exceptions,
@@ -520,13 +1481,13 @@ impl attribute_info {
                 }
             }
             "InnerClasses" => {
-                let number_of_classes = input.read_u16::<BigEndian>()?;
+                let number_of_classes = input.read_u16()?;
                 let mut classes = Vec::with_capacity(number_of_classes as usize);
                 for _ in 0..number_of_classes {
-                    let inner_class_info_index = input.read_u16::<BigEndian>()?;
-                    let outer_class_info_index = input.read_u16::<BigEndian>()?;
-                    let inner_name_index = input.read_u16::<BigEndian>()?;
-                    let inner_class_access_flags = input.read_u16::<BigEndian>()?;
+                    let inner_class_info_index = input.read_u16()?;
+                    let outer_class_info_index = input.read_u16()?;
+                    let inner_name_index = input.read_u16()?;
+                    let inner_class_access_flags = input.read_u16()?;
                     classes.push(inner_class {
                         inner_class_info_index,
                         outer_class_info_index,
@@ -540,8 +1501,8 @@ impl attribute_info {
                 }
             }
             "EnclosingMethod" => {
-                let class_index = input.read_u16::<BigEndian>()?;
-                let method_index = input.read_u16::<BigEndian>()?;
+                let class_index = input.read_u16()?;
+                let method_index = input.read_u16()?;
                 EnclosingMethod_attribute {
                     class_index,
                     method_index,
@@ -549,24 +1510,23 @@ impl attribute_info {
             }
             "Synthetic" => Synthetic_attribute,
             "Signature" => {
-                let signature_index = input.read_u16::<BigEndian>()?;
+                let signature_index = input.read_u16()?;
                 Signature_attribute { signature_index }
             }
             "SourceFile" => {
-                let sourcefile_index = input.read_u16::<BigEndian>()?;
+                let sourcefile_index = input.read_u16()?;
                 SourceFile_attribute { sourcefile_index }
             }
             "SourceDebugExtension" => {
-                let mut debug_extension = vec![0u8; attribute_length as usize];
-                input.read_exact(&mut debug_extension)?;
+                let debug_extension = input.read_bytes(attribute_length as usize)?.to_vec();
                 SourceDebugExtension { debug_extension }
             }
             "LineNumberTable" => {
-                let line_number_table_length = input.read_u16::<BigEndian>()?;
+                let line_number_table_length = input.read_u16()?;
                 let mut line_number_table = Vec::with_capacity(line_number_table_length as usize);
                 for _ in 0..line_number_table_length {
-                    let start_pc = input.read_u16::<BigEndian>()?;
-                    let line_number = input.read_u16::<BigEndian>()?;
+                    let start_pc = input.read_u16()?;
+                    let line_number = input.read_u16()?;
                     line_number_table.push(line_number_table_entry {
                         start_pc,
                         line_number,
@@ -578,15 +1538,15 @@ impl attribute_info {
                 }
             }
             "LocalVariableTable" => {
-                let local_variable_table_length = input.read_u16::<BigEndian>()?;
+                let local_variable_table_length = input.read_u16()?;
                 let mut local_variable_table =
                     Vec::with_capacity(local_variable_table_length as usize);
                 for _ in 0..local_variable_table_length {
-                    let start_pc = input.read_u16::<BigEndian>()?;
-                    let length = input.read_u16::<BigEndian>()?;
-                    let name_index = input.read_u16::<BigEndian>()?;
-                    let descriptor_index = input.read_u16::<BigEndian>()?;
-                    let index = input.read_u16::<BigEndian>()?;
+                    let start_pc = input.read_u16()?;
+                    let length = input.read_u16()?;
+                    let name_index = input.read_u16()?;
+                    let descriptor_index = input.read_u16()?;
+                    let index = input.read_u16()?;
                     local_variable_table.push(local_variable_table_entry {
                         start_pc,
                         length,
@@ -601,15 +1561,15 @@ impl attribute_info {
                 }
             }
             "LocalVariableTypeTable" => {
-                let local_variable_type_table_length = input.read_u16::<BigEndian>()?;
+                let local_variable_type_table_length = input.read_u16()?;
                 let mut local_variable_type_table =
                     Vec::with_capacity(local_variable_type_table_length as usize);
                 for _ in 0..local_variable_type_table_length {
-                    let start_pc = input.read_u16::<BigEndian>()?;
-                    let length = input.read_u16::<BigEndian>()?;
-                    let name_index = input.read_u16::<BigEndian>()?;
-                    let signature_index = input.read_u16::<BigEndian>()?;
-                    let index = input.read_u16::<BigEndian>()?;
+                    let start_pc = input.read_u16()?;
+                    let length = input.read_u16()?;
+                    let name_index = input.read_u16()?;
+                    let signature_index = input.read_u16()?;
+                    let index = input.read_u16()?;
                     local_variable_type_table.push(local_variable_type_table_entry {
                         start_pc,
                         length,
@@ -625,7 +1585,7 @@ impl attribute_info {
             }
             "Deprecated" => Deprecated_attribute,
             "RuntimeVisibleAnnotations" => {
-                let num_annotations = input.read_u16::<BigEndian>()?;
+                let num_annotations = input.read_u16()?;
                 let mut annotations = Vec::with_capacity(num_annotations as usize);
                 for _ in 0..num_annotations {
                     annotations.push(annotation::new(input)?)
@@ -636,7 +1596,7 @@ impl attribute_info {
                 }
             }
             "RuntimeInvisibleAnnotations" => {
-                let num_annotations = input.read_u16::<BigEndian>()?;
+                let num_annotations = input.read_u16()?;
                 let mut annotations = Vec::with_capacity(num_annotations as usize);
                 for _ in 0..num_annotations {
                     annotations.push(annotation::new(input)?)
@@ -650,7 +1610,7 @@ impl attribute_info {
                 let num_parameters = input.read_u8()?;
                 let mut parameter_annotations = Vec::with_capacity(num_parameters as usize);
                 for _ in 0..num_parameters {
-                    let num_annotations = input.read_u16::<BigEndian>()?;
+                    let num_annotations = input.read_u16()?;
                     let mut annotations = Vec::with_capacity(num_annotations as usize);
                     for _ in 0..num_annotations {
                         annotations.push(annotation::new(input)?);
@@ -669,7 +1629,7 @@ impl attribute_info {
                 let num_parameters = input.read_u8()?;
                 let mut parameter_annotations = Vec::with_capacity(num_parameters as usize);
                 for _ in 0..num_parameters {
-                    let num_annotations = input.read_u16::<BigEndian>()?;
+                    let num_annotations = input.read_u16()?;
                     let mut annotations = Vec::with_capacity(num_annotations as usize);
                     for _ in 0..num_annotations {
                         annotations.push(annotation::new(input)?);
@@ -689,15 +1649,15 @@ impl attribute_info {
                 AnnotationDefault_attribute { default_value }
             }
             "BootstrapMethods" => {
-                let num_bootstrap_methods = input.read_u16::<BigEndian>()?;
+                let num_bootstrap_methods = input.read_u16()?;
                 let mut bootstrap_methods = Vec::with_capacity(num_bootstrap_methods as usize);
                 for _ in 0..num_bootstrap_methods {
-                    let bootstrap_method_ref = input.read_u16::<BigEndian>()?;
-                    let num_bootstrap_arguments = input.read_u16::<BigEndian>()?;
+                    let bootstrap_method_ref = input.read_u16()?;
+                    let num_bootstrap_arguments = input.read_u16()?;
                     let mut bootstrap_arguments =
                         Vec::with_capacity(num_bootstrap_arguments as usize);
                     for _ in 0..num_bootstrap_arguments {
-                        bootstrap_arguments.push(input.read_u16::<BigEndian>()?);
+                        bootstrap_arguments.push(input.read_u16()?);
                     }
                     bootstrap_methods.push(bootstrap_method {
                         bootstrap_method_ref,
@@ -710,17 +1670,142 @@ impl attribute_info {
                     bootstrap_methods,
                 }
             }
+            "Module" => {
+                let module_name_index = input.read_u16()?;
+                let module_flags = input.read_u16()?;
+                let module_version_index = input.read_u16()?;
+                let requires_count = input.read_u16()?;
+                let mut requires = Vec::with_capacity(requires_count as usize);
+                for _ in 0..requires_count {
+                    requires.push(module_requires::new(input)?);
+                }
+                let exports_count = input.read_u16()?;
+                let mut exports = Vec::with_capacity(exports_count as usize);
+                for _ in 0..exports_count {
+                    exports.push(module_exports::new(input)?);
+                }
+                let opens_count = input.read_u16()?;
+                let mut opens = Vec::with_capacity(opens_count as usize);
+                for _ in 0..opens_count {
+                    opens.push(module_opens::new(input)?);
+                }
+                let uses_count = input.read_u16()?;
+                let uses_index = module::read_indices(input, uses_count)?;
+                let provides_count = input.read_u16()?;
+                let mut provides = Vec::with_capacity(provides_count as usize);
+                for _ in 0..provides_count {
+                    provides.push(module_provides::new(input)?);
+                }
+                Module_attribute {
+                    module_name_index,
+                    module_flags,
+                    module_version_index,
+                    requires_count,
+                    requires,
+                    exports_count,
+                    exports,
+                    opens_count,
+                    opens,
+                    uses_count,
+                    uses_index,
+                    provides_count,
+                    provides,
+                }
+            }
+            "ModulePackages" => {
+                let package_count = input.read_u16()?;
+                let package_index = module::read_indices(input, package_count)?;
+                ModulePackages_attribute {
+                    package_count,
+                    package_index,
+                }
+            }
+            "ModuleMainClass" => {
+                let main_class_index = input.read_u16()?;
+                ModuleMainClass_attribute { main_class_index }
+            }
+            "NestHost" => {
+                let host_class_index = input.read_u16()?;
+                NestHost_attribute { host_class_index }
+            }
+            "NestMembers" => {
+                let number_of_classes = input.read_u16()?;
+                let classes = module::read_indices(input, number_of_classes)?;
+                NestMembers_attribute {
+                    number_of_classes,
+                    classes,
+                }
+            }
+            "Record" => {
+                let components_count = input.read_u16()?;
+                let mut components = Vec::with_capacity(components_count as usize);
+                for _ in 0..components_count {
+                    components.push(record_component_info::new(
+                        input,
+                        constant_pool,
+                        major_version,
+                    )?);
+                }
+                Record_attribute {
+                    components_count,
+                    components,
+                }
+            }
+            "PermittedSubclasses" => {
+                let number_of_classes = input.read_u16()?;
+                let classes = module::read_indices(input, number_of_classes)?;
+                PermittedSubclasses_attribute {
+                    number_of_classes,
+                    classes,
+                }
+            }
+            "MethodParameters" => {
+                let parameters_count = input.read_u8()?;
+                let mut parameters = Vec::with_capacity(parameters_count as usize);
+                for _ in 0..parameters_count {
+                    let name_index = input.read_u16()?;
+                    let access_flags = input.read_u16()?;
+                    parameters.push(method_parameter {
+                        name_index,
+                        access_flags,
+                    })
+                }
+                MethodParameters_attribute {
+                    parameters_count,
+                    parameters,
+                }
+            }
+            "RuntimeVisibleTypeAnnotations" => {
+                let num_annotations = input.read_u16()?;
+                let mut annotations = Vec::with_capacity(num_annotations as usize);
+                for _ in 0..num_annotations {
+                    annotations.push(type_annotation::new(input)?)
+                }
+                RuntimeVisibleTypeAnnotations_attribute {
+                    num_annotations,
+                    annotations,
+                }
+            }
+            "RuntimeInvisibleTypeAnnotations" => {
+                let num_annotations = input.read_u16()?;
+                let mut annotations = Vec::with_capacity(num_annotations as usize);
+                for _ in 0..num_annotations {
+                    annotations.push(type_annotation::new(input)?)
+                }
+                RuntimeInvisibleTypeAnnotations_attribute {
+                    num_annotations,
+                    annotations,
+                }
+            }
             _ => {
-                println!("Read Unknown Attribute: {}", name);
-                let mut infoVec = vec![0u8; attribute_length as usize];
-                input.read_exact(&mut infoVec)?;
+                let infoVec = input.read_bytes(attribute_length as usize)?.to_vec();
                 Unknown_attribute { info: infoVec }
             }
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct exception_info {
     start_pc: u16,
     end_pc: u16,
@@ -728,15 +1813,192 @@ pub struct exception_info {
     catch_type: u16,
 }
 
-enum InnerClassAccessFlag {
-    ACC_PUBLIC = 0x0001,
-    ACC_PRIVATE = 0x0002,
-    ACC_PROTECTED = 0x0004,
-    ACC_STATIC = 0x0008,
-    ACC_FINAL = 0x0010,
-    ACC_INTERFACE = 0x0200,
-    ACC_ABSTRACT = 0x0400,
-    ACC_SYNTHETIC = 0x1000,
-    ACC_ANNOTATION = 0x2000,
-    ACC_ENUM = 0x4000,
+impl exception_info {
+    pub fn get_start_pc(&self) -> u16 {
+        self.start_pc
+    }
+
+    /// Exclusive end of the range of code the handler is active in
+    pub fn get_end_pc(&self) -> u16 {
+        self.end_pc
+    }
+
+    pub fn get_handler_pc(&self) -> u16 {
+        self.handler_pc
+    }
+
+    /// constant_pool index of the `CONSTANT_Class_info` of the exception class caught, or 0 if
+    /// the handler catches everything
+    pub fn get_catch_type(&self) -> u16 {
+        self.catch_type
+    }
+
+    fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.start_pc);
+        output.write_u16(self.end_pc);
+        output.write_u16(self.handler_pc);
+        output.write_u16(self.catch_type);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assembler::assemble;
+    use attribute::attribute_info_Data::*;
+    use attribute::target_info::*;
+    use class_file::ClassFile;
+    use disassembler::disassemble;
+    use format_check::{check_format, FormatCheck};
+    use typed_arena::Arena;
+
+    /// A class with type annotations in each of the places they can appear
+    const TYPE_ANNOTATED: &[u8] = include_bytes!("../tests/fixtures/classes/p/TypeAnnotated.class");
+
+    #[test]
+    fn type_annotations_are_parsed_wherever_they_appear() {
+        let strings = Arena::new();
+        let class = ClassFile::new(&mut &TYPE_ANNOTATED[..], &strings).unwrap();
+        let annotations = class.get_type_annotations();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].get_target_type(), 0x10);
+        assert!(matches!(
+            annotations[0].get_target_info(),
+            supertype_target { supertype_index: 65535 }
+        ));
+        assert!(annotations[0].get_target_path().is_empty());
+
+        let annotations = class.get_fields()[0].get_type_annotations();
+        assert_eq!(annotations.len(), 1);
+        assert!(matches!(annotations[0].get_target_info(), empty_target));
+        let path = annotations[0].get_target_path();
+        assert_eq!(path.len(), 1);
+        assert_eq!(path[0].get_type_path_kind(), 3);
+        assert_eq!(path[0].get_type_argument_index(), 0);
+
+        // Those on the method itself come before those within its code
+        let annotations = class.get_methods()[0].get_type_annotations();
+        let target_types: Vec<u8> = annotations.iter()
+            .map(|annotation| annotation.get_target_type())
+            .collect();
+        assert_eq!(target_types, [0x16, 0x17, 0x44, 0x40]);
+        assert!(matches!(
+            annotations[0].get_target_info(),
+            formal_parameter_target { formal_parameter_index: 0 }
+        ));
+        assert!(matches!(
+            annotations[1].get_target_info(),
+            throws_target { throws_type_index: 0 }
+        ));
+        assert!(matches!(annotations[2].get_target_info(), offset_target { offset: 0 }));
+        match annotations[3].get_target_info() {
+            localvar_target { table, .. } => {
+                assert_eq!(table.len(), 1);
+                assert_eq!(
+                    (table[0].get_start_pc(), table[0].get_length(), table[0].get_index()),
+                    (0, 8, 0)
+                );
+            }
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(annotations[3].get_target_path()[0].get_type_path_kind(), 0);
+    }
+
+    #[test]
+    fn illegal_type_annotation_targets_are_rejected() {
+        let mut bytes = TYPE_ANNOTATED.to_vec();
+        let strings = Arena::new();
+        // The class annotation ends the file: target_type, type_index, path_length, type_index
+        // and num_element_value_pairs
+        let offset = bytes.len() - 8;
+        assert_eq!(bytes[offset], 0x10);
+        bytes[offset] = 0x20;
+        assert!(ClassFile::new(&mut &bytes[..], &strings).is_err());
+    }
+
+    const SOURCE: &str = "public class p.A
+  minor version: 0
+  major version: 52
+{
+  public static int m(int) throws java.io.IOException;
+    descriptor: (I)I
+    Code:
+      stack=2, locals=1, args_size=1
+        0: iload_0
+        1: iconst_1
+        2: iadd
+        3: ireturn
+      LineNumberTable:
+        line 7: 0
+      LocalVariableTable:
+        Start  Length  Slot  Name   Signature
+            0       4     0 value   I
+    Exceptions:
+      throws java.io.IOException
+    Deprecated: true
+}
+";
+
+    /// The class with the code_length of its Code attribute running past the end of the
+    /// attribute
+    fn truncated_code() -> Vec<u8> {
+        let mut bytes = assemble(SOURCE).unwrap();
+        let strings = Arena::new();
+        let offset = {
+            let class = ClassFile::from_bytes(&bytes, &strings).unwrap();
+            class.get_methods()[0].get_attributes()[0].get_offset()
+        };
+        // attribute_name_index, attribute_length, max_stack and max_locals come first
+        bytes[offset + 13] = 0xff;
+        bytes
+    }
+
+    fn disassembly(class: &ClassFile) -> String {
+        let mut output = Vec::new();
+        disassemble(class, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn lazy_attributes_decode_like_eager_ones() {
+        let bytes = assemble(SOURCE).unwrap();
+        let strings = Arena::new();
+        let eager = ClassFile::from_bytes(&bytes, &strings).unwrap();
+        let lazy = ClassFile::from_bytes_lazy(&bytes, &strings).unwrap();
+        let attributes = lazy.get_methods()[0].get_attributes();
+        assert!(attributes.iter().all(|info| !info.is_decoded()));
+        check_format(&lazy, FormatCheck::Strict).unwrap();
+        assert!(attributes.iter().all(|info| !info.is_decoded()));
+        assert_eq!(disassembly(&lazy), disassembly(&eager));
+        assert!(attributes.iter().all(|info| info.is_decoded()));
+        assert_eq!(lazy.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn malformed_lazy_attributes_are_not_hidden() {
+        let bytes = truncated_code();
+        let strings = Arena::new();
+        assert!(ClassFile::from_bytes(&bytes, &strings).is_err());
+        let class = ClassFile::from_bytes_lazy(&bytes, &strings).unwrap();
+        let info = &class.get_methods()[0].get_attributes()[0];
+        assert!(info.check().is_err());
+        assert!(!info.is_decoded());
+        assert!(matches!(info.get_data(), Malformed_attribute { .. }));
+        // Once seen as malformed, it stays an error rather than becoming the malformed variant
+        assert!(info.decode().is_err());
+        assert!(info.check().is_err());
+        assert!(class.get_methods()[0].get_code().is_none());
+        assert_eq!(class.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn malformed_lazy_code_fails_the_strict_format_check() {
+        let bytes = truncated_code();
+        let strings = Arena::new();
+        let class = ClassFile::from_bytes_lazy(&bytes, &strings).unwrap();
+        // A lenient check leaves the code to the verifier
+        check_format(&class, FormatCheck::Lenient).unwrap();
+        assert!(check_format(&class, FormatCheck::Strict).is_err());
+        assert!(!class.get_methods()[0].get_attributes()[0].is_decoded());
+        assert!(class.get_methods()[0].get_parameters().is_err());
+    }
 }
@@ -0,0 +1,65 @@
+extern crate jvm;
+
+use jvm::assembler::assemble;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "usage: jvm-jasm <source> [-o <class file>]";
+
+/// Assembles a class from the format printed by jvm-javap
+///
+/// The class file is written next to the source, with its extension replaced by `.class`,
+/// unless another path is given
+fn main() {
+    let mut source = None;
+    let mut output = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(path) => output = Some(path),
+                None => exit_with_usage(),
+            },
+            "-h" | "-help" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') || source.is_some() => exit_with_usage(),
+            _ => source = Some(arg),
+        }
+    }
+    let source = match source {
+        Some(source) => source,
+        None => exit_with_usage(),
+    };
+    let output = match output {
+        Some(output) => output,
+        None => Path::new(&source).with_extension("class").to_string_lossy().into_owned(),
+    };
+
+    let text = match fs::read_to_string(&source) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("Error: could not read {}: {}", source, error);
+            process::exit(1);
+        }
+    };
+    let bytes = match assemble(&text) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            eprintln!("Error: {}: {}", source, error);
+            process::exit(1);
+        }
+    };
+    if let Err(error) = fs::write(&output, bytes) {
+        eprintln!("Error: could not write {}: {}", output, error);
+        process::exit(1);
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
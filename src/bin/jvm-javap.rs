@@ -0,0 +1,105 @@
+extern crate jvm;
+extern crate typed_arena;
+
+use jvm::class_file::{ClassFile, ClassLoadingError};
+use jvm::class_path::{path_to_classpath, search_classpath, ClassBytes, ClassPath};
+use jvm::disassembler::disassemble;
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::process;
+use typed_arena::Arena;
+
+const USAGE: &str = "usage: jvm-javap [-cp <path>] <class>...";
+
+/// Prints classes in the format of `javap -c -v -p`
+///
+/// Classes are given either by name, eg `java.lang.Object`, and searched for on the classpath,
+/// or as paths to class files
+fn main() {
+    let mut class_path = String::from(".");
+    let mut classes = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" | "-classpath" | "--class-path" => match args.next() {
+                Some(path) => class_path = path,
+                None => exit_with_usage(),
+            },
+            "-h" | "-help" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => exit_with_usage(),
+            _ => classes.push(arg),
+        }
+    }
+    if classes.is_empty() {
+        exit_with_usage();
+    }
+
+    let byte_allocator = Arena::new();
+    let string_allocator = Arena::new();
+    let mut class_path_list = Vec::new();
+    for path in env::split_paths(&class_path) {
+        let path = path.to_string_lossy();
+        match path_to_classpath(&path, &byte_allocator) {
+            Ok(entry) => class_path_list.push(entry),
+            Err(error) => {
+                eprintln!("Error: could not open classpath entry {}: {}", path, error);
+                process::exit(1);
+            }
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let mut failed = false;
+    for class in &classes {
+        let result = find_class(class, &mut class_path_list, &byte_allocator)
+            .and_then(|bytes| ClassFile::from_bytes(bytes, &string_allocator));
+        match result {
+            Ok(class_file) => {
+                if let Err(error) = disassemble(&class_file, &mut output) {
+                    // The output was piped into something that stopped reading
+                    if error.kind() == io::ErrorKind::BrokenPipe {
+                        process::exit(0);
+                    }
+                    eprintln!("Error: could not write {}: {}", class, error);
+                    process::exit(1);
+                }
+            }
+            Err(ClassLoadingError::NoClassDefFoundError) => {
+                eprintln!("Error: class not found: {}", class);
+                failed = true;
+            }
+            Err(error) => {
+                eprintln!("Error: could not read {}: {}", class, error);
+                failed = true;
+            }
+        }
+    }
+    let _ = output.flush();
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Reads a class file given as a path, or searches the classpath for a class given by name
+fn find_class<'a>(
+    class: &str,
+    class_path_list: &mut Vec<ClassPath<'a>>,
+    byte_allocator: &'a Arena<ClassBytes>,
+) -> Result<&'a [u8], ClassLoadingError> {
+    if class.ends_with(".class") && fs::metadata(class).is_ok() {
+        let bytes = fs::read(class)?;
+        return Ok(byte_allocator.alloc(ClassBytes::Read(bytes)));
+    }
+    search_classpath(class_path_list, &class.replace('.', "/"), byte_allocator)
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
@@ -1,17 +1,19 @@
 use class::Class::*;
 use class_array::ClassArray;
 use class_file::ClassFile;
+use class_file::ClassLoadingError;
 use std::cell::RefCell;
 use lazy::LazyResolve;
 use class::ClassRef::{Static, Symbolic};
 
 #[derive(Debug)]
 pub enum Class<'a> {
-    File(ClassFile<'a>),
+    /// Boxed, as a class file is many times the size of an array class
+    File(Box<ClassFile<'a>>),
     Array(ClassArray<'a>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ClassRef<'a> {
     Symbolic(&'a str),
     Static(&'a RefCell<Class<'a>>)
@@ -26,15 +28,20 @@ impl<'a> ClassRef<'a> {
         }
     }
 
-    pub fn resolve<'b, 'c, T>(&'b mut self, resolver: &'c mut T) -> &'a RefCell<Class<'a>>
+    /// Loads the class if it hasn't been already, leaving the reference unresolved if it can't
+    /// be loaded
+    pub fn resolve<T>(
+        &mut self,
+        resolver: &mut T,
+    ) -> Result<&'a RefCell<Class<'a>>, ClassLoadingError>
         where T: LazyResolve<'a, RefCell<Class<'a>>> {
         let class_name = match self {
             Symbolic(class_name) => *class_name,
-            Static(class_ref) => return class_ref
+            Static(class_ref) => return Ok(class_ref)
         };
 
-        *self = Static(resolver.resolve(class_name));
-        self.get()
+        *self = Static(resolver.resolve(class_name)?);
+        Ok(self.get())
     }
 }
 
@@ -54,8 +61,8 @@ impl<'a> Class<'a> {
     }
 }
 
-/// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.1-200-E.1>
 bitflags! {
+    /// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.1-200-E.1>
     pub struct ClassAccessFlag: u16 {
         const ACC_PUBLIC      = 0x0001;
         const ACC_FINAL       = 0x0010;
@@ -65,5 +72,6 @@ bitflags! {
         const ACC_SYNTHETIC   = 0x1000;
         const ACC_ANNOTATION  = 0x2000;
         const ACC_ENUM        = 0x4000;
+        const ACC_MODULE      = 0x8000;
     }
 }
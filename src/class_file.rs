@@ -1,18 +1,40 @@
+use annotation;
+use annotation::Annotation;
 use attribute;
-use byteorder::{BigEndian, ReadBytesExt};
+use attribute::AttributeSource;
 use class::ClassRef;
 use class_file::ClassLoadingError::*;
+use class_reader::ClassReader;
+use class_writer::ClassWriter;
 use constant_pool::cp_info;
 use constant_pool::cp_info::*;
 use constant_pool::read_constant_pool;
 use constant_pool::ConstantPool;
+use attribute::attribute_info_Data::{
+    BootstrapMethods_attribute, NestHost_attribute, NestMembers_attribute,
+    PermittedSubclasses_attribute, SourceFile_attribute,
+};
 use field;
 use field::FieldInfo;
+use inner_class;
+use inner_class::{EnclosingMethod, InnerClass, InnerClassAccessFlag, NestingKind};
 use method;
+use module;
+use module::ModuleInfo;
+use record;
+use record::RecordComponent;
+use signature;
+use signature::ClassSignature;
+use source_map;
+use source_map::{SourceLocation, SourceMap};
 use std;
+use std::borrow::Cow;
 use std::convert::From;
+use std::fmt;
 use std::io::ErrorKind;
 use std::io::Read;
+use std::io::Write;
+use std::rc::Rc;
 use typed_arena::Arena;
 use class::ClassAccessFlag;
 use class::ClassRef::Symbolic;
@@ -23,31 +45,62 @@ pub struct ClassFile<'a> {
     minor_version: u16,
     major_version: u16,
     constant_pool_count: u16,
-    constant_pool: ConstantPool<'a>,
+    constant_pool: Rc<ConstantPool<'a>>,
     access_flags: ClassAccessFlag,
+    /// `access_flags` as they appear in the class file, including reserved bits
+    raw_access_flags: u16,
+    this_class_index: u16,
     this_class: &'a str,
+    super_class_index: u16,
     super_class: Option<ClassRef<'a>>,
     interfaces_count: u16,
+    interface_indices: Vec<u16>,
     interfaces: Vec<ClassRef<'a>>,
     fields_count: u16,
     fields: Vec<FieldInfo<'a>>,
     methods_count: u16,
     methods: Vec<method::MethodInfo<'a>>,
     attributes_count: u16,
-    attributes: Vec<attribute::attribute_info>,
+    attributes: Vec<attribute::attribute_info<'a>>,
+    module: Option<ModuleInfo<'a>>,
+    nest_host: Option<&'a str>,
+    nest_members: Vec<&'a str>,
+    record_components: Option<Vec<RecordComponent<'a>>>,
+    permitted_subclasses: Option<Vec<&'a str>>,
+    inner_classes: Vec<InnerClass<'a>>,
+    enclosing_method: Option<EnclosingMethod<'a>>,
 }
 
 impl<'a> ClassFile<'a> {
-    const CURRENT_VERSION: u16 = 52;
+    const MAGIC: u32 = 0xCAFEBABE;
+    const MIN_VERSION: u16 = 45;
+    const CURRENT_VERSION: u16 = 65;
+    /// The first major version that may contain modules, and the `ACC_MODULE` flag
+    const MODULE_VERSION: u16 = 53;
+    /// From this major version on, the minor version must be 0 (or 65535 for preview features)
+    const MINOR_VERSION_RESTRICTED: u16 = 56;
 
-    fn get_constant_entry(&self, index: u16) -> &cp_info {
+    fn get_constant_entry(&self, index: u16) -> Result<&cp_info<'_>, ClassLoadingError> {
         self.constant_pool.get_entry(index)
     }
 
-    pub fn get_string_entry(&self, index: u16) -> &str {
+    pub fn get_string_entry(&self, index: u16) -> Result<&str, ClassLoadingError> {
         self.constant_pool.get_string_entry(index)
     }
 
+    /// The constant pool of this class, with typed accessors for each kind of entry
+    pub fn get_constant_pool(&self) -> &ConstantPool<'a> {
+        &self.constant_pool
+    }
+
+    pub fn get_minor_version(&self) -> u16 {
+        self.minor_version
+    }
+
+    pub fn get_major_version(&self) -> u16 {
+        self.major_version
+    }
+
     pub fn has_super_class(&self) -> bool {
         self.super_class.is_some()
     }
@@ -60,72 +113,452 @@ impl<'a> ClassFile<'a> {
         self.access_flags
     }
 
-    pub fn get_name(&self) -> &str {
+    /// `access_flags` as they appear in the class file, including reserved bits
+    pub fn get_raw_access_flags(&self) -> u16 {
+        self.raw_access_flags
+    }
+
+    pub fn get_name(&self) -> &'a str {
         self.this_class
     }
 
+    /// constant_pool index of the `CONSTANT_Class_info` naming this class
+    pub fn get_this_class_index(&self) -> u16 {
+        self.this_class_index
+    }
+
+    /// constant_pool index of the `CONSTANT_Class_info` naming the superclass, or 0 for
+    /// `java/lang/Object` and module-info classes
+    pub fn get_super_class_index(&self) -> u16 {
+        self.super_class_index
+    }
+
+    /// The name of the direct superclass, `None` for `java/lang/Object` and module-info classes
+    pub fn get_super_class_name(&self) -> Option<&'a str> {
+        match self.super_class_index {
+            0 => None,
+            index => self.constant_pool.class_name(index).ok(),
+        }
+    }
+
     pub fn get_super_class(&self) -> &Option<ClassRef<'a>> {
         &self.super_class
     }
 
-    pub fn new<'b>(
-        input: &'b mut Read,
+    pub fn get_fields(&self) -> &[FieldInfo<'a>] {
+        &self.fields
+    }
+
+    pub fn get_methods(&self) -> &[method::MethodInfo<'a>] {
+        &self.methods
+    }
+
+    pub fn resolve_interfaces(&mut self) -> &mut Vec<ClassRef<'a>> {
+        &mut self.interfaces
+    }
+
+    pub fn get_interfaces(&self) -> &[ClassRef<'a>] {
+        &self.interfaces
+    }
+
+    /// constant_pool indices of the `CONSTANT_Class_info`s naming the direct superinterfaces
+    pub fn get_interface_indices(&self) -> &[u16] {
+        &self.interface_indices
+    }
+
+    pub fn get_attributes(&self) -> &[attribute::attribute_info<'a>] {
+        &self.attributes
+    }
+
+    /// The generic signature of this class, from its Signature attribute
+    pub fn get_signature(&self) -> Result<Option<ClassSignature<'a>>, ClassLoadingError> {
+        signature::read_signature(&self.attributes, &self.constant_pool, signature::parse_class_signature)
+    }
+
+    /// The visible and invisible type annotations on the declaration of this class
+    pub fn get_type_annotations(&self) -> Vec<&attribute::type_annotation> {
+        attribute::get_type_annotations(&self.attributes)
+    }
+
+    /// The annotations on the declaration of this class, both visible and invisible
+    pub fn get_annotations(&self) -> Result<Vec<Annotation<'a>>, ClassLoadingError> {
+        annotation::read_annotations(&self.attributes, &self.constant_pool)
+    }
+
+    /// The annotation of an interface, given by its field descriptor, on this class
+    pub fn find_annotation(
+        &self,
+        type_descriptor: &str,
+    ) -> Result<Option<Annotation<'a>>, ClassLoadingError> {
+        Ok(annotation::find_annotation(self.get_annotations()?, type_descriptor))
+    }
+
+    /// The name of the file this class was compiled from, from its SourceFile attribute
+    pub fn get_source_file(&self) -> Result<Option<&'a str>, ClassLoadingError> {
+        for info in self.attributes.iter() {
+            if let SourceFile_attribute { sourcefile_index } = info.get_data() {
+                return self.constant_pool.get_string_entry(*sourcefile_index)
+                    .map(Some)
+                    .map_err(|error| error.at_offset(info.get_offset()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The SMAP in this class's SourceDebugExtension attribute, which maps its lines back to
+    /// the sources it was generated from
+    pub fn get_source_map(&self) -> Result<Option<SourceMap>, ClassLoadingError> {
+        source_map::read_source_map(&self.attributes)
+    }
+
+    /// The source file and line of the instruction at `pc` in one of this class's methods
+    ///
+    /// The line is looked up in the method's LineNumberTable, then mapped to the default stratum
+    /// of the class's SMAP if it has one. Without an SMAP, or when its default stratum is
+    /// `Java`, the line is in the file named by the SourceFile attribute. `None` if the class
+    /// doesn't have the debug information to tell, or the SMAP doesn't map the line.
+    pub fn get_source_location(
+        &self,
+        method: &method::MethodInfo<'a>,
+        pc: u32,
+    ) -> Result<Option<SourceLocation>, ClassLoadingError> {
+        let line = match method.get_line_number(pc) {
+            Some(line) => u32::from(line),
+            None => return Ok(None),
+        };
+        if let Some(source_map) = self.get_source_map()? {
+            if source_map.get_default_stratum() != "Java" {
+                return Ok(source_map.get_stratum(source_map.get_default_stratum())
+                    .and_then(|stratum| stratum.map_line(line)));
+            }
+        }
+        Ok(self.get_source_file()?.map(|file_name| {
+            SourceLocation::new(String::from(file_name), None, line)
+        }))
+    }
+
+    /// Whether this class is a record, having a Record attribute
+    pub fn is_record(&self) -> bool {
+        self.record_components.is_some()
+    }
+
+    /// The components of a record class, from its Record attribute
+    pub fn get_record_components(&self) -> Option<&[RecordComponent<'a>]> {
+        self.record_components.as_deref()
+    }
+
+    /// Whether this class is sealed, restricting its direct subclasses with a
+    /// PermittedSubclasses attribute
+    pub fn is_sealed(&self) -> bool {
+        self.permitted_subclasses.is_some()
+    }
+
+    /// The classes allowed to directly extend or implement this sealed class
+    pub fn get_permitted_subclasses(&self) -> Option<&[&'a str]> {
+        self.permitted_subclasses.as_deref()
+    }
+
+    /// Gets the module descriptor of a module-info class
+    pub fn get_module(&self) -> Option<&ModuleInfo<'a>> {
+        self.module.as_ref()
+    }
+
+    /// The nest host this class claims membership of through its `NestHost_attribute`
+    ///
+    /// The claim only holds once the host agrees, see `ClassFile::is_nest_host_of`
+    pub fn get_nest_host(&self) -> Option<&'a str> {
+        self.nest_host
+    }
+
+    /// The classes this class lists as members of its nest through its `NestMembers_attribute`
+    pub fn get_nest_members(&self) -> &[&'a str] {
+        &self.nest_members
+    }
+
+    /// The name of the package this class is a member of, eg `java/lang` for `java/lang/Object`
+    pub fn get_package_name(&self) -> &'a str {
+        package_of(self.this_class)
+    }
+
+    /// The nested classes this class refers to or declares, from its InnerClasses attribute,
+    /// including this class itself if it is nested
+    pub fn get_inner_classes(&self) -> &[InnerClass<'a>] {
+        &self.inner_classes
+    }
+
+    /// The entry of this class's InnerClasses attribute that describes this class, if it is
+    /// nested
+    pub fn get_inner_class_entry(&self) -> Option<&InnerClass<'a>> {
+        self.inner_classes.iter().find(|entry| entry.get_inner_class() == self.this_class)
+    }
+
+    /// The method or initializer a local or anonymous class is declared in, from its
+    /// EnclosingMethod attribute
+    pub fn get_enclosing_method(&self) -> Option<&EnclosingMethod<'a>> {
+        self.enclosing_method.as_ref()
+    }
+
+    /// Whether this class is top level, a member of another class, local or anonymous
+    ///
+    /// As with `Class::isAnonymousClass`, a class with an EnclosingMethod attribute that
+    /// doesn't describe itself in its InnerClasses attribute is anonymous
+    pub fn get_nesting_kind(&self) -> NestingKind {
+        match self.get_inner_class_entry() {
+            Some(entry) if entry.get_outer_class().is_some() && self.enclosing_method.is_none() => {
+                NestingKind::Member
+            }
+            None if self.enclosing_method.is_none() => NestingKind::TopLevel,
+            Some(entry) if entry.get_inner_name().is_some() => NestingKind::Local,
+            _ => NestingKind::Anonymous,
+        }
+    }
+
+    /// The class this class is a member of, `None` unless it is a member class
+    pub fn get_declaring_class(&self) -> Option<&'a str> {
+        match self.get_nesting_kind() {
+            NestingKind::Member => self.get_inner_class_entry()?.get_outer_class(),
+            _ => None,
+        }
+    }
+
+    /// The innermost class the declaration of this class is within, its declaring class if it
+    /// is a member and otherwise the class of its enclosing method
+    pub fn get_enclosing_class(&self) -> Option<&'a str> {
+        self.get_declaring_class()
+            .or_else(|| self.enclosing_method.map(|method| method.get_class()))
+    }
+
+    /// The name of this class in its source, eg `Entry` for `java/util/Map$Entry`, or an empty
+    /// string if it is anonymous
+    pub fn get_simple_name(&self) -> &'a str {
+        match self.get_nesting_kind() {
+            NestingKind::TopLevel => {
+                let name = self.this_class;
+                name.rfind('/').map_or(name, |index| &name[index + 1..])
+            }
+            NestingKind::Anonymous => "",
+            _ => self.get_inner_class_entry()
+                .and_then(|entry| entry.get_inner_name())
+                .unwrap_or(""),
+        }
+    }
+
+    /// The access flags this class was declared with in its source
+    ///
+    /// A nested class takes them from its InnerClasses entry, as its class file can't be
+    /// private, protected or static. The flags of a top level class are those of its class
+    /// file, without `ACC_SUPER` and `ACC_MODULE`.
+    pub fn get_source_access_flags(&self) -> InnerClassAccessFlag {
+        match self.get_inner_class_entry() {
+            Some(entry) => entry.get_access_flags(),
+            None => InnerClassAccessFlag::from_bits_truncate(self.access_flags.bits()),
+        }
+    }
+
+    /// Whether `member` is validly a member of the nest hosted by this class
+    ///
+    /// Both sides must agree: `member` must name this class as its nest host, this class must
+    /// list `member` among its nest members, and both must be in the same run-time package
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4>
+    pub fn is_nest_host_of(&self, member: &ClassFile) -> bool {
+        member.nest_host == Some(self.this_class)
+            && self.nest_members.contains(&member.this_class)
+            && self.get_package_name() == member.get_package_name()
+    }
+
+    /// Gets the entries of this class's `BootstrapMethods_attribute`, if it has one
+    pub fn get_bootstrap_methods(&self) -> Option<&[attribute::bootstrap_method]> {
+        find_bootstrap_methods(&self.attributes)
+    }
+
+    /// Gets the bootstrap method that a `CONSTANT_InvokeDynamic_info` refers to
+    /// through its `bootstrap_method_attr_index`
+    pub fn get_bootstrap_method(&self, index: u16) -> Option<&attribute::bootstrap_method> {
+        self.get_bootstrap_methods()
+            .and_then(|methods| methods.get(index as usize))
+    }
+
+    /// Serializes the class file, reproducing the bytes it was parsed from
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = ClassWriter::new();
+        output.write_u32(self.magic);
+        output.write_u16(self.minor_version);
+        output.write_u16(self.major_version);
+        self.constant_pool.write(&mut output);
+        output.write_u16(self.raw_access_flags);
+        output.write_u16(self.this_class_index);
+        output.write_u16(self.super_class_index);
+        output.write_u16_table(&self.interface_indices);
+        output.write_u16(self.fields.len() as u16);
+        for field in self.fields.iter() {
+            field.write(&mut output);
+        }
+        output.write_u16(self.methods.len() as u16);
+        for method in self.methods.iter() {
+            method.write(&mut output);
+        }
+        attribute::write_attributes(&mut output, &self.attributes);
+        output.into_bytes()
+    }
+
+    /// Writes the class file to a stream, such as a .class file
+    pub fn write(&self, output: &mut dyn Write) -> std::io::Result<()> {
+        output.write_all(&self.to_bytes())
+    }
+
+    /// Reads and parses a class file, copying its strings into `string_allocator`
+    pub fn new(
+        input: &mut dyn Read,
         string_allocator: &'a Arena<String>
     ) -> Result<ClassFile<'a>, ClassLoadingError> {
-        let magic = input.read_u32::<BigEndian>()?;
-        let minor_version = input.read_u16::<BigEndian>()?;
-        let major_version = input.read_u16::<BigEndian>()?;
-        if major_version > ClassFile::CURRENT_VERSION {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        ClassFile::parse(&bytes, None, &mut |string| {
+            string_allocator.alloc(string.into_owned()).as_str()
+        })
+    }
+
+    /// Parses a class file in place
+    ///
+    /// Strings that are stored as plain UTF-8 are borrowed from `bytes`, only those that
+    /// modified UTF-8 decoding changes are allocated in `string_allocator`
+    pub fn from_bytes(
+        bytes: &'a [u8],
+        string_allocator: &'a Arena<String>
+    ) -> Result<ClassFile<'a>, ClassLoadingError> {
+        ClassFile::parse(bytes, None, &mut |string| match string {
+            Cow::Borrowed(string) => string,
+            Cow::Owned(string) => string_allocator.alloc(string).as_str(),
+        })
+    }
+
+    /// Parses a class file in place like `from_bytes`, but only locates the attributes of its
+    /// fields and methods, decoding each one when it is first accessed
+    ///
+    /// This suits loading many classes when only their names, descriptors and hierarchy are
+    /// needed. Malformed field and method attributes go unnoticed until they are decoded, or
+    /// until the class is checked by `check_format`
+    pub fn from_bytes_lazy(
+        bytes: &'a [u8],
+        string_allocator: &'a Arena<String>
+    ) -> Result<ClassFile<'a>, ClassLoadingError> {
+        ClassFile::parse(bytes, Some(bytes), &mut |string| match string {
+            Cow::Borrowed(string) => string,
+            Cow::Owned(string) => string_allocator.alloc(string).as_str(),
+        })
+    }
+
+    /// Parses a class file, failing with a `ClassFormatError` that gives the byte offset of
+    /// the problem if it is malformed
+    ///
+    /// `lazy_source` is given the same bytes when the attributes of fields and methods are to
+    /// be decoded from them as they are accessed
+    fn parse<'b>(
+        bytes: &'b [u8],
+        lazy_source: Option<&'a [u8]>,
+        strings: &mut dyn FnMut(Cow<'b, str>) -> &'a str,
+    ) -> Result<ClassFile<'a>, ClassLoadingError> {
+        let input = &mut ClassReader::new(bytes);
+        let magic = input.read_u32()?;
+        if magic != ClassFile::MAGIC {
+            return Err(ClassFormatError(format!("Bad magic number: {:#010x}", magic)).at_offset(0));
+        }
+        let minor_version = input.read_u16()?;
+        let major_version = input.read_u16()?;
+        if !(ClassFile::MIN_VERSION..=ClassFile::CURRENT_VERSION).contains(&major_version) {
             return Err(UnsupportedClassVersionError);
         }
-        let constant_pool_count = input.read_u16::<BigEndian>()?;
-        let constant_pool = read_constant_pool(input, constant_pool_count, string_allocator)?;
-        let access_flags = ClassAccessFlag::from_bits(input.read_u16::<BigEndian>()?)
-            .expect("Couldn't parse Class Access Flags");
-        let this_class_index = input.read_u16::<BigEndian>()?;
-        let this_class = {
-            let this_class_data = constant_pool.get_entry(this_class_index);
-            if let CONSTANT_Class_info { name_index } = this_class_data {
-                constant_pool.get_string_entry(*name_index)
-            } else {
-                panic!(
-                    "ClassFile#this_class pointed to non CONSTANT_Class_attribute: {:?}",
-                    this_class_data
-                )
-            }
-        };
-        let super_class_index = input.read_u16::<BigEndian>()?;
+        // Preview features (minor version 65535) are not supported
+        if major_version >= ClassFile::MINOR_VERSION_RESTRICTED && minor_version != 0 {
+            return Err(UnsupportedClassVersionError);
+        }
+        let constant_pool_count = input.read_u16()?;
+        let constant_pool =
+            Rc::new(read_constant_pool(input, constant_pool_count, strings, major_version)?);
+        let source = lazy_source.map(|bytes| {
+            Rc::new(AttributeSource::new(bytes, constant_pool.clone(), major_version))
+        });
+        // Flags not assigned at this class file version are reserved and ignored
+        let raw_access_flags = input.read_u16()?;
+        let mut access_flags = ClassAccessFlag::from_bits_truncate(raw_access_flags);
+        if major_version < ClassFile::MODULE_VERSION {
+            access_flags.remove(ClassAccessFlag::ACC_MODULE);
+        }
+        let offset = input.get_offset();
+        let this_class_index = input.read_u16()?;
+        let this_class = constant_pool.class_name(this_class_index)
+            .map_err(|error| error.at_offset(offset))?;
+        let offset = input.get_offset();
+        let super_class_index = input.read_u16()?;
         let super_class = if super_class_index == 0 {
             None
         } else {
-            let super_class_data = constant_pool.get_entry(super_class_index);
-            if let CONSTANT_Class_info { name_index } = super_class_data {
-                let super_class_name = constant_pool.get_string_entry(*name_index);
-                Some(Symbolic(super_class_name))
-            } else {
-                panic!(
-                    "ClassFile#super_class didn't point to CONSTANT_Class_info, instead: {:?}",
-                    super_class_data
-                )
-            }
+            let super_class_name = constant_pool.class_name(super_class_index)
+                .map_err(|error| error.at_offset(offset))?;
+            Some(Symbolic(super_class_name))
         };
-        let interfaces_count = input.read_u16::<BigEndian>()?;
-        let interfaces = read_interfaces(input, interfaces_count)?;
-        let interfaces = interfaces.iter().map(|i| {
-            let class_info = constant_pool.get_entry(*i);
-            let string_index = if let CONSTANT_Class_info { name_index } = class_info {
-                name_index
-            } else {
-                panic!("ClassFile#interfaces index {} didn't contain CONSTANT_Class_info, instead: {:?}", i, class_info)
-            };
-            Symbolic(constant_pool.get_string_entry(*string_index))
-        }).collect();
-        let fields_count = input.read_u16::<BigEndian>()?;
-        let fields = field::read_fields(input, fields_count, &constant_pool, this_class)?;
-        let methods_count = input.read_u16::<BigEndian>()?;
-        let methods = method::read_methods(input, methods_count, &constant_pool, this_class)?;
-        let attributes_count = input.read_u16::<BigEndian>()?;
-        let attributes = attribute::read_attributes(input, attributes_count, &constant_pool)?;
+        let interfaces_count = input.read_u16()?;
+        let offset = input.get_offset();
+        let interface_indices = read_interfaces(input, interfaces_count)?;
+        let interfaces = interface_indices.iter().enumerate().map(|(i, index)| {
+            constant_pool.class_name(*index)
+                .map(Symbolic)
+                .map_err(|error| error.at_offset(offset + 2 * i))
+        }).collect::<Result<Vec<_>, ClassLoadingError>>()?;
+        let fields_count = input.read_u16()?;
+        let fields = field::read_fields(
+            input,
+            fields_count,
+            &constant_pool,
+            this_class,
+            access_flags,
+            major_version,
+            source.as_ref(),
+        )?;
+        let methods_count = input.read_u16()?;
+        let methods = method::read_methods(
+            input,
+            methods_count,
+            &constant_pool,
+            this_class,
+            access_flags,
+            major_version,
+            source.as_ref(),
+        )?;
+        // Class attributes are always decoded, they are needed to load and link the class
+        let attributes_count = input.read_u16()?;
+        let attributes = attribute::read_attributes(
+            input,
+            attributes_count,
+            &constant_pool,
+            major_version,
+            None,
+        )?;
+        if input.remaining() != 0 {
+            return Err(input.error(format!(
+                "{} extra bytes after the end of the class file",
+                input.remaining()
+            )));
+        }
+        check_bootstrap_methods(&constant_pool, &attributes)?;
+        if access_flags.contains(ClassAccessFlag::ACC_MODULE) {
+            check_module_structure(
+                this_class,
+                super_class_index,
+                interfaces_count,
+                fields_count,
+                methods_count,
+            )?;
+        } else {
+            check_no_module_constants(&constant_pool)?;
+        }
+        let module = module::read_module(&attributes, &constant_pool)?;
+        let (nest_host, nest_members) = read_nest(&attributes, &constant_pool)?;
+        let record_components = record::read_record(&attributes, &constant_pool)?;
+        let permitted_subclasses = read_permitted_subclasses(&attributes, &constant_pool)?;
+        let inner_classes = inner_class::read_inner_classes(&attributes, &constant_pool)?;
+        let enclosing_method = inner_class::read_enclosing_method(&attributes, &constant_pool)?;
         Ok(ClassFile {
             magic,
             minor_version,
@@ -133,9 +566,13 @@ impl<'a> ClassFile<'a> {
             constant_pool_count,
             constant_pool,
             access_flags,
+            raw_access_flags,
+            this_class_index,
             this_class,
+            super_class_index,
             super_class,
             interfaces_count,
+            interface_indices,
             interfaces,
             fields_count,
             fields,
@@ -143,11 +580,18 @@ impl<'a> ClassFile<'a> {
             methods,
             attributes_count,
             attributes,
+            module,
+            nest_host,
+            nest_members,
+            record_components,
+            permitted_subclasses,
+            inner_classes,
+            enclosing_method,
         })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ClassLoadingError {
     LinkageError,
     ClassFormatError(String),
@@ -155,11 +599,41 @@ pub enum ClassLoadingError {
     NoClassDefFoundError,
     IncompatibleClassChangeError,
     ClassCircularityError,
+    /// Code that fails verification, in a method given by its class, name and descriptor
+    VerifyError {
+        method: String,
+        pc: u32,
+        message: String,
+    },
+}
+
+impl ClassLoadingError {
+    /// Records the byte offset into the class file where a `ClassFormatError` was found
+    ///
+    /// Other kinds of error are left as is
+    pub fn at_offset(self, offset: usize) -> ClassLoadingError {
+        match self {
+            ClassFormatError(message) => ClassFormatError(format!("{} at byte {}", message, offset)),
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for ClassLoadingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClassFormatError(message) => write!(f, "ClassFormatError: {}", message),
+            VerifyError { method, pc, message } => {
+                write!(f, "VerifyError: {} in {} at pc {}", message, method, pc)
+            }
+            other => write!(f, "{:?}", other),
+        }
+    }
 }
 
 impl From<zip::result::ZipError> for ClassLoadingError {
-    fn from(_: zip::result::ZipError) -> Self {
-        panic!("Error reading zip file")
+    fn from(error: zip::result::ZipError) -> Self {
+        ClassFormatError(format!("Error reading zip file: {}", error))
     }
 }
 
@@ -168,20 +642,350 @@ impl From<std::io::Error> for ClassLoadingError {
         if error.kind() == ErrorKind::UnexpectedEof {
             return ClassFormatError(String::from("Parsing reached end of Class File"));
         }
-        panic!("Unknown error parsing class file: {}", error);
+        ClassFormatError(format!("Error reading class file: {}", error))
     }
 }
 
 impl From<cesu8::Cesu8DecodingError> for ClassLoadingError {
     fn from(error: cesu8::Cesu8DecodingError) -> Self {
-        panic!("Error decoding Modified UTF8: {}", error)
+        ClassFormatError(format!("Error decoding Modified UTF8: {}", error))
+    }
+}
+
+fn find_bootstrap_methods<'b>(
+    attributes: &'b [attribute::attribute_info],
+) -> Option<&'b [attribute::bootstrap_method]> {
+    attributes.iter().filter_map(|info| {
+        if let BootstrapMethods_attribute { bootstrap_methods, .. } = info.get_data() {
+            Some(bootstrap_methods.as_slice())
+        } else {
+            None
+        }
+    }).next()
+}
+
+/// Every `CONSTANT_InvokeDynamic_info` must index into the class's `BootstrapMethods_attribute`
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.7.23>
+fn check_bootstrap_methods(
+    constant_pool: &ConstantPool,
+    attributes: &[attribute::attribute_info],
+) -> Result<(), ClassLoadingError> {
+    let bootstrap_methods = find_bootstrap_methods(attributes);
+    for entry in constant_pool.entries() {
+        let bootstrap_method_attr_index = match entry {
+            CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, .. }
+            | CONSTANT_Dynamic_info { bootstrap_method_attr_index, .. } => {
+                *bootstrap_method_attr_index
+            }
+            _ => continue,
+        };
+        match bootstrap_methods {
+            None => return Err(ClassFormatError(format!(
+                "{:?} present without a BootstrapMethods attribute",
+                entry
+            ))),
+            Some(methods) if bootstrap_method_attr_index as usize >= methods.len() => {
+                return Err(ClassFormatError(format!(
+                    "bootstrap_method_attr_index {} out of bounds: {:?}",
+                    bootstrap_method_attr_index, entry
+                )))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// A class file with `ACC_MODULE` set describes a module, not a class or interface
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.1-200-E.1>
+fn check_module_structure(
+    this_class: &str,
+    super_class_index: u16,
+    interfaces_count: u16,
+    fields_count: u16,
+    methods_count: u16,
+) -> Result<(), ClassLoadingError> {
+    if this_class != "module-info" {
+        return Err(ClassFormatError(format!(
+            "Class {} has ACC_MODULE set but is not module-info",
+            this_class
+        )));
+    }
+    if super_class_index != 0 || interfaces_count != 0 || fields_count != 0 || methods_count != 0 {
+        return Err(ClassFormatError(String::from(
+            "module-info must not declare a superclass, interfaces, fields or methods",
+        )));
+    }
+    Ok(())
+}
+
+/// `CONSTANT_Module_info` and `CONSTANT_Package_info` may only appear in module-info
+fn check_no_module_constants(constant_pool: &ConstantPool) -> Result<(), ClassLoadingError> {
+    for entry in constant_pool.entries() {
+        match entry {
+            CONSTANT_Module_info { .. } | CONSTANT_Package_info { .. } => {
+                return Err(ClassFormatError(format!(
+                    "{:?} is only allowed in a class file with ACC_MODULE set",
+                    entry
+                )))
+            }
+            _ => {}
+        }
     }
+    Ok(())
 }
 
-fn read_interfaces(input: &mut Read, length: u16) -> Result<Vec<u16>, ClassLoadingError> {
+/// Resolves the `NestHost_attribute` and `NestMembers_attribute` of a class
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.28>
+fn read_nest<'a>(
+    attributes: &[attribute::attribute_info],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<(Option<&'a str>, Vec<&'a str>), ClassLoadingError> {
+    let mut nest_host = None;
+    let mut nest_members = None;
+    for info in attributes {
+        match info.get_data() {
+            NestHost_attribute { host_class_index } => {
+                nest_host = Some(constant_pool.class_name(*host_class_index)
+                    .map_err(|error| error.at_offset(info.get_offset()))?);
+            }
+            NestMembers_attribute { classes, .. } => {
+                nest_members = Some(classes.iter()
+                    .map(|index| constant_pool.class_name(*index))
+                    .collect::<Result<Vec<_>, ClassLoadingError>>()
+                    .map_err(|error| error.at_offset(info.get_offset()))?);
+            }
+            _ => {}
+        }
+    }
+    if nest_host.is_some() && nest_members.is_some() {
+        return Err(ClassFormatError(String::from(
+            "Class has both a NestHost and a NestMembers attribute",
+        )));
+    }
+    Ok((nest_host, nest_members.unwrap_or_default()))
+}
+
+/// Resolves the `PermittedSubclasses_attribute` of a sealed class
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.31>
+fn read_permitted_subclasses<'a>(
+    attributes: &[attribute::attribute_info],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Option<Vec<&'a str>>, ClassLoadingError> {
+    for info in attributes {
+        if let PermittedSubclasses_attribute { classes, .. } = info.get_data() {
+            let classes = classes.iter()
+                .map(|index| constant_pool.class_name(*index))
+                .collect::<Result<Vec<_>, ClassLoadingError>>()
+                .map_err(|error| error.at_offset(info.get_offset()))?;
+            return Ok(Some(classes));
+        }
+    }
+    Ok(None)
+}
+
+/// The package portion of a binary class name
+pub fn package_of(class_name: &str) -> &str {
+    class_name.rfind('/').map_or("", |index| &class_name[..index])
+}
+
+fn read_interfaces(input: &mut ClassReader, length: u16) -> Result<Vec<u16>, ClassLoadingError> {
     let mut vector = Vec::with_capacity(length as usize);
     for _ in 0..length {
-        vector.push(input.read_u16::<BigEndian>()?);
+        vector.push(input.read_u16()?);
     }
     Ok(vector)
 }
+
+#[cfg(test)]
+mod tests {
+    use class::ClassAccessFlag;
+    use class_file::ClassFile;
+    use class_file::ClassLoadingError::{ClassFormatError, UnsupportedClassVersionError};
+    use typed_arena::Arena;
+
+    /// Has a long constant, a method with code and a SourceFile and an empty BootstrapMethods
+    /// attribute, at major version 51
+    const A: &[u8] = include_bytes!("../tests/fixtures/classes/p/A.class");
+
+    /// A class calling a static method through invokedynamic, with a BootstrapMethods attribute
+    /// of `bootstrap_methods` entries
+    fn class(bootstrap_method_attr_index: u8, bootstrap_methods: u8) -> Vec<u8> {
+        let mut bytes = vec![
+            0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52, 0, 10,
+            1, 0, 3, b'p', b'/', b'A', // #1 = Utf8 p/A
+            7, 0, 1, // #2 = Class #1
+            1, 0, 1, b'm', // #3 = Utf8 m
+            1, 0, 3, b'(', b')', b'V', // #4 = Utf8 ()V
+            12, 0, 3, 0, 4, // #5 = NameAndType #3:#4
+            10, 0, 2, 0, 5, // #6 = Methodref #2.#5
+            15, 6, 0, 6, // #7 = MethodHandle REF_invokeStatic #6
+            18, 0, bootstrap_method_attr_index, 0, 5, // #8 = InvokeDynamic
+        ];
+        bytes.extend_from_slice(&[1, 0, 16]);
+        bytes.extend_from_slice(b"BootstrapMethods"); // #9
+        bytes.extend_from_slice(&[0x00, 0x21, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]);
+        if bootstrap_methods == 0 {
+            bytes.extend_from_slice(&[0, 0]);
+        } else {
+            bytes.extend_from_slice(&[0, 1, 0, 9, 0, 0, 0, 2 + 4 * bootstrap_methods]);
+            bytes.extend_from_slice(&[0, bootstrap_methods]);
+            for _ in 0..bootstrap_methods {
+                bytes.extend_from_slice(&[0, 7, 0, 0]);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn call_sites_refer_to_bootstrap_methods() {
+        let strings = Arena::new();
+        let class_file = ClassFile::new(&mut &class(1, 2)[..], &strings).unwrap();
+        assert_eq!(class_file.get_bootstrap_methods().unwrap().len(), 2);
+        let bootstrap_method = class_file.get_bootstrap_method(1).unwrap();
+        assert_eq!(bootstrap_method.get_method_ref(), 7);
+        assert!(class_file.get_bootstrap_method(2).is_none());
+    }
+
+    #[test]
+    fn call_sites_without_bootstrap_methods_are_rejected() {
+        let strings = Arena::new();
+        for (index, bootstrap_methods) in &[(0, 0), (1, 1), (2, 2)] {
+            let bytes = class(*index, *bootstrap_methods);
+            let result = ClassFile::new(&mut &bytes[..], &strings);
+            assert!(matches!(result, Err(ClassFormatError(_))), "{}", index);
+        }
+    }
+
+    /// The class file `A` with its version replaced
+    fn with_version(minor_version: u16, major_version: u16) -> Vec<u8> {
+        let mut bytes = A.to_vec();
+        bytes[4..6].copy_from_slice(&minor_version.to_be_bytes());
+        bytes[6..8].copy_from_slice(&major_version.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn versions_from_java_1_to_21_are_supported() {
+        let strings = Arena::new();
+        for major_version in 45..=65 {
+            let bytes = with_version(0, major_version);
+            ClassFile::new(&mut &bytes[..], &strings).unwrap();
+        }
+        for &major_version in &[44, 66] {
+            let bytes = with_version(0, major_version);
+            let result = ClassFile::new(&mut &bytes[..], &strings);
+            assert!(matches!(result, Err(UnsupportedClassVersionError)), "{}", major_version);
+        }
+    }
+
+    #[test]
+    fn minor_versions_are_restricted_from_java_12_on() {
+        let strings = Arena::new();
+        ClassFile::new(&mut &with_version(3, 55)[..], &strings).unwrap();
+        // Neither other minor versions nor preview features are accepted
+        for &minor_version in &[1, 65535] {
+            let bytes = with_version(minor_version, 56);
+            let result = ClassFile::new(&mut &bytes[..], &strings);
+            assert!(matches!(result, Err(UnsupportedClassVersionError)), "{}", minor_version);
+        }
+    }
+
+    #[test]
+    fn attributes_newer_than_the_class_file_are_not_recognized() {
+        let strings = Arena::new();
+        let class = ClassFile::new(&mut &A[..], &strings).unwrap();
+        assert_eq!(class.get_bootstrap_methods().map(|methods| methods.len()), Some(0));
+        let class = ClassFile::new(&mut &with_version(0, 50)[..], &strings).unwrap();
+        assert!(class.get_bootstrap_methods().is_none());
+    }
+
+    #[test]
+    fn acc_module_is_ignored_before_java_9() {
+        let strings = Arena::new();
+        let mut bytes = A.to_vec();
+        // The access flags follow the constant pool
+        assert_eq!(bytes[182..184], [0x00, 0x21]);
+        bytes[182] = 0x80;
+        let class = ClassFile::new(&mut &bytes[..], &strings).unwrap();
+        assert!(!class.get_access_flags().contains(ClassAccessFlag::ACC_MODULE));
+        bytes[6..8].copy_from_slice(&53u16.to_be_bytes());
+        let result = ClassFile::new(&mut &bytes[..], &strings);
+        assert!(matches!(result, Err(ClassFormatError(_))));
+    }
+
+    #[test]
+    fn class_files_are_written_as_they_were_read() {
+        let strings = Arena::new();
+        for bytes in &[
+            A,
+            &include_bytes!("../tests/fixtures/classes/module-info.class")[..],
+            &include_bytes!("../tests/fixtures/classes/p/Point.class")[..],
+            &include_bytes!("../tests/fixtures/classes/p/TypeAnnotated.class")[..],
+        ] {
+            let class = ClassFile::new(&mut &bytes[..], &strings).unwrap();
+            assert_eq!(class.to_bytes(), *bytes, "{}", class.get_name());
+        }
+    }
+
+    #[test]
+    fn written_class_files_match_their_source() {
+        let strings = Arena::new();
+        let class = ClassFile::from_bytes(A, &strings).unwrap();
+        assert_eq!(class.to_bytes(), A);
+        let mut output = Vec::new();
+        class.write(&mut output).unwrap();
+        assert_eq!(output, A);
+
+        let class = ClassFile::from_bytes_lazy(A, &strings).unwrap();
+        assert_eq!(class.to_bytes(), A);
+        // Decoding some of the attributes doesn't change what is written
+        class.get_methods()[0].get_code().unwrap();
+        assert_eq!(class.to_bytes(), A);
+    }
+
+    #[test]
+    fn strings_are_borrowed_from_the_class_file() {
+        let strings = Arena::new();
+        let class = ClassFile::from_bytes(A, &strings).unwrap();
+        let range = A.as_ptr_range();
+        assert!(range.contains(&class.get_name().as_ptr()));
+        assert!(range.contains(&class.get_methods()[0].get_name().as_ptr()));
+    }
+
+    #[test]
+    fn truncated_class_files_are_rejected() {
+        let strings = Arena::new();
+        ClassFile::from_bytes(A, &strings).unwrap();
+        for length in 0..A.len() {
+            let truncated = &A[..length];
+            assert!(ClassFile::from_bytes(truncated, &strings).is_err(), "{} bytes", length);
+            assert!(ClassFile::from_bytes_lazy(truncated, &strings).is_err(), "{} bytes", length);
+        }
+    }
+
+    #[test]
+    fn bad_constant_pool_indices_are_reported_with_offsets() {
+        let mut bytes = A.to_vec();
+        let strings = Arena::new();
+        let this_class = {
+            let class = ClassFile::from_bytes(&bytes, &strings).unwrap();
+            class.get_this_class_index()
+        };
+        // this_class follows the constant pool and the access flags
+        let offset = (0..bytes.len() - 4)
+            .find(|&offset| {
+                bytes[offset..offset + 4] == [0x00, 0x21, (this_class >> 8) as u8, this_class as u8]
+            })
+            .unwrap() + 2;
+        bytes[offset] = 0xff;
+        match ClassFile::from_bytes(&bytes, &strings) {
+            Err(ClassFormatError(message)) => assert!(message.contains("at byte"), "{}", message),
+            other => panic!("{:?}", other.map(|class| class.get_name())),
+        }
+    }
+}
@@ -5,46 +5,96 @@ use class_file::ClassFile;
 use class_file::ClassLoadingError;
 use class_file::ClassLoadingError::*;
 use field;
-use std::cell::{RefCell, Ref};
+use format_check::{check_format, FormatCheck};
+use inner_class::{NestedClass, NestingTree};
+use verifier;
+use verifier::ClassHierarchy;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::io::Cursor;
 use std::ops::Index;
+use std::ptr;
 use typed_arena::Arena;
 use lazy::LazyResolve;
-use class_path::{ClassPath, search_classpath};
-use class_path::path_to_classpath;
+use class_path::{ClassBytes, ClassPath, search_classpath};
+use class_path::{list_classpath, path_to_classpath};
 use field::FieldDescriptor::Reference;
 use field::FieldDescriptor;
 
 pub struct ClassLoader<'a> {
-    classpath: Vec<ClassPath>,
+    classpath: Vec<ClassPath<'a>>,
     class_map: HashMap<String, &'a RefCell<Class<'a>>>,
     strings: &'a Arena<String>,
     classes: &'a Arena<RefCell<Class<'a>>>,
+    /// The class files and archives that loaded classes borrow from
+    bytes: &'a Arena<ClassBytes>,
+    /// Whether the attributes of fields and methods are decoded when first accessed
+    lazy_attributes: bool,
+    /// How strictly loaded classes are checked against the class file format
+    format_check: FormatCheck,
+    /// The classes whose superclasses and interfaces are being loaded
+    inheritance_stack: HashSet<String>,
+    /// The superclass and whether it is an interface of each class the verifier has asked about
+    hierarchy: HashMap<String, (Option<&'a str>, bool)>,
 }
 
-impl<'a> LazyResolve<'a, RefCell<Class<'a>>> for &'a mut ClassLoader<'a> {
-    fn resolve(&mut self, name: &'a str) -> &'a RefCell<Class<'a>> {
+impl<'a> LazyResolve<'a, RefCell<Class<'a>>> for ClassLoader<'a> {
+    fn resolve(&mut self, name: &'a str) -> Result<&'a RefCell<Class<'a>>, ClassLoadingError> {
         self.create_class(name)
     }
 }
 
+impl<'a> ClassHierarchy<'a> for ClassLoader<'a> {
+    fn get_super_class(
+        &mut self,
+        class_name: &'a str,
+    ) -> Result<Option<&'a str>, ClassLoadingError> {
+        Ok(self.get_hierarchy(class_name)?.0)
+    }
+
+    fn is_interface(&mut self, class_name: &'a str) -> Result<bool, ClassLoadingError> {
+        Ok(self.get_hierarchy(class_name)?.1)
+    }
+}
+
 impl<'a> ClassLoader<'a> {
+    /// Fails if a jar on the classpath can't be opened
     pub fn new(
         classpath: Vec<String>,
         allocator: &'a Arena<RefCell<Class<'a>>>,
         string_allocator: &'a Arena<String>,
-    ) -> Self {
-        ClassLoader {
+        byte_allocator: &'a Arena<ClassBytes>,
+    ) -> Result<Self, ClassLoadingError> {
+        Ok(ClassLoader {
             classpath: classpath
                 .iter()
-                .map(|s| path_to_classpath(s.as_str()).unwrap())
-                .collect(),
+                .map(|s| path_to_classpath(s.as_str(), byte_allocator))
+                .collect::<Result<_, _>>()?,
             class_map: HashMap::new(),
             strings: string_allocator,
             classes: allocator,
-        }
+            bytes: byte_allocator,
+            lazy_attributes: false,
+            format_check: FormatCheck::Strict,
+            inheritance_stack: HashSet::new(),
+            hierarchy: HashMap::new(),
+        })
+    }
+
+    /// Defers decoding the attributes of the fields and methods of the classes loaded from now
+    /// on until they are accessed, see `ClassFile::from_bytes_lazy`
+    ///
+    /// The attributes are still checked when each class is loaded, so a malformed class is
+    /// rejected either way. What is saved is keeping the attributes that are never accessed
+    /// decoded.
+    pub fn set_lazy_attributes(&mut self, lazy_attributes: bool) {
+        self.lazy_attributes = lazy_attributes;
+    }
+
+    /// Sets how strictly the classes loaded from now on are checked against the class file
+    /// format, see `check_format`
+    pub fn set_format_check(&mut self, format_check: FormatCheck) {
+        self.format_check = format_check;
     }
 
     /// Place a loaded class into the list of classes
@@ -54,24 +104,18 @@ impl<'a> ClassLoader<'a> {
         class_ref
     }
 
-    /// Get a reference an existing class or load one
-    pub fn create_class(&mut self, class_name: &'a str) -> &'a RefCell<Class<'a>> {
-        self.create_class_rec(class_name, &mut HashSet::new())
-    }
-
     //TODO: Don't do 2 lookups if a class is already loaded
-    /// create_class but with a Set to prevent cyclic inheritance
-    fn create_class_rec(
+    /// Get a reference an existing class or load one, returning the error if it can't be loaded
+    pub fn create_class(
         &mut self,
         class_name: &'a str,
-        inheritance_stack: &mut HashSet<String>,
-    ) -> &'a RefCell<Class<'a>> {
+    ) -> Result<&'a RefCell<Class<'a>>, ClassLoadingError> {
         let already_loaded = self.class_map.contains_key(class_name);
         if !already_loaded {
-            let class = self.load_class(class_name, inheritance_stack);
-            self.register_class(class_name, class)
+            let class = self.load_class(class_name)?;
+            Ok(self.register_class(class_name, class))
         } else {
-            self.get_class(class_name)
+            Ok(self.get_class(class_name))
         }
     }
 
@@ -81,52 +125,41 @@ impl<'a> ClassLoader<'a> {
     }
 
     /// Create a new class from a name
-    fn load_class(
-        &mut self,
-        class_name: &'a str,
-        inheritance_stack: &mut HashSet<String>,
-    ) -> Class<'a> {
-        let class = if class_name.starts_with('[') {
-            Array(self.load_array_class(class_name))
+    fn load_class(&mut self, class_name: &'a str) -> Result<Class<'a>, ClassLoadingError> {
+        if class_name.starts_with('[') {
+            Ok(Array(self.load_array_class(class_name)?))
         } else {
-            File(self.load_file_class(class_name, inheritance_stack).unwrap())
-        };
-        println!("Loaded Class: {}", class.get_name());
-        class
+            Ok(File(Box::new(self.load_file_class(class_name)?)))
+        }
     }
 
     /// Create an array class based on a component and a number of diemsions
     ///
     /// The type will be a number of '[' characters followed by a component type
-    fn load_array_class(&mut self, class_name: &'a str) -> ClassArray<'a> {
-        let mut name_chars = class_name.chars();
-        let mut dimensions: u8 = 0;
-        while name_chars.next().map_or_else(|| false, |c| c == '[') {
-            dimensions += 1;
-        }
-        let component_type_str: &'a str = &class_name[(dimensions as usize)..];
-        let mut component_type: FieldDescriptor<'a> = field::parse_field_descriptor(
-            &mut component_type_str.chars().enumerate().peekable(),
-            component_type_str,
-        );
+    fn load_array_class(&mut self, class_name: &'a str) -> Result<ClassArray<'a>, ClassLoadingError> {
+        // Checks the name as a whole, including that it has no more than 255 dimensions
+        field::parse_field_descriptor(class_name)?;
+        let component_type_str: &'a str = class_name.trim_start_matches('[');
+        let dimensions = (class_name.len() - component_type_str.len()) as u8;
+        let mut component_type: FieldDescriptor<'a> =
+            field::parse_field_descriptor(component_type_str)?;
         if let Reference(class_ref) = &mut component_type {
             let class: &mut ClassRef<'a> = class_ref;
-            class.resolve(&mut self);
+            class.resolve(self)?;
         }
-        ClassArray::new(dimensions, component_type, class_name)
+        Ok(ClassArray::new(dimensions, component_type, class_name))
     }
 
     /// Create a class by attempting to load a .class file from the classpath
-    fn load_file_class(
-        &mut self,
-        class_name: &str,
-        inheritance_stack: &mut HashSet<String>,
-    ) -> Result<ClassFile<'a>, ClassLoadingError> {
-        println!("Attempting to load Class: {}", class_name);
-        let bytes = search_classpath(&mut self.classpath, class_name)?;
-        let mut stream = Cursor::new(bytes);
+    fn load_file_class(&mut self, class_name: &str) -> Result<ClassFile<'a>, ClassLoadingError> {
+        let bytes = search_classpath(&mut self.classpath, class_name, self.bytes)?;
         // Load and parse the the .class file
-        let mut class = ClassFile::new(&mut stream, self.strings)?;
+        let mut class = if self.lazy_attributes {
+            ClassFile::from_bytes_lazy(bytes, self.strings)?
+        } else {
+            ClassFile::from_bytes(bytes, self.strings)?
+        };
+        check_format(&class, self.format_check)?;
 
         // If this class has already been loaded
         if self.class_map.contains_key(class.get_name()) {
@@ -139,31 +172,35 @@ impl<'a> ClassLoader<'a> {
         }
 
         // The list of classes loaded recursively contains this class
-        if inheritance_stack.contains(class_name) {
+        if self.inheritance_stack.contains(class_name) {
             return Err(ClassCircularityError);
         }
 
-        {
-            if !class.has_super_class() {
-                if !class.get_name().eq("java/lang/Object") {
+        // Prevent this class from being loaded again, creating infinite recursion
+        self.inheritance_stack.insert(String::from(class_name));
+        let loaded = self.load_supertypes(&mut class);
+        self.inheritance_stack.remove(class_name);
+        loaded?;
+
+        self.link_class(&class)?;
+
+        Ok(class)
+    }
+
+    /// Loads the superclass and interfaces of a class and checks that it may extend and implement
+    /// them
+    fn load_supertypes(&mut self, class: &mut ClassFile<'a>) -> Result<(), ClassLoadingError> {
+        let class_name = class.get_name();
+        match class.resolve_super_class() {
+            None => {
+                if class_name != "java/lang/Object" {
                     return Err(ClassFormatError(String::from(
                         "Class does not have direct superclass",
                     )));
                 }
-            } else {
-                // Prevent this class from being loaded again, creating infinite recursion
-                inheritance_stack.insert(String::from(class.get_name()));
-
-
-                let super_class_ref = class.resolve_super_class();
-
-                println!(
-                    "During {}, recursing to superclass {:?}",
-                    class_name,
-                    super_class_ref
-                );
-
-                let super_class = super_class_ref.as_mut().unwrap().resolve(&mut self);
+            }
+            Some(super_class_ref) => {
+                let super_class = super_class_ref.resolve(self)?;
 
                 let super_is_interface = super_class
                     .borrow()
@@ -174,12 +211,320 @@ impl<'a> ClassLoader<'a> {
                     return Err(IncompatibleClassChangeError);
                 }
 
+                check_permitted_subclass(class, &super_class.borrow())?;
+            }
+        }
+
+        let interfaces = class.resolve_interfaces()
+            .iter_mut()
+            .map(|interface| interface.resolve(self))
+            .collect::<Result<Vec<_>, _>>()?;
+        for interface in interfaces {
+            let interface = interface.borrow();
+            if !interface.get_access_flags().intersects(ClassAccessFlag::ACC_INTERFACE) {
+                return Err(IncompatibleClassChangeError);
+            }
+            check_permitted_subclass(class, &interface)?;
+        }
+        Ok(())
+    }
+
+    /// Determine the nest host of a loaded class
+    ///
+    /// A class is its own nest host unless it names a host that can be loaded, is in the same
+    /// run-time package and lists the class among its nest members
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.4.4>
+    pub fn get_nest_host(&mut self, class: &'a RefCell<Class<'a>>) -> &'a RefCell<Class<'a>> {
+        let host_name = match &*class.borrow() {
+            File(class_file) => class_file.get_nest_host(),
+            Array(_) => None,
+        };
+        let host_name = match host_name {
+            Some(host_name) => host_name,
+            None => return class,
+        };
+        // Failing to load the host is not an error, the class is simply its own nest host
+        let host = match self.create_class(host_name) {
+            Ok(host) => host,
+            Err(_) => return class,
+        };
+        let is_valid_host = match (&*host.borrow(), &*class.borrow()) {
+            (File(host_file), File(class_file)) => host_file.is_nest_host_of(class_file),
+            _ => false,
+        };
+        if is_valid_host {
+            host
+        } else {
+            class
+        }
+    }
 
+    /// Whether two loaded classes are nestmates, allowing them to access each other's private
+    /// members
+    pub fn are_nestmates(
+        &mut self,
+        first: &'a RefCell<Class<'a>>,
+        second: &'a RefCell<Class<'a>>,
+    ) -> bool {
+        if ptr::eq(first, second) {
+            return true;
+        }
+        let first_host = self.get_nest_host(first);
+        let second_host = self.get_nest_host(second);
+        ptr::eq(first_host, second_host)
+    }
+
+    /// Arranges every class on the classpath by the class it is declared in
+    ///
+    /// Classes that haven't been loaded are read from the classpath without loading them
+    pub fn get_nesting_tree(&mut self) -> Result<NestingTree<'a>, ClassLoadingError> {
+        let class_names = list_classpath(&mut self.classpath)?;
+        let mut classes = Vec::with_capacity(class_names.len());
+        for class_name in class_names {
+            let class = match self.class_map.get(class_name.as_str()) {
+                Some(class) => match &*class.borrow() {
+                    File(class_file) => NestedClass::new(class_file),
+                    Array(_) => continue,
+                },
+                None => {
+                    let bytes = search_classpath(&mut self.classpath, &class_name, self.bytes)?;
+                    NestedClass::new(&ClassFile::from_bytes_lazy(bytes, self.strings)?)
+                }
             };
+            classes.push(class);
         }
+        Ok(NestingTree::new(classes))
+    }
 
-        Ok(class)
+    /// Verifies the code of a class once its superclass and interfaces have been loaded
+    fn link_class(&mut self, class: &ClassFile<'a>) -> Result<(), ClassLoadingError> {
+        let strings = self.strings;
+        verifier::verify_class(class, self, strings)
+    }
+
+    /// The superclass of a class and whether it is an interface, for the verifier
+    ///
+    /// A class that hasn't been loaded is read from the classpath without loading it, since
+    /// loading it would verify it in turn, which may need the class being verified
+    fn get_hierarchy(
+        &mut self,
+        class_name: &'a str,
+    ) -> Result<(Option<&'a str>, bool), ClassLoadingError> {
+        if let Some(entry) = self.hierarchy.get(class_name) {
+            return Ok(*entry);
+        }
+        let entry = match self.class_map.get(class_name) {
+            Some(class) => match &*class.borrow() {
+                File(class_file) => hierarchy_entry(class_file),
+                Array(_) => (Some("java/lang/Object"), false),
+            },
+            None => {
+                let bytes = search_classpath(&mut self.classpath, class_name, self.bytes)?;
+                hierarchy_entry(&ClassFile::from_bytes_lazy(bytes, self.strings)?)
+            }
+        };
+        self.hierarchy.insert(String::from(class_name), entry);
+        Ok(entry)
+    }
+}
+
+fn hierarchy_entry<'a>(class: &ClassFile<'a>) -> (Option<&'a str>, bool) {
+    let is_interface = class.get_access_flags().contains(ClassAccessFlag::ACC_INTERFACE);
+    (class.get_super_class_name(), is_interface)
+}
+
+/// A sealed class or interface may only be directly extended or implemented by the classes its
+/// PermittedSubclasses attribute lists, which must be in the same package unless public
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-5.html#jvms-5.3.5>
+fn check_permitted_subclass(class: &ClassFile, super_class: &Class) -> Result<(), ClassLoadingError> {
+    let super_class = match super_class {
+        File(super_class) => super_class,
+        Array(_) => return Ok(()),
+    };
+    let permitted_subclasses = match super_class.get_permitted_subclasses() {
+        Some(permitted_subclasses) => permitted_subclasses,
+        None => return Ok(()),
+    };
+    let is_public = class.get_access_flags().contains(ClassAccessFlag::ACC_PUBLIC);
+    if !is_public && class.get_package_name() != super_class.get_package_name() {
+        return Err(IncompatibleClassChangeError);
+    }
+    if !permitted_subclasses.contains(&class.get_name()) {
+        return Err(IncompatibleClassChangeError);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assembler::assemble;
+    use class::Class::{Array, File};
+    use class_file::ClassLoadingError::*;
+    use class_file::ClassFile;
+    use class_loader::ClassLoader;
+    use format_check::FormatCheck;
+    use std::fs;
+    use std::ptr;
+    use test_util::{class_header, write_classpath};
+    use typed_arena::Arena;
+
+    const OBJECT: &str = "public class java.lang.Object
+  minor version: 0
+  major version: 52
+  super_class: #0
+";
+
+    /// A classpath of directories under tests/fixtures, after one holding java/lang/Object
+    fn fixtures(directory: &str) -> Vec<String> {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/");
+        vec![fixtures.to_owned() + "runtime", fixtures.to_owned() + directory]
     }
 
-    fn link_class(&mut self, class: &mut ClassFile<'a>) {}
+    #[test]
+    fn nestmates_need_the_agreement_of_their_host() {
+        let (classes, strings, bytes) = (Arena::new(), Arena::new(), Arena::new());
+        let mut loader = ClassLoader::new(fixtures("nests"), &classes, &strings, &bytes).unwrap();
+        let outer = loader.create_class("p/Outer").unwrap();
+        let inner = loader.create_class("p/Outer$Inner").unwrap();
+        let other = loader.create_class("p/Outer$Other").unwrap();
+        // Claims membership without being listed by the host
+        let liar = loader.create_class("p/Outer$Liar").unwrap();
+        // Is listed by the host, but in another run-time package
+        let stranger = loader.create_class("q/Stranger").unwrap();
+        // Names a host that doesn't exist
+        let orphan = loader.create_class("p/Orphan").unwrap();
+
+        assert!(ptr::eq(loader.get_nest_host(inner), outer));
+        assert!(loader.are_nestmates(outer, inner));
+        assert!(loader.are_nestmates(inner, other));
+        for &class in &[liar, stranger, orphan] {
+            assert!(ptr::eq(loader.get_nest_host(class), class));
+            assert!(!loader.are_nestmates(outer, class));
+            assert!(!loader.are_nestmates(inner, class));
+        }
+    }
+
+    #[test]
+    fn only_permitted_subclasses_extend_sealed_classes() {
+        let (classes, strings, bytes) = (Arena::new(), Arena::new(), Arena::new());
+        let mut loader = ClassLoader::new(fixtures("sealed"), &classes, &strings, &bytes).unwrap();
+        assert!(loader.create_class("p/Circle").is_ok());
+        assert!(loader.create_class("q/Square").is_ok());
+        // q/Hidden is listed, but neither public nor in the package of p/Shape
+        for class_name in &["q/Hidden", "p/Triangle", "p/Derived"] {
+            let result = loader.create_class(class_name);
+            assert!(matches!(result, Err(IncompatibleClassChangeError)), "{}", class_name);
+        }
+    }
+
+    #[test]
+    fn loads_superclasses_and_interfaces() {
+        let classpath = write_classpath("class-loader-hierarchy", &[
+            ("java/lang/Object", OBJECT),
+            ("p/I", &class_header("public interface p.I", 52)),
+            ("p/A", &class_header("public class p.A implements p.I", 52)),
+            ("p/B", &class_header("public class p.B extends p.A", 52)),
+        ]);
+        let (classes, strings, bytes) = (Arena::new(), Arena::new(), Arena::new());
+        let mut loader = ClassLoader::new(vec![classpath], &classes, &strings, &bytes).unwrap();
+        let class = loader.create_class("p/B").unwrap();
+        assert_eq!(class.borrow().get_name(), "p/B");
+        let array = loader.create_class("[[Lp/A;").unwrap();
+        assert_eq!(array.borrow().get_name(), "[[Lp/A;");
+    }
+
+    #[test]
+    fn missing_classes_are_errors() {
+        let classpath = write_classpath("class-loader-missing", &[
+            ("java/lang/Object", OBJECT),
+            ("p/A", &class_header("public class p.A extends p.Missing", 52)),
+            ("p/B", &class_header("public class p.B implements p.Missing", 52)),
+        ]);
+        let (classes, strings, bytes) = (Arena::new(), Arena::new(), Arena::new());
+        let mut loader = ClassLoader::new(vec![classpath], &classes, &strings, &bytes).unwrap();
+        assert!(matches!(loader.create_class("p/Missing"), Err(NoClassDefFoundError)));
+        assert!(matches!(loader.create_class("p/A"), Err(NoClassDefFoundError)));
+        assert!(matches!(loader.create_class("p/B"), Err(NoClassDefFoundError)));
+        assert!(matches!(loader.create_class("[Lp/Missing;"), Err(NoClassDefFoundError)));
+    }
+
+    #[test]
+    fn incompatible_superclasses_are_errors() {
+        let classpath = write_classpath("class-loader-incompatible", &[
+            ("java/lang/Object", OBJECT),
+            ("p/I", &class_header("public interface p.I", 52)),
+            ("p/C", &class_header("public class p.C", 52)),
+            ("p/A", &class_header("public class p.A extends p.I", 52)),
+            ("p/B", &class_header("public class p.B implements p.C", 52)),
+            ("p/D", &class_header("public class p.D extends p.D", 52)),
+        ]);
+        let (classes, strings, bytes) = (Arena::new(), Arena::new(), Arena::new());
+        let mut loader = ClassLoader::new(vec![classpath], &classes, &strings, &bytes).unwrap();
+        assert!(matches!(loader.create_class("p/A"), Err(IncompatibleClassChangeError)));
+        assert!(matches!(loader.create_class("p/B"), Err(IncompatibleClassChangeError)));
+        assert!(matches!(loader.create_class("p/D"), Err(ClassCircularityError)));
+    }
+
+    #[test]
+    fn malformed_code_is_rejected_when_loading_lazily() {
+        let source = class_header("public class p.A", 52) + "{
+  public static void m();
+    descriptor: ()V
+    Code:
+      stack=0, locals=0, args_size=0
+        0: return
+}
+";
+        let mut bytes = assemble(&source).unwrap();
+        // Lengthen the code by a byte, so that the rest of the Code attribute runs past its end
+        let strings = Arena::new();
+        let offset = {
+            let class = ClassFile::from_bytes(&bytes, &strings).unwrap();
+            class.get_methods()[0].get_attributes()[0].get_offset()
+        };
+        bytes[offset + 13] = 2;
+        let classpath =
+            write_classpath("class-loader-malformed-code", &[("java/lang/Object", OBJECT)]);
+        fs::create_dir_all(format!("{}/p", classpath)).unwrap();
+        fs::write(format!("{}/p/A.class", classpath), bytes).unwrap();
+
+        for format_check in &[FormatCheck::Strict, FormatCheck::Lenient] {
+            for lazy_attributes in &[false, true] {
+                let (classes, strings, bytes) = (Arena::new(), Arena::new(), Arena::new());
+                let mut loader =
+                    ClassLoader::new(vec![classpath.clone()], &classes, &strings, &bytes).unwrap();
+                loader.set_format_check(*format_check);
+                loader.set_lazy_attributes(*lazy_attributes);
+                assert!(matches!(loader.create_class("p/A"), Err(ClassFormatError(_))));
+            }
+        }
+    }
+
+    #[test]
+    fn lazily_loaded_classes_leave_their_attributes_undecoded() {
+        let (classes, strings, bytes) = (Arena::new(), Arena::new(), Arena::new());
+        let mut loader = ClassLoader::new(fixtures("classes"), &classes, &strings, &bytes).unwrap();
+        loader.set_format_check(FormatCheck::Strict);
+        loader.set_lazy_attributes(true);
+        let class = loader.create_class("p/A").unwrap().borrow();
+        let class = match &*class {
+            File(class) => class,
+            Array(_) => unreachable!(),
+        };
+        let fields = class.get_fields().iter().map(|field| field.get_attributes());
+        let methods = class.get_methods().iter().map(|method| method.get_attributes());
+        let attributes: Vec<_> = fields.chain(methods).flatten().collect();
+        assert!(!attributes.is_empty());
+        assert!(attributes.iter().all(|info| !info.is_decoded()));
+    }
+
+    #[test]
+    fn unreadable_jars_are_errors() {
+        let classpath = write_classpath("class-loader-jar", &[]);
+        let jar = format!("{}/missing.jar", classpath);
+        let (classes, strings, bytes) = (Arena::new(), Arena::new(), Arena::new());
+        assert!(ClassLoader::new(vec![jar], &classes, &strings, &bytes).is_err());
+    }
 }
@@ -1,25 +1,74 @@
 use std::borrow::ToOwned;
 use std::fs::File;
+use std::io::Cursor;
+use std::ops::Deref;
+use zip::CompressionMethod;
 use zip::ZipArchive;
 use class_path::ClassPath::{Jar, Directory};
 use class_file::ClassLoadingError;
 use class_file::ClassLoadingError::NoClassDefFoundError;
+use memmap2::Mmap;
 use std::io::Read;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use typed_arena::Arena;
 
-type ClassPathList = Vec<ClassPath>;
+type ClassPathList<'a> = Vec<ClassPath<'a>>;
 
-pub enum ClassPath {
+pub enum ClassPath<'a> {
     Directory(String),
-    Jar(ZipArchive<File>),
+    /// The mapped archive, and the zip index over it
+    Jar(&'a [u8], ZipArchive<Cursor<&'a [u8]>>),
+}
+
+/// The contents of a file on the classpath, kept alive for as long as the classes parsed from
+/// them so that they can be borrowed rather than copied
+pub enum ClassBytes {
+    /// An archive mapped into memory
+    Mapped(Mmap),
+    /// A class file read into memory, or a class decompressed out of an archive
+    Read(Vec<u8>),
+}
+
+impl Deref for ClassBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            ClassBytes::Mapped(map) => map,
+            ClassBytes::Read(bytes) => bytes,
+        }
+    }
+}
+
+/// Maps an archive into memory
+///
+/// The archive must not be modified in place while it is mapped
+fn map_file<'a>(path: &str, byte_allocator: &'a Arena<ClassBytes>) -> Result<&'a [u8], ClassLoadingError> {
+    let file = File::open(path)?;
+    // Empty files can't be mapped
+    if file.metadata()?.len() == 0 {
+        return Ok(&[]);
+    }
+    // SAFETY: the map is only read, and is kept in `byte_allocator` for as long as anything
+    // borrows from it. The borrows assume that the jar isn't modified in place while it is
+    // mapped, as the JVM does: build tools replace a jar by renaming a new file over it, which
+    // leaves this map on the old file. Class files in directories are rewritten in place by
+    // incremental builds, so `search_directory` copies them rather than mapping them.
+    let map = unsafe { Mmap::map(&file)? };
+    Ok(byte_allocator.alloc(ClassBytes::Mapped(map)))
 }
 
 /// Converts a string to the appropriate ClassPath object
-pub fn path_to_classpath(path: &str) -> Result<ClassPath, ClassLoadingError> {
+pub fn path_to_classpath<'a>(
+    path: &str,
+    byte_allocator: &'a Arena<ClassBytes>,
+) -> Result<ClassPath<'a>, ClassLoadingError> {
     if path.ends_with(".jar") {
-        let archive_file = File::open(path)?;
-        let archive = ZipArchive::new(archive_file)?;
-        Ok(Jar(archive))
+        let bytes = map_file(path, byte_allocator)?;
+        let archive = ZipArchive::new(Cursor::new(bytes))?;
+        Ok(Jar(bytes, archive))
     } else {
         Ok(Directory(path.to_owned()))
     }
@@ -27,21 +76,25 @@ pub fn path_to_classpath(path: &str) -> Result<ClassPath, ClassLoadingError> {
 
 /// Search the classpath for a specific class
     /// eg: java/lang/Object
-pub fn search_classpath(class_path_list: &mut ClassPathList, class_name: &str) -> Result<Vec<u8>, ClassLoadingError> {
+pub fn search_classpath<'a>(
+    class_path_list: &mut ClassPathList<'a>,
+    class_name: &str,
+    byte_allocator: &'a Arena<ClassBytes>,
+) -> Result<&'a [u8], ClassLoadingError> {
     let mut class_file_name = String::from(class_name);
     class_file_name.push_str(".class");
     for classpath_dir in class_path_list {
         match classpath_dir {
             Directory(path) => {
                 if let Some(path) =
-                search_directory(path.as_str(), class_file_name.as_str())?
+                search_directory(path.as_str(), class_file_name.as_str(), byte_allocator)?
                 {
                     return Ok(path);
                 }
             }
-            Jar(archive) => {
+            Jar(archive_bytes, archive) => {
                 if let Some(path) =
-                search_archive(archive, class_file_name.as_str())?
+                search_archive(archive_bytes, archive, class_file_name.as_str(), byte_allocator)?
                 {
                     return Ok(path);
                 }
@@ -53,33 +106,193 @@ pub fn search_classpath(class_path_list: &mut ClassPathList, class_name: &str) -
     Err(NoClassDefFoundError)
 }
 
+/// Lists the classes on the classpath, by their binary names
+///
+/// A class that is shadowed by one with the same name earlier on the classpath is only listed
+/// once. `module-info` and the versioned classes of multi-release jars are left out.
+pub fn list_classpath(
+    class_path_list: &mut ClassPathList,
+) -> Result<Vec<String>, ClassLoadingError> {
+    let mut seen = HashSet::new();
+    let mut class_names = Vec::new();
+    for classpath_dir in class_path_list {
+        let mut file_names = Vec::new();
+        match classpath_dir {
+            Directory(path) => list_directory(&PathBuf::from(path.as_str()), "", &mut file_names)?,
+            Jar(_, archive) => {
+                for index in 0..archive.len() {
+                    file_names.push(String::from(archive.by_index(index)?.name()));
+                }
+            }
+        }
+        for file_name in file_names {
+            if !file_name.ends_with(".class") || file_name.starts_with("META-INF/") {
+                continue;
+            }
+            let class_name = &file_name[..file_name.len() - ".class".len()];
+            if class_name != "module-info" && seen.insert(String::from(class_name)) {
+                class_names.push(String::from(class_name));
+            }
+        }
+    }
+    Ok(class_names)
+}
+
+/// Lists the files under a directory, with their paths relative to the base of the classpath
+fn list_directory(
+    dir: &Path,
+    prefix: &str,
+    file_names: &mut Vec<String>,
+) -> Result<(), ClassLoadingError> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    // Directory order is arbitrary, list the classes in the same order every time
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            list_directory(&entry.path(), &format!("{}/", name), file_names)?;
+        } else {
+            file_names.push(name);
+        }
+    }
+    Ok(())
+}
+
 /// Searches a filesystem folder structure for a named class
-fn search_directory(
+///
+/// The class file is read rather than mapped, as a compiler may overwrite it at any time
+fn search_directory<'a>(
     base_dir: &str,
     class_file_name: &str,
-) -> Result<Option<Vec<u8>>, ClassLoadingError> {
+    byte_allocator: &'a Arena<ClassBytes>,
+) -> Result<Option<&'a [u8]>, ClassLoadingError> {
     let mut path = PathBuf::from(base_dir);
     path.push(class_file_name);
-    println!("Loading {:?} from {:?}", class_file_name, path);
     if path.exists() {
-        let file = File::open(path)?;
-        let bytes = file.bytes().map(|i| i.unwrap()).collect();
-        Ok(Some(bytes))
+        let bytes = fs::read(path)?;
+        Ok(Some(byte_allocator.alloc(ClassBytes::Read(bytes))))
     } else {
         Ok(None)
     }
 }
 
 /// Searches a .jar archive for a named class
-fn search_archive(
-    archive: &mut ZipArchive<File>,
+///
+/// Entries that are stored uncompressed are borrowed straight out of the mapped archive
+fn search_archive<'a>(
+    archive_bytes: &'a [u8],
+    archive: &mut ZipArchive<Cursor<&'a [u8]>>,
     class_file_name: &str,
-) -> Result<Option<Vec<u8>>, ClassLoadingError> {
+    byte_allocator: &'a Arena<ClassBytes>,
+) -> Result<Option<&'a [u8]>, ClassLoadingError> {
     let archive_entry = archive.by_name(class_file_name);
-    if let Ok(zip_stream) = archive_entry {
-        let bytes = zip_stream.bytes().map(|i| i.unwrap()).collect();
-        Ok(Some(bytes))
+    if let Ok(mut zip_stream) = archive_entry {
+        if zip_stream.compression() == CompressionMethod::Stored {
+            let start = zip_stream.data_start() as usize;
+            let end = start.checked_add(zip_stream.size() as usize);
+            if let Some(bytes) = end.and_then(|end| archive_bytes.get(start..end)) {
+                return Ok(Some(bytes));
+            }
+        }
+        // The size is only what the archive claims, so it can't be trusted to size the buffer
+        let mut bytes = Vec::new();
+        zip_stream.read_to_end(&mut bytes)?;
+        Ok(Some(byte_allocator.alloc(ClassBytes::Read(bytes))))
     } else {
         Ok(None)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use class_path::{list_classpath, path_to_classpath, search_classpath};
+    use class_file::ClassLoadingError::NoClassDefFoundError;
+    use std::fs;
+    use std::io::{Cursor, Write};
+    use test_util::temp_dir;
+    use typed_arena::Arena;
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    #[test]
+    fn finds_stored_and_compressed_classes_in_jars() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer.start_file("p/Stored.class", stored).unwrap();
+        writer.write_all(b"stored bytes").unwrap();
+        writer.start_file("p/Deflated.class", deflated).unwrap();
+        writer.write_all(&[7; 1000]).unwrap();
+        writer.start_file("META-INF/versions/9/p/Stored.class", stored).unwrap();
+        writer.start_file("module-info.class", stored).unwrap();
+        let jar = format!("{}/classes.jar", temp_dir("class-path-jar"));
+        fs::write(&jar, writer.finish().unwrap().into_inner()).unwrap();
+
+        let bytes = Arena::new();
+        let mut classpath = vec![path_to_classpath(&jar, &bytes).unwrap()];
+        let stored = search_classpath(&mut classpath, "p/Stored", &bytes).unwrap();
+        assert_eq!(stored, b"stored bytes");
+        let deflated = search_classpath(&mut classpath, "p/Deflated", &bytes).unwrap();
+        assert_eq!(deflated, &[7; 1000][..]);
+        assert!(matches!(
+            search_classpath(&mut classpath, "p/Missing", &bytes),
+            Err(NoClassDefFoundError)
+        ));
+        assert_eq!(list_classpath(&mut classpath).unwrap(), ["p/Stored", "p/Deflated"]);
+    }
+
+    #[test]
+    fn earlier_directories_shadow_later_ones() {
+        let (first, second) = (temp_dir("class-path-first"), temp_dir("class-path-second"));
+        fs::create_dir_all(format!("{}/p", first)).unwrap();
+        fs::create_dir_all(format!("{}/p/q", second)).unwrap();
+        fs::write(format!("{}/p/A.class", first), b"first").unwrap();
+        fs::write(format!("{}/p/A.class", second), b"second").unwrap();
+        fs::write(format!("{}/p/q/B.class", second), b"nested").unwrap();
+        fs::write(format!("{}/p/q/notes.txt", second), b"text").unwrap();
+
+        let bytes = Arena::new();
+        let mut classpath = vec![
+            path_to_classpath(&first, &bytes).unwrap(),
+            path_to_classpath(&second, &bytes).unwrap(),
+        ];
+        assert_eq!(search_classpath(&mut classpath, "p/A", &bytes).unwrap(), b"first");
+        assert_eq!(search_classpath(&mut classpath, "p/q/B", &bytes).unwrap(), b"nested");
+        assert_eq!(list_classpath(&mut classpath).unwrap(), ["p/A", "p/q/B"]);
+    }
+
+    #[test]
+    fn sizes_claimed_by_jars_are_not_trusted() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer.start_file("p/A.class", deflated).unwrap();
+        writer.write_all(b"class bytes").unwrap();
+        let mut jar_bytes = writer.finish().unwrap().into_inner();
+
+        // Claim an uncompressed size of u64::MAX through a zip64 extra field on the central
+        // directory header, growing the directory recorded by the end of central directory
+        let find = |bytes: &[u8], signature| {
+            bytes.windows(4).position(|window| window == signature).unwrap()
+        };
+        let header = find(&jar_bytes, b"PK\x01\x02");
+        let end = find(&jar_bytes, b"PK\x05\x06");
+        jar_bytes[header + 24..header + 28].copy_from_slice(&[0xff; 4]);
+        let name_length = u16::from_le_bytes([jar_bytes[header + 28], jar_bytes[header + 29]]);
+        let extra_length = u16::from_le_bytes([jar_bytes[header + 30], jar_bytes[header + 31]]);
+        jar_bytes[header + 30..header + 32].copy_from_slice(&(extra_length + 12).to_le_bytes());
+        let extra_end = header + 46 + (name_length + extra_length) as usize;
+        let zip64_extra = [&[1, 0, 8, 0][..], &u64::MAX.to_le_bytes()].concat();
+        jar_bytes.splice(extra_end..extra_end, zip64_extra);
+        let (end, directory_size) = (end + 12, (end + 12 - header) as u32);
+        jar_bytes[end + 12..end + 16].copy_from_slice(&directory_size.to_le_bytes());
+        let jar = format!("{}/zip64.jar", temp_dir("class-path-zip64"));
+        fs::write(&jar, jar_bytes).unwrap();
+
+        let bytes = Arena::new();
+        let mut classpath = vec![path_to_classpath(&jar, &bytes).unwrap()];
+        assert_eq!(search_classpath(&mut classpath, "p/A", &bytes).unwrap(), b"class bytes");
+    }
+}
@@ -0,0 +1,100 @@
+use byteorder::{BigEndian, ByteOrder};
+use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+
+/// Reads the big-endian structures of a class file from a byte slice, keeping track of the
+/// byte offset into the class file so that format errors can say where they occurred
+pub struct ClassReader<'b> {
+    bytes: &'b [u8],
+    position: usize,
+    /// offset of `bytes[0]` within the class file
+    base: usize,
+}
+
+impl<'b> ClassReader<'b> {
+    pub fn new(bytes: &'b [u8]) -> ClassReader<'b> {
+        ClassReader {
+            bytes,
+            position: 0,
+            base: 0,
+        }
+    }
+
+    /// Reads bytes that were found at `offset` within a class file, such as the contents of an
+    /// attribute decoded after the rest of the class was parsed
+    pub fn new_at(bytes: &'b [u8], offset: usize) -> ClassReader<'b> {
+        ClassReader {
+            bytes,
+            position: 0,
+            base: offset,
+        }
+    }
+
+    /// The byte offset into the class file of the next byte to be read
+    pub fn get_offset(&self) -> usize {
+        self.base + self.position
+    }
+
+    /// The number of bytes left to read
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    /// Creates a `ClassFormatError` located at the current offset
+    pub fn error(&self, message: String) -> ClassLoadingError {
+        ClassFormatError(message).at_offset(self.get_offset())
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'b [u8], ClassLoadingError> {
+        if length > self.remaining() {
+            return Err(self.error(format!(
+                "Unexpected end of data reading {} bytes, only {} remain",
+                length,
+                self.remaining()
+            )));
+        }
+        let bytes = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    /// Splits off the next `length` bytes into their own reader, such as the contents of an
+    /// attribute, so that parsing them can't run past their end
+    pub fn sub_reader(&mut self, length: usize) -> Result<ClassReader<'b>, ClassLoadingError> {
+        let base = self.get_offset();
+        let bytes = self.read_bytes(length)?;
+        Ok(ClassReader {
+            bytes,
+            position: 0,
+            base,
+        })
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, ClassLoadingError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, ClassLoadingError> {
+        Ok(BigEndian::read_u16(self.read_bytes(2)?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, ClassLoadingError> {
+        Ok(BigEndian::read_u32(self.read_bytes(4)?))
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, ClassLoadingError> {
+        Ok(BigEndian::read_i32(self.read_bytes(4)?))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, ClassLoadingError> {
+        Ok(BigEndian::read_f32(self.read_bytes(4)?))
+    }
+
+    pub fn read_i64(&mut self) -> Result<i64, ClassLoadingError> {
+        Ok(BigEndian::read_i64(self.read_bytes(8)?))
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, ClassLoadingError> {
+        Ok(BigEndian::read_f64(self.read_bytes(8)?))
+    }
+}
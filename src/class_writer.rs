@@ -0,0 +1,106 @@
+use byteorder::{BigEndian, ByteOrder};
+
+/// Builds the big-endian byte stream of a class file, the counterpart of `ClassReader`
+#[derive(Default)]
+pub struct ClassWriter {
+    bytes: Vec<u8>,
+}
+
+impl ClassWriter {
+    pub fn new() -> ClassWriter {
+        ClassWriter { bytes: Vec::new() }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        let mut buffer = [0u8; 2];
+        BigEndian::write_u16(&mut buffer, value);
+        self.write_bytes(&buffer);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        let mut buffer = [0u8; 4];
+        BigEndian::write_u32(&mut buffer, value);
+        self.write_bytes(&buffer);
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_u32(value as u32);
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    pub fn write_i64(&mut self, value: i64) {
+        let mut buffer = [0u8; 8];
+        BigEndian::write_i64(&mut buffer, value);
+        self.write_bytes(&buffer);
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_i64(value.to_bits() as i64);
+    }
+
+    /// Writes a table of u16 values, such as constant_pool indices, preceded by its length
+    pub fn write_u16_table(&mut self, values: &[u16]) {
+        self.write_u16(values.len() as u16);
+        for value in values {
+            self.write_u16(*value);
+        }
+    }
+
+    /// Writes the contents of a structure preceded by its length in bytes as a u4, such as
+    /// the `info` of an attribute
+    pub fn write_with_length<F: FnOnce(&mut ClassWriter)>(&mut self, contents: F) {
+        let start = self.bytes.len();
+        self.write_u32(0);
+        contents(self);
+        let length = (self.bytes.len() - start - 4) as u32;
+        BigEndian::write_u32(&mut self.bytes[start..start + 4], length);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use class_writer::ClassWriter;
+
+    #[test]
+    fn writes_big_endian_values() {
+        let mut output = ClassWriter::new();
+        output.write_u8(0xca);
+        output.write_u16(0xfeba);
+        output.write_u32(0xbe00_0102);
+        output.write_i32(-2);
+        output.write_f32(1.0);
+        output.write_i64(-1);
+        output.write_f64(2.0);
+        assert_eq!(output.into_bytes(), [
+            0xca, 0xfe, 0xba, 0xbe, 0x00, 0x01, 0x02, 0xff, 0xff, 0xff, 0xfe, 0x3f, 0x80, 0x00,
+            0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x40, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00,
+        ]);
+    }
+
+    #[test]
+    fn writes_lengths_before_contents() {
+        let mut output = ClassWriter::new();
+        output.write_u16_table(&[1, 0x0203]);
+        output.write_with_length(|output| {
+            output.write_u8(4);
+            output.write_u16(5);
+        });
+        assert_eq!(output.into_bytes(), [0, 2, 0, 1, 2, 3, 0, 0, 0, 3, 4, 0, 5]);
+    }
+}
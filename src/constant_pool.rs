@@ -1,26 +1,346 @@
-use byteorder::BigEndian;
-use byteorder::ReadBytesExt;
-use cesu8::from_java_cesu8;
+use cesu8::{from_java_cesu8, to_java_cesu8};
 use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+use class_reader::ClassReader;
+use class_writer::ClassWriter;
 use constant_pool::cp_info::*;
-use std::io::Read;
-use std::ops::Index;
-use typed_arena::Arena;
+use std::borrow::Cow;
 
 #[derive(Debug)]
 pub struct ConstantPool<'a>(Vec<Option<cp_info<'a>>>);
 
 impl<'a> ConstantPool<'a> {
-    pub fn get_entry(&self, index: u16) -> &cp_info<'a> {
-        self.0.index(index as usize).as_ref().unwrap()
+    pub fn get_entry(&self, index: u16) -> Result<&cp_info<'a>, ClassLoadingError> {
+        self.0.get(index as usize)
+            .and_then(|entry| entry.as_ref())
+            .ok_or_else(|| ClassFormatError(format!(
+                "Constant pool index {} is out of bounds or unusable",
+                index
+            )))
     }
 
-    pub fn get_string_entry(&self, index: u16) -> &'a str {
-        match self.get_entry(index) {
-            CONSTANT_Utf8_info { bytes } => *bytes,
-            other => panic!("Symbolic Class reference in ClassFile#super_class didn't point to CONSTANT_Utf8_info, instead: {:?}", other)
+    pub fn get_string_entry(&self, index: u16) -> Result<&'a str, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Utf8_info { bytes, .. } => Ok(*bytes),
+            other => Err(wrong_kind(index, "CONSTANT_Utf8_info", other)),
         }
     }
+
+    /// The binary name of the class named by a `CONSTANT_Class_info`
+    pub fn class_name(&self, index: u16) -> Result<&'a str, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Class_info { name_index } => self.get_string_entry(*name_index),
+            other => Err(wrong_kind(index, "CONSTANT_Class_info", other)),
+        }
+    }
+
+    /// The name and descriptor of a `CONSTANT_NameAndType_info`
+    pub fn name_and_type(&self, index: u16) -> Result<NameAndType<'a>, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_NameAndType_info { name_index, descriptor_index } => Ok(NameAndType {
+                name: self.get_string_entry(*name_index)?,
+                descriptor: self.get_string_entry(*descriptor_index)?,
+            }),
+            other => Err(wrong_kind(index, "CONSTANT_NameAndType_info", other)),
+        }
+    }
+
+    /// The field referred to by a `CONSTANT_Fieldref_info`
+    pub fn field_ref(&self, index: u16) -> Result<MemberRef<'a>, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Fieldref_info { class_index, name_and_type_index } => {
+                self.member_ref(*class_index, *name_and_type_index, false)
+            }
+            other => Err(wrong_kind(index, "CONSTANT_Fieldref_info", other)),
+        }
+    }
+
+    /// The method referred to by a `CONSTANT_Methodref_info` or
+    /// `CONSTANT_InterfaceMethodref_info`
+    ///
+    /// `invokestatic` and `invokespecial` may refer to either kind
+    pub fn method_ref(&self, index: u16) -> Result<MemberRef<'a>, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Methodref_info { class_index, name_and_type_index } => {
+                self.member_ref(*class_index, *name_and_type_index, false)
+            }
+            CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index } => {
+                self.member_ref(*class_index, *name_and_type_index, true)
+            }
+            other => Err(wrong_kind(
+                index,
+                "CONSTANT_Methodref_info or CONSTANT_InterfaceMethodref_info",
+                other,
+            )),
+        }
+    }
+
+    /// The interface method referred to by a `CONSTANT_InterfaceMethodref_info`
+    pub fn interface_method_ref(&self, index: u16) -> Result<MemberRef<'a>, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index } => {
+                self.member_ref(*class_index, *name_and_type_index, true)
+            }
+            other => Err(wrong_kind(index, "CONSTANT_InterfaceMethodref_info", other)),
+        }
+    }
+
+    fn member_ref(
+        &self,
+        class_index: u16,
+        name_and_type_index: u16,
+        is_interface: bool,
+    ) -> Result<MemberRef<'a>, ClassLoadingError> {
+        let name_and_type = self.name_and_type(name_and_type_index)?;
+        Ok(MemberRef {
+            class_name: self.class_name(class_index)?,
+            name: name_and_type.name,
+            descriptor: name_and_type.descriptor,
+            is_interface,
+        })
+    }
+
+    /// The value of a `CONSTANT_String_info`
+    pub fn string(&self, index: u16) -> Result<&'a str, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_String_info { string_index } => self.get_string_entry(*string_index),
+            other => Err(wrong_kind(index, "CONSTANT_String_info", other)),
+        }
+    }
+
+    pub fn integer(&self, index: u16) -> Result<i32, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Integer_info { bytes } => Ok(*bytes),
+            other => Err(wrong_kind(index, "CONSTANT_Integer_info", other)),
+        }
+    }
+
+    pub fn float(&self, index: u16) -> Result<f32, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Float_info { bytes } => Ok(*bytes),
+            other => Err(wrong_kind(index, "CONSTANT_Float_info", other)),
+        }
+    }
+
+    pub fn long(&self, index: u16) -> Result<i64, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Long_info { value } => Ok(*value),
+            other => Err(wrong_kind(index, "CONSTANT_Long_info", other)),
+        }
+    }
+
+    pub fn double(&self, index: u16) -> Result<f64, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Double_info { value } => Ok(*value),
+            other => Err(wrong_kind(index, "CONSTANT_Double_info", other)),
+        }
+    }
+
+    /// The method descriptor of a `CONSTANT_MethodType_info`
+    pub fn method_type(&self, index: u16) -> Result<&'a str, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_MethodType_info { descriptor_index } => {
+                self.get_string_entry(*descriptor_index)
+            }
+            other => Err(wrong_kind(index, "CONSTANT_MethodType_info", other)),
+        }
+    }
+
+    /// The kind and target of a `CONSTANT_MethodHandle_info`, checking that the reference is a
+    /// field for the field access kinds and a method otherwise
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.8>
+    pub fn method_handle(&self, index: u16) -> Result<MethodHandle<'a>, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_MethodHandle_info { reference_kind, reference_index } => {
+                let reference = match reference_kind {
+                    kind if kind.is_field_access() => self.field_ref(*reference_index)?,
+                    ReferenceKind::REF_invokeVirtual | ReferenceKind::REF_newInvokeSpecial => {
+                        let reference = self.method_ref(*reference_index)?;
+                        if reference.is_interface {
+                            return Err(wrong_kind(
+                                *reference_index,
+                                "CONSTANT_Methodref_info",
+                                self.get_entry(*reference_index)?,
+                            ));
+                        }
+                        reference
+                    }
+                    ReferenceKind::REF_invokeInterface => {
+                        self.interface_method_ref(*reference_index)?
+                    }
+                    _ => self.method_ref(*reference_index)?,
+                };
+                Ok(MethodHandle {
+                    reference_kind: *reference_kind,
+                    reference,
+                })
+            }
+            other => Err(wrong_kind(index, "CONSTANT_MethodHandle_info", other)),
+        }
+    }
+
+    /// The bootstrap method index, name and method descriptor of a
+    /// `CONSTANT_InvokeDynamic_info`
+    pub fn invoke_dynamic(&self, index: u16) -> Result<DynamicRef<'a>, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, name_and_type_index } => {
+                self.dynamic_ref(*bootstrap_method_attr_index, *name_and_type_index)
+            }
+            other => Err(wrong_kind(index, "CONSTANT_InvokeDynamic_info", other)),
+        }
+    }
+
+    /// The bootstrap method index, name and field descriptor of a `CONSTANT_Dynamic_info`
+    pub fn dynamic(&self, index: u16) -> Result<DynamicRef<'a>, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Dynamic_info { bootstrap_method_attr_index, name_and_type_index } => {
+                self.dynamic_ref(*bootstrap_method_attr_index, *name_and_type_index)
+            }
+            other => Err(wrong_kind(index, "CONSTANT_Dynamic_info", other)),
+        }
+    }
+
+    fn dynamic_ref(
+        &self,
+        bootstrap_method_attr_index: u16,
+        name_and_type_index: u16,
+    ) -> Result<DynamicRef<'a>, ClassLoadingError> {
+        let name_and_type = self.name_and_type(name_and_type_index)?;
+        Ok(DynamicRef {
+            bootstrap_method_attr_index,
+            name: name_and_type.name,
+            descriptor: name_and_type.descriptor,
+        })
+    }
+
+    /// The name of the module named by a `CONSTANT_Module_info`
+    pub fn module_name(&self, index: u16) -> Result<&'a str, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Module_info { name_index } => self.get_string_entry(*name_index),
+            other => Err(wrong_kind(index, "CONSTANT_Module_info", other)),
+        }
+    }
+
+    /// The internal name of the package named by a `CONSTANT_Package_info`
+    pub fn package_name(&self, index: u16) -> Result<&'a str, ClassLoadingError> {
+        match self.get_entry(index)? {
+            CONSTANT_Package_info { name_index } => self.get_string_entry(*name_index),
+            other => Err(wrong_kind(index, "CONSTANT_Package_info", other)),
+        }
+    }
+
+    /// `constant_pool_count`, one more than the highest index into the pool
+    pub fn get_count(&self) -> u16 {
+        self.0.len() as u16
+    }
+
+    /// Iterates over every present entry, skipping index 0 and the slot after each long or double
+    pub fn entries(&self) -> impl Iterator<Item = &cp_info<'a>> {
+        self.0.iter().filter_map(|entry| entry.as_ref())
+    }
+
+    /// Writes `constant_pool_count` followed by the entries
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.0.len() as u16);
+        for entry in self.entries() {
+            entry.write(output);
+        }
+    }
+}
+
+fn wrong_kind(index: u16, expected: &str, found: &cp_info) -> ClassLoadingError {
+    ClassFormatError(format!(
+        "Constant pool index {} should be a {}, instead: {:?}",
+        index, expected, found
+    ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A resolved `CONSTANT_NameAndType_info`
+pub struct NameAndType<'a> {
+    name: &'a str,
+    descriptor: &'a str,
+}
+
+impl<'a> NameAndType<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_descriptor(&self) -> &'a str {
+        self.descriptor
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A resolved `CONSTANT_Fieldref_info`, `CONSTANT_Methodref_info` or
+/// `CONSTANT_InterfaceMethodref_info`
+pub struct MemberRef<'a> {
+    class_name: &'a str,
+    name: &'a str,
+    descriptor: &'a str,
+    is_interface: bool,
+}
+
+impl<'a> MemberRef<'a> {
+    /// The class or interface the member is looked up in
+    pub fn get_class_name(&self) -> &'a str {
+        self.class_name
+    }
+
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_descriptor(&self) -> &'a str {
+        self.descriptor
+    }
+
+    /// Whether this came from a `CONSTANT_InterfaceMethodref_info`
+    pub fn is_interface(&self) -> bool {
+        self.is_interface
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A resolved `CONSTANT_MethodHandle_info`
+pub struct MethodHandle<'a> {
+    reference_kind: ReferenceKind,
+    reference: MemberRef<'a>,
+}
+
+impl<'a> MethodHandle<'a> {
+    pub fn get_reference_kind(&self) -> ReferenceKind {
+        self.reference_kind
+    }
+
+    pub fn get_reference(&self) -> &MemberRef<'a> {
+        &self.reference
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A resolved `CONSTANT_InvokeDynamic_info` or `CONSTANT_Dynamic_info`
+pub struct DynamicRef<'a> {
+    bootstrap_method_attr_index: u16,
+    name: &'a str,
+    descriptor: &'a str,
+}
+
+impl<'a> DynamicRef<'a> {
+    /// Index into the `bootstrap_methods` of the class's `BootstrapMethods_attribute`
+    pub fn get_bootstrap_method_attr_index(&self) -> u16 {
+        self.bootstrap_method_attr_index
+    }
+
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_descriptor(&self) -> &'a str {
+        self.descriptor
+    }
 }
 
 #[derive(Debug)]
@@ -76,21 +396,114 @@ pub enum cp_info<'a> {
     },
 
     /// `bytes` -> bytes of the string
-    CONSTANT_Utf8_info { bytes: &'a str },
+    ///
+    /// `raw` -> the original modified UTF-8, kept only when encoding `bytes` wouldn't reproduce
+    /// it, such as when it contained unpaired surrogates
+    CONSTANT_Utf8_info { bytes: &'a str, raw: Option<Vec<u8>> },
+
+    /// `reference_kind` -> the kind of this method handle, which characterizes its bytecode behavior
+    ///
+    /// `reference_index` -> constant_pool index of a `CONSTANT_Fieldref_info`,
+    /// `CONSTANT_Methodref_info` or `CONSTANT_InterfaceMethodref_info` depending on `reference_kind`
+    CONSTANT_MethodHandle_info {
+        reference_kind: ReferenceKind,
+        reference_index: u16,
+    },
+
+    /// `descriptor_index` -> constant_pool index of a `CONSTANT_utf8_info` that is a method descriptor
+    CONSTANT_MethodType_info { descriptor_index: u16 },
+
+    /// `bootstrap_method_attr_index` -> index into the `bootstrap_methods` array of the
+    /// `BootstrapMethods_attribute` of this class
+    ///
+    /// `name_and_type_index` -> constant_pool index of a `CONSTANT_NameAndType_info`
+    CONSTANT_InvokeDynamic_info {
+        bootstrap_method_attr_index: u16,
+        name_and_type_index: u16,
+    },
+
+    /// `bootstrap_method_attr_index` -> index into the `bootstrap_methods` array of the
+    /// `BootstrapMethods_attribute` of this class
+    ///
+    /// `name_and_type_index` -> constant_pool index of a `CONSTANT_NameAndType_info` whose
+    /// descriptor is a field descriptor
+    CONSTANT_Dynamic_info {
+        bootstrap_method_attr_index: u16,
+        name_and_type_index: u16,
+    },
+
+    /// `name_index` -> constant_pool index of a `CONSTANT_utf8_info` that is the name of a module
+    CONSTANT_Module_info { name_index: u16 },
+
+    /// `name_index` -> constant_pool index of a `CONSTANT_utf8_info` that is the internal name
+    /// of a package
+    CONSTANT_Package_info { name_index: u16 },
+}
+
+/// The bytecode behavior of a `CONSTANT_MethodHandle_info`
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-5.html#jvms-5.4.3.5-220>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    REF_getField = 1,
+    REF_getStatic = 2,
+    REF_putField = 3,
+    REF_putStatic = 4,
+    REF_invokeVirtual = 5,
+    REF_invokeStatic = 6,
+    REF_invokeSpecial = 7,
+    REF_newInvokeSpecial = 8,
+    REF_invokeInterface = 9,
+}
+
+impl ReferenceKind {
+    pub fn from_u8(kind: u8) -> Option<ReferenceKind> {
+        use constant_pool::ReferenceKind::*;
+        Some(match kind {
+            1 => REF_getField,
+            2 => REF_getStatic,
+            3 => REF_putField,
+            4 => REF_putStatic,
+            5 => REF_invokeVirtual,
+            6 => REF_invokeStatic,
+            7 => REF_invokeSpecial,
+            8 => REF_newInvokeSpecial,
+            9 => REF_invokeInterface,
+            _ => return None,
+        })
+    }
+
+    /// Whether the handle refers to a field (`CONSTANT_Fieldref_info`) rather than a method
+    pub fn is_field_access(self) -> bool {
+        (self as u8) <= ReferenceKind::REF_putStatic as u8
+    }
 }
 
+/// Reads the constant pool, handing each decoded `CONSTANT_Utf8_info` to `strings` to be kept
+/// for the lifetime of the class, which borrows it in place when it can
 pub fn read_constant_pool<'a, 'b>(
-    input: &'b mut Read,
+    input: &mut ClassReader<'b>,
     constant_pool_count: u16,
-    string_allocator: &'a Arena<String>,
+    strings: &mut dyn FnMut(Cow<'b, str>) -> &'a str,
+    major_version: u16,
 ) -> Result<ConstantPool<'a>, ClassLoadingError> {
+    if constant_pool_count == 0 {
+        return Err(input.error(String::from("constant_pool_count must be at least 1")));
+    }
     let mut iter = constant_pool_count - 1;
     let mut pool = Vec::with_capacity(constant_pool_count as usize);
     pool.push(Option::None);
     while iter > 0 {
-        let info = cp_info::new(input, string_allocator)?;
+        let offset = input.get_offset();
+        let info = cp_info::new(input, strings, major_version)?;
         match info {
             CONSTANT_Double_info { .. } | CONSTANT_Long_info { .. } => {
+                // Longs and doubles take up two entries
+                if iter < 2 {
+                    return Err(ClassFormatError(String::from(
+                        "CONSTANT_Long_info or CONSTANT_Double_info is the last constant pool entry",
+                    )).at_offset(offset));
+                }
                 pool.push(Option::Some(info));
                 pool.push(Option::None);
                 iter -= 2;
@@ -105,82 +518,518 @@ pub fn read_constant_pool<'a, 'b>(
 }
 
 impl<'a> cp_info<'a> {
-    fn new(
-        input: &mut Read,
-        allocator: &'a Arena<String>,
+    /// The first class file major version in which a constant pool tag is valid
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4-310>
+    pub fn tag_version(tag: u8) -> Option<u16> {
+        Some(match tag {
+            1 | 3 | 4 | 5 | 6 | 7 | 8 | 9 | 10 | 11 | 12 => 45,
+            15 | 16 | 18 => 51,
+            19 | 20 => 53,
+            17 => 55,
+            _ => return None,
+        })
+    }
+
+    fn new<'b>(
+        input: &mut ClassReader<'b>,
+        strings: &mut dyn FnMut(Cow<'b, str>) -> &'a str,
+        major_version: u16,
     ) -> Result<cp_info<'a>, ClassLoadingError> {
+        let offset = input.get_offset();
         let tag = input.read_u8()?;
+        if let Some(version) = cp_info::tag_version(tag) {
+            if major_version < version {
+                return Err(ClassFormatError(format!(
+                    "Constant Pool Tag {} is not valid in class file version {}",
+                    tag, major_version
+                )).at_offset(offset));
+            }
+        }
         Ok(match tag {
             7 => {
-                let class_index = input.read_u16::<BigEndian>()?;
+                let class_index = input.read_u16()?;
                 CONSTANT_Class_info {
                     name_index: class_index,
                 }
             }
             9 => {
-                let class_index = input.read_u16::<BigEndian>()?;
-                let name_and_type_index = input.read_u16::<BigEndian>()?;
+                let class_index = input.read_u16()?;
+                let name_and_type_index = input.read_u16()?;
                 CONSTANT_Fieldref_info {
                     class_index,
                     name_and_type_index,
                 }
             }
             10 => {
-                let class_index = input.read_u16::<BigEndian>()?;
-                let name_and_type_index = input.read_u16::<BigEndian>()?;
+                let class_index = input.read_u16()?;
+                let name_and_type_index = input.read_u16()?;
                 CONSTANT_Methodref_info {
                     class_index,
                     name_and_type_index,
                 }
             }
             11 => {
-                let class_index = input.read_u16::<BigEndian>()?;
-                let name_and_type_index = input.read_u16::<BigEndian>()?;
+                let class_index = input.read_u16()?;
+                let name_and_type_index = input.read_u16()?;
                 CONSTANT_InterfaceMethodref_info {
                     class_index,
                     name_and_type_index,
                 }
             }
             8 => {
-                let string_index = input.read_u16::<BigEndian>()?;
+                let string_index = input.read_u16()?;
                 CONSTANT_String_info { string_index }
             }
             3 => {
-                let bytes = input.read_i32::<BigEndian>()?;
+                let bytes = input.read_i32()?;
                 CONSTANT_Integer_info { bytes }
             }
             4 => {
-                let bytes = input.read_f32::<BigEndian>()?;
+                let bytes = input.read_f32()?;
 
                 CONSTANT_Float_info { bytes }
             }
             5 => {
-                let value = input.read_i64::<BigEndian>()?;
+                let value = input.read_i64()?;
                 CONSTANT_Long_info { value }
             }
             6 => {
-                let value = input.read_f64::<BigEndian>()?;
+                let value = input.read_f64()?;
                 CONSTANT_Double_info { value }
             }
             12 => {
-                let name_index = input.read_u16::<BigEndian>()?;
-                let descriptor_index = input.read_u16::<BigEndian>()?;
+                let name_index = input.read_u16()?;
+                let descriptor_index = input.read_u16()?;
                 CONSTANT_NameAndType_info {
                     name_index,
                     descriptor_index,
                 }
             }
             1 => {
-                let length = input.read_u16::<BigEndian>()?;
-                let mut bytes = vec![0u8; length as usize];
-                input.read_exact(&mut bytes)?;
-                let string = from_java_cesu8(&bytes)?;
-                let reference = allocator.alloc(string.to_string());
+                let length = input.read_u16()?;
+                let bytes = input.read_bytes(length as usize)?;
+                let string = decode_modified_utf8(bytes)
+                    .map_err(|message| ClassFormatError(message).at_offset(offset))?;
+                // Whether borrowed or decoded, a string that doesn't encode back to the same bytes
+                // keeps them, so that it is written as it was read
+                let raw = if *to_java_cesu8(&string) != *bytes {
+                    Some(bytes.to_vec())
+                } else {
+                    None
+                };
                 CONSTANT_Utf8_info {
-                    bytes: reference.as_str(),
+                    bytes: strings(string),
+                    raw,
+                }
+            }
+            15 => {
+                let kind = input.read_u8()?;
+                let reference_kind = ReferenceKind::from_u8(kind).ok_or_else(|| {
+                    ClassFormatError(format!(
+                        "CONSTANT_MethodHandle_info has invalid reference_kind: {}",
+                        kind
+                    )).at_offset(offset)
+                })?;
+                let reference_index = input.read_u16()?;
+                CONSTANT_MethodHandle_info {
+                    reference_kind,
+                    reference_index,
+                }
+            }
+            16 => {
+                let descriptor_index = input.read_u16()?;
+                CONSTANT_MethodType_info { descriptor_index }
+            }
+            18 => {
+                let bootstrap_method_attr_index = input.read_u16()?;
+                let name_and_type_index = input.read_u16()?;
+                CONSTANT_InvokeDynamic_info {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
                 }
             }
-            _ => panic!("Unknown Constant Pool Tag parsed: {}", tag),
+            17 => {
+                let bootstrap_method_attr_index = input.read_u16()?;
+                let name_and_type_index = input.read_u16()?;
+                CONSTANT_Dynamic_info {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                }
+            }
+            19 => {
+                let name_index = input.read_u16()?;
+                CONSTANT_Module_info { name_index }
+            }
+            20 => {
+                let name_index = input.read_u16()?;
+                CONSTANT_Package_info { name_index }
+            }
+            _ => {
+                return Err(ClassFormatError(format!(
+                    "Unknown Constant Pool Tag parsed: {}",
+                    tag
+                )).at_offset(offset))
+            }
         })
     }
+
+    /// The tag byte that identifies this kind of entry in the class file
+    pub fn get_tag(&self) -> u8 {
+        match self {
+            CONSTANT_Utf8_info { .. } => 1,
+            CONSTANT_Integer_info { .. } => 3,
+            CONSTANT_Float_info { .. } => 4,
+            CONSTANT_Long_info { .. } => 5,
+            CONSTANT_Double_info { .. } => 6,
+            CONSTANT_Class_info { .. } => 7,
+            CONSTANT_String_info { .. } => 8,
+            CONSTANT_Fieldref_info { .. } => 9,
+            CONSTANT_Methodref_info { .. } => 10,
+            CONSTANT_InterfaceMethodref_info { .. } => 11,
+            CONSTANT_NameAndType_info { .. } => 12,
+            CONSTANT_MethodHandle_info { .. } => 15,
+            CONSTANT_MethodType_info { .. } => 16,
+            CONSTANT_Dynamic_info { .. } => 17,
+            CONSTANT_InvokeDynamic_info { .. } => 18,
+            CONSTANT_Module_info { .. } => 19,
+            CONSTANT_Package_info { .. } => 20,
+        }
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u8(self.get_tag());
+        match self {
+            CONSTANT_Class_info { name_index }
+            | CONSTANT_Module_info { name_index }
+            | CONSTANT_Package_info { name_index } => output.write_u16(*name_index),
+            CONSTANT_Fieldref_info { class_index, name_and_type_index }
+            | CONSTANT_Methodref_info { class_index, name_and_type_index }
+            | CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index } => {
+                output.write_u16(*class_index);
+                output.write_u16(*name_and_type_index);
+            }
+            CONSTANT_String_info { string_index } => output.write_u16(*string_index),
+            CONSTANT_Integer_info { bytes } => output.write_i32(*bytes),
+            CONSTANT_Float_info { bytes } => output.write_f32(*bytes),
+            CONSTANT_Long_info { value } => output.write_i64(*value),
+            CONSTANT_Double_info { value } => output.write_f64(*value),
+            CONSTANT_NameAndType_info { name_index, descriptor_index } => {
+                output.write_u16(*name_index);
+                output.write_u16(*descriptor_index);
+            }
+            CONSTANT_Utf8_info { bytes, raw } => {
+                let encoded = match raw {
+                    Some(raw) => Cow::Borrowed(raw.as_slice()),
+                    None => to_java_cesu8(bytes),
+                };
+                output.write_u16(encoded.len() as u16);
+                output.write_bytes(&encoded);
+            }
+            CONSTANT_MethodHandle_info { reference_kind, reference_index } => {
+                output.write_u8(*reference_kind as u8);
+                output.write_u16(*reference_index);
+            }
+            CONSTANT_MethodType_info { descriptor_index } => output.write_u16(*descriptor_index),
+            CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, name_and_type_index }
+            | CONSTANT_Dynamic_info { bootstrap_method_attr_index, name_and_type_index } => {
+                output.write_u16(*bootstrap_method_attr_index);
+                output.write_u16(*name_and_type_index);
+            }
+        }
+    }
+}
+
+/// Decodes the modified UTF-8 of a `CONSTANT_Utf8_info`
+///
+/// Java strings may contain unpaired surrogates, which can't be represented in a `str` and are
+/// replaced with U+FFFD
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.4.7>
+fn decode_modified_utf8(bytes: &[u8]) -> Result<Cow<'_, str>, String> {
+    // Bytes that never appear in modified UTF-8 would otherwise be borrowed as plain UTF-8
+    if let Some(byte) = bytes.iter().find(|&&byte| byte == 0 || byte >= 0xF0) {
+        return Err(format!("Illegal byte {:#04x} in modified UTF-8", byte));
+    }
+    if let Ok(string) = from_java_cesu8(bytes) {
+        return Ok(string);
+    }
+    Ok(Cow::Owned(String::from_utf16_lossy(&decode_modified_utf8_units(bytes)?)))
+}
+
+/// Decodes modified UTF-8 into UTF-16 code units, which unlike a `str` can hold unpaired
+/// surrogates
+pub fn decode_modified_utf8_units(bytes: &[u8]) -> Result<Vec<u16>, String> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&first) = iter.next() {
+        let mut continuation = || match iter.next() {
+            Some(&byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+            _ => Err(String::from("Truncated modified UTF-8 sequence")),
+        };
+        let unit = match first {
+            0x01..=0x7F => first as u16,
+            0xC0..=0xDF => ((first & 0x1F) as u16) << 6 | continuation()?,
+            0xE0..=0xEF => {
+                let second = continuation()?;
+                ((first & 0x0F) as u16) << 12 | second << 6 | continuation()?
+            }
+            _ => {
+                return Err(format!("Illegal byte {:#04x} in modified UTF-8", first))
+            }
+        };
+        units.push(unit);
+    }
+    Ok(units)
+}
+
+/// Encodes UTF-16 code units as modified UTF-8, the counterpart of
+/// `decode_modified_utf8_units`
+pub fn encode_modified_utf8_units(units: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(units.len());
+    for &unit in units {
+        match unit {
+            0x01..=0x7F => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cp_info::*;
+    use super::{cp_info, read_constant_pool, ConstantPool, ReferenceKind};
+    use class_file::ClassLoadingError;
+    use class_file::ClassLoadingError::ClassFormatError;
+    use class_reader::ClassReader;
+    use class_writer::ClassWriter;
+    use std::borrow::Cow;
+    use typed_arena::Arena;
+
+    /// A class, a static method and every invokedynamic-era constant referring to them
+    const POOL: [u8; 41] = [
+        1, 0, 3, b'p', b'/', b'A', // #1 = Utf8 p/A
+        7, 0, 1, // #2 = Class #1
+        1, 0, 1, b'm', // #3 = Utf8 m
+        1, 0, 3, b'(', b')', b'V', // #4 = Utf8 ()V
+        12, 0, 3, 0, 4, // #5 = NameAndType #3:#4
+        10, 0, 2, 0, 5, // #6 = Methodref #2.#5
+        15, 6, 0, 6, // #7 = MethodHandle REF_invokeStatic #6
+        16, 0, 4, // #8 = MethodType #4
+        18, 0, 0, 0, 5, // #9 = InvokeDynamic #0:#5
+    ];
+
+    fn read<'a>(
+        bytes: &[u8],
+        constant_pool_count: u16,
+        strings: &'a Arena<String>,
+        major_version: u16,
+    ) -> Result<ConstantPool<'a>, ClassLoadingError> {
+        let mut reader = ClassReader::new(bytes);
+        let mut allocate = |string: Cow<str>| -> &'a str { strings.alloc(string.into_owned()) };
+        read_constant_pool(&mut reader, constant_pool_count, &mut allocate, major_version)
+    }
+
+    #[test]
+    fn reads_method_handles_method_types_and_call_sites() {
+        let strings = Arena::new();
+        let pool = read(&POOL, 10, &strings, 52).unwrap();
+        match pool.get_entry(7).unwrap() {
+            CONSTANT_MethodHandle_info { reference_kind, reference_index } => {
+                assert_eq!(*reference_kind, ReferenceKind::REF_invokeStatic);
+                assert_eq!(*reference_index, 6);
+            }
+            other => panic!("{:?}", other),
+        }
+        match pool.get_entry(8).unwrap() {
+            CONSTANT_MethodType_info { descriptor_index } => {
+                assert_eq!(pool.get_string_entry(*descriptor_index).unwrap(), "()V")
+            }
+            other => panic!("{:?}", other),
+        }
+        match pool.get_entry(9).unwrap() {
+            CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, name_and_type_index } => {
+                assert_eq!((*bootstrap_method_attr_index, *name_and_type_index), (0, 5))
+            }
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(pool.entries().count(), 9);
+    }
+
+    #[test]
+    fn unknown_reference_kinds_are_rejected() {
+        let strings = Arena::new();
+        for kind in &[0, 10, 255] {
+            let bytes = [15, *kind, 0, 1];
+            let result = read(&bytes, 2, &strings, 52);
+            assert!(matches!(result, Err(ClassFormatError(_))), "{}", kind);
+        }
+    }
+
+    #[test]
+    fn only_the_first_four_reference_kinds_access_fields() {
+        for kind in 1..10 {
+            let reference_kind = ReferenceKind::from_u8(kind).unwrap();
+            assert_eq!(reference_kind as u8, kind);
+            assert_eq!(reference_kind.is_field_access(), kind <= 4);
+        }
+    }
+
+    #[test]
+    fn tags_are_rejected_before_their_versions() {
+        let strings = Arena::new();
+        read(&POOL, 10, &strings, 51).unwrap();
+        let result = read(&POOL, 10, &strings, 50);
+        assert!(matches!(result, Err(ClassFormatError(_))));
+        let package = [20, 0, 1];
+        read(&package, 2, &strings, 53).unwrap();
+        let result = read(&package, 2, &strings, 52);
+        assert!(matches!(result, Err(ClassFormatError(_))));
+        assert_eq!(cp_info::tag_version(1), Some(45));
+        assert_eq!(cp_info::tag_version(17), Some(55));
+        assert_eq!(cp_info::tag_version(2), None);
+    }
+
+    /// p/A with a field f:I, a method m()V and constants of each kind
+    const CONSTANTS: &[u8] = &[
+        1, 0, 3, b'p', b'/', b'A', // #1 = Utf8 p/A
+        7, 0, 1, // #2 = Class #1
+        1, 0, 1, b'm', // #3 = Utf8 m
+        1, 0, 3, b'(', b')', b'V', // #4 = Utf8 ()V
+        12, 0, 3, 0, 4, // #5 = NameAndType #3:#4
+        10, 0, 2, 0, 5, // #6 = Methodref #2.#5
+        11, 0, 2, 0, 5, // #7 = InterfaceMethodref #2.#5
+        9, 0, 2, 0, 9, // #8 = Fieldref #2.#9
+        12, 0, 3, 0, 10, // #9 = NameAndType #3:#10
+        1, 0, 1, b'I', // #10 = Utf8 I
+        5, 0, 0, 1, 0, 0, 0, 0, 0, // #11 = Long 1 << 40
+        8, 0, 3, // #13 = String #3
+        3, 0xff, 0xff, 0xff, 0xff, // #14 = Integer -1
+        6, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0, // #15 = Double 0.5
+        4, 0x3f, 0xc0, 0, 0, // #17 = Float 1.5
+    ];
+
+    #[test]
+    fn typed_accessors_resolve_entries() {
+        let strings = Arena::new();
+        let pool = read(CONSTANTS, 18, &strings, 52).unwrap();
+        assert_eq!(pool.class_name(2).unwrap(), "p/A");
+        let name_and_type = pool.name_and_type(5).unwrap();
+        assert_eq!((name_and_type.get_name(), name_and_type.get_descriptor()), ("m", "()V"));
+        let method = pool.method_ref(6).unwrap();
+        assert_eq!((method.get_class_name(), method.get_name()), ("p/A", "m"));
+        assert!(!method.is_interface());
+        assert!(pool.method_ref(7).unwrap().is_interface());
+        assert!(pool.interface_method_ref(7).unwrap().is_interface());
+        assert_eq!(pool.field_ref(8).unwrap().get_descriptor(), "I");
+        assert_eq!(pool.long(11).unwrap(), 1 << 40);
+        assert_eq!(pool.string(13).unwrap(), "m");
+        assert_eq!(pool.integer(14).unwrap(), -1);
+        assert_eq!(pool.double(15).unwrap(), 0.5);
+        assert_eq!(pool.float(17).unwrap(), 1.5);
+    }
+
+    #[test]
+    fn entries_of_the_wrong_kind_are_errors() {
+        let strings = Arena::new();
+        let pool = read(CONSTANTS, 18, &strings, 52).unwrap();
+        assert!(matches!(pool.class_name(1), Err(ClassFormatError(_))));
+        assert!(matches!(pool.get_string_entry(2), Err(ClassFormatError(_))));
+        assert!(matches!(pool.interface_method_ref(6), Err(ClassFormatError(_))));
+        assert!(matches!(pool.field_ref(6), Err(ClassFormatError(_))));
+        assert!(matches!(pool.integer(11), Err(ClassFormatError(_))));
+        // Index 0 and the slot after a long are unusable
+        for index in &[0, 12, 18] {
+            assert!(matches!(pool.get_entry(*index), Err(ClassFormatError(_))), "{}", index);
+        }
+    }
+
+    #[test]
+    fn method_handles_refer_to_members_of_their_kind() {
+        let handles = [
+            15, 6, 0, 6, // #18 = MethodHandle REF_invokeStatic #6
+            15, 6, 0, 7, // #19 = MethodHandle REF_invokeStatic #7
+            15, 1, 0, 8, // #20 = MethodHandle REF_getField #8
+            15, 9, 0, 7, // #21 = MethodHandle REF_invokeInterface #7
+            15, 5, 0, 7, // #22 = MethodHandle REF_invokeVirtual #7
+            15, 9, 0, 6, // #23 = MethodHandle REF_invokeInterface #6
+            15, 4, 0, 6, // #24 = MethodHandle REF_putStatic #6
+        ];
+        let bytes = [CONSTANTS, &handles[..]].concat();
+        let strings = Arena::new();
+        let pool = read(&bytes, 25, &strings, 52).unwrap();
+        let handle = pool.method_handle(18).unwrap();
+        assert_eq!(handle.get_reference_kind(), ReferenceKind::REF_invokeStatic);
+        assert_eq!(handle.get_reference().get_name(), "m");
+        assert!(pool.method_handle(19).unwrap().get_reference().is_interface());
+        assert_eq!(pool.method_handle(20).unwrap().get_reference().get_descriptor(), "I");
+        pool.method_handle(21).unwrap();
+        for index in 22..25 {
+            assert!(matches!(pool.method_handle(index), Err(ClassFormatError(_))), "{}", index);
+        }
+    }
+
+    #[test]
+    fn resolves_method_types_and_call_sites() {
+        let call_sites = [
+            16, 0, 4, // #18 = MethodType #4
+            18, 0, 0, 0, 5, // #19 = InvokeDynamic #0:#5
+        ];
+        let bytes = [CONSTANTS, &call_sites[..]].concat();
+        let strings = Arena::new();
+        let pool = read(&bytes, 20, &strings, 52).unwrap();
+        assert_eq!(pool.method_type(18).unwrap(), "()V");
+        let call_site = pool.invoke_dynamic(19).unwrap();
+        assert_eq!(call_site.get_bootstrap_method_attr_index(), 0);
+        assert_eq!((call_site.get_name(), call_site.get_descriptor()), ("m", "()V"));
+        assert!(matches!(pool.method_type(19), Err(ClassFormatError(_))));
+        assert!(matches!(pool.invoke_dynamic(18), Err(ClassFormatError(_))));
+    }
+
+    #[test]
+    fn writes_the_pool_it_read() {
+        let strings = Arena::new();
+        let pool = read(CONSTANTS, 18, &strings, 52).unwrap();
+        let mut output = ClassWriter::new();
+        pool.write(&mut output);
+        assert_eq!(output.into_bytes(), [&[0, 18], CONSTANTS].concat());
+    }
+
+    #[test]
+    fn nul_bytes_and_four_byte_sequences_are_not_modified_utf8() {
+        let strings = Arena::new();
+        for bytes in &[&[1, 0, 3, b'a', 0, b'b'][..], &[1, 0, 4, 0xf0, 0x9f, 0x98, 0x80]] {
+            let result = read(bytes, 2, &strings, 52);
+            assert!(matches!(result, Err(ClassFormatError(_))), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn strings_are_written_as_they_were_read() {
+        const STRINGS: &[u8] = &[
+            1, 0, 3, 0xc3, 0xa9, b'!', // #1 = Utf8 é!, borrowed
+            1, 0, 3, 0xe2, 0x82, 0xac, // #2 = Utf8 €, borrowed
+            1, 0, 2, 0xc0, 0x80, // #3 = Utf8 with NUL, decoded
+            1, 0, 6, 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80, // #4 = Utf8 with a surrogate pair
+            1, 0, 3, 0xed, 0xa0, 0x80, // #5 = Utf8 with an unpaired surrogate
+            1, 0, 2, 0xc1, 0x81, // #6 = Utf8 A, overlong
+        ];
+        let strings = Arena::new();
+        let pool = read(STRINGS, 7, &strings, 52).unwrap();
+        assert_eq!(pool.get_string_entry(1).unwrap(), "é!");
+        assert_eq!(pool.get_string_entry(3).unwrap(), "\0");
+        assert_eq!(pool.get_string_entry(4).unwrap(), "\u{1f600}");
+        let mut output = ClassWriter::new();
+        pool.write(&mut output);
+        assert_eq!(output.into_bytes(), [&[0, 7], STRINGS].concat());
+    }
 }
@@ -0,0 +1,1791 @@
+use attribute::attribute_info_Data::*;
+use attribute::{
+    annotation, attribute_info, element_value, element_value_data, exception_info,
+    stack_map_frame, stack_map_frame_data, target_info, type_annotation, verification_type_info,
+    verification_type_info_data,
+};
+use class_file::ClassFile;
+use constant_pool::cp_info;
+use constant_pool::cp_info::*;
+use constant_pool::{decode_modified_utf8_units, ConstantPool};
+use field::FieldInfo;
+use instruction::Instruction::*;
+use instruction::{Instruction, InstructionIter, WideInstruction};
+use method::MethodInfo;
+use record::record_component_info;
+use signature;
+use signature::{ClassSignature, TypeParameter};
+use std::io;
+use std::io::Write;
+use std::slice;
+
+/// The column trailing comments are lined up at, not counting indentation
+const COMMENT_COLUMN: usize = 40;
+
+const CLASS_FLAGS: &[(u16, &str)] = &[
+    (0x0001, "ACC_PUBLIC"),
+    (0x0010, "ACC_FINAL"),
+    (0x0020, "ACC_SUPER"),
+    (0x0200, "ACC_INTERFACE"),
+    (0x0400, "ACC_ABSTRACT"),
+    (0x1000, "ACC_SYNTHETIC"),
+    (0x2000, "ACC_ANNOTATION"),
+    (0x4000, "ACC_ENUM"),
+    (0x8000, "ACC_MODULE"),
+];
+
+const FIELD_FLAGS: &[(u16, &str)] = &[
+    (0x0001, "ACC_PUBLIC"),
+    (0x0002, "ACC_PRIVATE"),
+    (0x0004, "ACC_PROTECTED"),
+    (0x0008, "ACC_STATIC"),
+    (0x0010, "ACC_FINAL"),
+    (0x0040, "ACC_VOLATILE"),
+    (0x0080, "ACC_TRANSIENT"),
+    (0x1000, "ACC_SYNTHETIC"),
+    (0x4000, "ACC_ENUM"),
+];
+
+const METHOD_FLAGS: &[(u16, &str)] = &[
+    (0x0001, "ACC_PUBLIC"),
+    (0x0002, "ACC_PRIVATE"),
+    (0x0004, "ACC_PROTECTED"),
+    (0x0008, "ACC_STATIC"),
+    (0x0010, "ACC_FINAL"),
+    (0x0020, "ACC_SYNCHRONIZED"),
+    (0x0040, "ACC_BRIDGE"),
+    (0x0080, "ACC_VARARGS"),
+    (0x0100, "ACC_NATIVE"),
+    (0x0400, "ACC_ABSTRACT"),
+    (0x0800, "ACC_STRICT"),
+    (0x1000, "ACC_SYNTHETIC"),
+];
+
+const CLASS_MODIFIERS: &[(u16, &str)] = &[(0x0001, "public"), (0x0010, "final"), (0x0400, "abstract")];
+
+const FIELD_MODIFIERS: &[(u16, &str)] = &[
+    (0x0001, "public"),
+    (0x0002, "private"),
+    (0x0004, "protected"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+    (0x0040, "volatile"),
+    (0x0080, "transient"),
+];
+
+const METHOD_MODIFIERS: &[(u16, &str)] = &[
+    (0x0001, "public"),
+    (0x0002, "private"),
+    (0x0004, "protected"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+    (0x0020, "synchronized"),
+    (0x0100, "native"),
+    (0x0400, "abstract"),
+    (0x0800, "strictfp"),
+];
+
+/// Inner class flags are printed in full, as a class file can't give any of them otherwise
+const INNER_CLASS_MODIFIERS: &[(u16, &str)] = &[
+    (0x0001, "public"),
+    (0x0002, "private"),
+    (0x0004, "protected"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+    (0x0200, "interface"),
+    (0x0400, "abstract"),
+    (0x1000, "synthetic"),
+    (0x2000, "annotation"),
+    (0x4000, "enum"),
+];
+
+const ACC_STATIC: u16 = 0x0008;
+const ACC_VARARGS: u16 = 0x0080;
+const ACC_INTERFACE: u16 = 0x0200;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_MODULE: u16 = 0x8000;
+
+/// Prints a class file in the format of `javap -c -v -p`
+///
+/// Constant pool entries are shown alongside what they resolve to, bytecode is disassembled
+/// with symbolic operands, and every attribute is printed in the order it appears in the class
+/// file
+pub fn disassemble(class: &ClassFile, output: &mut dyn Write) -> io::Result<()> {
+    Disassembler {
+        class,
+        constant_pool: class.get_constant_pool(),
+        out: Printer {
+            output,
+            indent: 0,
+            line: String::new(),
+        },
+    }.write_class()
+}
+
+/// Builds up lines of output at a level of indentation
+struct Printer<'b> {
+    output: &'b mut dyn Write,
+    indent: usize,
+    line: String,
+}
+
+impl<'b> Printer<'b> {
+    fn print(&mut self, text: &str) {
+        self.line.push_str(text);
+    }
+
+    /// Pads the line out to the column trailing comments start at
+    fn tab(&mut self) {
+        let length = self.line.chars().count();
+        let padding = if length < COMMENT_COLUMN { COMMENT_COLUMN - length } else { 1 };
+        for _ in 0..padding {
+            self.line.push(' ');
+        }
+    }
+
+    /// Finishes the line, leaving off any trailing spaces
+    fn println(&mut self, text: &str) -> io::Result<()> {
+        self.line.push_str(text);
+        let result = {
+            let line = self.line.trim_end_matches(' ');
+            if line.is_empty() {
+                writeln!(self.output)
+            } else {
+                writeln!(self.output, "{:2$}{}", "", line, self.indent * 2)
+            }
+        };
+        self.line.clear();
+        result
+    }
+}
+
+struct Disassembler<'b, 'a: 'b> {
+    class: &'b ClassFile<'a>,
+    constant_pool: &'b ConstantPool<'a>,
+    out: Printer<'b>,
+}
+
+impl<'b, 'a> Disassembler<'b, 'a> {
+    fn write_class(&mut self) -> io::Result<()> {
+        let class = self.class;
+        let source_file = class.get_attributes().iter().filter_map(|info| {
+            if let SourceFile_attribute { sourcefile_index } = info.get_data() {
+                Some(*sourcefile_index)
+            } else {
+                None
+            }
+        }).next();
+        if let Some(sourcefile_index) = source_file {
+            let source_file = self.raw_utf8(sourcefile_index);
+            self.out.indent += 1;
+            self.out.println(&format!("Compiled from \"{}\"", source_file))?;
+            self.out.indent -= 1;
+        }
+        let declaration = self.class_declaration();
+        self.out.println(&declaration)?;
+
+        self.out.indent += 1;
+        self.out.println(&format!("minor version: {}", class.get_minor_version()))?;
+        self.out.println(&format!("major version: {}", class.get_major_version()))?;
+        self.write_flags(class.get_raw_access_flags(), CLASS_FLAGS)?;
+        let this_class_index = class.get_this_class_index();
+        self.out.print(&format!("this_class: #{}", this_class_index));
+        self.out.tab();
+        let this_class = self.constant(this_class_index);
+        self.out.println(&format!("// {}", this_class))?;
+        let super_class_index = class.get_super_class_index();
+        self.out.print(&format!("super_class: #{}", super_class_index));
+        if super_class_index != 0 {
+            self.out.tab();
+            let super_class = self.constant(super_class_index);
+            self.out.print(&format!("// {}", super_class));
+        }
+        self.out.println("")?;
+        self.out.println(&format!(
+            "interfaces: {}, fields: {}, methods: {}, attributes: {}",
+            class.get_interface_indices().len(),
+            class.get_fields().len(),
+            class.get_methods().len(),
+            class.get_attributes().len()
+        ))?;
+        self.out.indent -= 1;
+
+        self.write_constant_pool()?;
+
+        self.out.println("{")?;
+        self.out.indent += 1;
+        let mut first = true;
+        for field in class.get_fields() {
+            if !first {
+                self.out.println("")?;
+            }
+            first = false;
+            self.write_field(field)?;
+        }
+        for method in class.get_methods() {
+            if !first {
+                self.out.println("")?;
+            }
+            first = false;
+            self.write_method(method)?;
+        }
+        self.out.indent -= 1;
+        self.out.println("}")?;
+        self.write_attributes(class.get_attributes(), None)
+    }
+
+    /// The class as it would be declared in Java, eg `public class Foo extends Bar`
+    fn class_declaration(&self) -> String {
+        let class = self.class;
+        let flags = class.get_raw_access_flags();
+        let is_interface = flags & ACC_INTERFACE != 0;
+        let modifier_flags = if is_interface { flags & !ACC_ABSTRACT } else { flags };
+        let mut declaration = modifiers(modifier_flags, CLASS_MODIFIERS);
+        if flags & ACC_MODULE != 0 {
+            declaration.push_str("module ");
+            match class.get_module() {
+                Some(module) => declaration.push_str(module.get_name()),
+                None => declaration.push_str(&java_name(class.get_name())),
+            }
+            return declaration;
+        }
+        declaration.push_str(if is_interface { "interface " } else { "class " });
+        declaration.push_str(&java_name(class.get_name()));
+        if let Ok(Some(signature)) = class.get_signature() {
+            declaration.push_str(&generic_supertypes(&signature, is_interface));
+            return declaration;
+        }
+        if !is_interface && class.get_super_class_index() != 0 {
+            let super_class = java_name(&self.class_name(class.get_super_class_index()));
+            if super_class != "java.lang.Object" {
+                declaration.push_str(" extends ");
+                declaration.push_str(&super_class);
+            }
+        }
+        let interfaces: Vec<String> = class.get_interface_indices().iter()
+            .map(|index| java_name(&self.class_name(*index)))
+            .collect();
+        if !interfaces.is_empty() {
+            declaration.push_str(if is_interface { " extends " } else { " implements " });
+            declaration.push_str(&interfaces.join(","));
+        }
+        declaration
+    }
+
+    fn write_flags(&mut self, flags: u16, names: &[(u16, &str)]) -> io::Result<()> {
+        let names: Vec<&str> = names.iter()
+            .filter(|(flag, _)| flags & flag != 0)
+            .map(|(_, name)| *name)
+            .collect();
+        self.out.println(&format!("flags: (0x{:04x}) {}", flags, names.join(", ")))
+    }
+
+    fn write_constant_pool(&mut self) -> io::Result<()> {
+        self.out.println("Constant pool:")?;
+        self.out.indent += 1;
+        let count = self.constant_pool.get_count();
+        let width = count.to_string().len() + 1;
+        for index in 1..count {
+            // The slot after a long or double is unusable
+            let entry = match self.constant_pool.get_entry(index) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+            let number = format!("#{}", index);
+            self.out.print(&format!("{:>2$} = {:<18} ", number, pool_tag_name(entry), width));
+            match entry {
+                CONSTANT_Class_info { name_index }
+                | CONSTANT_String_info { string_index: name_index }
+                | CONSTANT_Module_info { name_index }
+                | CONSTANT_Package_info { name_index } => {
+                    self.out.print(&format!("#{}", name_index));
+                    self.write_comment(entry)?;
+                }
+                CONSTANT_Fieldref_info { class_index, name_and_type_index }
+                | CONSTANT_Methodref_info { class_index, name_and_type_index }
+                | CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index } => {
+                    self.out.print(&format!("#{}.#{}", class_index, name_and_type_index));
+                    self.write_comment(entry)?;
+                }
+                CONSTANT_NameAndType_info { name_index, descriptor_index } => {
+                    self.out.print(&format!("#{}:#{}", name_index, descriptor_index));
+                    self.write_comment(entry)?;
+                }
+                CONSTANT_MethodHandle_info { reference_kind, reference_index } => {
+                    self.out.print(&format!("{}:#{}", *reference_kind as u8, reference_index));
+                    self.write_comment(entry)?;
+                }
+                CONSTANT_MethodType_info { descriptor_index } => {
+                    self.out.print(&format!("#{}", descriptor_index));
+                    self.out.tab();
+                    let value = self.constant_value(entry);
+                    self.out.println(&format!("//  {}", value))?;
+                }
+                CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, name_and_type_index }
+                | CONSTANT_Dynamic_info { bootstrap_method_attr_index, name_and_type_index } => {
+                    self.out.print(&format!("#{}:#{}", bootstrap_method_attr_index, name_and_type_index));
+                    self.write_comment(entry)?;
+                }
+                CONSTANT_Utf8_info { bytes, raw } => {
+                    self.out.println(&escape_trailing_spaces(&escape_utf8(bytes, raw)))?;
+                }
+                CONSTANT_Integer_info { .. }
+                | CONSTANT_Float_info { .. }
+                | CONSTANT_Long_info { .. }
+                | CONSTANT_Double_info { .. } => {
+                    let value = self.constant_value(entry);
+                    self.out.println(&value)?;
+                }
+            }
+        }
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    /// Finishes a line with a comment giving the value of a constant
+    fn write_comment(&mut self, entry: &cp_info) -> io::Result<()> {
+        self.out.tab();
+        let value = self.constant_value(entry);
+        self.out.println(&format!("// {}", value))
+    }
+
+    fn write_field(&mut self, field: &FieldInfo<'a>) -> io::Result<()> {
+        let descriptor = self.raw_utf8(field.get_descriptor_index());
+        let flags = field.get_raw_access_flags();
+        let field_type = match field.get_signature() {
+            Ok(Some(signature)) => signature.to_string(),
+            _ => java_type(&descriptor).0,
+        };
+        self.out.println(&format!(
+            "{}{} {};",
+            modifiers(flags, FIELD_MODIFIERS),
+            field_type,
+            field.get_name()
+        ))?;
+        self.out.indent += 1;
+        self.out.println(&format!("descriptor: {}", descriptor))?;
+        self.write_flags(flags, FIELD_FLAGS)?;
+        self.write_attributes(field.get_attributes(), None)?;
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    fn write_method(&mut self, method: &MethodInfo<'a>) -> io::Result<()> {
+        let descriptor = self.raw_utf8(method.get_descriptor_index());
+        let flags = method.get_raw_access_flags();
+        // Types come from the generic signature when there is one, which for the constructors of
+        // inner classes and enums leaves out the synthetic parameters
+        let signature = method.get_signature().ok().and_then(|signature| signature);
+        let (parameters, return_type) = match signature {
+            Some(ref signature) => (
+                signature.get_parameters().iter().map(|parameter| parameter.to_string()).collect(),
+                signature.get_return_type().to_string(),
+            ),
+            None => java_method_types(&descriptor),
+        };
+        let mut parameters = format!("({})", parameters.join(", "));
+        if flags & ACC_VARARGS != 0 {
+            // Like javap, this takes the last array in the parameters to be the variable one
+            if let Some(array) = parameters.rfind("[]") {
+                parameters.replace_range(array..array + 2, "...");
+            }
+        }
+        let mut declaration = modifiers(flags, METHOD_MODIFIERS);
+        if let Some(ref signature) = signature {
+            declaration.push_str(&type_parameters(signature.get_type_parameters(), "> "));
+        }
+        match method.get_name() {
+            "<init>" => {
+                declaration.push_str(&java_name(self.class.get_name()));
+                declaration.push_str(&parameters);
+            }
+            "<clinit>" => declaration.push_str("{}"),
+            name => declaration.push_str(&format!("{} {}{}", return_type, name, parameters)),
+        }
+        for info in method.get_attributes() {
+            if let Exceptions_attribute { exception_index_table, .. } = info.get_data() {
+                let exceptions: Vec<String> = match signature {
+                    Some(ref signature) if !signature.get_throws().is_empty() => {
+                        signature.get_throws().iter().map(|exception| exception.to_string()).collect()
+                    }
+                    _ => exception_index_table.iter()
+                        .map(|index| java_name(&self.class_name(*index)))
+                        .collect(),
+                };
+                declaration.push_str(" throws ");
+                declaration.push_str(&exceptions.join(", "));
+            }
+        }
+        declaration.push(';');
+        self.out.println(&declaration)?;
+        self.out.indent += 1;
+        self.out.println(&format!("descriptor: {}", descriptor))?;
+        self.write_flags(flags, METHOD_FLAGS)?;
+        self.write_attributes(method.get_attributes(), Some(method))?;
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    /// Prints attributes, with `method` being the method that any Code attribute belongs to
+    fn write_attributes(&mut self, attributes: &[attribute_info], method: Option<&MethodInfo>) -> io::Result<()> {
+        for info in attributes {
+            self.write_attribute(info, method)?;
+        }
+        Ok(())
+    }
+
+    fn write_attribute(&mut self, info: &attribute_info, method: Option<&MethodInfo>) -> io::Result<()> {
+        match info.get_data() {
+            ConstantValue_attribute { constantvalue_index } => {
+                let value = self.operand_constant(*constantvalue_index);
+                // Only the spaces of the value itself, not the one after its kind
+                let (kind, value) = value.split_at(value.find(' ').map_or(0, |space| space + 1));
+                self.out.println(&format!("ConstantValue: {}{}", kind, escape_trailing_spaces(value)))
+            }
+            Code_attribute { max_stack, max_locals, code, exception_table, attributes, .. } => {
+                let args_size = method.map_or(0, |method| {
+                    let parameters = method.get_descriptor().get_parameters().len();
+                    let this_size = if method.get_raw_access_flags() & ACC_STATIC != 0 { 0 } else { 1 };
+                    parameters + this_size
+                });
+                self.out.println("Code:")?;
+                self.out.indent += 1;
+                self.out.println(&format!(
+                    "stack={}, locals={}, args_size={}",
+                    max_stack, max_locals, args_size
+                ))?;
+                for instruction in InstructionIter::new(code) {
+                    match instruction {
+                        Ok((pc, instruction)) => self.write_instruction(pc, &instruction)?,
+                        Err(error) => self.out.println(&format!("error: {}", error))?,
+                    }
+                }
+                self.write_exception_table(exception_table)?;
+                self.write_attributes(attributes, None)?;
+                self.out.indent -= 1;
+                Ok(())
+            }
+            StackMapTable_attribute { number_of_entries, entries } => {
+                self.out.println(&format!("StackMapTable: number_of_entries = {}", number_of_entries))?;
+                self.out.indent += 1;
+                for frame in entries {
+                    self.write_frame(frame)?;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            Exceptions_attribute { exception_index_table, .. } => {
+                let exceptions: Vec<String> = exception_index_table.iter()
+                    .map(|index| java_name(&self.class_name(*index)))
+                    .collect();
+                self.out.println("Exceptions:")?;
+                self.out.indent += 1;
+                self.out.println(&format!("throws {}", exceptions.join(", ")))?;
+                self.out.indent -= 1;
+                Ok(())
+            }
+            InnerClasses_attribute { classes, .. } => {
+                self.out.println("InnerClasses:")?;
+                self.out.indent += 1;
+                for inner in classes {
+                    // The raw flags keep any reserved bits, the modifiers are for reading
+                    let flags = inner.get_inner_class_access_flags();
+                    self.out.print(&format!("(0x{:04x}) ", flags));
+                    self.out.print(&modifiers(flags, INNER_CLASS_MODIFIERS));
+                    let inner_name_index = inner.get_inner_name_index();
+                    let outer_class_index = inner.get_outer_class_info_index();
+                    if inner_name_index != 0 {
+                        self.out.print(&format!("#{}= ", inner_name_index));
+                    }
+                    self.out.print(&format!("#{}", inner.get_inner_class_info_index()));
+                    if outer_class_index != 0 {
+                        self.out.print(&format!(" of #{}", outer_class_index));
+                    }
+                    self.out.print(";");
+                    self.out.tab();
+                    let mut comment = String::from("// ");
+                    if inner_name_index != 0 {
+                        comment.push_str(&self.raw_utf8(inner_name_index));
+                        comment.push('=');
+                    }
+                    comment.push_str(&self.operand_constant(inner.get_inner_class_info_index()));
+                    if outer_class_index != 0 {
+                        comment.push_str(" of ");
+                        comment.push_str(&self.operand_constant(outer_class_index));
+                    }
+                    self.out.println(&comment)?;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            EnclosingMethod_attribute { class_index, method_index } => {
+                self.out.print(&format!("EnclosingMethod: #{}.#{}", class_index, method_index));
+                self.out.tab();
+                let mut comment = format!("// {}", java_name(&self.class_name(*class_index)));
+                if *method_index != 0 {
+                    comment.push('.');
+                    comment.push_str(&self.name_and_type_name(*method_index));
+                }
+                self.out.println(&comment)
+            }
+            Synthetic_attribute => self.out.println("Synthetic: true"),
+            Signature_attribute { signature_index } => {
+                self.out.print(&format!("Signature: #{}", signature_index));
+                self.out.tab();
+                let signature = self.utf8(*signature_index);
+                self.out.println(&format!("// {}", signature))
+            }
+            SourceFile_attribute { sourcefile_index } => {
+                let source_file = self.raw_utf8(*sourcefile_index);
+                self.out.println(&format!("SourceFile: \"{}\"", source_file))
+            }
+            SourceDebugExtension { debug_extension } => {
+                self.out.println("SourceDebugExtension:")?;
+                self.out.indent += 1;
+                let debug_extension = String::from_utf8_lossy(debug_extension);
+                for line in debug_extension.split(&['\r', '\n'][..]) {
+                    if !line.is_empty() {
+                        self.out.println(line)?;
+                    }
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            LineNumberTable_attribute { line_number_table, .. } => {
+                self.out.println("LineNumberTable:")?;
+                self.out.indent += 1;
+                for entry in line_number_table {
+                    self.out.println(&format!("line {}: {}", entry.get_line_number(), entry.get_start_pc()))?;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            LocalVariableTable_attribute { local_variable_table, .. } => {
+                self.out.println("LocalVariableTable:")?;
+                self.out.indent += 1;
+                self.out.println("Start  Length  Slot  Name   Signature")?;
+                for entry in local_variable_table {
+                    let line = format!(
+                        "{:5} {:7} {:5} {:>5}   {}",
+                        entry.get_start_pc(),
+                        entry.get_length(),
+                        entry.get_index(),
+                        self.utf8(entry.get_name_index()),
+                        self.utf8(entry.get_descriptor_index())
+                    );
+                    self.out.println(&line)?;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            LocalVariableTypeTable_attribute { local_variable_type_table, .. } => {
+                self.out.println("LocalVariableTypeTable:")?;
+                self.out.indent += 1;
+                self.out.println("Start  Length  Slot  Name   Signature")?;
+                for entry in local_variable_type_table {
+                    let line = format!(
+                        "{:5} {:7} {:5} {:>5}   {}",
+                        entry.get_start_pc(),
+                        entry.get_length(),
+                        entry.get_index(),
+                        self.utf8(entry.get_name_index()),
+                        self.utf8(entry.get_signature_index())
+                    );
+                    self.out.println(&line)?;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            Deprecated_attribute => self.out.println("Deprecated: true"),
+            RuntimeVisibleAnnotations_attribute { annotations, .. } => {
+                self.write_annotations("RuntimeVisibleAnnotations", annotations)
+            }
+            RuntimeInvisibleAnnotations_attribute { annotations, .. } => {
+                self.write_annotations("RuntimeInvisibleAnnotations", annotations)
+            }
+            RuntimeVisibleParameterAnnotations_attribute { parameter_annotations, .. } => {
+                self.out.println("RuntimeVisibleParameterAnnotations:")?;
+                self.write_parameter_annotations(parameter_annotations)
+            }
+            RuntimeInvisibleParameterAnnotations_attribute { parameter_annotations, .. } => {
+                self.out.println("RuntimeInvisibleParameterAnnotations:")?;
+                self.write_parameter_annotations(parameter_annotations)
+            }
+            AnnotationDefault_attribute { default_value } => {
+                self.out.println("AnnotationDefault:")?;
+                self.out.indent += 1;
+                let raw_value = self.raw_element_value(default_value);
+                self.out.println(&format!("default_value: {}", raw_value))?;
+                self.out.indent += 1;
+                self.write_element_value(default_value)?;
+                self.out.println("")?;
+                self.out.indent -= 2;
+                Ok(())
+            }
+            BootstrapMethods_attribute { bootstrap_methods, .. } => {
+                self.out.println("BootstrapMethods:")?;
+                self.out.indent += 1;
+                for (index, bootstrap_method) in bootstrap_methods.iter().enumerate() {
+                    let method_ref = bootstrap_method.get_method_ref();
+                    let method_handle = self.constant(method_ref);
+                    self.out.println(&format!("{}: #{} {}", index, method_ref, method_handle))?;
+                    self.out.indent += 1;
+                    self.out.println("Method arguments:")?;
+                    self.out.indent += 1;
+                    for argument in bootstrap_method.get_arguments() {
+                        let value = self.constant(*argument);
+                        self.out.println(&format!("#{} {}", argument, value))?;
+                    }
+                    self.out.indent -= 2;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            Module_attribute {
+                module_name_index,
+                module_flags,
+                module_version_index,
+                requires,
+                exports,
+                opens,
+                uses_index,
+                provides,
+                ..
+            } => {
+                self.out.println("Module:")?;
+                self.out.indent += 1;
+                self.out.print(&format!("#{},{:x}", module_name_index, module_flags));
+                self.out.tab();
+                let mut comment = format!("// {}", self.constant(*module_name_index));
+                comment.push_str(&module_flag_names(
+                    *module_flags,
+                    &[(0x0020, "ACC_OPEN"), (0x1000, "ACC_SYNTHETIC"), (0x8000, "ACC_MANDATED")],
+                ));
+                self.out.println(&comment)?;
+                self.write_version(*module_version_index)?;
+
+                self.write_count(requires.len(), "requires")?;
+                for entry in requires {
+                    let requires_index = entry.get_requires_index();
+                    let flags = entry.get_requires_flags();
+                    self.out.print(&format!("#{},{:x}", requires_index, flags));
+                    self.out.tab();
+                    let mut comment = format!("// {}", self.constant(requires_index));
+                    comment.push_str(&module_flag_names(flags, &[
+                        (0x0020, "ACC_TRANSITIVE"),
+                        (0x0040, "ACC_STATIC_PHASE"),
+                        (0x1000, "ACC_SYNTHETIC"),
+                        (0x8000, "ACC_MANDATED"),
+                    ]));
+                    self.out.println(&comment)?;
+                    self.write_version(entry.get_requires_version_index())?;
+                }
+                self.out.indent -= 1;
+
+                self.write_count(exports.len(), "exports")?;
+                for entry in exports {
+                    self.write_package_directive(
+                        entry.get_exports_index(),
+                        entry.get_exports_flags(),
+                        entry.get_exports_to_index(),
+                    )?;
+                }
+                self.out.indent -= 1;
+
+                self.write_count(opens.len(), "opens")?;
+                for entry in opens {
+                    self.write_package_directive(
+                        entry.get_opens_index(),
+                        entry.get_opens_flags(),
+                        entry.get_opens_to_index(),
+                    )?;
+                }
+                self.out.indent -= 1;
+
+                self.write_count(uses_index.len(), "uses")?;
+                for index in uses_index {
+                    self.out.print(&format!("#{}", index));
+                    self.out.tab();
+                    let service = self.constant(*index);
+                    self.out.println(&format!("// {}", service))?;
+                }
+                self.out.indent -= 1;
+
+                self.write_count(provides.len(), "provides")?;
+                for entry in provides {
+                    let provides_index = entry.get_provides_index();
+                    let with = entry.get_provides_with_index();
+                    self.out.print(&format!("#{}", provides_index));
+                    self.out.tab();
+                    let service = self.constant(provides_index);
+                    self.out.println(&format!("// {} with ... {}", service, with.len()))?;
+                    self.out.indent += 1;
+                    for index in with {
+                        self.out.print(&format!("#{}", index));
+                        self.out.tab();
+                        let implementation = self.constant(*index);
+                        self.out.println(&format!("// ... with {}", implementation))?;
+                    }
+                    self.out.indent -= 1;
+                }
+                self.out.indent -= 2;
+                Ok(())
+            }
+            ModulePackages_attribute { package_index, .. } => {
+                self.out.println("ModulePackages:")?;
+                self.out.indent += 1;
+                for index in package_index {
+                    self.out.print(&format!("#{}", index));
+                    self.out.tab();
+                    let package = java_name(&self.constant(*index));
+                    self.out.println(&format!("// {}", package))?;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            ModuleMainClass_attribute { main_class_index } => {
+                self.out.print(&format!("ModuleMainClass: #{}", main_class_index));
+                self.out.tab();
+                let main_class = java_name(&self.class_name(*main_class_index));
+                self.out.println(&format!("// {}", main_class))
+            }
+            NestHost_attribute { host_class_index } => {
+                let host_class = self.operand_constant(*host_class_index);
+                self.out.println(&format!("NestHost: {}", host_class))
+            }
+            NestMembers_attribute { classes, .. } => self.write_class_list("NestMembers:", classes),
+            PermittedSubclasses_attribute { classes, .. } => {
+                self.write_class_list("PermittedSubclasses:", classes)
+            }
+            Record_attribute { components, .. } => {
+                self.out.println("Record:")?;
+                self.out.indent += 1;
+                for component in components {
+                    self.write_record_component(component)?;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            MethodParameters_attribute { parameters, .. } => {
+                self.out.println("MethodParameters:")?;
+                self.out.indent += 1;
+                let names: Vec<String> = parameters.iter()
+                    .map(|parameter| match parameter.get_name_index() {
+                        0 => String::from("<no name>"),
+                        index => self.utf8(index),
+                    })
+                    .collect();
+                // Long names push the flags column out, so that the two never run together
+                let width = names.iter().map(|name| name.chars().count() + 1).fold(31, usize::max);
+                self.out.println(&format!("{:<width$}{}", "Name", "Flags", width = width))?;
+                for (parameter, name) in parameters.iter().zip(names) {
+                    let flags = parameter.get_access_flags();
+                    let mut flag_names = String::new();
+                    if flags & 0x0010 != 0 {
+                        flag_names.push_str("final ");
+                    }
+                    if flags & 0x8000 != 0 {
+                        flag_names.push_str("mandated ");
+                    }
+                    if flags & 0x1000 != 0 {
+                        flag_names.push_str("synthetic");
+                    }
+                    let row = format!("{:<width$}{}", name, flag_names, width = width);
+                    self.out.println(row.trim_end())?;
+                }
+                self.out.indent -= 1;
+                Ok(())
+            }
+            RuntimeVisibleTypeAnnotations_attribute { annotations, .. } => {
+                self.write_type_annotations("RuntimeVisibleTypeAnnotations", annotations)
+            }
+            RuntimeInvisibleTypeAnnotations_attribute { annotations, .. } => {
+                self.write_type_annotations("RuntimeInvisibleTypeAnnotations", annotations)
+            }
+            Unknown_attribute { info: bytes } => {
+                let name = self.raw_utf8(info.get_name_index());
+                self.out.println(&format!("{}: length = 0x{:X} (unknown attribute)", name, bytes.len()))?;
+                self.write_bytes(bytes)
+            }
+            Malformed_attribute { info: bytes, error } => {
+                let name = self.raw_utf8(info.get_name_index());
+                self.out.println(&format!("{}: length = 0x{:X} (malformed attribute)", name, bytes.len()))?;
+                self.out.println(&format!("   // {}", error))?;
+                self.write_bytes(bytes)
+            }
+        }
+    }
+
+    /// Writes the contents of an attribute in hex, 16 bytes to a line
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        for line in bytes.chunks(16) {
+            let hex: Vec<String> = line.iter().map(|byte| format!("{:02X}", byte)).collect();
+            self.out.println(&format!("   {}", hex.join(" ")))?;
+        }
+        Ok(())
+    }
+
+    fn write_instruction(&mut self, pc: u32, instruction: &Instruction) -> io::Result<()> {
+        let mnemonic = match instruction {
+            Wide(wide) => format!("{}_w", wide.get_mnemonic()),
+            _ => String::from(instruction.get_mnemonic()),
+        };
+        self.out.print(&format!("{:4}: {:<13} ", pc, mnemonic));
+        let branch = |offset: i32| (pc as i64 + offset as i64).to_string();
+        match *instruction {
+            Bipush(value) => self.out.print(&value.to_string()),
+            Sipush(value) => self.out.print(&value.to_string()),
+            Ldc(index) => self.write_constant_operand(index as u16),
+            LdcW(index)
+            | Ldc2W(index)
+            | Getstatic(index)
+            | Putstatic(index)
+            | Getfield(index)
+            | Putfield(index)
+            | Invokevirtual(index)
+            | Invokespecial(index)
+            | Invokestatic(index)
+            | New(index)
+            | Anewarray(index)
+            | Checkcast(index)
+            | Instanceof(index) => self.write_constant_operand(index),
+            Iload(index)
+            | Lload(index)
+            | Fload(index)
+            | Dload(index)
+            | Aload(index)
+            | Istore(index)
+            | Lstore(index)
+            | Fstore(index)
+            | Dstore(index)
+            | Astore(index)
+            | Ret(index) => self.out.print(&index.to_string()),
+            Iinc { index, constant } => self.out.print(&format!("{}, {}", index, constant)),
+            Ifeq(offset)
+            | Ifne(offset)
+            | Iflt(offset)
+            | Ifge(offset)
+            | Ifgt(offset)
+            | Ifle(offset)
+            | IfIcmpeq(offset)
+            | IfIcmpne(offset)
+            | IfIcmplt(offset)
+            | IfIcmpge(offset)
+            | IfIcmpgt(offset)
+            | IfIcmple(offset)
+            | IfAcmpeq(offset)
+            | IfAcmpne(offset)
+            | Goto(offset)
+            | Jsr(offset)
+            | Ifnull(offset)
+            | Ifnonnull(offset) => self.out.print(&branch(offset as i32)),
+            GotoW(offset) | JsrW(offset) => self.out.print(&branch(offset)),
+            Tableswitch { default, low, high, ref offsets } => {
+                self.out.println(&format!("{{ // {} to {}", low, high))?;
+                for (index, offset) in offsets.iter().enumerate() {
+                    let key = low as i64 + index as i64;
+                    self.out.println(&format!("{:>18}: {}", key, branch(*offset)))?;
+                }
+                self.out.println(&format!("{:>18}: {}", "default", branch(default)))?;
+                self.out.print("      }");
+            }
+            Lookupswitch { default, ref pairs } => {
+                self.out.println(&format!("{{ // {}", pairs.len()))?;
+                for (key, offset) in pairs {
+                    self.out.println(&format!("{:>18}: {}", key, branch(*offset)))?;
+                }
+                self.out.println(&format!("{:>18}: {}", "default", branch(default)))?;
+                self.out.print("      }");
+            }
+            Invokeinterface { index, count } => {
+                self.out.print(&format!("#{},  {}", index, count));
+                self.write_operand_comment(index);
+            }
+            Invokedynamic(index) => {
+                self.out.print(&format!("#{},  0", index));
+                self.write_operand_comment(index);
+            }
+            Multianewarray { index, dimensions } => {
+                self.out.print(&format!("#{},  {}", index, dimensions));
+                self.write_operand_comment(index);
+            }
+            Newarray(atype) => self.out.print(&format!(" {}", array_type_name(atype))),
+            Wide(WideInstruction::Iinc { index, constant }) => {
+                self.out.print(&format!("{}, {}", index, constant))
+            }
+            Wide(ref wide) => self.out.print(&wide.get_index().to_string()),
+            _ => {}
+        }
+        self.out.println("")
+    }
+
+    fn write_constant_operand(&mut self, index: u16) {
+        self.out.print(&format!("#{}", index));
+        self.write_operand_comment(index);
+    }
+
+    fn write_operand_comment(&mut self, index: u16) {
+        self.out.tab();
+        let constant = self.operand_constant(index);
+        self.out.print(&format!("// {}", constant));
+    }
+
+    fn write_exception_table(&mut self, exception_table: &[exception_info]) -> io::Result<()> {
+        if exception_table.is_empty() {
+            return Ok(());
+        }
+        self.out.println("Exception table:")?;
+        self.out.indent += 1;
+        self.out.println(" from    to  target type")?;
+        for entry in exception_table {
+            let catch_type = match entry.get_catch_type() {
+                0 => String::from("any"),
+                index => format!("Class {}", self.constant(index)),
+            };
+            self.out.println(&format!(
+                " {:5} {:5} {:5}   {}",
+                entry.get_start_pc(),
+                entry.get_end_pc(),
+                entry.get_handler_pc(),
+                catch_type
+            ))?;
+        }
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    fn write_frame(&mut self, frame: &stack_map_frame) -> io::Result<()> {
+        let data = frame.get_frame_data();
+        let kind = match data {
+            stack_map_frame_data::same_frame => "same",
+            stack_map_frame_data::same_locals_1_stack_item_frame { .. } => "same_locals_1_stack_item",
+            stack_map_frame_data::same_locals_1_stack_item_frame_extended { .. } => {
+                "same_locals_1_stack_item_frame_extended"
+            }
+            stack_map_frame_data::chop_frame { .. } => "chop",
+            stack_map_frame_data::same_frame_extended { .. } => "same_frame_extended",
+            stack_map_frame_data::append_frame { .. } => "append",
+            stack_map_frame_data::full_frame { .. } => "full_frame",
+        };
+        self.out.println(&format!("frame_type = {} /* {} */", frame.get_frame_type(), kind))?;
+        self.out.indent += 1;
+        match data {
+            stack_map_frame_data::same_frame => {}
+            stack_map_frame_data::same_locals_1_stack_item_frame { stack } => {
+                self.write_verification_types("stack", slice::from_ref(stack))?;
+            }
+            stack_map_frame_data::same_locals_1_stack_item_frame_extended { offset_delta, stack } => {
+                self.out.println(&format!("offset_delta = {}", offset_delta))?;
+                self.write_verification_types("stack", slice::from_ref(stack))?;
+            }
+            stack_map_frame_data::chop_frame { offset_delta }
+            | stack_map_frame_data::same_frame_extended { offset_delta } => {
+                self.out.println(&format!("offset_delta = {}", offset_delta))?;
+            }
+            stack_map_frame_data::append_frame { offset_delta, locals } => {
+                self.out.println(&format!("offset_delta = {}", offset_delta))?;
+                self.write_verification_types("locals", locals)?;
+            }
+            stack_map_frame_data::full_frame { offset_delta, locals, stack, .. } => {
+                self.out.println(&format!("offset_delta = {}", offset_delta))?;
+                self.write_verification_types("locals", locals)?;
+                self.write_verification_types("stack", stack)?;
+            }
+        }
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    fn write_verification_types(&mut self, name: &str, types: &[verification_type_info]) -> io::Result<()> {
+        let types: Vec<String> = types.iter()
+            .map(|verification_type| match verification_type.get_data() {
+                verification_type_info_data::Top_variable_info => String::from("top"),
+                verification_type_info_data::Integer_variable_info => String::from("int"),
+                verification_type_info_data::Float_variable_info => String::from("float"),
+                verification_type_info_data::Long_variable_info => String::from("long"),
+                verification_type_info_data::Double_variable_info => String::from("double"),
+                verification_type_info_data::Null_variable_info => String::from("null"),
+                verification_type_info_data::UninitializedThis_variable_info => String::from("this"),
+                verification_type_info_data::Object_variable_info { cpool_index } => {
+                    format!("class {}", self.constant(*cpool_index))
+                }
+                verification_type_info_data::Uninitialized_variable_info { offset } => {
+                    format!("uninitialized {}", offset)
+                }
+            })
+            .collect();
+        if types.is_empty() {
+            self.out.println(&format!("{} = []", name))
+        } else {
+            self.out.println(&format!("{} = [ {} ]", name, types.join(", ")))
+        }
+    }
+
+    fn write_count(&mut self, count: usize, directive: &str) -> io::Result<()> {
+        self.out.print(&count.to_string());
+        self.out.tab();
+        self.out.println(&format!("// {}", directive))?;
+        self.out.indent += 1;
+        Ok(())
+    }
+
+    fn write_version(&mut self, version_index: u16) -> io::Result<()> {
+        self.out.print(&format!("#{}", version_index));
+        if version_index != 0 {
+            self.out.tab();
+            let version = self.utf8(version_index);
+            self.out.print(&format!("// {}", version));
+        }
+        self.out.println("")
+    }
+
+    /// Prints an `exports` or `opens` directive of a Module attribute
+    fn write_package_directive(&mut self, package_index: u16, flags: u16, to_index: &[u16]) -> io::Result<()> {
+        self.out.print(&format!("#{},{:x}", package_index, flags));
+        self.out.tab();
+        let mut comment = format!("// {}", self.constant(package_index));
+        comment.push_str(&module_flag_names(flags, &[(0x1000, "ACC_SYNTHETIC"), (0x8000, "ACC_MANDATED")]));
+        if to_index.is_empty() {
+            return self.out.println(&comment);
+        }
+        comment.push_str(&format!(" to ... {}", to_index.len()));
+        self.out.println(&comment)?;
+        self.out.indent += 1;
+        for index in to_index {
+            self.out.print(&format!("#{}", index));
+            self.out.tab();
+            let module = self.constant(*index);
+            self.out.println(&format!("// ... to {}", module))?;
+        }
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    fn write_class_list(&mut self, header: &str, classes: &[u16]) -> io::Result<()> {
+        self.out.println(header)?;
+        self.out.indent += 1;
+        for index in classes {
+            let class = self.constant(*index);
+            self.out.println(&class)?;
+        }
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    fn write_record_component(&mut self, component: &record_component_info) -> io::Result<()> {
+        let descriptor = self.raw_utf8(component.get_descriptor_index());
+        let name = self.raw_utf8(component.get_name_index());
+        let signature = signature::read_signature(
+            component.get_attributes(),
+            self.constant_pool,
+            signature::parse_field_signature,
+        );
+        let component_type = match signature {
+            Ok(Some(signature)) => signature.to_string(),
+            _ => java_type(&descriptor).0,
+        };
+        self.out.println(&format!("{} {};", component_type, name))?;
+        self.out.indent += 1;
+        self.out.println(&format!("descriptor: {}", descriptor))?;
+        self.write_attributes(component.get_attributes(), None)?;
+        self.out.indent -= 1;
+        self.out.println("")
+    }
+
+    fn write_annotations(&mut self, header: &str, annotations: &[annotation]) -> io::Result<()> {
+        self.out.println(&format!("{}:", header))?;
+        self.out.indent += 1;
+        for (index, annotation) in annotations.iter().enumerate() {
+            let raw_annotation = self.raw_annotation(annotation);
+            self.out.println(&format!("{}: {}", index, raw_annotation))?;
+            self.out.indent += 1;
+            self.write_annotation(annotation)?;
+            self.out.println("")?;
+            self.out.indent -= 1;
+        }
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    fn write_parameter_annotations(&mut self, parameter_annotations: &[::attribute::annotation_list]) -> io::Result<()> {
+        self.out.indent += 1;
+        for (parameter, annotations) in parameter_annotations.iter().enumerate() {
+            self.out.println(&format!("parameter {}:", parameter))?;
+            self.out.indent += 1;
+            for (index, annotation) in annotations.get_annotations().iter().enumerate() {
+                let raw_annotation = self.raw_annotation(annotation);
+                self.out.println(&format!("{}: {}", index, raw_annotation))?;
+                self.out.indent += 1;
+                self.write_annotation(annotation)?;
+                self.out.println("")?;
+                self.out.indent -= 1;
+            }
+            self.out.indent -= 1;
+        }
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    fn write_type_annotations(&mut self, header: &str, annotations: &[type_annotation]) -> io::Result<()> {
+        self.out.println(&format!("{}:", header))?;
+        self.out.indent += 1;
+        for (index, annotation) in annotations.iter().enumerate() {
+            let raw_annotation = self.raw_annotation(annotation.get_annotation());
+            let target = type_annotation_target(annotation);
+            self.out.println(&format!("{}: {}: {}", index, raw_annotation, target))?;
+            self.out.indent += 1;
+            self.write_annotation(annotation.get_annotation())?;
+            self.out.println("")?;
+            self.out.indent -= 1;
+        }
+        self.out.indent -= 1;
+        Ok(())
+    }
+
+    /// Prints an annotation with its type and element names resolved, leaving the line open
+    fn write_annotation(&mut self, annotation: &annotation) -> io::Result<()> {
+        let annotation_type = java_type(&self.raw_utf8(annotation.get_type_index())).0;
+        self.out.print(&annotation_type);
+        let pairs = annotation.get_element_value_pairs();
+        if !pairs.is_empty() {
+            self.out.println("(")?;
+            self.out.indent += 1;
+            for pair in pairs {
+                let name = self.utf8(pair.get_element_name_index());
+                self.out.print(&format!("{}=", name));
+                self.write_element_value(pair.get_value())?;
+                self.out.println("")?;
+            }
+            self.out.indent -= 1;
+            self.out.print(")");
+        }
+        Ok(())
+    }
+
+    /// Prints an element value of an annotation as it would appear in Java, leaving the line
+    /// open
+    fn write_element_value(&mut self, value: &element_value) -> io::Result<()> {
+        match value.get_value() {
+            element_value_data::const_value_index(index) => {
+                let constant = self.element_constant(value.get_tag(), *index);
+                self.out.print(&constant);
+            }
+            element_value_data::enum_const_value { type_name_index, const_name_index } => {
+                let enum_value = format!("{}.{}", self.utf8(*type_name_index), self.utf8(*const_name_index));
+                self.out.print(&enum_value);
+            }
+            element_value_data::class_info_index(index) => {
+                let class = format!("class {}", self.utf8(*index));
+                self.out.print(&class);
+            }
+            element_value_data::annotation_value(annotation) => {
+                self.out.print("@");
+                self.write_annotation(annotation)?;
+            }
+            element_value_data::array_value { values, .. } => {
+                self.out.print("[");
+                for (index, value) in values.iter().enumerate() {
+                    if index != 0 {
+                        self.out.print(",");
+                    }
+                    self.write_element_value(value)?;
+                }
+                self.out.print("]");
+            }
+        }
+        Ok(())
+    }
+
+    /// An annotation with constant pool indices in place of its type and elements, eg
+    /// `#12(#13=s#14)`
+    fn raw_annotation(&self, annotation: &annotation) -> String {
+        let pairs: Vec<String> = annotation.get_element_value_pairs().iter()
+            .map(|pair| format!("#{}={}", pair.get_element_name_index(), self.raw_element_value(pair.get_value())))
+            .collect();
+        format!("#{}({})", annotation.get_type_index(), pairs.join(","))
+    }
+
+    fn raw_element_value(&self, value: &element_value) -> String {
+        match value.get_value() {
+            element_value_data::const_value_index(index) => format!("{}#{}", value.get_tag() as char, index),
+            element_value_data::enum_const_value { type_name_index, const_name_index } => {
+                format!("e#{}.#{}", type_name_index, const_name_index)
+            }
+            element_value_data::class_info_index(index) => format!("c#{}", index),
+            element_value_data::annotation_value(annotation) => format!("@{}", self.raw_annotation(annotation)),
+            element_value_data::array_value { values, .. } => {
+                let values: Vec<String> = values.iter().map(|value| self.raw_element_value(value)).collect();
+                format!("[{}]", values.join(","))
+            }
+        }
+    }
+
+    /// The value of a constant element of an annotation, as it would be written in Java
+    fn element_constant(&self, tag: u8, index: u16) -> String {
+        let entry = match self.constant_pool.get_entry(index) {
+            Ok(entry) => entry,
+            Err(_) => return invalid_index(index),
+        };
+        match (tag, entry) {
+            (b'Z', CONSTANT_Integer_info { bytes }) => (*bytes != 0).to_string(),
+            (b'C', CONSTANT_Integer_info { bytes }) => match ::std::char::from_u32(*bytes as u32) {
+                Some(character) => format!("'{}'", escape(&character.to_string())),
+                None => bytes.to_string(),
+            },
+            (b'B', CONSTANT_Integer_info { bytes }) => format!("(byte) {}", bytes),
+            (b'S', CONSTANT_Integer_info { bytes }) => format!("(short) {}", bytes),
+            (b's', CONSTANT_Utf8_info { bytes, .. }) => format!("\"{}\"", escape(bytes)),
+            _ => self.constant_value(entry),
+        }
+    }
+
+    /// The value of a constant as shown in comments, eg `java/lang/Object."<init>":()V` for a
+    /// `CONSTANT_Methodref_info`
+    fn constant(&self, index: u16) -> String {
+        match self.constant_pool.get_entry(index) {
+            Ok(entry) => self.constant_value(entry),
+            Err(_) => invalid_index(index),
+        }
+    }
+
+    fn constant_value(&self, entry: &cp_info) -> String {
+        match entry {
+            CONSTANT_Class_info { name_index }
+            | CONSTANT_Module_info { name_index }
+            | CONSTANT_Package_info { name_index } => self.raw_name(*name_index),
+            CONSTANT_Fieldref_info { class_index, name_and_type_index }
+            | CONSTANT_Methodref_info { class_index, name_and_type_index }
+            | CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index } => format!(
+                "{}.{}",
+                self.checked_class_name(*class_index),
+                self.name_and_type(*name_and_type_index)
+            ),
+            CONSTANT_String_info { string_index } => self.utf8(*string_index),
+            CONSTANT_Integer_info { bytes } => bytes.to_string(),
+            CONSTANT_Float_info { bytes } => format!("{}f", java_float(*bytes as f64, format!("{:e}", bytes))),
+            CONSTANT_Long_info { value } => format!("{}l", value),
+            CONSTANT_Double_info { value } => format!("{}d", java_float(*value, format!("{:e}", value))),
+            CONSTANT_NameAndType_info { .. } => self.name_and_type_value(entry),
+            CONSTANT_Utf8_info { bytes, raw } => escape_utf8(bytes, raw),
+            CONSTANT_MethodHandle_info { reference_kind, reference_index } => {
+                let reference = match self.constant_pool.get_entry(*reference_index) {
+                    Ok(entry @ CONSTANT_Fieldref_info { .. })
+                    | Ok(entry @ CONSTANT_Methodref_info { .. })
+                    | Ok(entry @ CONSTANT_InterfaceMethodref_info { .. }) => self.constant_value(entry),
+                    _ => invalid_index(*reference_index),
+                };
+                format!("{:?} {}", reference_kind, reference)
+            }
+            CONSTANT_MethodType_info { descriptor_index } => self.utf8(*descriptor_index),
+            CONSTANT_InvokeDynamic_info { bootstrap_method_attr_index, name_and_type_index }
+            | CONSTANT_Dynamic_info { bootstrap_method_attr_index, name_and_type_index } => format!(
+                "#{}:{}",
+                bootstrap_method_attr_index,
+                self.name_and_type(*name_and_type_index)
+            ),
+        }
+    }
+
+    /// A constant referred to by an instruction or attribute, prefixed by its kind, eg
+    /// `Method java/lang/Object."<init>":()V`
+    ///
+    /// Members of this class are shown without the class name
+    fn operand_constant(&self, index: u16) -> String {
+        let entry = match self.constant_pool.get_entry(index) {
+            Ok(entry) => entry,
+            Err(_) => return invalid_index(index),
+        };
+        let value = match entry {
+            CONSTANT_Fieldref_info { class_index, name_and_type_index }
+            | CONSTANT_Methodref_info { class_index, name_and_type_index }
+            | CONSTANT_InterfaceMethodref_info { class_index, name_and_type_index }
+                if *class_index == self.class.get_this_class_index() =>
+            {
+                self.name_and_type(*name_and_type_index)
+            }
+            _ => self.constant_value(entry),
+        };
+        format!("{} {}", operand_tag_name(entry), value)
+    }
+
+    fn name_and_type(&self, index: u16) -> String {
+        match self.constant_pool.get_entry(index) {
+            Ok(entry @ CONSTANT_NameAndType_info { .. }) => self.name_and_type_value(entry),
+            _ => invalid_index(index),
+        }
+    }
+
+    fn name_and_type_value(&self, entry: &cp_info) -> String {
+        match entry {
+            CONSTANT_NameAndType_info { name_index, descriptor_index } => {
+                format!("{}:{}", self.raw_name(*name_index), self.utf8(*descriptor_index))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// The name of the member a `CONSTANT_NameAndType_info` refers to
+    fn name_and_type_name(&self, index: u16) -> String {
+        match self.constant_pool.get_entry(index) {
+            Ok(CONSTANT_NameAndType_info { name_index, .. }) => self.raw_utf8(*name_index),
+            _ => invalid_index(index),
+        }
+    }
+
+    /// The name of a class, quoted if it isn't made up of identifiers
+    fn checked_class_name(&self, index: u16) -> String {
+        match self.constant_pool.get_entry(index) {
+            Ok(CONSTANT_Class_info { name_index }) => self.raw_name(*name_index),
+            _ => invalid_index(index),
+        }
+    }
+
+    /// The binary name of a class, eg `java/lang/Object`
+    fn class_name(&self, index: u16) -> String {
+        self.constant_pool.class_name(index)
+            .map(String::from)
+            .unwrap_or_else(|_| invalid_index(index))
+    }
+
+    /// A name, quoted if it isn't made up of identifiers separated by slashes
+    fn raw_name(&self, index: u16) -> String {
+        match self.constant_pool.get_string_entry(index) {
+            Ok(name) => check_name(name),
+            Err(_) => invalid_index(index),
+        }
+    }
+
+    fn utf8(&self, index: u16) -> String {
+        match self.constant_pool.get_entry(index) {
+            Ok(CONSTANT_Utf8_info { bytes, raw }) => escape_utf8(bytes, raw),
+            _ => invalid_index(index),
+        }
+    }
+
+    fn raw_utf8(&self, index: u16) -> String {
+        self.constant_pool.get_string_entry(index)
+            .map(String::from)
+            .unwrap_or_else(|_| invalid_index(index))
+    }
+}
+
+fn invalid_index(index: u16) -> String {
+    format!("<invalid constant pool index {}>", index)
+}
+
+fn pool_tag_name(entry: &cp_info) -> &'static str {
+    match entry {
+        CONSTANT_Class_info { .. } => "Class",
+        CONSTANT_Fieldref_info { .. } => "Fieldref",
+        CONSTANT_Methodref_info { .. } => "Methodref",
+        CONSTANT_InterfaceMethodref_info { .. } => "InterfaceMethodref",
+        CONSTANT_String_info { .. } => "String",
+        CONSTANT_Integer_info { .. } => "Integer",
+        CONSTANT_Float_info { .. } => "Float",
+        CONSTANT_Long_info { .. } => "Long",
+        CONSTANT_Double_info { .. } => "Double",
+        CONSTANT_NameAndType_info { .. } => "NameAndType",
+        CONSTANT_Utf8_info { .. } => "Utf8",
+        CONSTANT_MethodHandle_info { .. } => "MethodHandle",
+        CONSTANT_MethodType_info { .. } => "MethodType",
+        CONSTANT_InvokeDynamic_info { .. } => "InvokeDynamic",
+        CONSTANT_Dynamic_info { .. } => "Dynamic",
+        CONSTANT_Module_info { .. } => "Module",
+        CONSTANT_Package_info { .. } => "Package",
+    }
+}
+
+fn operand_tag_name(entry: &cp_info) -> &'static str {
+    match entry {
+        CONSTANT_Class_info { .. } => "class",
+        CONSTANT_Fieldref_info { .. } => "Field",
+        CONSTANT_Methodref_info { .. } => "Method",
+        CONSTANT_InterfaceMethodref_info { .. } => "InterfaceMethod",
+        CONSTANT_Integer_info { .. } => "int",
+        CONSTANT_Float_info { .. } => "float",
+        CONSTANT_Long_info { .. } => "long",
+        CONSTANT_Double_info { .. } => "double",
+        other => pool_tag_name(other),
+    }
+}
+
+fn array_type_name(atype: u8) -> String {
+    match atype {
+        4 => String::from("boolean"),
+        5 => String::from("char"),
+        6 => String::from("float"),
+        7 => String::from("double"),
+        8 => String::from("byte"),
+        9 => String::from("short"),
+        10 => String::from("int"),
+        11 => String::from("long"),
+        other => format!("{} (unknown)", other),
+    }
+}
+
+/// The Java keywords for access flags, each followed by a space
+fn modifiers(flags: u16, names: &[(u16, &str)]) -> String {
+    let mut modifiers = String::new();
+    for (flag, name) in names {
+        if flags & flag != 0 {
+            modifiers.push_str(name);
+            modifiers.push(' ');
+        }
+    }
+    modifiers
+}
+
+/// The names of the flags of a Module attribute directive, each preceded by a space
+fn module_flag_names(flags: u16, names: &[(u16, &str)]) -> String {
+    let mut flag_names = String::new();
+    for (flag, name) in names {
+        if flags & flag != 0 {
+            flag_names.push(' ');
+            flag_names.push_str(name);
+        }
+    }
+    flag_names
+}
+
+/// Describes what a type annotation applies to, eg `CAST, offset=9, type_index=0`
+fn type_annotation_target(annotation: &type_annotation) -> String {
+    let target_type = annotation.get_target_type();
+    let name = match target_type {
+        0x00 => "CLASS_TYPE_PARAMETER",
+        0x01 => "METHOD_TYPE_PARAMETER",
+        0x10 => "CLASS_EXTENDS",
+        0x11 => "CLASS_TYPE_PARAMETER_BOUND",
+        0x12 => "METHOD_TYPE_PARAMETER_BOUND",
+        0x13 => "FIELD",
+        0x14 => "METHOD_RETURN",
+        0x15 => "METHOD_RECEIVER",
+        0x16 => "METHOD_FORMAL_PARAMETER",
+        0x17 => "THROWS",
+        0x40 => "LOCAL_VARIABLE",
+        0x41 => "RESOURCE_VARIABLE",
+        0x42 => "EXCEPTION_PARAMETER",
+        0x43 => "INSTANCEOF",
+        0x44 => "NEW",
+        0x45 => "CONSTRUCTOR_REFERENCE",
+        0x46 => "METHOD_REFERENCE",
+        0x47 => "CAST",
+        0x48 => "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT",
+        0x49 => "METHOD_INVOCATION_TYPE_ARGUMENT",
+        0x4A => "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT",
+        0x4B => "METHOD_REFERENCE_TYPE_ARGUMENT",
+        _ => "UNKNOWN",
+    };
+    let mut target = String::from(name);
+    match annotation.get_target_info() {
+        target_info::type_parameter_target { type_parameter_index } => {
+            target.push_str(&format!(", param_index={}", type_parameter_index));
+        }
+        target_info::supertype_target { supertype_index } => {
+            target.push_str(&format!(", type_index={}", supertype_index));
+        }
+        target_info::type_parameter_bound_target { type_parameter_index, bound_index } => {
+            target.push_str(&format!(", param_index={}, bound_index={}", type_parameter_index, bound_index));
+        }
+        target_info::empty_target => {}
+        target_info::formal_parameter_target { formal_parameter_index } => {
+            target.push_str(&format!(", param_index={}", formal_parameter_index));
+        }
+        target_info::throws_target { throws_type_index } => {
+            target.push_str(&format!(", type_index={}", throws_type_index));
+        }
+        target_info::localvar_target { table, .. } => {
+            let ranges: Vec<String> = table.iter()
+                .map(|entry| format!(
+                    "start_pc={}, length={}, index={}",
+                    entry.get_start_pc(),
+                    entry.get_length(),
+                    entry.get_index()
+                ))
+                .collect();
+            target.push_str(&format!(", {{{}}}", ranges.join("; ")));
+        }
+        target_info::catch_target { exception_table_index } => {
+            target.push_str(&format!(", exception_index={}", exception_table_index));
+        }
+        target_info::offset_target { offset } => {
+            target.push_str(&format!(", offset={}", offset));
+        }
+        target_info::type_argument_target { offset, type_argument_index } => {
+            target.push_str(&format!(", offset={}, type_index={}", offset, type_argument_index));
+        }
+    }
+    let path = annotation.get_target_path();
+    if !path.is_empty() {
+        let path: Vec<String> = path.iter()
+            .map(|entry| match entry.get_type_path_kind() {
+                0 => String::from("ARRAY"),
+                1 => String::from("INNER_TYPE"),
+                2 => String::from("WILDCARD"),
+                3 => format!("TYPE_ARGUMENT({})", entry.get_type_argument_index()),
+                other => format!("UNKNOWN({})", other),
+            })
+            .collect();
+        target.push_str(&format!(", location=[{}]", path.join(", ")));
+    }
+    target
+}
+
+/// Converts a binary name to the form used in Java source, eg `java/lang/Object` to
+/// `java.lang.Object`
+fn java_name(name: &str) -> String {
+    name.replace('/', ".")
+}
+
+/// Converts the field type at the start of a descriptor to Java syntax, eg `[Ljava/lang/String;`
+/// to `java.lang.String[]`, giving the rest of the descriptor
+fn java_type(descriptor: &str) -> (String, &str) {
+    let component = descriptor.trim_start_matches('[');
+    let dimensions = descriptor.len() - component.len();
+    let (mut name, rest) = match component.chars().next() {
+        Some('L') => match component.find(';') {
+            Some(end) => (java_name(&component[1..end]), &component[end + 1..]),
+            None => (java_name(&component[1..]), ""),
+        },
+        Some(character) => {
+            let name = match character {
+                'B' => "byte",
+                'C' => "char",
+                'D' => "double",
+                'F' => "float",
+                'I' => "int",
+                'J' => "long",
+                'S' => "short",
+                'Z' => "boolean",
+                'V' => "void",
+                _ => "?",
+            };
+            (String::from(name), &component[character.len_utf8()..])
+        }
+        None => (String::new(), component),
+    };
+    for _ in 0..dimensions {
+        name.push_str("[]");
+    }
+    (name, rest)
+}
+
+/// Converts a method descriptor to the Java syntax of its parameter types and return type
+fn java_method_types(descriptor: &str) -> (Vec<String>, String) {
+    let mut rest = descriptor.trim_start_matches('(');
+    let mut parameters = Vec::new();
+    while !rest.is_empty() && !rest.starts_with(')') {
+        let (parameter, remaining) = java_type(rest);
+        parameters.push(parameter);
+        rest = remaining;
+    }
+    let return_type = java_type(rest.get(1..).unwrap_or("")).0;
+    (parameters, return_type)
+}
+
+/// The type parameters of a generic class or method in Java syntax, eg `<T extends Foo>`,
+/// followed by `end`, or nothing if there are none
+fn type_parameters(type_parameters: &[TypeParameter], end: &str) -> String {
+    if type_parameters.is_empty() {
+        return String::new();
+    }
+    let type_parameters: Vec<String> = type_parameters.iter()
+        .map(|type_parameter| type_parameter.to_string())
+        .collect();
+    format!("<{}{}", type_parameters.join(", "), end)
+}
+
+/// The type parameters and supertypes of a generic class, following its name in its declaration
+fn generic_supertypes(signature: &ClassSignature, is_interface: bool) -> String {
+    let mut declaration = type_parameters(signature.get_type_parameters(), ">");
+    let interfaces: Vec<String> = signature.get_interfaces().iter()
+        .map(|interface| interface.to_string())
+        .collect();
+    // javap can't tell a signature with nothing but a superclass from the signature of a field,
+    // so shows it as what the class extends even for an interface
+    if signature.get_type_parameters().is_empty() && interfaces.is_empty() {
+        declaration.push_str(&format!(" extends {}", signature.get_super_class()));
+    } else if is_interface {
+        if !interfaces.is_empty() {
+            declaration.push_str(&format!(" extends {}", interfaces.join(", ")));
+        }
+    } else {
+        declaration.push_str(&format!(" extends {}", signature.get_super_class()));
+        if !interfaces.is_empty() {
+            declaration.push_str(&format!(" implements {}", interfaces.join(", ")));
+        }
+    }
+    declaration
+}
+
+/// Quotes a name that isn't made up of Java identifiers separated by slashes
+fn check_name(name: &str) -> String {
+    let mut previous = '/';
+    for character in name.chars() {
+        let is_start = character.is_alphabetic() || character == '_' || character == '$';
+        let is_part = is_start || character.is_numeric();
+        if (previous == '/' && !is_start) || (character != '/' && !is_part) {
+            return format!("\"{}\"", escape(name));
+        }
+        previous = character;
+    }
+    if name.is_empty() {
+        return String::from("\"\"");
+    }
+    String::from(name)
+}
+
+/// Escapes a string as it would be written in a Java string literal
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for character in string.chars() {
+        match character {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '"' => escaped.push_str("\\\""),
+            '\'' => escaped.push_str("\\'"),
+            '\\' => escaped.push_str("\\\\"),
+            character if character.is_control() => {
+                escaped.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Escapes the string of a `CONSTANT_Utf8_info`, writing any unpaired surrogates in its raw
+/// modified UTF-8 as `\uXXXX`
+fn escape_utf8(bytes: &str, raw: &Option<Vec<u8>>) -> String {
+    let units = match raw.as_ref().map(|raw| decode_modified_utf8_units(raw)) {
+        Some(Ok(units)) => units,
+        _ => return escape(bytes),
+    };
+    char::decode_utf16(units)
+        .map(|character| match character {
+            Ok(character) => escape(&character.to_string()),
+            Err(error) => format!("\\u{:04x}", error.unpaired_surrogate()),
+        })
+        .collect()
+}
+
+/// Escapes the spaces at the end of a line, which would otherwise be lost when it is trimmed
+fn escape_trailing_spaces(string: &str) -> String {
+    let trimmed = string.trim_end_matches(' ');
+    let spaces = string.len() - trimmed.len();
+    format!("{}{}", trimmed, "\\u0020".repeat(spaces))
+}
+
+/// Formats a floating point number the way Java's `Double.toString` does, given the shortest
+/// scientific notation that represents it, eg `7.5e-1` for 0.75
+///
+/// Numbers from 10^-3 up to 10^7 are written in full, others in scientific notation, and there
+/// is always a digit after the decimal point
+fn java_float(value: f64, scientific: String) -> String {
+    if value.is_nan() {
+        return String::from("NaN");
+    }
+    if value.is_infinite() {
+        return String::from(if value > 0.0 { "Infinity" } else { "-Infinity" });
+    }
+    let (mantissa, exponent) = match scientific.find('e') {
+        Some(e) => (&scientific[..e], scientific[e + 1..].parse::<i32>().unwrap_or(0)),
+        None => (scientific.as_str(), 0),
+    };
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let magnitude = value.abs();
+    if magnitude == 0.0 || (1e-3..1e7).contains(&magnitude) {
+        if exponent >= 0 {
+            let point = exponent as usize + 1;
+            let mut integer = String::from(&digits[..point.min(digits.len())]);
+            while integer.len() < point {
+                integer.push('0');
+            }
+            let fraction = if digits.len() > point { &digits[point..] } else { "0" };
+            format!("{}{}.{}", sign, integer, fraction)
+        } else {
+            format!("{}0.{}{}", sign, "0".repeat((-exponent - 1) as usize), digits)
+        }
+    } else {
+        let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+        format!("{}{}.{}E{}", sign, &digits[..1], fraction, exponent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assembler::assemble_class;
+    use disassembler::disassemble;
+    use typed_arena::Arena;
+
+    fn disassembly(source: &str) -> String {
+        let strings = Arena::new();
+        let class = assemble_class(source, &strings).unwrap();
+        let mut output = Vec::new();
+        disassemble(&class, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn prints_every_inner_class_flag() {
+        let output = disassembly("public class p.Outer
+  minor version: 0
+  major version: 61
+Constant pool:
+   #1 = Class              #2             // p/Outer
+   #2 = Utf8               p/Outer
+   #3 = Class              #4             // java/lang/Object
+   #4 = Utf8               java/lang/Object
+   #5 = Class              #6             // p/Outer$I
+   #6 = Utf8               p/Outer$I
+   #7 = Utf8               I
+   #8 = Class              #9             // p/Outer$E
+   #9 = Utf8               p/Outer$E
+  #10 = Utf8               E
+  #11 = Utf8               InnerClasses
+  #12 = Class              #13            // p/Outer$1
+  #13 = Utf8               p/Outer$1
+{
+}
+InnerClasses:
+  public static interface abstract #7= #5 of #1;
+  private static final enum #10= #8 of #1;
+  synthetic #12;
+");
+        assert!(output.contains("\n  (0x0609) public static interface abstract #7= #5 of #1; "));
+        assert!(output.contains("\n  (0x401a) private static final enum #10= #8 of #1; "));
+        assert!(output.contains("\n  (0x1000) synthetic #12; "));
+    }
+
+    #[test]
+    fn long_parameter_names_stay_clear_of_their_flags() {
+        let output = disassembly("public abstract class p.A
+  minor version: 0
+  major version: 61
+{
+  public abstract void m(int, int);
+    descriptor: (II)V
+    MethodParameters:
+      Name                           Flags
+      aParameterNameLongerThanTheFlagsColumn final
+      short                          final mandated
+}
+");
+        let rows: Vec<&str> = output.lines()
+            .skip_while(|line| !line.contains("MethodParameters:"))
+            .skip(1)
+            .take(3)
+            .collect();
+        assert_eq!(rows, [
+            "      Name                                   Flags",
+            "      aParameterNameLongerThanTheFlagsColumn final",
+            "      short                                  final mandated",
+        ]);
+    }
+}
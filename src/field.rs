@@ -1,28 +1,33 @@
+use annotation;
+use annotation::Annotation;
 use attribute;
-use byteorder::BigEndian;
-use byteorder::ReadBytesExt;
+use attribute::AttributeSource;
 use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+use class_reader::ClassReader;
+use class_writer::ClassWriter;
 use constant_pool::ConstantPool;
 use field::FieldDescriptor::*;
-use std::io::Read;
-use std::iter::{Enumerate, Peekable};
-use std::str::Chars;
+use std::fmt;
 use class::ClassRef::Symbolic;
-use class::ClassRef;
+use class::{ClassAccessFlag, ClassRef};
+use std::rc::Rc;
+use signature;
+use signature::FieldSignature;
 
 #[derive(Debug)]
 /// Raw data contained in a .class file (ClassFile#fields[])
 ///
 ///  <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.1>
-pub struct field_info {
+pub struct field_info<'a> {
     access_flags: u16,
     name_index: u16,
     descriptor_index: u16,
     attributes_count: u16,
-    attributes: Vec<attribute::attribute_info>,
+    attributes: Vec<attribute::attribute_info<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Describes the type of a field
 pub enum FieldDescriptor<'a> {
     Byte,
@@ -43,6 +48,77 @@ pub struct FieldInfo<'a> {
     parent_class: ClassRef<'a>,
     descriptor: FieldDescriptor<'a>,
     index: u16,
+    access_flags: FieldAccessFlag,
+    raw_access_flags: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    attributes: Vec<attribute::attribute_info<'a>>,
+    /// The constant pool of the class, which the attributes refer into
+    constant_pool: Rc<ConstantPool<'a>>,
+}
+
+impl<'a> FieldInfo<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_access_flags(&self) -> FieldAccessFlag {
+        self.access_flags
+    }
+
+    /// `access_flags` as they appear in the class file, including reserved bits
+    pub fn get_raw_access_flags(&self) -> u16 {
+        self.raw_access_flags
+    }
+
+    pub fn get_descriptor(&self) -> &FieldDescriptor<'a> {
+        &self.descriptor
+    }
+
+    pub fn get_name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn get_descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn get_attributes(&self) -> &[attribute::attribute_info<'a>] {
+        &self.attributes
+    }
+
+    /// The generic type of the field, from its Signature attribute
+    pub fn get_signature(&self) -> Result<Option<FieldSignature<'a>>, ClassLoadingError> {
+        let parse = signature::parse_field_signature;
+        signature::read_signature(&self.attributes, &self.constant_pool, parse)
+    }
+
+    /// The annotations on the declaration of the field, both visible and invisible
+    pub fn get_annotations(&self) -> Result<Vec<Annotation<'a>>, ClassLoadingError> {
+        annotation::read_annotations(&self.attributes, &self.constant_pool)
+    }
+
+    /// The annotation of an interface, given by its field descriptor, on the field, eg
+    /// `Lorg/junit/Test;`
+    pub fn find_annotation(
+        &self,
+        type_descriptor: &str,
+    ) -> Result<Option<Annotation<'a>>, ClassLoadingError> {
+        Ok(annotation::find_annotation(self.get_annotations()?, type_descriptor))
+    }
+
+    /// The visible and invisible type annotations on the type of the field
+    pub fn get_type_annotations(&self) -> Vec<&attribute::type_annotation> {
+        attribute::get_type_annotations(&self.attributes)
+    }
+
+    /// Writes the field back out as a `field_info`
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.raw_access_flags);
+        output.write_u16(self.name_index);
+        output.write_u16(self.descriptor_index);
+        attribute::write_attributes(output, &self.attributes);
+    }
 }
 
 #[derive(Debug)]
@@ -55,119 +131,209 @@ pub enum FieldRef<'a> {
 /// Reads the array of fields from a class file
 ///
 /// self_reference_index -> CONSTANT_Utf8_attribute that is the name of this class
-pub fn read_fields<'a, 'b, 'c>(
-    input: &'b mut Read,
+///
+/// The attributes of the fields are decoded lazily if given a `source`
+pub fn read_fields<'a>(
+    input: &mut ClassReader,
     length: u16,
-    constant_pool: &'c ConstantPool<'a>,
-    self_reference_name: &'a str
+    constant_pool: &Rc<ConstantPool<'a>>,
+    self_reference_name: &'a str,
+    class_access_flags: ClassAccessFlag,
+    major_version: u16,
+    source: Option<&Rc<AttributeSource<'a>>>,
 ) -> Result<Vec<FieldInfo<'a>>, ClassLoadingError> {
     let mut vector = Vec::with_capacity(length as usize);
     for index in 0..length {
-        let field_meta = field_info::new(input, constant_pool)?;
-        let name = constant_pool.get_string_entry(field_meta.name_index);
-        let descriptor_str = constant_pool.get_string_entry(field_meta.descriptor_index);
-        let descriptor = parse_field_descriptor(
-            &mut descriptor_str.chars().enumerate().peekable(),
-            descriptor_str,
-        );
+        let offset = input.get_offset();
+        let field_meta = field_info::new(input, constant_pool, major_version, source)?;
+        let name = constant_pool.get_string_entry(field_meta.name_index)
+            .map_err(|error| error.at_offset(offset))?;
+        let access_flags = FieldAccessFlag::from_bits_truncate(field_meta.access_flags);
+        let is_interface = class_access_flags.contains(ClassAccessFlag::ACC_INTERFACE);
+        check_access_flags(access_flags, is_interface).map_err(|reason| {
+            ClassFormatError(format!(
+                "Illegal access flags {:#06x} on field {}.{}: {}",
+                field_meta.access_flags, self_reference_name, name, reason
+            )).at_offset(offset)
+        })?;
+        let descriptor_str = constant_pool.get_string_entry(field_meta.descriptor_index)
+            .map_err(|error| error.at_offset(offset))?;
+        let descriptor = parse_field_descriptor(descriptor_str)
+            .map_err(|error| error.at_offset(offset))?;
         let parent_class = Symbolic(self_reference_name);
         let field_info = FieldInfo {
             name,
             parent_class,
             descriptor,
             index,
+            access_flags,
+            raw_access_flags: field_meta.access_flags,
+            name_index: field_meta.name_index,
+            descriptor_index: field_meta.descriptor_index,
+            attributes: field_meta.attributes,
+            constant_pool: constant_pool.clone(),
         };
         vector.push(field_info);
     }
     Ok(vector)
 }
 
-/// Parse the type of a field from a valid field descriptor
+/// Parse the type of a field from a field descriptor, which must make up the whole of `source`
 ///
 /// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.3.2>
-pub fn parse_field_descriptor<'a, 'b>(
-    chars: &mut Peekable<Enumerate<Chars<'a>>>,
-    source: &'b str,
-) -> FieldDescriptor<'b> {
-    parse_field_descriptor_index(chars, source).0
+pub fn parse_field_descriptor(source: &str) -> Result<FieldDescriptor<'_>, ClassLoadingError> {
+    let mut position = 0;
+    let descriptor = read_field_descriptor(source, &mut position)?;
+    check_finished(source, position)?;
+    Ok(descriptor)
+}
+
+/// The error for a descriptor that ends before it is complete
+pub fn truncated_descriptor(source: &str) -> ClassLoadingError {
+    ClassFormatError(format!("Descriptor ended unexpectedly: {}", source))
 }
 
-/// internal method which gives the parsed field and the index of the last character accessed
-fn parse_field_descriptor_index<'a, 'b>(
-    chars: &mut Peekable<Enumerate<Chars<'a>>>,
+/// Checks that nothing in a descriptor follows what has been parsed
+pub fn check_finished(source: &str, position: usize) -> Result<(), ClassLoadingError> {
+    match source[position..].chars().next() {
+        Some(character) => Err(ClassFormatError(format!(
+            "Illegal character after the end of descriptor: {} -> '{}'",
+            source, character
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Parses the field descriptor starting at `position` in `source`, leaving `position` just after
+/// it
+///
+/// Arrays are given as references to the array class, named by their descriptor (eg `[[I`)
+pub fn read_field_descriptor<'b>(
     source: &'b str,
-) -> (FieldDescriptor<'b>, usize) {
-    let first_char = chars.peek().unwrap().1;
-    match first_char {
-        'L' => return parse_field_descriptor_reference(chars, source),
-        '[' => return parse_field_descriptor_array(chars, source),
-        _ => {}
-    }
-    let first_char = chars.next().unwrap();
-    (
-        match first_char.1 {
-            'B' => Byte,
-            'C' => Character,
-            'D' => Double,
-            'F' => Float,
-            'I' => Integer,
-            'J' => Long,
-            'S' => Short,
-            'Z' => Boolean,
-
-            _ => panic!(
+    position: &mut usize,
+) -> Result<FieldDescriptor<'b>, ClassLoadingError> {
+    let start = *position;
+    while source[*position..].starts_with('[') {
+        *position += 1;
+    }
+    let dimensions = *position - start;
+    if dimensions > 255 {
+        return Err(ClassFormatError(format!(
+            "Array in field descriptor has more than 255 dimensions: {}",
+            source
+        )));
+    }
+    let first_char = source[*position..].chars().next()
+        .ok_or_else(|| truncated_descriptor(source))?;
+    *position += first_char.len_utf8();
+    let descriptor = match first_char {
+        'B' => Byte,
+        'C' => Character,
+        'D' => Double,
+        'F' => Float,
+        'I' => Integer,
+        'J' => Long,
+        'S' => Short,
+        'Z' => Boolean,
+        'L' => {
+            let length = source[*position..].find(';').ok_or_else(|| truncated_descriptor(source))?;
+            let name = &source[*position..*position + length];
+            *position += length + 1;
+            if name.split('/').any(|part| part.is_empty() || part.contains(&['.', '['][..])) {
+                return Err(ClassFormatError(format!(
+                    "Illegal class name in field descriptor: {}",
+                    source
+                )));
+            }
+            Reference(Symbolic(name))
+        }
+        _ => {
+            return Err(ClassFormatError(format!(
                 "Illegal character in field descriptor: {} -> '{}'",
-                source, first_char.1
-            ),
-        },
-        first_char.0,
-    )
+                source, first_char
+            )))
+        }
+    };
+    if dimensions == 0 {
+        Ok(descriptor)
+    } else {
+        Ok(Reference(Symbolic(&source[start..*position])))
+    }
 }
 
-/// parse a reference type (eg `Ljava/lang/Object;`)
-fn parse_field_descriptor_reference<'a, 'b>(
-    chars: &mut Peekable<Enumerate<Chars<'a>>>,
-    source: &'b str,
-) -> (FieldDescriptor<'b>, usize) {
-    let first = chars.next().unwrap();
-    let second_index = first.0 + 1;
-    let mut last_index = second_index;
-    while chars.next().unwrap().1 != ';' {
-        last_index += 1;
-    }
-    (
-        Reference(Symbolic(&source[second_index..last_index])),
-        last_index,
-    )
-}
-
-/// parse an array type (eg `[[I` or `[java/lang/Object;`)
-fn parse_field_descriptor_array<'a, 'b>(
-    chars: &mut Peekable<Enumerate<Chars<'a>>>,
-    source: &'b str,
-) -> (FieldDescriptor<'b>, usize) {
-    let first_index = chars.peek().unwrap().0;
-    while chars.peek().unwrap().1 == '[' {
-        chars.next();
+impl<'a> FieldDescriptor<'a> {
+    /// The number of local variable slots a value of this type occupies
+    pub fn get_size(&self) -> u16 {
+        match self {
+            Long | Double => 2,
+            _ => 1,
+        }
     }
-    let component = parse_field_descriptor_index(chars, source);
-    let last_index = component.1 + 1;
-    (
-        Reference(Symbolic(&source[first_index..last_index])),
-        last_index,
-    )
 }
 
-impl field_info {
+impl<'a> fmt::Display for FieldDescriptor<'a> {
+    /// Writes the type as a descriptor, eg `[Ljava/lang/String;`, or with the alternate flag
+    /// (`{:#}`) in Java syntax, eg `java.lang.String[]`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (descriptor, java) = match self {
+            Byte => ("B", "byte"),
+            Character => ("C", "char"),
+            Double => ("D", "double"),
+            Float => ("F", "float"),
+            Integer => ("I", "int"),
+            Long => ("J", "long"),
+            Short => ("S", "short"),
+            Boolean => ("Z", "boolean"),
+            Reference(Symbolic(name)) => return write_reference(f, name),
+            Reference(ClassRef::Static(class)) => {
+                return write_reference(f, class.borrow().get_name())
+            }
+        };
+        write!(f, "{}", if f.alternate() { java } else { descriptor })
+    }
+}
+
+/// Writes a reference to a class or array class, named as in a CONSTANT_Class_info
+fn write_reference(f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+    let dimensions = name.len() - name.trim_start_matches('[').len();
+    if dimensions == 0 {
+        return if f.alternate() {
+            write!(f, "{}", name.replace('/', "."))
+        } else {
+            write!(f, "L{};", name)
+        };
+    }
+    if !f.alternate() {
+        return write!(f, "{}", name);
+    }
+    match parse_field_descriptor(&name[dimensions..]) {
+        Ok(component) => write!(f, "{:#}", component)?,
+        Err(_) => write!(f, "{}", &name[dimensions..])?,
+    }
+    for _ in 0..dimensions {
+        write!(f, "[]")?;
+    }
+    Ok(())
+}
+
+impl<'a> field_info<'a> {
     fn new(
-        input: &mut Read,
-        constant_pool: &ConstantPool,
-    ) -> Result<field_info, ClassLoadingError> {
-        let access_flags = input.read_u16::<BigEndian>()?;
-        let name_index = input.read_u16::<BigEndian>()?;
-        let descriptor_index = input.read_u16::<BigEndian>()?;
-        let attributes_count = input.read_u16::<BigEndian>()?;
-        let attributes = attribute::read_attributes(input, attributes_count, constant_pool)?;
+        input: &mut ClassReader,
+        constant_pool: &ConstantPool<'a>,
+        major_version: u16,
+        source: Option<&Rc<AttributeSource<'a>>>,
+    ) -> Result<field_info<'a>, ClassLoadingError> {
+        let access_flags = input.read_u16()?;
+        let name_index = input.read_u16()?;
+        let descriptor_index = input.read_u16()?;
+        let attributes_count = input.read_u16()?;
+        let attributes = attribute::read_attributes(
+            input,
+            attributes_count,
+            constant_pool,
+            major_version,
+            source,
+        )?;
         Ok(field_info {
             access_flags,
             name_index,
@@ -178,15 +344,131 @@ impl field_info {
     }
 }
 
-/// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.5-200-A.1>
-enum FieldAccessFlag {
-    ACC_PUBLIC = 0x0001,
-    ACC_PRIVATE = 0x0002,
-    ACC_PROTECTED = 0x0004,
-    ACC_STATIC = 0x0008,
-    ACC_FINAL = 0x0010,
-    ACC_VOLATILE = 0x0040,
-    ACC_TRANSIENT = 0x0080,
-    ACC_SYNTHETIC = 0x1000,
-    ACC_ENUM = 0x4000,
+bitflags! {
+    /// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.5-200-A.1>
+    pub struct FieldAccessFlag: u16 {
+        const ACC_PUBLIC      = 0x0001;
+        const ACC_PRIVATE     = 0x0002;
+        const ACC_PROTECTED   = 0x0004;
+        const ACC_STATIC      = 0x0008;
+        const ACC_FINAL       = 0x0010;
+        const ACC_VOLATILE    = 0x0040;
+        const ACC_TRANSIENT   = 0x0080;
+        const ACC_SYNTHETIC   = 0x1000;
+        const ACC_ENUM        = 0x4000;
+    }
+}
+
+/// Checks that a field doesn't combine flags that are not allowed together
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.5>
+fn check_access_flags(
+    access_flags: FieldAccessFlag,
+    is_interface: bool,
+) -> Result<(), &'static str> {
+    let constant =
+        FieldAccessFlag::ACC_PUBLIC | FieldAccessFlag::ACC_STATIC | FieldAccessFlag::ACC_FINAL;
+    let visibility = access_flags & (FieldAccessFlag::ACC_PUBLIC
+        | FieldAccessFlag::ACC_PRIVATE
+        | FieldAccessFlag::ACC_PROTECTED);
+    if is_interface {
+        if !access_flags.contains(constant)
+            || access_flags.intersects(!(constant | FieldAccessFlag::ACC_SYNTHETIC))
+        {
+            return Err("fields of interfaces must be public, static and final, and nothing else \
+                but synthetic");
+        }
+    } else if visibility.bits().count_ones() > 1 {
+        return Err("at most one of public, private and protected may be set");
+    } else if access_flags.contains(FieldAccessFlag::ACC_FINAL | FieldAccessFlag::ACC_VOLATILE) {
+        return Err("a field can't be both final and volatile");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_access_flags, parse_field_descriptor, FieldAccessFlag};
+    use assembler::{assemble_class, AssemblyError};
+    use class_file::ClassLoadingError::ClassFormatError;
+    use typed_arena::Arena;
+
+    fn render(source: &str) -> (String, String) {
+        let descriptor = parse_field_descriptor(source).unwrap();
+        (format!("{}", descriptor), format!("{:#}", descriptor))
+    }
+
+    #[test]
+    fn renders_descriptors_and_java_types() {
+        assert_eq!(render("J"), ("J".into(), "long".into()));
+        assert_eq!(
+            render("Ljava/lang/String;"),
+            ("Ljava/lang/String;".into(), "java.lang.String".into())
+        );
+        assert_eq!(render("[[Z"), ("[[Z".into(), "boolean[][]".into()));
+        assert_eq!(render("[Lp/A$B;"), ("[Lp/A$B;".into(), "p.A$B[]".into()));
+        assert_eq!(parse_field_descriptor("D").unwrap().get_size(), 2);
+        assert_eq!(parse_field_descriptor("[J").unwrap().get_size(), 1);
+    }
+
+    #[test]
+    fn arrays_have_at_most_255_dimensions() {
+        let descriptor = |dimensions| format!("{}I", "[".repeat(dimensions));
+        parse_field_descriptor(&descriptor(255)).unwrap();
+        assert!(matches!(parse_field_descriptor(&descriptor(256)), Err(ClassFormatError(_))));
+    }
+
+    #[test]
+    fn malformed_descriptors_are_rejected() {
+        for source in &[
+            "", "V", "[", "II", "L;", "Ljava/lang/String", "Ljava.lang.String;", "Ljava//String;",
+            "L[I;", "Lp/A;I", "é",
+        ] {
+            assert!(
+                matches!(parse_field_descriptor(source), Err(ClassFormatError(_))),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn checks_combinations_of_access_flags() {
+        let flags = |bits| FieldAccessFlag::from_bits(bits).unwrap();
+        // public static final, with and without synthetic
+        for &bits in &[0x0019, 0x1019] {
+            assert!(check_access_flags(flags(bits), true).is_ok());
+            assert!(check_access_flags(flags(bits), false).is_ok());
+        }
+        // Interface fields must be constants, and nothing else
+        for &bits in &[0x0009, 0x0011, 0x0018, 0x0099] {
+            assert!(check_access_flags(flags(bits), true).is_err(), "{:#06x}", bits);
+        }
+        assert!(check_access_flags(flags(0x0003), false).is_err());
+        assert!(check_access_flags(flags(0x0050), false).is_err());
+        assert!(check_access_flags(flags(0x40c2), false).is_ok());
+    }
+
+    #[test]
+    fn illegal_access_flags_are_class_format_errors() {
+        let source = "public interface p.I
+  minor version: 0
+  major version: 52
+{
+  public static int VALUE;
+    descriptor: I
+    flags: (0x0009) ACC_PUBLIC, ACC_STATIC
+}
+";
+        let strings = Arena::new();
+        match assemble_class(source, &strings) {
+            Err(AssemblyError::ClassLoadingError(ClassFormatError(message))) => {
+                assert!(message.starts_with("Illegal access flags 0x0009 on field p/I.VALUE"))
+            }
+            other => panic!("{:?}", other.map(|class| class.get_name().to_owned())),
+        }
+        let class = assemble_class(&source.replace("0x0009", "0x0019"), &strings).unwrap();
+        let field = &class.get_fields()[0];
+        assert_eq!(field.get_access_flags(), FieldAccessFlag::from_bits(0x0019).unwrap());
+    }
 }
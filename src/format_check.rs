@@ -0,0 +1,665 @@
+use attribute;
+use attribute::attribute_info;
+use attribute::attribute_info_Data::{
+    Code_attribute, InnerClasses_attribute, MethodParameters_attribute,
+};
+use class_file::ClassFile;
+use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+use constant_pool::cp_info::*;
+use constant_pool::{ConstantPool, ReferenceKind};
+use field;
+use method;
+use method::{MethodAccessFlag, ReturnDescriptor};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How thoroughly a parsed class file is checked against the rules of JVMS 4.8
+pub enum FormatCheck {
+    /// Rejects any class file that breaks the rules
+    Strict,
+    /// Only checks that the constant pool entries refer to entries of the right kinds, which the
+    /// rest of the class relies on
+    ///
+    /// Illegal names, duplicate members and misplaced attributes, as left behind by obfuscators,
+    /// are tolerated
+    Lenient,
+}
+
+/// Where in a class file an attributes table is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttributeLocation {
+    ClassFile,
+    Field,
+    Method,
+    Code,
+    RecordComponent,
+}
+
+impl AttributeLocation {
+    /// Whether a predefined attribute may appear here
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7-320>
+    fn allows(self, name: &str) -> bool {
+        use self::AttributeLocation::*;
+        match name {
+            "Synthetic" | "Deprecated" => self != Code && self != RecordComponent,
+            "Signature" | "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                self != Code
+            }
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => true,
+            "SourceFile" | "InnerClasses" | "EnclosingMethod" | "SourceDebugExtension"
+            | "BootstrapMethods" | "Module" | "ModulePackages" | "ModuleMainClass" | "NestHost"
+            | "NestMembers" | "Record" | "PermittedSubclasses" => self == ClassFile,
+            "ConstantValue" => self == Field,
+            "Code" | "Exceptions" | "RuntimeVisibleParameterAnnotations"
+            | "RuntimeInvisibleParameterAnnotations" | "AnnotationDefault"
+            | "MethodParameters" => self == Method,
+            "LineNumberTable" | "LocalVariableTable" | "LocalVariableTypeTable"
+            | "StackMapTable" => self == Code,
+            _ => true,
+        }
+    }
+
+    fn get_name(self) -> &'static str {
+        match self {
+            AttributeLocation::ClassFile => "ClassFile",
+            AttributeLocation::Field => "field_info",
+            AttributeLocation::Method => "method_info",
+            AttributeLocation::Code => "Code attribute",
+            AttributeLocation::RecordComponent => "record_component_info",
+        }
+    }
+}
+
+/// Checks the structure of a parsed class beyond what is needed to read it: that the entries of
+/// the constant pool refer to each other correctly and, when strict, that names and descriptors
+/// are legal, that no field or method is declared twice, and that attributes are where they are
+/// allowed to be
+///
+/// The attributes of fields and methods of a class parsed with `ClassFile::from_bytes_lazy` are
+/// only located when it is parsed, with their names resolved and their lengths checked to fit.
+/// A strict check reads the contents of those it looks into without keeping them decoded. Code
+/// is otherwise left to the verifier, and the other attributes to whatever first accesses them.
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.8>
+pub fn check_format(class: &ClassFile, mode: FormatCheck) -> Result<(), ClassLoadingError> {
+    let constant_pool = class.get_constant_pool();
+    let strict = mode == FormatCheck::Strict;
+    for index in 1..constant_pool.get_count() {
+        // The slot after a long or double is unusable
+        if constant_pool.get_entry(index).is_ok() {
+            check_constant(constant_pool, index, class.get_major_version(), strict)?;
+        }
+    }
+    if strict {
+        check_fields(class)?;
+        check_methods(class)?;
+        check_attributes(class)?;
+        check_inner_classes(class)?;
+    }
+    Ok(())
+}
+
+/// Checks that a constant pool entry refers to entries of the right kinds and, when strict, that
+/// the names and descriptors it refers to are legal
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4>
+fn check_constant(
+    constant_pool: &ConstantPool,
+    index: u16,
+    major_version: u16,
+    strict: bool,
+) -> Result<(), ClassLoadingError> {
+    let illegal = |kind: &str, name: &str| {
+        ClassFormatError(format!(
+            "Illegal {} at constant pool index {}: {}",
+            kind, index, name
+        ))
+    };
+    let at_index = |error: ClassLoadingError| match error {
+        ClassFormatError(message) => {
+            ClassFormatError(format!("{} at constant pool index {}", message, index))
+        }
+        other => other,
+    };
+    match constant_pool.get_entry(index)? {
+        CONSTANT_Class_info { .. } => {
+            let name = constant_pool.class_name(index)?;
+            if strict && !is_class_name(name) {
+                return Err(illegal("class name", name));
+            }
+        }
+        CONSTANT_String_info { .. } => {
+            constant_pool.string(index)?;
+        }
+        CONSTANT_Fieldref_info { .. } => {
+            let field = constant_pool.field_ref(index)?;
+            if strict {
+                if !is_unqualified_name(field.get_name()) {
+                    return Err(illegal("field name", field.get_name()));
+                }
+                field::parse_field_descriptor(field.get_descriptor()).map_err(at_index)?;
+            }
+        }
+        CONSTANT_Methodref_info { .. } | CONSTANT_InterfaceMethodref_info { .. } => {
+            let method = constant_pool.method_ref(index)?;
+            if strict {
+                let name = method.get_name();
+                // Only an instance initializer can be referred to by its special name, and only
+                // in a class
+                let is_initializer = name == "<init>" && !method.is_interface();
+                if !is_method_name(name) || name.starts_with('<') && !is_initializer {
+                    return Err(illegal("method name", name));
+                }
+                let descriptor = method::parse_method_descriptor(method.get_descriptor())
+                    .map_err(at_index)?;
+                if name == "<init>" {
+                    if let ReturnDescriptor::Value(_) = descriptor.get_return_type() {
+                        let descriptor = method.get_descriptor();
+                        return Err(illegal("instance initializer descriptor", descriptor));
+                    }
+                }
+            }
+        }
+        CONSTANT_NameAndType_info { .. } => {
+            let name_and_type = constant_pool.name_and_type(index)?;
+            if strict {
+                if !is_method_name(name_and_type.get_name()) {
+                    return Err(illegal("name", name_and_type.get_name()));
+                }
+                let descriptor = name_and_type.get_descriptor();
+                if descriptor.starts_with('(') {
+                    method::parse_method_descriptor(descriptor).map_err(at_index)?;
+                } else {
+                    field::parse_field_descriptor(descriptor).map_err(at_index)?;
+                }
+            }
+        }
+        CONSTANT_MethodType_info { .. } => {
+            let descriptor = constant_pool.method_type(index)?;
+            if strict {
+                method::parse_method_descriptor(descriptor).map_err(at_index)?;
+            }
+        }
+        CONSTANT_MethodHandle_info { .. } => {
+            let handle = constant_pool.method_handle(index)?;
+            let reference = handle.get_reference();
+            if strict {
+                let name = reference.get_name();
+                match handle.get_reference_kind() {
+                    kind if kind.is_field_access() => {}
+                    ReferenceKind::REF_newInvokeSpecial => {
+                        if name != "<init>" {
+                            return Err(illegal("method name for REF_newInvokeSpecial", name));
+                        }
+                    }
+                    kind => {
+                        if name == "<init>" || name == "<clinit>" {
+                            return Err(illegal("method name for a method handle", name));
+                        }
+                        // Static and private methods of interfaces arrived with version 52
+                        let is_static_or_special = kind == ReferenceKind::REF_invokeStatic
+                            || kind == ReferenceKind::REF_invokeSpecial;
+                        if is_static_or_special && reference.is_interface() && major_version < 52 {
+                            return Err(ClassFormatError(format!(
+                                "Method handle at constant pool index {} refers to an interface \
+                                    method in class file version {}",
+                                index, major_version
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+        CONSTANT_Dynamic_info { .. } => {
+            let dynamic = constant_pool.dynamic(index)?;
+            if strict {
+                if !is_unqualified_name(dynamic.get_name()) {
+                    return Err(illegal("name", dynamic.get_name()));
+                }
+                field::parse_field_descriptor(dynamic.get_descriptor()).map_err(at_index)?;
+            }
+        }
+        CONSTANT_InvokeDynamic_info { .. } => {
+            let dynamic = constant_pool.invoke_dynamic(index)?;
+            if strict {
+                if !is_method_name(dynamic.get_name()) || dynamic.get_name().starts_with('<') {
+                    return Err(illegal("method name", dynamic.get_name()));
+                }
+                method::parse_method_descriptor(dynamic.get_descriptor()).map_err(at_index)?;
+            }
+        }
+        CONSTANT_Module_info { .. } => {
+            let name = constant_pool.module_name(index)?;
+            if strict && !is_module_name(name) {
+                return Err(illegal("module name", name));
+            }
+        }
+        CONSTANT_Package_info { .. } => {
+            let name = constant_pool.package_name(index)?;
+            if strict && !is_binary_name(name) {
+                return Err(illegal("package name", name));
+            }
+        }
+        CONSTANT_Utf8_info { .. } | CONSTANT_Integer_info { .. } | CONSTANT_Float_info { .. }
+        | CONSTANT_Long_info { .. } | CONSTANT_Double_info { .. } => {}
+    }
+    Ok(())
+}
+
+/// Checks that the fields have legal names and that no two share a name and descriptor
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.5>
+fn check_fields(class: &ClassFile) -> Result<(), ClassLoadingError> {
+    let mut declared = HashSet::new();
+    for field in class.get_fields() {
+        let name = field.get_name();
+        if !is_unqualified_name(name) {
+            return Err(ClassFormatError(format!(
+                "Illegal field name: {}.{}",
+                class.get_name(), name
+            )));
+        }
+        let descriptor = field.get_descriptor().to_string();
+        if !declared.insert((name, descriptor)) {
+            return Err(ClassFormatError(format!(
+                "Duplicate field: {}.{} {}",
+                class.get_name(), name, field.get_descriptor()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the methods have legal names, that initializers have the right descriptors, that
+/// no two share a name and descriptor, and that exactly those methods with bytecode have a Code
+/// attribute
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.6>
+fn check_methods(class: &ClassFile) -> Result<(), ClassLoadingError> {
+    let constant_pool = class.get_constant_pool();
+    let mut declared = HashSet::new();
+    for method in class.get_methods() {
+        let name = method.get_name();
+        let descriptor = method.get_descriptor().to_string();
+        let illegal = |message: &str| {
+            ClassFormatError(format!(
+                "{}: {}.{}{}",
+                message, class.get_name(), name, descriptor
+            ))
+        };
+        if !is_method_name(name) {
+            return Err(illegal("Illegal method name"));
+        }
+        let is_void = match method.get_descriptor().get_return_type() {
+            ReturnDescriptor::Void => true,
+            ReturnDescriptor::Value(_) => false,
+        };
+        if name == "<init>" && !is_void || name == "<clinit>" && descriptor != "()V" {
+            return Err(illegal("Illegal initializer descriptor"));
+        }
+        let code_attributes = named_attributes(constant_pool, method.get_attributes(), "Code")
+            .count();
+        let has_bytecode = name == "<clinit>" || !method.get_access_flags()
+            .intersects(MethodAccessFlag::ACC_NATIVE | MethodAccessFlag::ACC_ABSTRACT);
+        if has_bytecode && code_attributes == 0 {
+            return Err(illegal("Method has no Code attribute"));
+        } else if !has_bytecode && code_attributes != 0 {
+            return Err(illegal("Native or abstract method has a Code attribute"));
+        }
+        if !declared.insert((name, descriptor.clone())) {
+            return Err(illegal("Duplicate method"));
+        }
+        check_method_parameters(constant_pool, method)?;
+    }
+    Ok(())
+}
+
+/// Checks that a MethodParameters attribute has an entry for each parameter in the descriptor,
+/// and that the parameters it names have legal names
+///
+/// `MethodInfo::get_parameters` falls back to the LocalVariableTable for an attribute that
+/// doesn't match the descriptor, so only a strict check rejects it
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.24>
+fn check_method_parameters(
+    constant_pool: &ConstantPool,
+    method: &method::MethodInfo,
+) -> Result<(), ClassLoadingError> {
+    for info in named_attributes(constant_pool, method.get_attributes(), "MethodParameters") {
+        let data = info.read()?;
+        let parameters = match &*data {
+            MethodParameters_attribute { parameters, .. } => parameters,
+            _ => continue,
+        };
+        let malformed = |message: String| {
+            ClassFormatError(format!(
+                "Malformed MethodParameters attribute of {}{}: {}",
+                method.get_name(), method.get_descriptor(), message
+            )).at_offset(info.get_offset())
+        };
+        let expected = method.get_descriptor().get_parameters().len();
+        if parameters.len() != expected {
+            return Err(malformed(format!(
+                "{} parameters where the descriptor has {}",
+                parameters.len(), expected
+            )));
+        }
+        for parameter in parameters {
+            let name_index = parameter.get_name_index();
+            if name_index == 0 {
+                continue;
+            }
+            let name = constant_pool.get_string_entry(name_index)
+                .map_err(|error| error.at_offset(info.get_offset()))?;
+            if !is_unqualified_name(name) {
+                return Err(malformed(format!("Illegal parameter name {:?}", name)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every predefined attribute is in a place it is allowed, and that those that may
+/// only appear once in a place do
+fn check_attributes(class: &ClassFile) -> Result<(), ClassLoadingError> {
+    let constant_pool = class.get_constant_pool();
+    let major_version = class.get_major_version();
+    let check = |attributes: &[attribute_info], location: AttributeLocation| {
+        check_attribute_table(constant_pool, major_version, attributes, location)
+    };
+    check(class.get_attributes(), AttributeLocation::ClassFile)?;
+    for field in class.get_fields() {
+        check(field.get_attributes(), AttributeLocation::Field)?;
+    }
+    for method in class.get_methods() {
+        check(method.get_attributes(), AttributeLocation::Method)?;
+        for info in named_attributes(constant_pool, method.get_attributes(), "Code") {
+            if let Code_attribute { attributes, .. } = &*info.read()? {
+                check(attributes, AttributeLocation::Code)?;
+            }
+        }
+    }
+    for component in class.get_record_components().unwrap_or_default() {
+        check(component.get_attributes(), AttributeLocation::RecordComponent)?;
+    }
+    Ok(())
+}
+
+/// Checks that no class is its own outer class in the InnerClasses attribute and, from version
+/// 51, that no anonymous class is a member of another class
+///
+/// `ClassFile::get_inner_classes` resolves the entries as they are, so only a strict check
+/// rejects them
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.6>
+fn check_inner_classes(class: &ClassFile) -> Result<(), ClassLoadingError> {
+    let constant_pool = class.get_constant_pool();
+    for info in named_attributes(constant_pool, class.get_attributes(), "InnerClasses") {
+        let classes = match info.decode()? {
+            InnerClasses_attribute { classes, .. } => classes,
+            _ => continue,
+        };
+        let class_name = |index| {
+            constant_pool.class_name(index).map_err(|error| error.at_offset(info.get_offset()))
+        };
+        for entry in classes {
+            let inner_class = class_name(entry.get_inner_class_info_index())?;
+            let outer_class = match entry.get_outer_class_info_index() {
+                0 => None,
+                index => Some(class_name(index)?),
+            };
+            let message = if outer_class == Some(inner_class) {
+                format!("Class {} is its own outer class", inner_class)
+            } else if class.get_major_version() >= 51
+                && entry.get_inner_name_index() == 0
+                && outer_class.is_some()
+            {
+                format!("Anonymous class {} is a member of another class", inner_class)
+            } else {
+                continue;
+            };
+            return Err(ClassFormatError(message).at_offset(info.get_offset()));
+        }
+    }
+    Ok(())
+}
+
+/// The attributes of a table with a name, found without decoding any of them
+fn named_attributes<'b, 'a>(
+    constant_pool: &'b ConstantPool,
+    attributes: &'b [attribute_info<'a>],
+    name: &'b str,
+) -> impl Iterator<Item = &'b attribute_info<'a>> {
+    attributes.iter().filter(move |info| {
+        constant_pool.get_string_entry(info.get_name_index()).ok() == Some(name)
+    })
+}
+
+fn check_attribute_table(
+    constant_pool: &ConstantPool,
+    major_version: u16,
+    attributes: &[attribute_info],
+    location: AttributeLocation,
+) -> Result<(), ClassLoadingError> {
+    let mut seen = HashSet::new();
+    for info in attributes {
+        let name = constant_pool.get_string_entry(info.get_name_index())
+            .map_err(|error| error.at_offset(info.get_offset()))?;
+        // Attributes that aren't predefined in this version are unknown, and allowed anywhere
+        let predefined = attribute::attribute_version(name)
+            .is_some_and(|version| major_version >= version);
+        if !predefined {
+            continue;
+        }
+        if !location.allows(name) {
+            return Err(ClassFormatError(format!(
+                "{} attribute is not allowed in a {}",
+                name, location.get_name()
+            )).at_offset(info.get_offset()));
+        }
+        let repeatable = matches!(
+            name,
+            "Synthetic" | "Deprecated" | "LineNumberTable" | "LocalVariableTable"
+                | "LocalVariableTypeTable"
+        );
+        if !repeatable && !seen.insert(name) {
+            return Err(ClassFormatError(format!(
+                "More than one {} attribute in a {}",
+                name, location.get_name()
+            )).at_offset(info.get_offset()));
+        }
+    }
+    Ok(())
+}
+
+/// An unqualified name, of a field or method, can't be empty or contain any of `. ; [ /`
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.2>
+fn is_unqualified_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+/// Method names also can't contain `<` or `>`, other than the initializers
+fn is_method_name(name: &str) -> bool {
+    name == "<init>"
+        || name == "<clinit>"
+        || is_unqualified_name(name) && !name.contains(['<', '>'])
+}
+
+/// A class or interface name in internal form is a sequence of unqualified names separated by
+/// `/`
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.1>
+fn is_binary_name(name: &str) -> bool {
+    name.split('/').all(is_unqualified_name)
+}
+
+/// A `CONSTANT_Class_info` names either a class or interface, or an array type by its descriptor
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.4.1>
+fn is_class_name(name: &str) -> bool {
+    if name.starts_with('[') {
+        field::parse_field_descriptor(name).is_ok()
+    } else {
+        is_binary_name(name)
+    }
+}
+
+/// A module name can't contain control characters, and `\` only escapes `\`, `:` and `@`, which
+/// must be escaped
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.2.3>
+fn is_module_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    while let Some(character) = chars.next() {
+        match character {
+            '\u{0}'..='\u{1f}' | ':' | '@' => return false,
+            '\\' if !matches!(chars.next(), Some('\\') | Some(':') | Some('@')) => return false,
+            _ => {}
+        }
+    }
+    !name.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_format, FormatCheck};
+    use assembler::assemble_class;
+    use class_file::ClassLoadingError;
+    use class_file::ClassLoadingError::ClassFormatError;
+    use typed_arena::Arena;
+
+    /// Assembles a version 61 class with the given members and class attributes, and checks it
+    /// in both modes
+    fn check(members: &str, attributes: &str) -> [Result<(), ClassLoadingError>; 2] {
+        let source = format!(
+            "public class p.A
+  minor version: 0
+  major version: 61
+{{
+{}
+}}
+{}
+",
+            members, attributes
+        );
+        let strings = Arena::new();
+        let class = assemble_class(&source, &strings).unwrap();
+        [check_format(&class, FormatCheck::Strict), check_format(&class, FormatCheck::Lenient)]
+    }
+
+    fn assert_strict_only(members: &str, attributes: &str, message: &str) {
+        match check(members, attributes) {
+            [Err(ClassFormatError(error)), Ok(())] if error.starts_with(message) => {}
+            results => panic!("Expected only the strict check to fail, found {:?}", results),
+        }
+    }
+
+    #[test]
+    fn legal_classes_pass_both_checks() {
+        let members = "  public static final int a;
+    descriptor: I
+    ConstantValue: int 1
+
+  public static void main(java.lang.String[]);
+    descriptor: ([Ljava/lang/String;)V
+    Code:
+      stack=0, locals=1, args_size=1
+        0: return
+
+  public abstract void b();
+    descriptor: ()V
+
+  public native void c();
+    descriptor: ()V";
+        let [strict, lenient] = check(members, "SourceFile: \"A.java\"");
+        strict.unwrap();
+        lenient.unwrap();
+    }
+
+    #[test]
+    fn illegal_names_are_only_rejected_when_strict() {
+        assert_strict_only("  int a.b;\n    descriptor: I", "", "Illegal field name");
+        let method = |name| format!("  abstract void {}();\n    descriptor: ()V", name);
+        assert_strict_only(&method("m/n"), "", "Illegal method name");
+        assert_strict_only(&method("<m>"), "", "Illegal method name");
+        let initializer = "  int <init>();
+    descriptor: ()I
+    Code:
+      stack=1, locals=1, args_size=1
+        0: iconst_0
+        1: ireturn";
+        assert_strict_only(initializer, "", "Illegal initializer descriptor");
+    }
+
+    #[test]
+    fn duplicate_members_are_only_rejected_when_strict() {
+        let fields = "  int a;\n    descriptor: I\n\n  int a;\n    descriptor: I";
+        assert_strict_only(fields, "", "Duplicate field");
+        let method = "  abstract void m();\n    descriptor: ()V\n";
+        assert_strict_only(&format!("{}\n{}", method, method), "", "Duplicate method");
+        // Fields may share a name if their types differ
+        let [strict, _] = check("  int a;\n    descriptor: I\n\n  long a;\n    descriptor: J", "");
+        strict.unwrap();
+    }
+
+    #[test]
+    fn code_must_be_given_exactly_to_methods_with_bytecode() {
+        assert_strict_only("  void m();\n    descriptor: ()V", "", "Method has no Code attribute");
+        let native_code = "  native void m();
+    descriptor: ()V
+    Code:
+      stack=0, locals=1, args_size=1
+        0: return";
+        assert_strict_only(native_code, "", "Native or abstract method has a Code attribute");
+    }
+
+    #[test]
+    fn attributes_must_be_in_their_places() {
+        let misplaced = "  abstract void m();
+    descriptor: ()V
+    SourceFile: \"A.java\"";
+        assert_strict_only(misplaced, "", "SourceFile attribute is not allowed");
+        let attributes = "SourceFile: \"A.java\"\nSourceFile: \"B.java\"";
+        assert_strict_only("", attributes, "More than one SourceFile attribute");
+        // Deprecated may be repeated
+        let [strict, _] = check("", "Deprecated: true\nDeprecated: true");
+        strict.unwrap();
+    }
+
+    #[test]
+    fn constants_are_checked_for_their_kinds_in_both_modes() {
+        let source = |name_and_type| format!(
+            "public class p.A
+  minor version: 0
+  major version: 61
+  this_class: #7
+  super_class: #0
+Constant pool:
+   #1 = Methodref          #2.#{}
+   #2 = Class              #3             // a;b
+   #3 = Utf8               a;b
+   #4 = NameAndType        #5:#6          // m:()V
+   #5 = Utf8               m
+   #6 = Utf8               ()V
+   #7 = Class              #8             // p/A
+   #8 = Utf8               p/A
+{{
+}}
+",
+            name_and_type
+        );
+        let strings = Arena::new();
+        let class = assemble_class(&source(4), &strings).unwrap();
+        check_format(&class, FormatCheck::Lenient).unwrap();
+        match check_format(&class, FormatCheck::Strict) {
+            Err(ClassFormatError(message)) => assert!(message.starts_with("Illegal class name")),
+            result => panic!("Expected a ClassFormatError, found {:?}", result),
+        }
+        // A Methodref must refer to a NameAndType
+        let class = assemble_class(&source(2), &strings).unwrap();
+        assert!(matches!(check_format(&class, FormatCheck::Lenient), Err(ClassFormatError(_))));
+    }
+}
@@ -0,0 +1,438 @@
+use attribute::{attribute_info, inner_class};
+use attribute::attribute_info_Data::{EnclosingMethod_attribute, InnerClasses_attribute};
+use class_file::ClassFile;
+use class_file::ClassLoadingError;
+use constant_pool::{ConstantPool, NameAndType};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+bitflags! {
+    /// The access flags a nested class was declared with in its source, which may differ from
+    /// those of its class file, as a class file can't be private, protected or static
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.6-300-D.1-D.1>
+    pub struct InnerClassAccessFlag: u16 {
+        const ACC_PUBLIC     = 0x0001;
+        const ACC_PRIVATE    = 0x0002;
+        const ACC_PROTECTED  = 0x0004;
+        const ACC_STATIC     = 0x0008;
+        const ACC_FINAL      = 0x0010;
+        const ACC_INTERFACE  = 0x0200;
+        const ACC_ABSTRACT   = 0x0400;
+        const ACC_SYNTHETIC  = 0x1000;
+        const ACC_ANNOTATION = 0x2000;
+        const ACC_ENUM       = 0x4000;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Where a class is declared in its source
+pub enum NestingKind {
+    /// Not nested in another class
+    TopLevel,
+    /// A named member of another class
+    Member,
+    /// A named class declared in a block of code
+    Local,
+    /// A class without a name, declared by an expression
+    Anonymous,
+}
+
+#[derive(Debug, Clone)]
+/// A resolved entry of an `InnerClasses_attribute`, describing a nested class that the class
+/// refers to or declares, or the class itself if it is nested
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.6>
+pub struct InnerClass<'a> {
+    inner_class: &'a str,
+    outer_class: Option<&'a str>,
+    inner_name: Option<&'a str>,
+    access_flags: InnerClassAccessFlag,
+    raw_access_flags: u16,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The resolved `EnclosingMethod_attribute` of a local or anonymous class
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.7>
+pub struct EnclosingMethod<'a> {
+    class: &'a str,
+    method: Option<NameAndType<'a>>,
+}
+
+#[derive(Debug, Clone)]
+/// A class in a `NestingTree`
+pub struct NestedClass<'a> {
+    name: &'a str,
+    simple_name: &'a str,
+    kind: NestingKind,
+    access_flags: InnerClassAccessFlag,
+    enclosing_class: Option<&'a str>,
+    enclosing_method: Option<EnclosingMethod<'a>>,
+    nested_classes: Vec<&'a str>,
+}
+
+#[derive(Debug)]
+/// A set of classes arranged by the classes they are declared in
+///
+/// Each class is placed by the InnerClasses and EnclosingMethod attributes of its own class
+/// file. A class whose enclosing class isn't in the set is at the top of the tree, along with
+/// the top level classes.
+pub struct NestingTree<'a> {
+    classes: HashMap<&'a str, NestedClass<'a>>,
+    roots: Vec<&'a str>,
+}
+
+impl<'a> InnerClass<'a> {
+    pub fn get_inner_class(&self) -> &'a str {
+        self.inner_class
+    }
+
+    /// The class the inner class is a member of, `None` unless it is a member class
+    pub fn get_outer_class(&self) -> Option<&'a str> {
+        self.outer_class
+    }
+
+    /// The simple name of the inner class in its source, `None` if it is anonymous
+    pub fn get_inner_name(&self) -> Option<&'a str> {
+        self.inner_name
+    }
+
+    pub fn get_access_flags(&self) -> InnerClassAccessFlag {
+        self.access_flags
+    }
+
+    /// `inner_class_access_flags` as they appear in the class file, including reserved bits
+    pub fn get_raw_access_flags(&self) -> u16 {
+        self.raw_access_flags
+    }
+}
+
+impl<'a> EnclosingMethod<'a> {
+    /// The innermost class that encloses the declaration of the class
+    pub fn get_class(&self) -> &'a str {
+        self.class
+    }
+
+    /// The name and descriptor of the method or constructor that the class is declared in,
+    /// `None` if it is declared in an initializer of the enclosing class
+    pub fn get_method(&self) -> Option<NameAndType<'a>> {
+        self.method
+    }
+}
+
+impl<'a> NestedClass<'a> {
+    /// Describes where a class is declared, without any of its nested classes
+    pub fn new(class: &ClassFile<'a>) -> NestedClass<'a> {
+        NestedClass {
+            name: class.get_name(),
+            simple_name: class.get_simple_name(),
+            kind: class.get_nesting_kind(),
+            access_flags: class.get_source_access_flags(),
+            enclosing_class: class.get_enclosing_class(),
+            enclosing_method: class.get_enclosing_method().cloned(),
+            nested_classes: Vec::new(),
+        }
+    }
+
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_simple_name(&self) -> &'a str {
+        self.simple_name
+    }
+
+    pub fn get_kind(&self) -> NestingKind {
+        self.kind
+    }
+
+    pub fn get_access_flags(&self) -> InnerClassAccessFlag {
+        self.access_flags
+    }
+
+    pub fn get_enclosing_class(&self) -> Option<&'a str> {
+        self.enclosing_class
+    }
+
+    pub fn get_enclosing_method(&self) -> Option<&EnclosingMethod<'a>> {
+        self.enclosing_method.as_ref()
+    }
+
+    /// The classes declared directly within this one, in the order they were added to the tree
+    pub fn get_nested_classes(&self) -> &[&'a str] {
+        &self.nested_classes
+    }
+}
+
+impl<'a> NestingTree<'a> {
+    /// Links each class to the class it is declared in
+    ///
+    /// Only the first of several classes with the same name is kept, as on a classpath
+    pub fn new(classes: Vec<NestedClass<'a>>) -> NestingTree<'a> {
+        let mut tree = NestingTree {
+            classes: HashMap::with_capacity(classes.len()),
+            roots: Vec::new(),
+        };
+        let mut names = Vec::with_capacity(classes.len());
+        for class in classes {
+            if let Entry::Vacant(entry) = tree.classes.entry(class.name) {
+                names.push(class.name);
+                entry.insert(class);
+            }
+        }
+        for name in names {
+            let enclosing_class = tree.classes[name].enclosing_class
+                .filter(|enclosing_class| tree.classes.contains_key(enclosing_class));
+            match enclosing_class {
+                Some(enclosing_class) => {
+                    tree.classes.get_mut(enclosing_class).unwrap().nested_classes.push(name)
+                }
+                None => tree.roots.push(name),
+            }
+        }
+        tree
+    }
+
+    /// The classes that aren't nested in another class of the tree
+    pub fn get_roots(&self) -> &[&'a str] {
+        &self.roots
+    }
+
+    pub fn get_class(&self, name: &str) -> Option<&NestedClass<'a>> {
+        self.classes.get(name)
+    }
+
+    pub fn get_classes(&self) -> impl Iterator<Item = &NestedClass<'a>> {
+        self.classes.values()
+    }
+
+    /// The outermost class of the tree that a class is nested in, or the class itself if it is
+    /// one of the roots
+    pub fn get_outermost_class(&self, name: &str) -> Option<&NestedClass<'a>> {
+        let mut class = self.classes.get(name)?;
+        // A cycle of classes claiming to enclose each other has no outermost class
+        for _ in 0..self.classes.len() {
+            match class.enclosing_class.and_then(|name| self.classes.get(name)) {
+                Some(enclosing_class) => class = enclosing_class,
+                None => return Some(class),
+            }
+        }
+        None
+    }
+}
+
+/// Resolves the entries of a class's `InnerClasses_attribute`
+///
+/// Entries that break the rules of JVMS 4.7.6, such as a class being its own outer class, are
+/// resolved as they are, and only rejected by a strict format check
+pub fn read_inner_classes<'a>(
+    attributes: &[attribute_info],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Vec<InnerClass<'a>>, ClassLoadingError> {
+    for info in attributes {
+        if let InnerClasses_attribute { classes, .. } = info.get_data() {
+            return classes.iter()
+                .map(|class| resolve_inner_class(class, constant_pool))
+                .collect::<Result<Vec<_>, ClassLoadingError>>()
+                .map_err(|error| error.at_offset(info.get_offset()));
+        }
+    }
+    Ok(Vec::new())
+}
+
+fn resolve_inner_class<'a>(
+    class: &inner_class,
+    constant_pool: &ConstantPool<'a>,
+) -> Result<InnerClass<'a>, ClassLoadingError> {
+    let inner_class = constant_pool.class_name(class.get_inner_class_info_index())?;
+    let outer_class = match class.get_outer_class_info_index() {
+        0 => None,
+        index => Some(constant_pool.class_name(index)?),
+    };
+    let inner_name = match class.get_inner_name_index() {
+        0 => None,
+        index => Some(constant_pool.get_string_entry(index)?),
+    };
+    let raw_access_flags = class.get_inner_class_access_flags();
+    Ok(InnerClass {
+        inner_class,
+        outer_class,
+        inner_name,
+        access_flags: InnerClassAccessFlag::from_bits_truncate(raw_access_flags),
+        raw_access_flags,
+    })
+}
+
+/// Resolves a class's `EnclosingMethod_attribute`, if it has one
+pub fn read_enclosing_method<'a>(
+    attributes: &[attribute_info],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Option<EnclosingMethod<'a>>, ClassLoadingError> {
+    for info in attributes {
+        if let EnclosingMethod_attribute { class_index, method_index } = info.get_data() {
+            let resolve = || {
+                let class = constant_pool.class_name(*class_index)?;
+                let method = match *method_index {
+                    0 => None,
+                    index => Some(constant_pool.name_and_type(index)?),
+                };
+                Ok(EnclosingMethod { class, method })
+            };
+            return resolve()
+                .map(Some)
+                .map_err(|error: ClassLoadingError| error.at_offset(info.get_offset()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InnerClassAccessFlag, NestedClass, NestingKind, NestingTree};
+    use assembler::assemble_class;
+    use format_check::{check_format, FormatCheck};
+    use test_util::class_header;
+    use typed_arena::Arena;
+
+    /// A class of version 61, with a constant pool of the classes nested in `p/Outer` and the
+    /// given class attributes
+    fn nested_source(name: &str, attributes: &str) -> String {
+        let header = class_header(&format!("class {}", name), 61);
+        format!(
+            "{}  super_class: #14
+Constant pool:
+   #1 = Class              #2             // p/Outer
+   #2 = Utf8               p/Outer
+   #3 = Class              #4             // p/Outer$Inner
+   #4 = Utf8               p/Outer$Inner
+   #5 = Utf8               Inner
+   #6 = Class              #7             // p/Outer$1Local
+   #7 = Utf8               p/Outer$1Local
+   #8 = Utf8               Local
+   #9 = Class              #10            // p/Outer$1
+  #10 = Utf8               p/Outer$1
+  #11 = NameAndType        #12:#13        // run:()V
+  #12 = Utf8               run
+  #13 = Utf8               ()V
+  #14 = Class              #15            // java/lang/Object
+  #15 = Utf8               java/lang/Object
+  #16 = Utf8               InnerClasses
+  #17 = Utf8               EnclosingMethod
+{{
+}}
+{}
+",
+            header, attributes
+        )
+    }
+
+    const OUTER: &str = "InnerClasses:
+  private static #5= #3 of #1;
+  #8= #6;
+  #9;";
+
+    const INNER: &str = "InnerClasses:
+  private static #5= #3 of #1;";
+
+    const LOCAL: &str = "InnerClasses:
+  final #8= #6;
+EnclosingMethod: #1.#11";
+
+    const ANONYMOUS: &str = "InnerClasses:
+  #9;
+EnclosingMethod: #1.#0";
+
+    #[test]
+    fn classes_are_described_by_their_own_entries() {
+        let strings = Arena::new();
+        let outer = assemble_class(&nested_source("p.Outer", OUTER), &strings).unwrap();
+        assert_eq!(outer.get_nesting_kind(), NestingKind::TopLevel);
+        assert_eq!(outer.get_simple_name(), "Outer");
+        assert_eq!(outer.get_inner_classes().len(), 3);
+        assert!(outer.get_inner_class_entry().is_none());
+
+        let inner = assemble_class(&nested_source("p.Outer$Inner", INNER), &strings).unwrap();
+        assert_eq!(inner.get_nesting_kind(), NestingKind::Member);
+        assert_eq!(inner.get_simple_name(), "Inner");
+        assert_eq!(inner.get_declaring_class(), Some("p/Outer"));
+        assert_eq!(
+            inner.get_source_access_flags(),
+            InnerClassAccessFlag::ACC_PRIVATE | InnerClassAccessFlag::ACC_STATIC
+        );
+
+        let local = assemble_class(&nested_source("p.Outer$1Local", LOCAL), &strings).unwrap();
+        assert_eq!(local.get_nesting_kind(), NestingKind::Local);
+        assert_eq!(local.get_simple_name(), "Local");
+        assert_eq!(local.get_declaring_class(), None);
+        assert_eq!(local.get_enclosing_class(), Some("p/Outer"));
+        let method = local.get_enclosing_method().unwrap().get_method().unwrap();
+        assert_eq!(method.get_name(), "run");
+
+        let anonymous = assemble_class(&nested_source("p.Outer$1", ANONYMOUS), &strings).unwrap();
+        assert_eq!(anonymous.get_nesting_kind(), NestingKind::Anonymous);
+        assert_eq!(anonymous.get_simple_name(), "");
+        assert_eq!(anonymous.get_enclosing_class(), Some("p/Outer"));
+        assert!(anonymous.get_enclosing_method().unwrap().get_method().is_none());
+    }
+
+    #[test]
+    fn nesting_trees_arrange_classes_by_their_enclosing_classes() {
+        let strings = Arena::new();
+        let classes = [
+            ("p.Outer$1Local", LOCAL),
+            ("p.Outer", OUTER),
+            ("p.Outer$Inner", INNER),
+            ("p.Outer$1", ANONYMOUS),
+            // Only the first of two classes with a name is kept
+            ("p.Outer$1", ""),
+        ];
+        let nested_classes = classes.iter().map(|(name, attributes)| {
+            NestedClass::new(&assemble_class(&nested_source(name, attributes), &strings).unwrap())
+        }).collect();
+        let tree = NestingTree::new(nested_classes);
+        assert_eq!(tree.get_roots(), ["p/Outer"]);
+        let outer = tree.get_class("p/Outer").unwrap();
+        assert_eq!(outer.get_nested_classes(), ["p/Outer$1Local", "p/Outer$Inner", "p/Outer$1"]);
+        assert_eq!(tree.get_class("p/Outer$1").unwrap().get_kind(), NestingKind::Anonymous);
+        assert_eq!(tree.get_outermost_class("p/Outer$Inner").unwrap().get_name(), "p/Outer");
+        assert!(tree.get_outermost_class("p/Missing").is_none());
+    }
+
+    #[test]
+    fn classes_enclosing_each_other_have_no_outermost_class() {
+        let strings = Arena::new();
+        let source = |name: &str, outer| {
+            let attributes = format!(
+                "InnerClasses:\n  static #5= #3 of #{};\n  static #8= #6 of #{};",
+                outer, outer
+            );
+            nested_source(name, &attributes)
+        };
+        // Each class names the other as its outer class in its own entry
+        let inner = assemble_class(&source("p.Outer$Inner", 6), &strings).unwrap();
+        let local = assemble_class(&source("p.Outer$1Local", 3), &strings).unwrap();
+        let tree = NestingTree::new(vec![NestedClass::new(&inner), NestedClass::new(&local)]);
+        assert!(tree.get_roots().is_empty());
+        assert!(tree.get_outermost_class("p/Outer$Inner").is_none());
+    }
+
+    #[test]
+    fn illegal_entries_are_resolved_but_fail_a_strict_check() {
+        let strings = Arena::new();
+        for attributes in &[
+            "InnerClasses:\n  static #5= #3 of #3;",
+            "InnerClasses:\n  static #9 of #1;",
+        ] {
+            let class = assemble_class(&nested_source("p.Outer", attributes), &strings).unwrap();
+            assert_eq!(class.get_inner_classes().len(), 1);
+            check_format(&class, FormatCheck::Lenient).unwrap();
+            assert!(check_format(&class, FormatCheck::Strict).is_err(), "{}", attributes);
+        }
+        // Anonymous classes were members of their enclosing classes before version 51
+        let source = nested_source("p.Outer", "InnerClasses:\n  static #9 of #1;")
+            .replace("major version: 61", "major version: 50");
+        let class = assemble_class(&source, &strings).unwrap();
+        check_format(&class, FormatCheck::Strict).unwrap();
+    }
+}
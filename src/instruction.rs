@@ -0,0 +1,1253 @@
+use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+use class_reader::ClassReader;
+use class_writer::ClassWriter;
+use instruction::Instruction::*;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single instruction of a method's code, with its operands as they are encoded
+///
+/// Operands that index the constant pool are left unresolved, and branch offsets are relative
+/// to the pc of the instruction itself
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5>
+pub enum Instruction {
+    Nop,
+    AconstNull,
+    IconstM1,
+    Iconst0,
+    Iconst1,
+    Iconst2,
+    Iconst3,
+    Iconst4,
+    Iconst5,
+    Lconst0,
+    Lconst1,
+    Fconst0,
+    Fconst1,
+    Fconst2,
+    Dconst0,
+    Dconst1,
+    Bipush(i8),
+    Sipush(i16),
+    Ldc(u8),
+    LdcW(u16),
+    Ldc2W(u16),
+    Iload(u8),
+    Lload(u8),
+    Fload(u8),
+    Dload(u8),
+    Aload(u8),
+    Iload0,
+    Iload1,
+    Iload2,
+    Iload3,
+    Lload0,
+    Lload1,
+    Lload2,
+    Lload3,
+    Fload0,
+    Fload1,
+    Fload2,
+    Fload3,
+    Dload0,
+    Dload1,
+    Dload2,
+    Dload3,
+    Aload0,
+    Aload1,
+    Aload2,
+    Aload3,
+    Iaload,
+    Laload,
+    Faload,
+    Daload,
+    Aaload,
+    Baload,
+    Caload,
+    Saload,
+    Istore(u8),
+    Lstore(u8),
+    Fstore(u8),
+    Dstore(u8),
+    Astore(u8),
+    Istore0,
+    Istore1,
+    Istore2,
+    Istore3,
+    Lstore0,
+    Lstore1,
+    Lstore2,
+    Lstore3,
+    Fstore0,
+    Fstore1,
+    Fstore2,
+    Fstore3,
+    Dstore0,
+    Dstore1,
+    Dstore2,
+    Dstore3,
+    Astore0,
+    Astore1,
+    Astore2,
+    Astore3,
+    Iastore,
+    Lastore,
+    Fastore,
+    Dastore,
+    Aastore,
+    Bastore,
+    Castore,
+    Sastore,
+    Pop,
+    Pop2,
+    Dup,
+    DupX1,
+    DupX2,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    Swap,
+    Iadd,
+    Ladd,
+    Fadd,
+    Dadd,
+    Isub,
+    Lsub,
+    Fsub,
+    Dsub,
+    Imul,
+    Lmul,
+    Fmul,
+    Dmul,
+    Idiv,
+    Ldiv,
+    Fdiv,
+    Ddiv,
+    Irem,
+    Lrem,
+    Frem,
+    Drem,
+    Ineg,
+    Lneg,
+    Fneg,
+    Dneg,
+    Ishl,
+    Lshl,
+    Ishr,
+    Lshr,
+    Iushr,
+    Lushr,
+    Iand,
+    Land,
+    Ior,
+    Lor,
+    Ixor,
+    Lxor,
+    Iinc { index: u8, constant: i8 },
+    I2l,
+    I2f,
+    I2d,
+    L2i,
+    L2f,
+    L2d,
+    F2i,
+    F2l,
+    F2d,
+    D2i,
+    D2l,
+    D2f,
+    I2b,
+    I2c,
+    I2s,
+    Lcmp,
+    Fcmpl,
+    Fcmpg,
+    Dcmpl,
+    Dcmpg,
+    Ifeq(i16),
+    Ifne(i16),
+    Iflt(i16),
+    Ifge(i16),
+    Ifgt(i16),
+    Ifle(i16),
+    IfIcmpeq(i16),
+    IfIcmpne(i16),
+    IfIcmplt(i16),
+    IfIcmpge(i16),
+    IfIcmpgt(i16),
+    IfIcmple(i16),
+    IfAcmpeq(i16),
+    IfAcmpne(i16),
+    Goto(i16),
+    Jsr(i16),
+    Ret(u8),
+    /// `offsets[i]` is the branch offset for the key `low + i`
+    Tableswitch {
+        default: i32,
+        low: i32,
+        high: i32,
+        offsets: Vec<i32>,
+    },
+    /// Pairs of a key and its branch offset, sorted by key
+    Lookupswitch {
+        default: i32,
+        pairs: Vec<(i32, i32)>,
+    },
+    Ireturn,
+    Lreturn,
+    Freturn,
+    Dreturn,
+    Areturn,
+    Return,
+    Getstatic(u16),
+    Putstatic(u16),
+    Getfield(u16),
+    Putfield(u16),
+    Invokevirtual(u16),
+    Invokespecial(u16),
+    Invokestatic(u16),
+    Invokeinterface { index: u16, count: u8 },
+    Invokedynamic(u16),
+    New(u16),
+    Newarray(u8),
+    Anewarray(u16),
+    Arraylength,
+    Athrow,
+    Checkcast(u16),
+    Instanceof(u16),
+    Monitorenter,
+    Monitorexit,
+    Wide(WideInstruction),
+    Multianewarray { index: u16, dimensions: u8 },
+    Ifnull(i16),
+    Ifnonnull(i16),
+    GotoW(i32),
+    JsrW(i32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An instruction modified by a `wide` prefix to take a 16 bit local variable index
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-6.html#jvms-6.5.wide>
+pub enum WideInstruction {
+    Iload(u16),
+    Lload(u16),
+    Fload(u16),
+    Dload(u16),
+    Aload(u16),
+    Istore(u16),
+    Lstore(u16),
+    Fstore(u16),
+    Dstore(u16),
+    Astore(u16),
+    Ret(u16),
+    Iinc { index: u16, constant: i16 },
+}
+
+impl Instruction {
+    /// Decodes the instruction at `pc`, the offset of the reader into the code array
+    pub fn new(input: &mut ClassReader, pc: u32) -> Result<Instruction, ClassLoadingError> {
+        let opcode = input.read_u8()?;
+        Ok(match opcode {
+            0x00 => Nop,
+            0x01 => AconstNull,
+            0x02 => IconstM1,
+            0x03 => Iconst0,
+            0x04 => Iconst1,
+            0x05 => Iconst2,
+            0x06 => Iconst3,
+            0x07 => Iconst4,
+            0x08 => Iconst5,
+            0x09 => Lconst0,
+            0x0a => Lconst1,
+            0x0b => Fconst0,
+            0x0c => Fconst1,
+            0x0d => Fconst2,
+            0x0e => Dconst0,
+            0x0f => Dconst1,
+            0x10 => Bipush(input.read_u8()? as i8),
+            0x11 => Sipush(input.read_u16()? as i16),
+            0x12 => Ldc(input.read_u8()?),
+            0x13 => LdcW(input.read_u16()?),
+            0x14 => Ldc2W(input.read_u16()?),
+            0x15 => Iload(input.read_u8()?),
+            0x16 => Lload(input.read_u8()?),
+            0x17 => Fload(input.read_u8()?),
+            0x18 => Dload(input.read_u8()?),
+            0x19 => Aload(input.read_u8()?),
+            0x1a => Iload0,
+            0x1b => Iload1,
+            0x1c => Iload2,
+            0x1d => Iload3,
+            0x1e => Lload0,
+            0x1f => Lload1,
+            0x20 => Lload2,
+            0x21 => Lload3,
+            0x22 => Fload0,
+            0x23 => Fload1,
+            0x24 => Fload2,
+            0x25 => Fload3,
+            0x26 => Dload0,
+            0x27 => Dload1,
+            0x28 => Dload2,
+            0x29 => Dload3,
+            0x2a => Aload0,
+            0x2b => Aload1,
+            0x2c => Aload2,
+            0x2d => Aload3,
+            0x2e => Iaload,
+            0x2f => Laload,
+            0x30 => Faload,
+            0x31 => Daload,
+            0x32 => Aaload,
+            0x33 => Baload,
+            0x34 => Caload,
+            0x35 => Saload,
+            0x36 => Istore(input.read_u8()?),
+            0x37 => Lstore(input.read_u8()?),
+            0x38 => Fstore(input.read_u8()?),
+            0x39 => Dstore(input.read_u8()?),
+            0x3a => Astore(input.read_u8()?),
+            0x3b => Istore0,
+            0x3c => Istore1,
+            0x3d => Istore2,
+            0x3e => Istore3,
+            0x3f => Lstore0,
+            0x40 => Lstore1,
+            0x41 => Lstore2,
+            0x42 => Lstore3,
+            0x43 => Fstore0,
+            0x44 => Fstore1,
+            0x45 => Fstore2,
+            0x46 => Fstore3,
+            0x47 => Dstore0,
+            0x48 => Dstore1,
+            0x49 => Dstore2,
+            0x4a => Dstore3,
+            0x4b => Astore0,
+            0x4c => Astore1,
+            0x4d => Astore2,
+            0x4e => Astore3,
+            0x4f => Iastore,
+            0x50 => Lastore,
+            0x51 => Fastore,
+            0x52 => Dastore,
+            0x53 => Aastore,
+            0x54 => Bastore,
+            0x55 => Castore,
+            0x56 => Sastore,
+            0x57 => Pop,
+            0x58 => Pop2,
+            0x59 => Dup,
+            0x5a => DupX1,
+            0x5b => DupX2,
+            0x5c => Dup2,
+            0x5d => Dup2X1,
+            0x5e => Dup2X2,
+            0x5f => Swap,
+            0x60 => Iadd,
+            0x61 => Ladd,
+            0x62 => Fadd,
+            0x63 => Dadd,
+            0x64 => Isub,
+            0x65 => Lsub,
+            0x66 => Fsub,
+            0x67 => Dsub,
+            0x68 => Imul,
+            0x69 => Lmul,
+            0x6a => Fmul,
+            0x6b => Dmul,
+            0x6c => Idiv,
+            0x6d => Ldiv,
+            0x6e => Fdiv,
+            0x6f => Ddiv,
+            0x70 => Irem,
+            0x71 => Lrem,
+            0x72 => Frem,
+            0x73 => Drem,
+            0x74 => Ineg,
+            0x75 => Lneg,
+            0x76 => Fneg,
+            0x77 => Dneg,
+            0x78 => Ishl,
+            0x79 => Lshl,
+            0x7a => Ishr,
+            0x7b => Lshr,
+            0x7c => Iushr,
+            0x7d => Lushr,
+            0x7e => Iand,
+            0x7f => Land,
+            0x80 => Ior,
+            0x81 => Lor,
+            0x82 => Ixor,
+            0x83 => Lxor,
+            0x84 => {
+                let index = input.read_u8()?;
+                let constant = input.read_u8()? as i8;
+                Iinc { index, constant }
+            },
+            0x85 => I2l,
+            0x86 => I2f,
+            0x87 => I2d,
+            0x88 => L2i,
+            0x89 => L2f,
+            0x8a => L2d,
+            0x8b => F2i,
+            0x8c => F2l,
+            0x8d => F2d,
+            0x8e => D2i,
+            0x8f => D2l,
+            0x90 => D2f,
+            0x91 => I2b,
+            0x92 => I2c,
+            0x93 => I2s,
+            0x94 => Lcmp,
+            0x95 => Fcmpl,
+            0x96 => Fcmpg,
+            0x97 => Dcmpl,
+            0x98 => Dcmpg,
+            0x99 => Ifeq(input.read_u16()? as i16),
+            0x9a => Ifne(input.read_u16()? as i16),
+            0x9b => Iflt(input.read_u16()? as i16),
+            0x9c => Ifge(input.read_u16()? as i16),
+            0x9d => Ifgt(input.read_u16()? as i16),
+            0x9e => Ifle(input.read_u16()? as i16),
+            0x9f => IfIcmpeq(input.read_u16()? as i16),
+            0xa0 => IfIcmpne(input.read_u16()? as i16),
+            0xa1 => IfIcmplt(input.read_u16()? as i16),
+            0xa2 => IfIcmpge(input.read_u16()? as i16),
+            0xa3 => IfIcmpgt(input.read_u16()? as i16),
+            0xa4 => IfIcmple(input.read_u16()? as i16),
+            0xa5 => IfAcmpeq(input.read_u16()? as i16),
+            0xa6 => IfAcmpne(input.read_u16()? as i16),
+            0xa7 => Goto(input.read_u16()? as i16),
+            0xa8 => Jsr(input.read_u16()? as i16),
+            0xa9 => Ret(input.read_u8()?),
+            0xaa => {
+                skip_padding(input, pc)?;
+                let default = input.read_i32()?;
+                let low = input.read_i32()?;
+                let high = input.read_i32()?;
+                if low > high {
+                    return Err(ClassFormatError(format!(
+                        "tableswitch at pc {} has low {} greater than high {}",
+                        pc, low, high
+                    )));
+                }
+                let count = (high as i64 - low as i64 + 1) as usize;
+                let mut offsets = Vec::with_capacity(count.min(input.remaining() / 4));
+                for _ in 0..count {
+                    offsets.push(input.read_i32()?);
+                }
+                Tableswitch {
+                    default,
+                    low,
+                    high,
+                    offsets,
+                }
+            },
+            0xab => {
+                skip_padding(input, pc)?;
+                let default = input.read_i32()?;
+                let npairs = input.read_i32()?;
+                if npairs < 0 {
+                    return Err(ClassFormatError(format!(
+                        "lookupswitch at pc {} has negative npairs {}",
+                        pc, npairs
+                    )));
+                }
+                let mut pairs = Vec::with_capacity((npairs as usize).min(input.remaining() / 8));
+                for _ in 0..npairs {
+                    let key = input.read_i32()?;
+                    let offset = input.read_i32()?;
+                    pairs.push((key, offset));
+                }
+                Lookupswitch { default, pairs }
+            },
+            0xac => Ireturn,
+            0xad => Lreturn,
+            0xae => Freturn,
+            0xaf => Dreturn,
+            0xb0 => Areturn,
+            0xb1 => Return,
+            0xb2 => Getstatic(input.read_u16()?),
+            0xb3 => Putstatic(input.read_u16()?),
+            0xb4 => Getfield(input.read_u16()?),
+            0xb5 => Putfield(input.read_u16()?),
+            0xb6 => Invokevirtual(input.read_u16()?),
+            0xb7 => Invokespecial(input.read_u16()?),
+            0xb8 => Invokestatic(input.read_u16()?),
+            0xb9 => {
+                let index = input.read_u16()?;
+                let count = input.read_u8()?;
+                check_zero(input.read_u8()?, "invokeinterface", pc)?;
+                Invokeinterface { index, count }
+            },
+            0xba => {
+                let index = input.read_u16()?;
+                check_zero(input.read_u8()?, "invokedynamic", pc)?;
+                check_zero(input.read_u8()?, "invokedynamic", pc)?;
+                Invokedynamic(index)
+            },
+            0xbb => New(input.read_u16()?),
+            0xbc => Newarray(input.read_u8()?),
+            0xbd => Anewarray(input.read_u16()?),
+            0xbe => Arraylength,
+            0xbf => Athrow,
+            0xc0 => Checkcast(input.read_u16()?),
+            0xc1 => Instanceof(input.read_u16()?),
+            0xc2 => Monitorenter,
+            0xc3 => Monitorexit,
+            0xc4 => Wide(WideInstruction::new(input, pc)?),
+            0xc5 => {
+                let index = input.read_u16()?;
+                let dimensions = input.read_u8()?;
+                Multianewarray { index, dimensions }
+            },
+            0xc6 => Ifnull(input.read_u16()? as i16),
+            0xc7 => Ifnonnull(input.read_u16()? as i16),
+            0xc8 => GotoW(input.read_i32()?),
+            0xc9 => JsrW(input.read_i32()?),
+            _ => {
+                return Err(ClassFormatError(format!(
+                    "Invalid opcode {:#04x} at pc {}",
+                    opcode, pc
+                )))
+            }
+        })
+    }
+
+    pub fn get_opcode(&self) -> u8 {
+        match self {
+            Nop => 0x00,
+            AconstNull => 0x01,
+            IconstM1 => 0x02,
+            Iconst0 => 0x03,
+            Iconst1 => 0x04,
+            Iconst2 => 0x05,
+            Iconst3 => 0x06,
+            Iconst4 => 0x07,
+            Iconst5 => 0x08,
+            Lconst0 => 0x09,
+            Lconst1 => 0x0a,
+            Fconst0 => 0x0b,
+            Fconst1 => 0x0c,
+            Fconst2 => 0x0d,
+            Dconst0 => 0x0e,
+            Dconst1 => 0x0f,
+            Bipush(..) => 0x10,
+            Sipush(..) => 0x11,
+            Ldc(..) => 0x12,
+            LdcW(..) => 0x13,
+            Ldc2W(..) => 0x14,
+            Iload(..) => 0x15,
+            Lload(..) => 0x16,
+            Fload(..) => 0x17,
+            Dload(..) => 0x18,
+            Aload(..) => 0x19,
+            Iload0 => 0x1a,
+            Iload1 => 0x1b,
+            Iload2 => 0x1c,
+            Iload3 => 0x1d,
+            Lload0 => 0x1e,
+            Lload1 => 0x1f,
+            Lload2 => 0x20,
+            Lload3 => 0x21,
+            Fload0 => 0x22,
+            Fload1 => 0x23,
+            Fload2 => 0x24,
+            Fload3 => 0x25,
+            Dload0 => 0x26,
+            Dload1 => 0x27,
+            Dload2 => 0x28,
+            Dload3 => 0x29,
+            Aload0 => 0x2a,
+            Aload1 => 0x2b,
+            Aload2 => 0x2c,
+            Aload3 => 0x2d,
+            Iaload => 0x2e,
+            Laload => 0x2f,
+            Faload => 0x30,
+            Daload => 0x31,
+            Aaload => 0x32,
+            Baload => 0x33,
+            Caload => 0x34,
+            Saload => 0x35,
+            Istore(..) => 0x36,
+            Lstore(..) => 0x37,
+            Fstore(..) => 0x38,
+            Dstore(..) => 0x39,
+            Astore(..) => 0x3a,
+            Istore0 => 0x3b,
+            Istore1 => 0x3c,
+            Istore2 => 0x3d,
+            Istore3 => 0x3e,
+            Lstore0 => 0x3f,
+            Lstore1 => 0x40,
+            Lstore2 => 0x41,
+            Lstore3 => 0x42,
+            Fstore0 => 0x43,
+            Fstore1 => 0x44,
+            Fstore2 => 0x45,
+            Fstore3 => 0x46,
+            Dstore0 => 0x47,
+            Dstore1 => 0x48,
+            Dstore2 => 0x49,
+            Dstore3 => 0x4a,
+            Astore0 => 0x4b,
+            Astore1 => 0x4c,
+            Astore2 => 0x4d,
+            Astore3 => 0x4e,
+            Iastore => 0x4f,
+            Lastore => 0x50,
+            Fastore => 0x51,
+            Dastore => 0x52,
+            Aastore => 0x53,
+            Bastore => 0x54,
+            Castore => 0x55,
+            Sastore => 0x56,
+            Pop => 0x57,
+            Pop2 => 0x58,
+            Dup => 0x59,
+            DupX1 => 0x5a,
+            DupX2 => 0x5b,
+            Dup2 => 0x5c,
+            Dup2X1 => 0x5d,
+            Dup2X2 => 0x5e,
+            Swap => 0x5f,
+            Iadd => 0x60,
+            Ladd => 0x61,
+            Fadd => 0x62,
+            Dadd => 0x63,
+            Isub => 0x64,
+            Lsub => 0x65,
+            Fsub => 0x66,
+            Dsub => 0x67,
+            Imul => 0x68,
+            Lmul => 0x69,
+            Fmul => 0x6a,
+            Dmul => 0x6b,
+            Idiv => 0x6c,
+            Ldiv => 0x6d,
+            Fdiv => 0x6e,
+            Ddiv => 0x6f,
+            Irem => 0x70,
+            Lrem => 0x71,
+            Frem => 0x72,
+            Drem => 0x73,
+            Ineg => 0x74,
+            Lneg => 0x75,
+            Fneg => 0x76,
+            Dneg => 0x77,
+            Ishl => 0x78,
+            Lshl => 0x79,
+            Ishr => 0x7a,
+            Lshr => 0x7b,
+            Iushr => 0x7c,
+            Lushr => 0x7d,
+            Iand => 0x7e,
+            Land => 0x7f,
+            Ior => 0x80,
+            Lor => 0x81,
+            Ixor => 0x82,
+            Lxor => 0x83,
+            Iinc { .. } => 0x84,
+            I2l => 0x85,
+            I2f => 0x86,
+            I2d => 0x87,
+            L2i => 0x88,
+            L2f => 0x89,
+            L2d => 0x8a,
+            F2i => 0x8b,
+            F2l => 0x8c,
+            F2d => 0x8d,
+            D2i => 0x8e,
+            D2l => 0x8f,
+            D2f => 0x90,
+            I2b => 0x91,
+            I2c => 0x92,
+            I2s => 0x93,
+            Lcmp => 0x94,
+            Fcmpl => 0x95,
+            Fcmpg => 0x96,
+            Dcmpl => 0x97,
+            Dcmpg => 0x98,
+            Ifeq(..) => 0x99,
+            Ifne(..) => 0x9a,
+            Iflt(..) => 0x9b,
+            Ifge(..) => 0x9c,
+            Ifgt(..) => 0x9d,
+            Ifle(..) => 0x9e,
+            IfIcmpeq(..) => 0x9f,
+            IfIcmpne(..) => 0xa0,
+            IfIcmplt(..) => 0xa1,
+            IfIcmpge(..) => 0xa2,
+            IfIcmpgt(..) => 0xa3,
+            IfIcmple(..) => 0xa4,
+            IfAcmpeq(..) => 0xa5,
+            IfAcmpne(..) => 0xa6,
+            Goto(..) => 0xa7,
+            Jsr(..) => 0xa8,
+            Ret(..) => 0xa9,
+            Tableswitch { .. } => 0xaa,
+            Lookupswitch { .. } => 0xab,
+            Ireturn => 0xac,
+            Lreturn => 0xad,
+            Freturn => 0xae,
+            Dreturn => 0xaf,
+            Areturn => 0xb0,
+            Return => 0xb1,
+            Getstatic(..) => 0xb2,
+            Putstatic(..) => 0xb3,
+            Getfield(..) => 0xb4,
+            Putfield(..) => 0xb5,
+            Invokevirtual(..) => 0xb6,
+            Invokespecial(..) => 0xb7,
+            Invokestatic(..) => 0xb8,
+            Invokeinterface { .. } => 0xb9,
+            Invokedynamic(..) => 0xba,
+            New(..) => 0xbb,
+            Newarray(..) => 0xbc,
+            Anewarray(..) => 0xbd,
+            Arraylength => 0xbe,
+            Athrow => 0xbf,
+            Checkcast(..) => 0xc0,
+            Instanceof(..) => 0xc1,
+            Monitorenter => 0xc2,
+            Monitorexit => 0xc3,
+            Wide(..) => 0xc4,
+            Multianewarray { .. } => 0xc5,
+            Ifnull(..) => 0xc6,
+            Ifnonnull(..) => 0xc7,
+            GotoW(..) => 0xc8,
+            JsrW(..) => 0xc9,
+        }
+    }
+
+    /// The name of the instruction as written in the JVM specification, eg `iload_0`
+    pub fn get_mnemonic(&self) -> &'static str {
+        match self {
+            Nop => "nop",
+            AconstNull => "aconst_null",
+            IconstM1 => "iconst_m1",
+            Iconst0 => "iconst_0",
+            Iconst1 => "iconst_1",
+            Iconst2 => "iconst_2",
+            Iconst3 => "iconst_3",
+            Iconst4 => "iconst_4",
+            Iconst5 => "iconst_5",
+            Lconst0 => "lconst_0",
+            Lconst1 => "lconst_1",
+            Fconst0 => "fconst_0",
+            Fconst1 => "fconst_1",
+            Fconst2 => "fconst_2",
+            Dconst0 => "dconst_0",
+            Dconst1 => "dconst_1",
+            Bipush(..) => "bipush",
+            Sipush(..) => "sipush",
+            Ldc(..) => "ldc",
+            LdcW(..) => "ldc_w",
+            Ldc2W(..) => "ldc2_w",
+            Iload(..) => "iload",
+            Lload(..) => "lload",
+            Fload(..) => "fload",
+            Dload(..) => "dload",
+            Aload(..) => "aload",
+            Iload0 => "iload_0",
+            Iload1 => "iload_1",
+            Iload2 => "iload_2",
+            Iload3 => "iload_3",
+            Lload0 => "lload_0",
+            Lload1 => "lload_1",
+            Lload2 => "lload_2",
+            Lload3 => "lload_3",
+            Fload0 => "fload_0",
+            Fload1 => "fload_1",
+            Fload2 => "fload_2",
+            Fload3 => "fload_3",
+            Dload0 => "dload_0",
+            Dload1 => "dload_1",
+            Dload2 => "dload_2",
+            Dload3 => "dload_3",
+            Aload0 => "aload_0",
+            Aload1 => "aload_1",
+            Aload2 => "aload_2",
+            Aload3 => "aload_3",
+            Iaload => "iaload",
+            Laload => "laload",
+            Faload => "faload",
+            Daload => "daload",
+            Aaload => "aaload",
+            Baload => "baload",
+            Caload => "caload",
+            Saload => "saload",
+            Istore(..) => "istore",
+            Lstore(..) => "lstore",
+            Fstore(..) => "fstore",
+            Dstore(..) => "dstore",
+            Astore(..) => "astore",
+            Istore0 => "istore_0",
+            Istore1 => "istore_1",
+            Istore2 => "istore_2",
+            Istore3 => "istore_3",
+            Lstore0 => "lstore_0",
+            Lstore1 => "lstore_1",
+            Lstore2 => "lstore_2",
+            Lstore3 => "lstore_3",
+            Fstore0 => "fstore_0",
+            Fstore1 => "fstore_1",
+            Fstore2 => "fstore_2",
+            Fstore3 => "fstore_3",
+            Dstore0 => "dstore_0",
+            Dstore1 => "dstore_1",
+            Dstore2 => "dstore_2",
+            Dstore3 => "dstore_3",
+            Astore0 => "astore_0",
+            Astore1 => "astore_1",
+            Astore2 => "astore_2",
+            Astore3 => "astore_3",
+            Iastore => "iastore",
+            Lastore => "lastore",
+            Fastore => "fastore",
+            Dastore => "dastore",
+            Aastore => "aastore",
+            Bastore => "bastore",
+            Castore => "castore",
+            Sastore => "sastore",
+            Pop => "pop",
+            Pop2 => "pop2",
+            Dup => "dup",
+            DupX1 => "dup_x1",
+            DupX2 => "dup_x2",
+            Dup2 => "dup2",
+            Dup2X1 => "dup2_x1",
+            Dup2X2 => "dup2_x2",
+            Swap => "swap",
+            Iadd => "iadd",
+            Ladd => "ladd",
+            Fadd => "fadd",
+            Dadd => "dadd",
+            Isub => "isub",
+            Lsub => "lsub",
+            Fsub => "fsub",
+            Dsub => "dsub",
+            Imul => "imul",
+            Lmul => "lmul",
+            Fmul => "fmul",
+            Dmul => "dmul",
+            Idiv => "idiv",
+            Ldiv => "ldiv",
+            Fdiv => "fdiv",
+            Ddiv => "ddiv",
+            Irem => "irem",
+            Lrem => "lrem",
+            Frem => "frem",
+            Drem => "drem",
+            Ineg => "ineg",
+            Lneg => "lneg",
+            Fneg => "fneg",
+            Dneg => "dneg",
+            Ishl => "ishl",
+            Lshl => "lshl",
+            Ishr => "ishr",
+            Lshr => "lshr",
+            Iushr => "iushr",
+            Lushr => "lushr",
+            Iand => "iand",
+            Land => "land",
+            Ior => "ior",
+            Lor => "lor",
+            Ixor => "ixor",
+            Lxor => "lxor",
+            Iinc { .. } => "iinc",
+            I2l => "i2l",
+            I2f => "i2f",
+            I2d => "i2d",
+            L2i => "l2i",
+            L2f => "l2f",
+            L2d => "l2d",
+            F2i => "f2i",
+            F2l => "f2l",
+            F2d => "f2d",
+            D2i => "d2i",
+            D2l => "d2l",
+            D2f => "d2f",
+            I2b => "i2b",
+            I2c => "i2c",
+            I2s => "i2s",
+            Lcmp => "lcmp",
+            Fcmpl => "fcmpl",
+            Fcmpg => "fcmpg",
+            Dcmpl => "dcmpl",
+            Dcmpg => "dcmpg",
+            Ifeq(..) => "ifeq",
+            Ifne(..) => "ifne",
+            Iflt(..) => "iflt",
+            Ifge(..) => "ifge",
+            Ifgt(..) => "ifgt",
+            Ifle(..) => "ifle",
+            IfIcmpeq(..) => "if_icmpeq",
+            IfIcmpne(..) => "if_icmpne",
+            IfIcmplt(..) => "if_icmplt",
+            IfIcmpge(..) => "if_icmpge",
+            IfIcmpgt(..) => "if_icmpgt",
+            IfIcmple(..) => "if_icmple",
+            IfAcmpeq(..) => "if_acmpeq",
+            IfAcmpne(..) => "if_acmpne",
+            Goto(..) => "goto",
+            Jsr(..) => "jsr",
+            Ret(..) => "ret",
+            Tableswitch { .. } => "tableswitch",
+            Lookupswitch { .. } => "lookupswitch",
+            Ireturn => "ireturn",
+            Lreturn => "lreturn",
+            Freturn => "freturn",
+            Dreturn => "dreturn",
+            Areturn => "areturn",
+            Return => "return",
+            Getstatic(..) => "getstatic",
+            Putstatic(..) => "putstatic",
+            Getfield(..) => "getfield",
+            Putfield(..) => "putfield",
+            Invokevirtual(..) => "invokevirtual",
+            Invokespecial(..) => "invokespecial",
+            Invokestatic(..) => "invokestatic",
+            Invokeinterface { .. } => "invokeinterface",
+            Invokedynamic(..) => "invokedynamic",
+            New(..) => "new",
+            Newarray(..) => "newarray",
+            Anewarray(..) => "anewarray",
+            Arraylength => "arraylength",
+            Athrow => "athrow",
+            Checkcast(..) => "checkcast",
+            Instanceof(..) => "instanceof",
+            Monitorenter => "monitorenter",
+            Monitorexit => "monitorexit",
+            Wide(..) => "wide",
+            Multianewarray { .. } => "multianewarray",
+            Ifnull(..) => "ifnull",
+            Ifnonnull(..) => "ifnonnull",
+            GotoW(..) => "goto_w",
+            JsrW(..) => "jsr_w",
+        }
+    }
+
+    /// Encodes the instruction at `pc`, the offset into the code array it is written at
+    pub fn write(&self, output: &mut ClassWriter, pc: u32) {
+        output.write_u8(self.get_opcode());
+        match *self {
+            Bipush(value) => output.write_u8(value as u8),
+            Sipush(value) => output.write_u16(value as u16),
+            Ldc(index)
+            | Iload(index)
+            | Lload(index)
+            | Fload(index)
+            | Dload(index)
+            | Aload(index)
+            | Istore(index)
+            | Lstore(index)
+            | Fstore(index)
+            | Dstore(index)
+            | Astore(index)
+            | Ret(index)
+            | Newarray(index) => output.write_u8(index),
+            LdcW(index)
+            | Ldc2W(index)
+            | Getstatic(index)
+            | Putstatic(index)
+            | Getfield(index)
+            | Putfield(index)
+            | Invokevirtual(index)
+            | Invokespecial(index)
+            | Invokestatic(index)
+            | New(index)
+            | Anewarray(index)
+            | Checkcast(index)
+            | Instanceof(index) => output.write_u16(index),
+            Iinc { index, constant } => {
+                output.write_u8(index);
+                output.write_u8(constant as u8);
+            }
+            Ifeq(offset)
+            | Ifne(offset)
+            | Iflt(offset)
+            | Ifge(offset)
+            | Ifgt(offset)
+            | Ifle(offset)
+            | IfIcmpeq(offset)
+            | IfIcmpne(offset)
+            | IfIcmplt(offset)
+            | IfIcmpge(offset)
+            | IfIcmpgt(offset)
+            | IfIcmple(offset)
+            | IfAcmpeq(offset)
+            | IfAcmpne(offset)
+            | Goto(offset)
+            | Jsr(offset)
+            | Ifnull(offset)
+            | Ifnonnull(offset) => output.write_u16(offset as u16),
+            GotoW(offset) | JsrW(offset) => output.write_i32(offset),
+            Tableswitch { default, low, high, ref offsets } => {
+                write_padding(output, pc);
+                output.write_i32(default);
+                output.write_i32(low);
+                output.write_i32(high);
+                for offset in offsets {
+                    output.write_i32(*offset);
+                }
+            }
+            Lookupswitch { default, ref pairs } => {
+                write_padding(output, pc);
+                output.write_i32(default);
+                output.write_i32(pairs.len() as i32);
+                for (key, offset) in pairs {
+                    output.write_i32(*key);
+                    output.write_i32(*offset);
+                }
+            }
+            Invokeinterface { index, count } => {
+                output.write_u16(index);
+                output.write_u8(count);
+                output.write_u8(0);
+            }
+            Invokedynamic(index) => {
+                output.write_u16(index);
+                output.write_u16(0);
+            }
+            Multianewarray { index, dimensions } => {
+                output.write_u16(index);
+                output.write_u8(dimensions);
+            }
+            Wide(ref instruction) => {
+                output.write_u8(instruction.get_opcode());
+                output.write_u16(instruction.get_index());
+                if let WideInstruction::Iinc { constant, .. } = instruction {
+                    output.write_u16(*constant as u16);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl WideInstruction {
+    fn new(input: &mut ClassReader, pc: u32) -> Result<WideInstruction, ClassLoadingError> {
+        let opcode = input.read_u8()?;
+        Ok(match opcode {
+            0x15 => WideInstruction::Iload(input.read_u16()?),
+            0x16 => WideInstruction::Lload(input.read_u16()?),
+            0x17 => WideInstruction::Fload(input.read_u16()?),
+            0x18 => WideInstruction::Dload(input.read_u16()?),
+            0x19 => WideInstruction::Aload(input.read_u16()?),
+            0x36 => WideInstruction::Istore(input.read_u16()?),
+            0x37 => WideInstruction::Lstore(input.read_u16()?),
+            0x38 => WideInstruction::Fstore(input.read_u16()?),
+            0x39 => WideInstruction::Dstore(input.read_u16()?),
+            0x3a => WideInstruction::Astore(input.read_u16()?),
+            0xa9 => WideInstruction::Ret(input.read_u16()?),
+            0x84 => {
+                let index = input.read_u16()?;
+                let constant = input.read_u16()? as i16;
+                WideInstruction::Iinc { index, constant }
+            }
+            _ => {
+                return Err(ClassFormatError(format!(
+                    "Opcode {:#04x} can't be modified by wide at pc {}",
+                    opcode, pc
+                )))
+            }
+        })
+    }
+
+    /// The opcode of the instruction the `wide` prefix modifies
+    pub fn get_opcode(&self) -> u8 {
+        match self {
+            WideInstruction::Iload(_) => 0x15,
+            WideInstruction::Lload(_) => 0x16,
+            WideInstruction::Fload(_) => 0x17,
+            WideInstruction::Dload(_) => 0x18,
+            WideInstruction::Aload(_) => 0x19,
+            WideInstruction::Istore(_) => 0x36,
+            WideInstruction::Lstore(_) => 0x37,
+            WideInstruction::Fstore(_) => 0x38,
+            WideInstruction::Dstore(_) => 0x39,
+            WideInstruction::Astore(_) => 0x3a,
+            WideInstruction::Ret(_) => 0xa9,
+            WideInstruction::Iinc { .. } => 0x84,
+        }
+    }
+
+    pub fn get_mnemonic(&self) -> &'static str {
+        match self {
+            WideInstruction::Iload(_) => "iload",
+            WideInstruction::Lload(_) => "lload",
+            WideInstruction::Fload(_) => "fload",
+            WideInstruction::Dload(_) => "dload",
+            WideInstruction::Aload(_) => "aload",
+            WideInstruction::Istore(_) => "istore",
+            WideInstruction::Lstore(_) => "lstore",
+            WideInstruction::Fstore(_) => "fstore",
+            WideInstruction::Dstore(_) => "dstore",
+            WideInstruction::Astore(_) => "astore",
+            WideInstruction::Ret(_) => "ret",
+            WideInstruction::Iinc { .. } => "iinc",
+        }
+    }
+
+    /// The local variable the instruction loads, stores or increments
+    pub fn get_index(&self) -> u16 {
+        match self {
+            WideInstruction::Iload(index)
+            | WideInstruction::Lload(index)
+            | WideInstruction::Fload(index)
+            | WideInstruction::Dload(index)
+            | WideInstruction::Aload(index)
+            | WideInstruction::Istore(index)
+            | WideInstruction::Lstore(index)
+            | WideInstruction::Fstore(index)
+            | WideInstruction::Dstore(index)
+            | WideInstruction::Astore(index)
+            | WideInstruction::Ret(index)
+            | WideInstruction::Iinc { index, .. } => *index,
+        }
+    }
+}
+
+/// tableswitch and lookupswitch are padded so that their operands start at a multiple of four
+/// bytes from the start of the code
+fn skip_padding(input: &mut ClassReader, pc: u32) -> Result<(), ClassLoadingError> {
+    let padding = 3 - pc % 4;
+    input.read_bytes(padding as usize)?;
+    Ok(())
+}
+
+fn write_padding(output: &mut ClassWriter, pc: u32) {
+    for _ in 0..3 - pc % 4 {
+        output.write_u8(0);
+    }
+}
+
+/// Some operand bytes are reserved and must be zero
+fn check_zero(byte: u8, mnemonic: &str, pc: u32) -> Result<(), ClassLoadingError> {
+    if byte != 0 {
+        return Err(ClassFormatError(format!(
+            "{} at pc {} has a non-zero reserved operand byte",
+            mnemonic, pc
+        )));
+    }
+    Ok(())
+}
+
+/// Iterates over the instructions of a code array, giving each along with its pc
+///
+/// Iteration stops after the first truncated or invalid instruction
+pub struct InstructionIter<'b> {
+    input: ClassReader<'b>,
+    /// offset of the start of the code within the class file
+    start: usize,
+    failed: bool,
+}
+
+impl<'b> InstructionIter<'b> {
+    pub fn new(code: &'b [u8]) -> InstructionIter<'b> {
+        InstructionIter::new_at(code, 0)
+    }
+
+    /// Iterates over code found at `offset` within a class file, so that errors give the
+    /// offset into the class file
+    pub fn new_at(code: &'b [u8], offset: usize) -> InstructionIter<'b> {
+        InstructionIter {
+            input: ClassReader::new_at(code, offset),
+            start: offset,
+            failed: false,
+        }
+    }
+}
+
+impl<'b> Iterator for InstructionIter<'b> {
+    type Item = Result<(u32, Instruction), ClassLoadingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.input.remaining() == 0 {
+            return None;
+        }
+        let pc = (self.input.get_offset() - self.start) as u32;
+        let instruction = Instruction::new(&mut self.input, pc);
+        self.failed = instruction.is_err();
+        Some(instruction.map(|instruction| (pc, instruction)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Instruction::*;
+    use super::{InstructionIter, WideInstruction};
+    use class_file::ClassLoadingError::ClassFormatError;
+    use class_writer::ClassWriter;
+
+    /// Code with padded switches, a wide instruction and reserved operand bytes
+    const CODE: &[u8] = &[
+        // 0: bipush -2
+        0x10, 0xfe,
+        // 2: tableswitch, padded to pc 4
+        0xaa, 0x00,
+        0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+        0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x12,
+        // 24: lookupswitch, padded to pc 28
+        0xab, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05,
+        0x00, 0x00, 0x00, 0x06,
+        // 44: wide iinc 256, -300
+        0xc4, 0x84, 0x01, 0x00, 0xfe, 0xd4,
+        // 50: invokeinterface #3, 2
+        0xb9, 0x00, 0x03, 0x02, 0x00,
+        // 55: goto_w 0
+        0xc8, 0xff, 0xff, 0xff, 0xc9,
+        // 60: return
+        0xb1,
+    ];
+
+    #[test]
+    fn decodes_instructions_at_their_pcs() {
+        let instructions = InstructionIter::new(CODE).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(instructions, [
+            (0, Bipush(-2)),
+            (2, Tableswitch { default: 20, low: 1, high: 2, offsets: vec![16, 18] }),
+            (24, Lookupswitch { default: 4, pairs: vec![(5, 6)] }),
+            (44, Wide(WideInstruction::Iinc { index: 256, constant: -300 })),
+            (50, Invokeinterface { index: 3, count: 2 }),
+            (55, GotoW(-55)),
+            (60, Return),
+        ]);
+        assert_eq!(instructions[3].1.get_mnemonic(), "wide");
+        assert_eq!(instructions[4].1.get_opcode(), 0xb9);
+    }
+
+    #[test]
+    fn iteration_stops_at_the_first_bad_instruction() {
+        let errors = [
+            &[0x00, 0xca, 0x00][..],
+            &[0x11, 0x01],
+            &[0xba, 0x00, 0x01, 0x00, 0x01],
+            &[0xc4, 0x60, 0x00, 0x01],
+            &[0xaa, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1],
+            &[0xab, 0x00, 0x00, 0x00, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff],
+        ];
+        for code in &errors {
+            let results: Vec<_> = InstructionIter::new(code).collect();
+            assert!(matches!(results.last(), Some(Err(ClassFormatError(_)))), "{:?}", code);
+            assert!(results[..results.len() - 1].iter().all(Result::is_ok), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn writes_instructions_as_they_were_read() {
+        let mut output = ClassWriter::new();
+        for instruction in InstructionIter::new(CODE) {
+            let (pc, instruction) = instruction.unwrap();
+            instruction.write(&mut output, pc);
+        }
+        assert_eq!(output.into_bytes(), CODE);
+    }
+}
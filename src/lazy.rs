@@ -1,8 +1,7 @@
-use class::Class;
-use std::cell::RefCell;
+use class_file::ClassLoadingError;
 
 pub trait LazyResolve<'a, T> {
-    fn resolve(&mut self, name: &'a str) -> &'a T;
+    fn resolve(&mut self, name: &'a str) -> Result<&'a T, ClassLoadingError>;
 }
 
 //pub trait ClassResolve<'a> {
@@ -0,0 +1,38 @@
+#![allow(non_snake_case, non_camel_case_types, non_upper_case_globals, dead_code)]
+
+extern crate byteorder;
+extern crate cesu8;
+#[macro_use]
+extern crate bitflags;
+extern crate core;
+extern crate memmap2;
+extern crate typed_arena;
+extern crate zip;
+
+pub mod lazy;
+pub mod assembler;
+pub mod annotation;
+pub mod attribute;
+pub mod class;
+pub mod class_array;
+pub mod class_file;
+pub mod class_loader;
+pub mod class_path;
+pub mod class_reader;
+pub mod class_writer;
+pub mod constant_pool;
+pub mod disassembler;
+pub mod field;
+pub mod format_check;
+pub mod inner_class;
+pub mod instruction;
+pub mod method;
+pub mod module;
+pub mod record;
+pub mod signature;
+pub mod source_map;
+pub mod stack_map;
+pub mod verifier;
+
+#[cfg(test)]
+mod test_util;
@@ -1,45 +1,99 @@
-#![allow(non_snake_case, non_camel_case_types, non_upper_case_globals, dead_code)]
+extern crate jvm;
+extern crate typed_arena;
 
-use class_loader::ClassLoader;
-use std::io;
+use jvm::class_loader::ClassLoader;
+use std::env;
+use std::path::Path;
+use std::process;
 use std::time::SystemTime;
 use typed_arena::Arena;
 
-extern crate byteorder;
-extern crate cesu8;
-#[macro_use]
-extern crate bitflags;
-extern crate core;
-extern crate typed_arena;
-extern crate zip;
+const USAGE: &str = "usage: jvm [-cp <path>] <class>...";
 
-mod lazy;
-mod attribute;
-mod class;
-mod class_array;
-mod class_file;
-mod class_loader;
-mod class_path;
-mod constant_pool;
-mod field;
-mod method;
+/// Loads classes and prints them along with how long loading took
+///
+/// Without `-cp`, classes are searched for in the runtime of the JDK at `JAVA_HOME` and then in
+/// the current directory
+fn main() {
+    let mut class_path = None;
+    let mut classes = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" | "-classpath" | "--class-path" => match args.next() {
+                Some(path) => class_path = Some(path),
+                None => exit_with_usage(),
+            },
+            "-h" | "-help" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => exit_with_usage(),
+            _ => classes.push(arg),
+        }
+    }
+    if classes.is_empty() {
+        exit_with_usage();
+    }
+    let class_path = match class_path {
+        Some(class_path) => env::split_paths(&class_path)
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        None => vec![runtime_jar(), String::from(".")],
+    };
 
-#[allow(unused_variables)]
-fn main() -> io::Result<()> {
     let start = SystemTime::now();
-    println!("Hello, world!");
-    let mut class_path = Vec::new();
-    class_path.push(String::from("/usr/lib/jvm/java-8-oracle/jre/lib/rt.jar"));
-    class_path.push(String::from("/home/robot_rover/Desktop/javaTest/"));
     let string_allocator = Arena::new();
     let allocator = Arena::new();
-    let mut loader = ClassLoader::new(class_path, &allocator, &string_allocator);
-    let class = loader.create_class("Square");
-    let main = loader.create_class("Main");
-    let interface = loader.create_class("NoOp");
-    let array = loader.create_class("[LMain;");
-    println!("{:#?}", main);
+    let byte_allocator = Arena::new();
+    let mut loader =
+        match ClassLoader::new(class_path, &allocator, &string_allocator, &byte_allocator) {
+            Ok(loader) => loader,
+            Err(error) => {
+                eprintln!("Error: could not open the classpath: {}", error);
+                process::exit(1);
+            }
+        };
+    let mut failed = false;
+    for class in classes {
+        // Class names are borrowed for as long as the loader lives
+        let class = string_allocator.alloc(class.replace('.', "/"));
+        match loader.create_class(class) {
+            Ok(class) => println!("{:#?}", class),
+            Err(error) => {
+                eprintln!("Error: could not load {}: {}", class, error);
+                failed = true;
+            }
+        }
+    }
     let since_start = SystemTime::now().duration_since(start).unwrap();
     println!("Duration: {:?}", since_start);
-    Ok(())
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// The `rt.jar` of the JDK or JRE at `JAVA_HOME`
+fn runtime_jar() -> String {
+    let java_home = match env::var("JAVA_HOME") {
+        Ok(java_home) => java_home,
+        Err(_) => {
+            eprintln!("Error: no classpath given, pass -cp or set JAVA_HOME");
+            exit_with_usage();
+        }
+    };
+    ["jre/lib/rt.jar", "lib/rt.jar"]
+        .iter()
+        .map(|jar| Path::new(&java_home).join(jar))
+        .find(|jar| jar.is_file())
+        .map(|jar| jar.to_string_lossy().into_owned())
+        .unwrap_or_else(|| {
+            eprintln!("Error: no rt.jar in JAVA_HOME {}, pass -cp instead", java_home);
+            process::exit(1);
+        })
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
 }
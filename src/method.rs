@@ -1,15 +1,23 @@
-use attribute::attribute_info;
+use annotation;
+use annotation::{Annotation, AnnotationValue};
+use attribute::{attribute_info, AttributeSource};
 use attribute::attribute_info_Data::*;
-use byteorder::{BigEndian, ReadBytesExt};
-use class::ClassRef;
+use class::{ClassAccessFlag, ClassRef};
 use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+use class_reader::ClassReader;
+use class_writer::ClassWriter;
 use constant_pool::ConstantPool;
 use field::FieldDescriptor;
-use method;
+use instruction::InstructionIter;
 use method::ReturnDescriptor::*;
-use std::io::Read;
-use std::iter::{Enumerate, Peekable};
-use std::str::Chars;
+use std::fmt;
+use std::rc::Rc;
+use signature;
+use signature::MethodSignature;
+use stack_map;
+use stack_map::StackMapFrame;
+use class_file::ClassFile;
 use {attribute, field};
 use class::ClassRef::Symbolic;
 
@@ -17,12 +25,12 @@ use class::ClassRef::Symbolic;
 /// Raw data contained in a .class file
 ///
 /// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.6>
-pub struct method_info {
+pub struct method_info<'a> {
     access_flags: u16,
     name_index: u16,
     descriptor_index: u16,
     attributes_count: u16,
-    attributes: Vec<attribute::attribute_info>,
+    attributes: Vec<attribute::attribute_info<'a>>,
 }
 
 #[derive(Debug)]
@@ -52,19 +60,275 @@ pub struct MethodInfo<'a> {
     name: &'a str,
     parent_class: ClassRef<'a>,
     descriptor: MethodDescriptor<'a>,
-    code: Option<Vec<u8>>,
+    access_flags: MethodAccessFlag,
+    raw_access_flags: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    attributes: Vec<attribute_info<'a>>,
+    /// The constant pool of the class, which the attributes refer into
+    constant_pool: Rc<ConstantPool<'a>>,
+}
+
+#[derive(Debug)]
+/// A formal parameter of a method
+pub struct MethodParameter<'a> {
+    name: Option<&'a str>,
+    access_flags: ParameterAccessFlag,
+    descriptor: FieldDescriptor<'a>,
+}
+
+impl<'a> MethodInfo<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_access_flags(&self) -> MethodAccessFlag {
+        self.access_flags
+    }
+
+    /// `access_flags` as they appear in the class file, including reserved bits
+    pub fn get_raw_access_flags(&self) -> u16 {
+        self.raw_access_flags
+    }
+
+    /// Writes the method back out as a `method_info`
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.raw_access_flags);
+        output.write_u16(self.name_index);
+        output.write_u16(self.descriptor_index);
+        attribute::write_attributes(output, &self.attributes);
+    }
+
+    pub fn get_descriptor(&self) -> &MethodDescriptor<'a> {
+        &self.descriptor
+    }
+
+    pub fn get_name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn get_descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    /// The parameters of the method, in declaration order
+    ///
+    /// Resolved from the attributes of the method when asked for, so that parsing lazily doesn't
+    /// have to decode its Code attribute
+    pub fn get_parameters(&self) -> Result<Vec<MethodParameter<'a>>, ClassLoadingError> {
+        let is_static = self.access_flags.contains(MethodAccessFlag::ACC_STATIC);
+        get_parameters(&self.attributes, &self.descriptor, is_static, &self.constant_pool)
+    }
+
+    /// The generic signature of the method, from its Signature attribute
+    pub fn get_signature(&self) -> Result<Option<MethodSignature<'a>>, ClassLoadingError> {
+        let parse = signature::parse_method_signature;
+        signature::read_signature(&self.attributes, &self.constant_pool, parse)
+    }
+
+    /// The annotations on the declaration of the method, both visible and invisible
+    pub fn get_annotations(&self) -> Result<Vec<Annotation<'a>>, ClassLoadingError> {
+        annotation::read_annotations(&self.attributes, &self.constant_pool)
+    }
+
+    /// The annotation of an interface, given by its field descriptor, on the method, eg
+    /// `Lorg/junit/Test;`
+    pub fn find_annotation(
+        &self,
+        type_descriptor: &str,
+    ) -> Result<Option<Annotation<'a>>, ClassLoadingError> {
+        Ok(annotation::find_annotation(self.get_annotations()?, type_descriptor))
+    }
+
+    /// The annotations on each formal parameter of the method, both visible and invisible
+    ///
+    /// May have fewer entries than the method descriptor has parameters, as compilers can leave
+    /// out synthetic and implicit parameters
+    pub fn get_parameter_annotations(&self) -> Result<Vec<Vec<Annotation<'a>>>, ClassLoadingError> {
+        annotation::read_parameter_annotations(&self.attributes, &self.constant_pool)
+    }
+
+    /// The default value of an annotation interface element, from its AnnotationDefault
+    /// attribute
+    pub fn get_annotation_default(&self) -> Result<Option<AnnotationValue<'a>>, ClassLoadingError> {
+        annotation::read_annotation_default(&self.attributes, &self.constant_pool)
+    }
+
+    /// The bytecode of the method, from its Code attribute
+    pub fn get_code(&self) -> Option<&[u8]> {
+        self.attributes.iter().filter_map(|info| {
+            if let Code_attribute { code, .. } = info.get_data() {
+                Some(code.as_slice())
+            } else {
+                None
+            }
+        }).next()
+    }
+
+    /// The types of `this` and the parameters when the method is entered, which the frames of
+    /// its StackMapTable are given relative to
+    pub fn get_initial_frame(
+        &self,
+        class: &ClassFile<'a>,
+    ) -> Result<StackMapFrame<'a>, ClassLoadingError> {
+        let descriptor = class.get_constant_pool().get_string_entry(self.descriptor_index)?;
+        let is_static = self.access_flags.contains(MethodAccessFlag::ACC_STATIC);
+        stack_map::initial_frame(class.get_name(), self.name, is_static, descriptor)
+    }
+
+    /// The frames of the method's StackMapTable, each with the complete types of the locals
+    /// and operand stack at its pc
+    ///
+    /// Empty if the method has no code or its code has no StackMapTable
+    pub fn get_stack_map_frames(
+        &self,
+        class: &ClassFile<'a>,
+    ) -> Result<Vec<StackMapFrame<'a>>, ClassLoadingError> {
+        for info in self.attributes.iter() {
+            if let Code_attribute { attributes, .. } = info.decode()? {
+                let initial_frame = self.get_initial_frame(class)?;
+                return stack_map::read_stack_map_frames(
+                    attributes,
+                    &initial_frame,
+                    class.get_constant_pool(),
+                );
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// The source line of the instruction at `pc`, from the LineNumberTable attributes of the
+    /// method's code
+    ///
+    /// An instruction is on the line of the closest entry that starts at or before it
+    pub fn get_line_number(&self, pc: u32) -> Option<u16> {
+        let mut line = None;
+        for info in self.attributes.iter() {
+            if let Code_attribute { attributes, .. } = info.get_data() {
+                for info in attributes {
+                    if let LineNumberTable_attribute { line_number_table, .. } = info.get_data() {
+                        for entry in line_number_table {
+                            let start_pc = u32::from(entry.get_start_pc());
+                            if start_pc <= pc && line.is_none_or(|(best, _)| start_pc >= best) {
+                                line = Some((start_pc, entry.get_line_number()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        line.map(|(_, line_number)| line_number)
+    }
+
+    /// Decodes the bytecode of the method, giving each instruction along with its pc
+    pub fn get_instructions<'b>(&'b self) -> Option<InstructionIter<'b>> {
+        self.attributes.iter().filter_map(|info| {
+            if let Code_attribute { code, .. } = info.get_data() {
+                // The code follows the attribute header, max_stack, max_locals and code_length
+                Some(InstructionIter::new_at(code, info.get_offset() + 14))
+            } else {
+                None
+            }
+        }).next()
+    }
+
+    pub fn get_attributes(&self) -> &[attribute_info<'a>] {
+        &self.attributes
+    }
+
+    /// The visible and invisible type annotations of the method, including those on types used
+    /// within its Code attribute
+    pub fn get_type_annotations(&self) -> Vec<&attribute::type_annotation> {
+        let mut annotations = attribute::get_type_annotations(&self.attributes);
+        for info in self.attributes.iter() {
+            if let Code_attribute { attributes, .. } = info.get_data() {
+                annotations.extend(attribute::get_type_annotations(attributes));
+            }
+        }
+        annotations
+    }
+}
+
+impl<'a> MethodDescriptor<'a> {
+    pub fn get_parameters(&self) -> &[FieldDescriptor<'a>] {
+        &self.parameters
+    }
+
+    pub fn get_return_type(&self) -> &ReturnDescriptor<'a> {
+        &self.return_type
+    }
+}
+
+impl<'a> fmt::Display for MethodDescriptor<'a> {
+    /// Writes the method type as a descriptor, eg `(I[Ljava/lang/String;)V`, or with the
+    /// alternate flag (`{:#}`) in Java syntax, eg `void (int, java.lang.String[])`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{:#} (", self.return_type)?;
+            for (index, parameter) in self.parameters.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{:#}", parameter)?;
+            }
+            write!(f, ")")
+        } else {
+            write!(f, "(")?;
+            for parameter in self.parameters.iter() {
+                write!(f, "{}", parameter)?;
+            }
+            write!(f, "){}", self.return_type)
+        }
+    }
+}
+
+impl<'a> fmt::Display for ReturnDescriptor<'a> {
+    /// Writes the return type as a descriptor, or with the alternate flag in Java syntax
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value(descriptor) => descriptor.fmt(f),
+            Void if f.alternate() => write!(f, "void"),
+            Void => write!(f, "V"),
+        }
+    }
+}
+
+impl<'a> MethodParameter<'a> {
+    /// The name of the parameter, from the MethodParameters attribute or, failing that, the
+    /// LocalVariableTable
+    ///
+    /// `None` if the class file doesn't record the name
+    pub fn get_name(&self) -> Option<&'a str> {
+        self.name
+    }
+
+    pub fn get_access_flags(&self) -> ParameterAccessFlag {
+        self.access_flags
+    }
+
+    pub fn get_descriptor(&self) -> &FieldDescriptor<'a> {
+        &self.descriptor
+    }
 }
 
-impl method_info {
+impl<'a> method_info<'a> {
     pub fn new(
-        input: &mut Read,
-        constant_pool: &ConstantPool,
-    ) -> Result<method_info, ClassLoadingError> {
-        let access_flags = input.read_u16::<BigEndian>().unwrap();
-        let name_index = input.read_u16::<BigEndian>().unwrap();
-        let descriptor_index = input.read_u16::<BigEndian>().unwrap();
-        let attributes_count = input.read_u16::<BigEndian>().unwrap();
-        let attributes = attribute::read_attributes(input, attributes_count, constant_pool)?;
+        input: &mut ClassReader,
+        constant_pool: &ConstantPool<'a>,
+        major_version: u16,
+        source: Option<&Rc<AttributeSource<'a>>>,
+    ) -> Result<method_info<'a>, ClassLoadingError> {
+        let access_flags = input.read_u16()?;
+        let name_index = input.read_u16()?;
+        let descriptor_index = input.read_u16()?;
+        let attributes_count = input.read_u16()?;
+        let attributes = attribute::read_attributes(
+            input,
+            attributes_count,
+            constant_pool,
+            major_version,
+            source,
+        )?;
         Ok(method_info {
             access_flags,
             name_index,
@@ -75,90 +339,400 @@ impl method_info {
     }
 }
 
-pub fn read_methods<'a, 'b, 'c>(
-    input: &mut Read,
+/// Reads the array of methods from a class file
+///
+/// The attributes of the methods are decoded lazily if given a `source`
+pub fn read_methods<'a>(
+    input: &mut ClassReader,
     length: u16,
-    constant_pool: &ConstantPool<'a>,
+    constant_pool: &Rc<ConstantPool<'a>>,
     self_reference_name: &'a str,
+    class_access_flags: ClassAccessFlag,
+    major_version: u16,
+    source: Option<&Rc<AttributeSource<'a>>>,
 ) -> Result<Vec<MethodInfo<'a>>, ClassLoadingError> {
     let mut vector = Vec::with_capacity(length as usize);
     for _ in 0..length {
-        let method_meta = method_info::new(input, constant_pool)?;
-        let name = constant_pool.get_string_entry(method_meta.name_index);
-        let descriptor_str = constant_pool.get_string_entry(method_meta.descriptor_index);
-        let descriptor = parse_method_descriptor(
-            &mut descriptor_str.chars().enumerate().peekable(),
-            descriptor_str,
-        );
-        let code = method::get_code(&method_meta.attributes);
+        let offset = input.get_offset();
+        let method_meta = method_info::new(input, constant_pool, major_version, source)?;
+        let name = constant_pool.get_string_entry(method_meta.name_index)
+            .map_err(|error| error.at_offset(offset))?;
+        let access_flags = MethodAccessFlag::from_bits_truncate(method_meta.access_flags);
+        let is_interface = class_access_flags.contains(ClassAccessFlag::ACC_INTERFACE);
+        check_access_flags(access_flags, name, is_interface, major_version).map_err(|reason| {
+            ClassFormatError(format!(
+                "Illegal access flags {:#06x} on method {}.{}: {}",
+                method_meta.access_flags, self_reference_name, name, reason
+            )).at_offset(offset)
+        })?;
+        let descriptor_str = constant_pool.get_string_entry(method_meta.descriptor_index)
+            .map_err(|error| error.at_offset(offset))?;
+        let descriptor = parse_method_descriptor(descriptor_str)
+            .map_err(|error| error.at_offset(offset))?;
         let method_info = MethodInfo {
             name,
             parent_class: Symbolic(self_reference_name),
             descriptor,
-            code,
+            access_flags,
+            raw_access_flags: method_meta.access_flags,
+            name_index: method_meta.name_index,
+            descriptor_index: method_meta.descriptor_index,
+            attributes: method_meta.attributes,
+            constant_pool: constant_pool.clone(),
         };
         vector.push(method_info);
     }
     Ok(vector)
 }
 
-fn get_code(attributes: &Vec<attribute_info>) -> Option<Vec<u8>> {
-    for info in attributes.iter() {
-        if let Code_attribute { code, .. } = info.get_data() {
-            return Some(code.clone());
+/// Gives the parameters of a method their names and flags
+///
+/// Names come from the MethodParameters attribute, or the entries of the LocalVariableTable that
+/// are live at the start of the method when it is absent, or doesn't match the descriptor. A
+/// strict format check rejects a class with such a MethodParameters attribute.
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.24>
+fn get_parameters<'a>(
+    attributes: &[attribute_info],
+    descriptor: &MethodDescriptor<'a>,
+    is_static: bool,
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Vec<MethodParameter<'a>>, ClassLoadingError> {
+    let mut method_parameters = None;
+    let mut code_attributes: &[attribute_info] = &[];
+    for info in attributes {
+        match info.decode()? {
+            MethodParameters_attribute { parameters, .. } if method_parameters.is_none() => {
+                method_parameters = Some((parameters, info.get_offset()));
+            }
+            Code_attribute { attributes, .. } => code_attributes = attributes,
+            _ => {}
+        }
+    }
+    // A MethodParameters attribute that disagrees with the descriptor can't be matched up
+    if let Some((method_parameters, offset)) = method_parameters {
+        if method_parameters.len() == descriptor.parameters.len() {
+            return method_parameters.iter().zip(descriptor.parameters.iter())
+                .map(|(parameter, descriptor)| {
+                    let name_index = parameter.get_name_index();
+                    Ok(MethodParameter {
+                        name: if name_index == 0 {
+                            None
+                        } else {
+                            Some(constant_pool.get_string_entry(name_index)
+                                .map_err(|error| error.at_offset(offset))?)
+                        },
+                        access_flags: ParameterAccessFlag::from_bits_truncate(
+                            parameter.get_access_flags(),
+                        ),
+                        descriptor: descriptor.clone(),
+                    })
+                }).collect();
         }
     }
-    None
+
+    let local_variables: Vec<_> = code_attributes.iter()
+        .filter_map(|info| {
+            if let LocalVariableTable_attribute { local_variable_table, .. } = info.get_data() {
+                Some(local_variable_table.iter().map(move |entry| (entry, info.get_offset())))
+            } else {
+                None
+            }
+        })
+        .flatten()
+        .filter(|(entry, _)| entry.get_start_pc() == 0)
+        .collect();
+    // Parameters are passed in the local variables following `this`
+    let mut slot = if is_static { 0 } else { 1 };
+    descriptor.parameters.iter().map(|descriptor| {
+        let name = match local_variables.iter().find(|(entry, _)| entry.get_index() == slot) {
+            Some((entry, offset)) => Some(constant_pool.get_string_entry(entry.get_name_index())
+                .map_err(|error| error.at_offset(*offset))?),
+            None => None,
+        };
+        slot += descriptor.get_size();
+        Ok(MethodParameter {
+            name,
+            access_flags: ParameterAccessFlag::empty(),
+            descriptor: descriptor.clone(),
+        })
+    }).collect()
 }
 
-/// Parse a method signature from a valid method descriptor
+/// Parse a method signature from a method descriptor, which must make up the whole of `source`
 ///
 /// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.3.3>
-fn parse_method_descriptor<'a, 'b>(
-    chars: &mut Peekable<Enumerate<Chars<'a>>>,
-    source: &'b str,
-) -> MethodDescriptor<'b> {
-    if chars.next().unwrap().1 != '(' {
-        panic!("Method Descriptor not valid: {}", source);
+pub fn parse_method_descriptor(source: &str) -> Result<MethodDescriptor<'_>, ClassLoadingError> {
+    if !source.starts_with('(') {
+        return Err(ClassFormatError(format!("Method Descriptor not valid: {}", source)));
     }
+    let mut position = 1;
     let mut parameters = Vec::new();
-    while chars.peek().unwrap().1 != ')' {
-        parameters.push(field::parse_field_descriptor(chars, source));
+    while !source[position..].starts_with(')') {
+        parameters.push(field::read_field_descriptor(source, &mut position)?);
     }
-    chars.next();
-    let return_type = parse_return_descriptor(chars, source);
-    MethodDescriptor {
+    position += 1;
+    let return_type = read_return_descriptor(source, &mut position)?;
+    field::check_finished(source, position)?;
+    Ok(MethodDescriptor {
         parameters,
         return_type,
-    }
+    })
 }
 
 /// Parse a return value from the end of a method descriptor
 ///
 /// This will either be a valid field descriptor or void (V)
-fn parse_return_descriptor<'a, 'b>(
-    chars: &mut Peekable<Enumerate<Chars<'a>>>,
+fn read_return_descriptor<'b>(
     source: &'b str,
-) -> ReturnDescriptor<'b> {
-    if chars.peek().unwrap().1 == 'V' {
-        Void
+    position: &mut usize,
+) -> Result<ReturnDescriptor<'b>, ClassLoadingError> {
+    if source[*position..].starts_with('V') {
+        *position += 1;
+        Ok(Void)
     } else {
-        Value(field::parse_field_descriptor(chars, source))
-    }
-}
-
-/// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.6-200-A.1>
-enum MethodAccessFlag {
-    ACC_PUBLIC = 0x0001,
-    ACC_PRIVATE = 0x0002,
-    ACC_PROTECTED = 0x0004,
-    ACC_STATIC = 0x0008,
-    ACC_FINAL = 0x0010,
-    ACC_SYNCHRONIZED = 0x0020,
-    ACC_BRIDGE = 0x0040,
-    ACC_VARARGS = 0x0080,
-    ACC_NATIVE = 0x0100,
-    ACC_ABSTRACT = 0x0400,
-    ACC_STRICT = 0x0800,
-    ACC_SYNTHETIC = 0x1000,
+        Ok(Value(field::read_field_descriptor(source, position)?))
+    }
+}
+
+bitflags! {
+    /// <https://docs.oracle.com/javase/specs/jvms/se8/html/jvms-4.html#jvms-4.6-200-A.1>
+    pub struct MethodAccessFlag: u16 {
+        const ACC_PUBLIC       = 0x0001;
+        const ACC_PRIVATE      = 0x0002;
+        const ACC_PROTECTED    = 0x0004;
+        const ACC_STATIC       = 0x0008;
+        const ACC_FINAL        = 0x0010;
+        const ACC_SYNCHRONIZED = 0x0020;
+        const ACC_BRIDGE       = 0x0040;
+        const ACC_VARARGS      = 0x0080;
+        const ACC_NATIVE       = 0x0100;
+        const ACC_ABSTRACT     = 0x0400;
+        const ACC_STRICT       = 0x0800;
+        const ACC_SYNTHETIC    = 0x1000;
+    }
+}
+
+/// Checks that a method doesn't combine flags that are not allowed together, or that are not
+/// allowed on initializers or in interfaces
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.6>
+fn check_access_flags(
+    access_flags: MethodAccessFlag,
+    name: &str,
+    is_interface: bool,
+    major_version: u16,
+) -> Result<(), &'static str> {
+    if name == "<clinit>" {
+        // Other than ACC_STRICT the flags of the class initializer are ignored, but from version
+        // 51 a method is only the class initializer if it is static
+        if major_version >= 51 && !access_flags.contains(MethodAccessFlag::ACC_STATIC) {
+            return Err("the class initializer must be static");
+        }
+        return Ok(());
+    }
+    let visibility = access_flags & (MethodAccessFlag::ACC_PUBLIC
+        | MethodAccessFlag::ACC_PRIVATE
+        | MethodAccessFlag::ACC_PROTECTED);
+    if visibility.bits().count_ones() > 1 {
+        return Err("at most one of public, private and protected may be set");
+    }
+    if name == "<init>" {
+        let allowed = MethodAccessFlag::ACC_PUBLIC
+            | MethodAccessFlag::ACC_PRIVATE
+            | MethodAccessFlag::ACC_PROTECTED
+            | MethodAccessFlag::ACC_VARARGS
+            | MethodAccessFlag::ACC_STRICT
+            | MethodAccessFlag::ACC_SYNTHETIC;
+        if is_interface {
+            return Err("interfaces can't have instance initializers");
+        } else if access_flags.intersects(!allowed) {
+            return Err("instance initializers may only also be varargs, strict or synthetic");
+        }
+        return Ok(());
+    }
+    if is_interface {
+        if major_version < 52 {
+            let required = MethodAccessFlag::ACC_PUBLIC | MethodAccessFlag::ACC_ABSTRACT;
+            if !access_flags.contains(required) {
+                return Err("methods of interfaces before version 52 must be public and abstract");
+            }
+        } else if visibility.is_empty() || visibility == MethodAccessFlag::ACC_PROTECTED {
+            return Err("methods of interfaces must be either public or private");
+        }
+        let illegal = MethodAccessFlag::ACC_PROTECTED
+            | MethodAccessFlag::ACC_FINAL
+            | MethodAccessFlag::ACC_SYNCHRONIZED
+            | MethodAccessFlag::ACC_NATIVE;
+        if access_flags.intersects(illegal) {
+            return Err("methods of interfaces can't be protected, final, synchronized or native");
+        }
+    }
+    if access_flags.contains(MethodAccessFlag::ACC_ABSTRACT) {
+        let mut illegal = MethodAccessFlag::ACC_PRIVATE
+            | MethodAccessFlag::ACC_STATIC
+            | MethodAccessFlag::ACC_FINAL
+            | MethodAccessFlag::ACC_SYNCHRONIZED
+            | MethodAccessFlag::ACC_NATIVE;
+        // strictfp was only a modifier from version 46 until every method became strict in 61
+        if (46..=60).contains(&major_version) {
+            illegal |= MethodAccessFlag::ACC_STRICT;
+        }
+        if access_flags.intersects(illegal) {
+            return Err("abstract methods can't be private, static, final, synchronized, native or \
+                strict");
+        }
+    }
+    Ok(())
+}
+
+bitflags! {
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.24>
+    pub struct ParameterAccessFlag: u16 {
+        const ACC_FINAL       = 0x0010;
+        const ACC_SYNTHETIC   = 0x1000;
+        const ACC_MANDATED    = 0x8000;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assembler::{assemble_class, AssemblyError};
+    use class_file::ClassLoadingError::ClassFormatError;
+    use format_check::{check_format, FormatCheck};
+    use method::{check_access_flags, parse_method_descriptor};
+    use method::{MethodAccessFlag, ParameterAccessFlag};
+    use test_util::method_source;
+    use typed_arena::Arena;
+
+    /// A class with a static method `m(int, String)` and the given MethodParameters rows
+    fn parameters_source(method_parameters: &str) -> String {
+        let method = "  public static void m(int, java.lang.String);
+    descriptor: (ILjava/lang/String;)V
+    Code:
+      stack=0, locals=2, args_size=2
+        0: return
+        1:
+      LocalVariableTable:
+        Start  Length  Slot  Name   Signature
+            0       1     0  index  I
+            0       1     1  text   Ljava/lang/String;
+    MethodParameters:
+      Name                           Flags
+";
+        method_source(61, &(method.to_owned() + method_parameters))
+    }
+
+    #[test]
+    fn parameters_are_named_by_method_parameters() {
+        let strings = Arena::new();
+        let source = parameters_source("      count final\n      <no name>");
+        let class = assemble_class(&source, &strings).unwrap();
+        let parameters = class.get_methods()[0].get_parameters().unwrap();
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(parameters[0].get_name(), Some("count"));
+        assert_eq!(parameters[0].get_access_flags(), ParameterAccessFlag::ACC_FINAL);
+        assert_eq!(parameters[1].get_name(), None);
+        assert_eq!(parameters[1].get_descriptor().to_string(), "Ljava/lang/String;");
+        check_format(&class, FormatCheck::Strict).unwrap();
+    }
+
+    #[test]
+    fn mismatched_method_parameters_fall_back_to_local_variables() {
+        let strings = Arena::new();
+        let class = assemble_class(&parameters_source("      count"), &strings).unwrap();
+        let parameters = class.get_methods()[0].get_parameters().unwrap();
+        let names: Vec<_> = parameters.iter().map(|parameter| parameter.get_name()).collect();
+        assert_eq!(names, [Some("index"), Some("text")]);
+        check_format(&class, FormatCheck::Lenient).unwrap();
+        assert!(check_format(&class, FormatCheck::Strict).is_err());
+    }
+
+    #[test]
+    fn illegal_parameter_names_are_rejected_when_strict() {
+        let strings = Arena::new();
+        let source = parameters_source("      count\n      java.lang");
+        let class = assemble_class(&source, &strings).unwrap();
+        check_format(&class, FormatCheck::Lenient).unwrap();
+        assert!(check_format(&class, FormatCheck::Strict).is_err());
+    }
+
+    #[test]
+    fn renders_method_descriptors_and_java_types() {
+        let descriptor = parse_method_descriptor("(I[Ljava/lang/String;J)V").unwrap();
+        assert_eq!(descriptor.get_parameters().len(), 3);
+        assert_eq!(descriptor.to_string(), "(I[Ljava/lang/String;J)V");
+        assert_eq!(format!("{:#}", descriptor), "void (int, java.lang.String[], long)");
+        let descriptor = parse_method_descriptor("()[[D").unwrap();
+        assert_eq!(format!("{:#}", descriptor), "double[][] ()");
+    }
+
+    #[test]
+    fn malformed_method_descriptors_are_rejected() {
+        for source in &[
+            "", "I", "V", "(", "()", "(I", "(V)V", "()VV", "()Ljava/lang/Object", "(I)V;",
+        ] {
+            assert!(
+                matches!(parse_method_descriptor(source), Err(ClassFormatError(_))),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn checks_combinations_of_access_flags() {
+        let check = |bits, name, is_interface, major_version| {
+            let flags = MethodAccessFlag::from_bits(bits).unwrap();
+            check_access_flags(flags, name, is_interface, major_version)
+        };
+        assert!(check(0x0003, "m", false, 52).is_err());
+        // abstract with final, static or private, and with strictfp only from 46 to 60
+        for &bits in &[0x0410, 0x0408, 0x0402] {
+            assert!(check(bits, "m", false, 52).is_err(), "{:#06x}", bits);
+        }
+        assert!(check(0x0c01, "m", false, 45).is_ok());
+        assert!(check(0x0c01, "m", false, 52).is_err());
+        assert!(check(0x0c01, "m", false, 61).is_ok());
+
+        // Interface methods are public and abstract until default and private methods in 52
+        assert!(check(0x0001, "m", true, 51).is_err());
+        assert!(check(0x0401, "m", true, 51).is_ok());
+        assert!(check(0x0001, "m", true, 52).is_ok());
+        assert!(check(0x0002, "m", true, 52).is_ok());
+        for &bits in &[0x0000, 0x0004, 0x0011, 0x0021, 0x0101] {
+            assert!(check(bits, "m", true, 52).is_err(), "{:#06x}", bits);
+        }
+
+        assert!(check(0x1881, "<init>", false, 52).is_ok());
+        assert!(check(0x0008, "<init>", false, 52).is_err());
+        assert!(check(0x0001, "<init>", true, 52).is_err());
+        // Only the static flag of the class initializer matters, and only from version 51
+        assert!(check(0x0413, "<clinit>", false, 50).is_ok());
+        assert!(check(0x0000, "<clinit>", false, 51).is_err());
+        assert!(check(0x0008, "<clinit>", true, 51).is_ok());
+    }
+
+    #[test]
+    fn illegal_access_flags_are_class_format_errors() {
+        let source = "public abstract class p.A
+  minor version: 0
+  major version: 52
+{
+  public abstract final void m();
+    descriptor: ()V
+    flags: (0x0411) ACC_PUBLIC, ACC_FINAL, ACC_ABSTRACT
+}
+";
+        let strings = Arena::new();
+        match assemble_class(source, &strings) {
+            Err(AssemblyError::ClassLoadingError(ClassFormatError(message))) => {
+                assert!(message.starts_with("Illegal access flags 0x0411 on method"), "{}", message)
+            }
+            other => panic!("{:?}", other.map(|class| class.get_name().to_owned())),
+        }
+        let class = assemble_class(&source.replace("0x0411", "0x0401"), &strings).unwrap();
+        let flags = class.get_methods()[0].get_access_flags();
+        assert_eq!(flags, MethodAccessFlag::ACC_PUBLIC | MethodAccessFlag::ACC_ABSTRACT);
+    }
 }
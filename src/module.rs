@@ -0,0 +1,523 @@
+use attribute::attribute_info;
+use attribute::attribute_info_Data::*;
+use class_file::ClassLoadingError;
+use class_reader::ClassReader;
+use class_writer::ClassWriter;
+use constant_pool::ConstantPool;
+
+#[derive(Debug, Clone)]
+/// Raw dependence of a module (Module_attribute#requires[])
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25>
+pub struct module_requires {
+    requires_index: u16,
+    requires_flags: u16,
+    requires_version_index: u16,
+}
+
+#[derive(Debug, Clone)]
+/// Raw package exported by a module (Module_attribute#exports[])
+pub struct module_exports {
+    exports_index: u16,
+    exports_flags: u16,
+    exports_to_count: u16,
+    exports_to_index: Vec<u16>,
+}
+
+#[derive(Debug, Clone)]
+/// Raw package opened by a module (Module_attribute#opens[])
+pub struct module_opens {
+    opens_index: u16,
+    opens_flags: u16,
+    opens_to_count: u16,
+    opens_to_index: Vec<u16>,
+}
+
+#[derive(Debug, Clone)]
+/// Raw service implementation provided by a module (Module_attribute#provides[])
+pub struct module_provides {
+    provides_index: u16,
+    provides_with_count: u16,
+    provides_with_index: Vec<u16>,
+}
+
+impl module_requires {
+    pub fn new(input: &mut ClassReader) -> Result<module_requires, ClassLoadingError> {
+        let requires_index = input.read_u16()?;
+        let requires_flags = input.read_u16()?;
+        let requires_version_index = input.read_u16()?;
+        Ok(module_requires {
+            requires_index,
+            requires_flags,
+            requires_version_index,
+        })
+    }
+
+    /// constant_pool index of the `CONSTANT_Module_info` of the required module
+    pub fn get_requires_index(&self) -> u16 {
+        self.requires_index
+    }
+
+    pub fn get_requires_flags(&self) -> u16 {
+        self.requires_flags
+    }
+
+    /// 0 if no version of the required module is recorded
+    pub fn get_requires_version_index(&self) -> u16 {
+        self.requires_version_index
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.requires_index);
+        output.write_u16(self.requires_flags);
+        output.write_u16(self.requires_version_index);
+    }
+}
+
+impl module_exports {
+    pub fn new(input: &mut ClassReader) -> Result<module_exports, ClassLoadingError> {
+        let exports_index = input.read_u16()?;
+        let exports_flags = input.read_u16()?;
+        let exports_to_count = input.read_u16()?;
+        let exports_to_index = read_indices(input, exports_to_count)?;
+        Ok(module_exports {
+            exports_index,
+            exports_flags,
+            exports_to_count,
+            exports_to_index,
+        })
+    }
+
+    /// constant_pool index of the `CONSTANT_Package_info` of the exported package
+    pub fn get_exports_index(&self) -> u16 {
+        self.exports_index
+    }
+
+    pub fn get_exports_flags(&self) -> u16 {
+        self.exports_flags
+    }
+
+    /// `CONSTANT_Module_info` indices of the modules the package is exported to, empty if it
+    /// is exported to every module
+    pub fn get_exports_to_index(&self) -> &[u16] {
+        &self.exports_to_index
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.exports_index);
+        output.write_u16(self.exports_flags);
+        output.write_u16_table(&self.exports_to_index);
+    }
+}
+
+impl module_opens {
+    pub fn new(input: &mut ClassReader) -> Result<module_opens, ClassLoadingError> {
+        let opens_index = input.read_u16()?;
+        let opens_flags = input.read_u16()?;
+        let opens_to_count = input.read_u16()?;
+        let opens_to_index = read_indices(input, opens_to_count)?;
+        Ok(module_opens {
+            opens_index,
+            opens_flags,
+            opens_to_count,
+            opens_to_index,
+        })
+    }
+
+    /// constant_pool index of the `CONSTANT_Package_info` of the opened package
+    pub fn get_opens_index(&self) -> u16 {
+        self.opens_index
+    }
+
+    pub fn get_opens_flags(&self) -> u16 {
+        self.opens_flags
+    }
+
+    /// `CONSTANT_Module_info` indices of the modules the package is opened to, empty if it is
+    /// opened to every module
+    pub fn get_opens_to_index(&self) -> &[u16] {
+        &self.opens_to_index
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.opens_index);
+        output.write_u16(self.opens_flags);
+        output.write_u16_table(&self.opens_to_index);
+    }
+}
+
+impl module_provides {
+    pub fn new(input: &mut ClassReader) -> Result<module_provides, ClassLoadingError> {
+        let provides_index = input.read_u16()?;
+        let provides_with_count = input.read_u16()?;
+        let provides_with_index = read_indices(input, provides_with_count)?;
+        Ok(module_provides {
+            provides_index,
+            provides_with_count,
+            provides_with_index,
+        })
+    }
+
+    /// constant_pool index of the `CONSTANT_Class_info` of the service interface
+    pub fn get_provides_index(&self) -> u16 {
+        self.provides_index
+    }
+
+    /// `CONSTANT_Class_info` indices of the implementations of the service
+    pub fn get_provides_with_index(&self) -> &[u16] {
+        &self.provides_with_index
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.provides_index);
+        output.write_u16_table(&self.provides_with_index);
+    }
+}
+
+/// Reads a table of constant_pool indices
+pub fn read_indices(input: &mut ClassReader, length: u16) -> Result<Vec<u16>, ClassLoadingError> {
+    let mut vector = Vec::with_capacity(length as usize);
+    for _ in 0..length {
+        vector.push(input.read_u16()?);
+    }
+    Ok(vector)
+}
+
+bitflags! {
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.25>
+    pub struct ModuleFlag: u16 {
+        const ACC_OPEN        = 0x0020;
+        const ACC_SYNTHETIC   = 0x1000;
+        const ACC_MANDATED    = 0x8000;
+    }
+}
+
+bitflags! {
+    /// Flags of a `requires` directive
+    pub struct RequiresFlag: u16 {
+        const ACC_TRANSITIVE    = 0x0020;
+        const ACC_STATIC_PHASE  = 0x0040;
+        const ACC_SYNTHETIC     = 0x1000;
+        const ACC_MANDATED      = 0x8000;
+    }
+}
+
+bitflags! {
+    /// Flags of an `exports` or `opens` directive
+    pub struct ExportsFlag: u16 {
+        const ACC_SYNTHETIC   = 0x1000;
+        const ACC_MANDATED    = 0x8000;
+    }
+}
+
+#[derive(Debug)]
+/// A module descriptor, resolved from the Module, ModulePackages and ModuleMainClass
+/// attributes of a module-info class
+pub struct ModuleInfo<'a> {
+    name: &'a str,
+    flags: ModuleFlag,
+    version: Option<&'a str>,
+    requires: Vec<ModuleRequires<'a>>,
+    exports: Vec<ModuleExports<'a>>,
+    opens: Vec<ModuleExports<'a>>,
+    uses: Vec<&'a str>,
+    provides: Vec<ModuleProvides<'a>>,
+    packages: Vec<&'a str>,
+    main_class: Option<&'a str>,
+}
+
+#[derive(Debug)]
+/// A module this module depends on
+pub struct ModuleRequires<'a> {
+    name: &'a str,
+    flags: RequiresFlag,
+    version: Option<&'a str>,
+}
+
+#[derive(Debug)]
+/// A package exported or opened by this module, optionally only to specific modules
+pub struct ModuleExports<'a> {
+    package: &'a str,
+    flags: ExportsFlag,
+    to: Vec<&'a str>,
+}
+
+#[derive(Debug)]
+/// A service interface and the classes this module provides as its implementations
+pub struct ModuleProvides<'a> {
+    service: &'a str,
+    with: Vec<&'a str>,
+}
+
+impl<'a> ModuleInfo<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_flags(&self) -> ModuleFlag {
+        self.flags
+    }
+
+    pub fn get_version(&self) -> Option<&'a str> {
+        self.version
+    }
+
+    pub fn get_requires(&self) -> &[ModuleRequires<'a>] {
+        &self.requires
+    }
+
+    pub fn get_exports(&self) -> &[ModuleExports<'a>] {
+        &self.exports
+    }
+
+    pub fn get_opens(&self) -> &[ModuleExports<'a>] {
+        &self.opens
+    }
+
+    /// Names of the service interfaces this module uses
+    pub fn get_uses(&self) -> &[&'a str] {
+        &self.uses
+    }
+
+    pub fn get_provides(&self) -> &[ModuleProvides<'a>] {
+        &self.provides
+    }
+
+    /// Every package of the module, from the ModulePackages attribute
+    pub fn get_packages(&self) -> &[&'a str] {
+        &self.packages
+    }
+
+    /// The main class of the module, from the ModuleMainClass attribute
+    pub fn get_main_class(&self) -> Option<&'a str> {
+        self.main_class
+    }
+}
+
+impl<'a> ModuleRequires<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_flags(&self) -> RequiresFlag {
+        self.flags
+    }
+
+    pub fn get_version(&self) -> Option<&'a str> {
+        self.version
+    }
+}
+
+impl<'a> ModuleExports<'a> {
+    pub fn get_package(&self) -> &'a str {
+        self.package
+    }
+
+    pub fn get_flags(&self) -> ExportsFlag {
+        self.flags
+    }
+
+    /// The modules this package is exported or opened to, empty if unqualified
+    pub fn get_to(&self) -> &[&'a str] {
+        &self.to
+    }
+}
+
+impl<'a> ModuleProvides<'a> {
+    pub fn get_service(&self) -> &'a str {
+        self.service
+    }
+
+    pub fn get_with(&self) -> &[&'a str] {
+        &self.with
+    }
+}
+
+/// Builds the module descriptor from the attributes of a class, if it has a Module attribute
+pub fn read_module<'a>(
+    attributes: &[attribute_info],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Option<ModuleInfo<'a>>, ClassLoadingError> {
+    let mut module = None;
+    let mut packages = Vec::new();
+    let mut main_class = None;
+    for info in attributes {
+        read_module_attribute(info, constant_pool, &mut module, &mut packages, &mut main_class)
+            .map_err(|error| error.at_offset(info.get_offset()))?;
+    }
+    if let Some(module) = module.as_mut() {
+        module.packages = packages;
+        module.main_class = main_class;
+    }
+    Ok(module)
+}
+
+fn read_module_attribute<'a>(
+    info: &attribute_info,
+    constant_pool: &ConstantPool<'a>,
+    module: &mut Option<ModuleInfo<'a>>,
+    packages: &mut Vec<&'a str>,
+    main_class: &mut Option<&'a str>,
+) -> Result<(), ClassLoadingError> {
+    match info.get_data() {
+        Module_attribute {
+            module_name_index,
+            module_flags,
+            module_version_index,
+            requires,
+            exports,
+            opens,
+            uses_index,
+            provides,
+            ..
+        } => {
+            let requires = requires.iter().map(|requires| {
+                Ok(ModuleRequires {
+                    name: constant_pool.module_name(requires.requires_index)?,
+                    flags: RequiresFlag::from_bits_truncate(requires.requires_flags),
+                    version: optional_string(constant_pool, requires.requires_version_index)?,
+                })
+            }).collect::<Result<_, ClassLoadingError>>()?;
+            let exports = exports.iter().map(|exports| {
+                Ok(ModuleExports {
+                    package: constant_pool.package_name(exports.exports_index)?,
+                    flags: ExportsFlag::from_bits_truncate(exports.exports_flags),
+                    to: module_names(constant_pool, &exports.exports_to_index)?,
+                })
+            }).collect::<Result<_, ClassLoadingError>>()?;
+            let opens = opens.iter().map(|opens| {
+                Ok(ModuleExports {
+                    package: constant_pool.package_name(opens.opens_index)?,
+                    flags: ExportsFlag::from_bits_truncate(opens.opens_flags),
+                    to: module_names(constant_pool, &opens.opens_to_index)?,
+                })
+            }).collect::<Result<_, ClassLoadingError>>()?;
+            let uses = uses_index.iter()
+                .map(|index| constant_pool.class_name(*index))
+                .collect::<Result<_, ClassLoadingError>>()?;
+            let provides = provides.iter().map(|provides| {
+                Ok(ModuleProvides {
+                    service: constant_pool.class_name(provides.provides_index)?,
+                    with: provides.provides_with_index.iter()
+                        .map(|index| constant_pool.class_name(*index))
+                        .collect::<Result<_, ClassLoadingError>>()?,
+                })
+            }).collect::<Result<_, ClassLoadingError>>()?;
+            *module = Some(ModuleInfo {
+                name: constant_pool.module_name(*module_name_index)?,
+                flags: ModuleFlag::from_bits_truncate(*module_flags),
+                version: optional_string(constant_pool, *module_version_index)?,
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
+                packages: Vec::new(),
+                main_class: None,
+            });
+        }
+        ModulePackages_attribute { package_index, .. } => {
+            for index in package_index {
+                packages.push(constant_pool.package_name(*index)?);
+            }
+        }
+        ModuleMainClass_attribute { main_class_index } => {
+            *main_class = Some(constant_pool.class_name(*main_class_index)?);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// An index of 0 denotes that no string is present
+fn optional_string<'a>(
+    constant_pool: &ConstantPool<'a>,
+    index: u16,
+) -> Result<Option<&'a str>, ClassLoadingError> {
+    if index == 0 {
+        Ok(None)
+    } else {
+        constant_pool.get_string_entry(index).map(Some)
+    }
+}
+
+fn module_names<'a>(
+    constant_pool: &ConstantPool<'a>,
+    indices: &[u16],
+) -> Result<Vec<&'a str>, ClassLoadingError> {
+    indices.iter().map(|index| constant_pool.module_name(*index)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use class_file::ClassFile;
+    use class_file::ClassLoadingError::ClassFormatError;
+    use module::{ExportsFlag, ModuleFlag, RequiresFlag};
+    use typed_arena::Arena;
+
+    /// Module m.a requires java.base and m.b, exports p/a, opens p/a/internal to m.b and provides
+    /// p/a/Service with p/a/internal/ServiceImpl
+    const MODULE_INFO: &[u8] = include_bytes!("../tests/fixtures/classes/module-info.class");
+
+    #[test]
+    fn module_descriptors_are_resolved() {
+        let strings = Arena::new();
+        let class = ClassFile::new(&mut &MODULE_INFO[..], &strings).unwrap();
+        let module = class.get_module().unwrap();
+        assert_eq!(module.get_name(), "m.a");
+        assert_eq!(module.get_flags(), ModuleFlag::empty());
+        assert_eq!(module.get_version(), Some("1.0"));
+
+        let requires = module.get_requires();
+        assert_eq!(requires.len(), 2);
+        assert_eq!(requires[0].get_name(), "java.base");
+        assert_eq!(requires[0].get_flags(), RequiresFlag::ACC_MANDATED);
+        assert_eq!(requires[0].get_version(), None);
+        assert_eq!(requires[1].get_name(), "m.b");
+        assert_eq!(requires[1].get_flags(), RequiresFlag::ACC_TRANSITIVE);
+        assert_eq!(requires[1].get_version(), Some("1.0"));
+
+        let exports = module.get_exports();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].get_package(), "p/a");
+        assert_eq!(exports[0].get_flags(), ExportsFlag::empty());
+        assert!(exports[0].get_to().is_empty());
+        let opens = module.get_opens();
+        assert_eq!(opens.len(), 1);
+        assert_eq!(opens[0].get_package(), "p/a/internal");
+        assert_eq!(opens[0].get_flags(), ExportsFlag::ACC_SYNTHETIC);
+        assert_eq!(opens[0].get_to(), ["m.b"]);
+
+        assert_eq!(module.get_uses(), ["p/a/Service"]);
+        let provides = module.get_provides();
+        assert_eq!(provides.len(), 1);
+        assert_eq!(provides[0].get_service(), "p/a/Service");
+        assert_eq!(provides[0].get_with(), ["p/a/internal/ServiceImpl"]);
+
+        assert_eq!(module.get_packages(), ["p/a", "p/a/internal"]);
+        assert_eq!(module.get_main_class(), Some("p/a/Main"));
+    }
+
+    #[test]
+    fn classes_without_a_module_attribute_have_no_module() {
+        let bytes = include_bytes!("../tests/fixtures/classes/p/A.class");
+        let strings = Arena::new();
+        let class = ClassFile::new(&mut &bytes[..], &strings).unwrap();
+        assert!(class.get_module().is_none());
+    }
+
+    #[test]
+    fn directives_naming_the_wrong_kind_of_constant_are_rejected() {
+        let mut bytes = MODULE_INFO.to_vec();
+        // Require the package p/a/internal (#10) instead of the module m.b (#12)
+        assert_eq!(bytes[232..238], [0x00, 0x0c, 0x00, 0x20, 0x00, 0x05]);
+        bytes[233] = 0x0a;
+        let strings = Arena::new();
+        match ClassFile::new(&mut &bytes[..], &strings) {
+            Err(ClassFormatError(message)) => {
+                assert!(message.contains("CONSTANT_Module_info"), "{}", message)
+            }
+            other => panic!("{:?}", other.map(|class| class.get_name().to_owned())),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use attribute;
+use attribute::attribute_info;
+use attribute::attribute_info_Data::*;
+use class_file::ClassLoadingError;
+use class_reader::ClassReader;
+use class_writer::ClassWriter;
+use constant_pool::ConstantPool;
+use field;
+use field::FieldDescriptor;
+
+#[derive(Debug, Clone)]
+/// Raw component of a record class (Record_attribute#components[])
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.30>
+pub struct record_component_info<'a> {
+    name_index: u16,
+    descriptor_index: u16,
+    attributes_count: u16,
+    attributes: Vec<attribute_info<'a>>,
+}
+
+impl<'a> record_component_info<'a> {
+    pub fn new(
+        input: &mut ClassReader,
+        constant_pool: &ConstantPool<'a>,
+        major_version: u16,
+    ) -> Result<record_component_info<'a>, ClassLoadingError> {
+        let name_index = input.read_u16()?;
+        let descriptor_index = input.read_u16()?;
+        let attributes_count = input.read_u16()?;
+        let attributes =
+            attribute::read_attributes(input, attributes_count, constant_pool, major_version, None)?;
+        Ok(record_component_info {
+            name_index,
+            descriptor_index,
+            attributes_count,
+            attributes,
+        })
+    }
+
+    pub fn get_name_index(&self) -> u16 {
+        self.name_index
+    }
+
+    pub fn get_descriptor_index(&self) -> u16 {
+        self.descriptor_index
+    }
+
+    pub fn get_attributes(&self) -> &[attribute_info<'a>] {
+        &self.attributes
+    }
+
+    pub fn write(&self, output: &mut ClassWriter) {
+        output.write_u16(self.name_index);
+        output.write_u16(self.descriptor_index);
+        attribute::write_attributes(output, &self.attributes);
+    }
+}
+
+#[derive(Debug)]
+/// A named component of a record class
+pub struct RecordComponent<'a> {
+    name: &'a str,
+    descriptor: FieldDescriptor<'a>,
+    signature: Option<&'a str>,
+    attributes: Vec<attribute_info<'a>>,
+}
+
+impl<'a> RecordComponent<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_descriptor(&self) -> &FieldDescriptor<'a> {
+        &self.descriptor
+    }
+
+    /// The generic signature of the component, from its Signature attribute
+    pub fn get_signature(&self) -> Option<&'a str> {
+        self.signature
+    }
+
+    /// The attributes of the component, such as Signature and its annotations
+    pub fn get_attributes(&self) -> &[attribute_info<'a>] {
+        &self.attributes
+    }
+
+    /// The visible and invisible type annotations on the type of the component
+    pub fn get_type_annotations(&self) -> Vec<&attribute::type_annotation> {
+        attribute::get_type_annotations(&self.attributes)
+    }
+}
+
+/// Resolves the components of a record class from its Record attribute, if it has one
+pub fn read_record<'a>(
+    attributes: &[attribute_info<'a>],
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Option<Vec<RecordComponent<'a>>>, ClassLoadingError> {
+    for info in attributes {
+        if let Record_attribute { components, .. } = info.get_data() {
+            return components.iter()
+                .map(|component| read_component(component, constant_pool))
+                .collect::<Result<Vec<_>, ClassLoadingError>>()
+                .map(Some)
+                .map_err(|error| error.at_offset(info.get_offset()));
+        }
+    }
+    Ok(None)
+}
+
+fn read_component<'a>(
+    component: &record_component_info<'a>,
+    constant_pool: &ConstantPool<'a>,
+) -> Result<RecordComponent<'a>, ClassLoadingError> {
+    let name = constant_pool.get_string_entry(component.name_index)?;
+    let descriptor_str = constant_pool.get_string_entry(component.descriptor_index)?;
+    let descriptor = field::parse_field_descriptor(descriptor_str)?;
+    let mut signature = None;
+    for info in component.attributes.iter() {
+        if let Signature_attribute { signature_index } = info.get_data() {
+            signature = Some(constant_pool.get_string_entry(*signature_index)?);
+            break;
+        }
+    }
+    Ok(RecordComponent {
+        name,
+        descriptor,
+        signature,
+        attributes: component.attributes.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use class::ClassRef::Symbolic;
+    use class_file::ClassFile;
+    use field::FieldDescriptor::{Integer, Reference};
+    use typed_arena::Arena;
+
+    /// Record p.Point with an int component x and a generic List component names
+    const POINT: &[u8] = include_bytes!("../tests/fixtures/classes/p/Point.class");
+
+    #[test]
+    fn record_components_are_resolved() {
+        let strings = Arena::new();
+        let class = ClassFile::new(&mut &POINT[..], &strings).unwrap();
+        assert!(class.is_record());
+        let components = class.get_record_components().unwrap();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0].get_name(), "x");
+        assert!(matches!(components[0].get_descriptor(), Integer));
+        assert_eq!(components[0].get_signature(), None);
+        assert!(components[0].get_attributes().is_empty());
+        assert_eq!(components[1].get_name(), "names");
+        assert!(matches!(
+            components[1].get_descriptor(),
+            Reference(Symbolic("java/util/List"))
+        ));
+        assert_eq!(components[1].get_signature(), Some("Ljava/util/List<Ljava/lang/String;>;"));
+        assert_eq!(components[1].get_attributes().len(), 1);
+    }
+
+    #[test]
+    fn records_need_java_16() {
+        let mut bytes = POINT.to_vec();
+        bytes[6..8].copy_from_slice(&59u16.to_be_bytes());
+        let strings = Arena::new();
+        let class = ClassFile::new(&mut &bytes[..], &strings).unwrap();
+        assert!(!class.is_record());
+        assert!(class.get_record_components().is_none());
+    }
+
+    #[test]
+    fn malformed_component_descriptors_are_rejected() {
+        let mut bytes = POINT.to_vec();
+        // The descriptor of x is the constant "I"
+        assert_eq!(bytes[49..53], [1, 0, 1, b'I']);
+        bytes[52] = b'Q';
+        let strings = Arena::new();
+        assert!(ClassFile::new(&mut &bytes[..], &strings).is_err());
+    }
+}
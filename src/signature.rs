@@ -0,0 +1,693 @@
+use attribute::attribute_info;
+use attribute::attribute_info_Data::Signature_attribute;
+use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+use constant_pool::ConstantPool;
+use signature::JavaTypeSignature::*;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+/// The generic signature of a class, giving its type parameters and the parameterized types it
+/// extends and implements
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.9.1>
+pub struct ClassSignature<'a> {
+    type_parameters: Vec<TypeParameter<'a>>,
+    super_class: ClassTypeSignature<'a>,
+    interfaces: Vec<ClassTypeSignature<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The generic signature of a method or constructor
+pub struct MethodSignature<'a> {
+    type_parameters: Vec<TypeParameter<'a>>,
+    parameters: Vec<JavaTypeSignature<'a>>,
+    return_type: ReturnSignature<'a>,
+    throws: Vec<ThrowsSignature<'a>>,
+}
+
+/// The generic type of a field, record component or local variable
+pub type FieldSignature<'a> = ReferenceTypeSignature<'a>;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A type parameter declared by a generic class or method, eg `T extends Comparable<T>`
+pub struct TypeParameter<'a> {
+    name: &'a str,
+    /// `None` if the only bounds are interfaces
+    class_bound: Option<ReferenceTypeSignature<'a>>,
+    interface_bounds: Vec<ReferenceTypeSignature<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A type that may be generic
+pub enum JavaTypeSignature<'a> {
+    Byte,
+    Character,
+    Double,
+    Float,
+    Integer,
+    Long,
+    Short,
+    Boolean,
+    Reference(ReferenceTypeSignature<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A reference type that may be generic
+pub enum ReferenceTypeSignature<'a> {
+    Class(ClassTypeSignature<'a>),
+    /// The name of a type parameter in scope, eg `T`
+    TypeVariable(&'a str),
+    /// The component type of the array
+    Array(Box<JavaTypeSignature<'a>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A class or interface type with any type arguments, eg `java/util/Map$Entry<TK;TV;>`
+///
+/// Type arguments can be given to each class it is nested within, eg `Outer<TT;>.Inner`
+pub struct ClassTypeSignature<'a> {
+    /// eg `java/util`, empty for the unnamed package
+    package: &'a str,
+    /// The outermost class named, followed by the classes nested within it
+    classes: Vec<SimpleClassTypeSignature<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single class of a `ClassTypeSignature`, without its package
+pub struct SimpleClassTypeSignature<'a> {
+    name: &'a str,
+    type_arguments: Vec<TypeArgument<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A type argument of a parameterized class type
+pub enum TypeArgument<'a> {
+    /// `?`
+    Any,
+    Exact(ReferenceTypeSignature<'a>),
+    /// `? extends`
+    Extends(ReferenceTypeSignature<'a>),
+    /// `? super`
+    Super(ReferenceTypeSignature<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The return type of a method signature
+pub enum ReturnSignature<'a> {
+    Value(JavaTypeSignature<'a>),
+    Void,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An exception a method signature declares it may throw
+pub enum ThrowsSignature<'a> {
+    Class(ClassTypeSignature<'a>),
+    TypeVariable(&'a str),
+}
+
+impl<'a> ClassSignature<'a> {
+    pub fn get_type_parameters(&self) -> &[TypeParameter<'a>] {
+        &self.type_parameters
+    }
+
+    pub fn get_super_class(&self) -> &ClassTypeSignature<'a> {
+        &self.super_class
+    }
+
+    pub fn get_interfaces(&self) -> &[ClassTypeSignature<'a>] {
+        &self.interfaces
+    }
+}
+
+impl<'a> MethodSignature<'a> {
+    pub fn get_type_parameters(&self) -> &[TypeParameter<'a>] {
+        &self.type_parameters
+    }
+
+    pub fn get_parameters(&self) -> &[JavaTypeSignature<'a>] {
+        &self.parameters
+    }
+
+    pub fn get_return_type(&self) -> &ReturnSignature<'a> {
+        &self.return_type
+    }
+
+    pub fn get_throws(&self) -> &[ThrowsSignature<'a>] {
+        &self.throws
+    }
+}
+
+impl<'a> TypeParameter<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_class_bound(&self) -> Option<&ReferenceTypeSignature<'a>> {
+        self.class_bound.as_ref()
+    }
+
+    pub fn get_interface_bounds(&self) -> &[ReferenceTypeSignature<'a>] {
+        &self.interface_bounds
+    }
+}
+
+impl<'a> ClassTypeSignature<'a> {
+    pub fn get_package(&self) -> &'a str {
+        self.package
+    }
+
+    pub fn get_classes(&self) -> &[SimpleClassTypeSignature<'a>] {
+        &self.classes
+    }
+
+    /// The binary name of the class, without its type arguments, eg `java/util/Map$Entry`
+    pub fn get_binary_name(&self) -> String {
+        let names: Vec<&str> = self.classes.iter().map(|class| class.name).collect();
+        if self.package.is_empty() {
+            names.join("$")
+        } else {
+            format!("{}/{}", self.package, names.join("$"))
+        }
+    }
+}
+
+impl<'a> SimpleClassTypeSignature<'a> {
+    pub fn get_name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn get_type_arguments(&self) -> &[TypeArgument<'a>] {
+        &self.type_arguments
+    }
+}
+
+/// Parses the signature of a class
+pub fn parse_class_signature(source: &str) -> Result<ClassSignature<'_>, ClassLoadingError> {
+    let mut parser = SignatureParser { source, position: 0 };
+    let type_parameters = parser.type_parameters()?;
+    let super_class = parser.class_type()?;
+    let mut interfaces = Vec::new();
+    while !parser.is_finished() {
+        interfaces.push(parser.class_type()?);
+    }
+    Ok(ClassSignature {
+        type_parameters,
+        super_class,
+        interfaces,
+    })
+}
+
+/// Parses the signature of a method
+pub fn parse_method_signature(source: &str) -> Result<MethodSignature<'_>, ClassLoadingError> {
+    let mut parser = SignatureParser { source, position: 0 };
+    let type_parameters = parser.type_parameters()?;
+    parser.expect('(')?;
+    let mut parameters = Vec::new();
+    while parser.peek()? != ')' {
+        parameters.push(parser.java_type()?);
+    }
+    parser.expect(')')?;
+    let return_type = if parser.peek()? == 'V' {
+        parser.next()?;
+        ReturnSignature::Void
+    } else {
+        ReturnSignature::Value(parser.java_type()?)
+    };
+    let mut throws = Vec::new();
+    while !parser.is_finished() {
+        parser.expect('^')?;
+        throws.push(match parser.reference_type()? {
+            ReferenceTypeSignature::Class(class) => ThrowsSignature::Class(class),
+            ReferenceTypeSignature::TypeVariable(name) => ThrowsSignature::TypeVariable(name),
+            ReferenceTypeSignature::Array(_) => return Err(parser.illegal_character('[')),
+        });
+    }
+    Ok(MethodSignature {
+        type_parameters,
+        parameters,
+        return_type,
+        throws,
+    })
+}
+
+/// Parses the signature of a field, record component or local variable
+pub fn parse_field_signature(source: &str) -> Result<FieldSignature<'_>, ClassLoadingError> {
+    let mut parser = SignatureParser { source, position: 0 };
+    let field_type = parser.reference_type()?;
+    if !parser.is_finished() {
+        let character = parser.peek()?;
+        return Err(parser.illegal_character(character));
+    }
+    Ok(field_type)
+}
+
+/// Finds the Signature attribute among `attributes` and parses the signature it refers to
+pub fn read_signature<'a, T>(
+    attributes: &[attribute_info],
+    constant_pool: &ConstantPool<'a>,
+    parse: fn(&'a str) -> Result<T, ClassLoadingError>,
+) -> Result<Option<T>, ClassLoadingError> {
+    for info in attributes {
+        if let Signature_attribute { signature_index } = info.decode()? {
+            return constant_pool.get_string_entry(*signature_index)
+                .and_then(parse)
+                .map(Some)
+                .map_err(|error| error.at_offset(info.get_offset()));
+        }
+    }
+    Ok(None)
+}
+
+struct SignatureParser<'a> {
+    source: &'a str,
+    /// Byte offset of the next character
+    position: usize,
+}
+
+impl<'a> SignatureParser<'a> {
+    fn is_finished(&self) -> bool {
+        self.position == self.source.len()
+    }
+
+    fn peek(&self) -> Result<char, ClassLoadingError> {
+        self.source[self.position..].chars().next().ok_or_else(|| {
+            ClassFormatError(format!("Signature ended unexpectedly: {}", self.source))
+        })
+    }
+
+    fn next(&mut self) -> Result<char, ClassLoadingError> {
+        let character = self.peek()?;
+        self.position += character.len_utf8();
+        Ok(character)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ClassLoadingError> {
+        let character = self.peek()?;
+        if character != expected {
+            return Err(self.illegal_character(character));
+        }
+        self.position += character.len_utf8();
+        Ok(())
+    }
+
+    fn illegal_character(&self, character: char) -> ClassLoadingError {
+        ClassFormatError(format!(
+            "Illegal character in signature: {} -> '{}'",
+            self.source, character
+        ))
+    }
+
+    /// An unqualified name, which runs up to the next character that can't be part of one
+    fn identifier(&mut self) -> Result<&'a str, ClassLoadingError> {
+        let start = self.position;
+        let rest = &self.source[start..];
+        let length = rest.find(['.', ';', '[', '/', '<', '>', ':'])
+            .unwrap_or(rest.len());
+        if length == 0 {
+            let character = self.peek()?;
+            return Err(self.illegal_character(character));
+        }
+        self.position += length;
+        Ok(&rest[..length])
+    }
+
+    fn type_parameters(&mut self) -> Result<Vec<TypeParameter<'a>>, ClassLoadingError> {
+        let mut type_parameters = Vec::new();
+        if self.peek()? != '<' {
+            return Ok(type_parameters);
+        }
+        self.next()?;
+        loop {
+            let name = self.identifier()?;
+            self.expect(':')?;
+            let class_bound = match self.peek()? {
+                ':' | '>' => None,
+                _ => Some(self.reference_type()?),
+            };
+            let mut interface_bounds = Vec::new();
+            while self.peek()? == ':' {
+                self.next()?;
+                interface_bounds.push(self.reference_type()?);
+            }
+            type_parameters.push(TypeParameter {
+                name,
+                class_bound,
+                interface_bounds,
+            });
+            if self.peek()? == '>' {
+                self.next()?;
+                return Ok(type_parameters);
+            }
+        }
+    }
+
+    fn java_type(&mut self) -> Result<JavaTypeSignature<'a>, ClassLoadingError> {
+        let base_type = match self.peek()? {
+            'B' => Byte,
+            'C' => Character,
+            'D' => Double,
+            'F' => Float,
+            'I' => Integer,
+            'J' => Long,
+            'S' => Short,
+            'Z' => Boolean,
+            _ => return Ok(Reference(self.reference_type()?)),
+        };
+        self.next()?;
+        Ok(base_type)
+    }
+
+    fn reference_type(&mut self) -> Result<ReferenceTypeSignature<'a>, ClassLoadingError> {
+        match self.peek()? {
+            'L' => Ok(ReferenceTypeSignature::Class(self.class_type()?)),
+            'T' => {
+                self.next()?;
+                let name = self.identifier()?;
+                self.expect(';')?;
+                Ok(ReferenceTypeSignature::TypeVariable(name))
+            }
+            '[' => {
+                self.next()?;
+                Ok(ReferenceTypeSignature::Array(Box::new(self.java_type()?)))
+            }
+            character => Err(self.illegal_character(character)),
+        }
+    }
+
+    fn class_type(&mut self) -> Result<ClassTypeSignature<'a>, ClassLoadingError> {
+        self.expect('L')?;
+        // The package is made up of the identifiers followed by a slash
+        let start = self.position;
+        let mut package_end = start;
+        let mut name = self.identifier()?;
+        while self.peek()? == '/' {
+            self.next()?;
+            package_end = self.position - 1;
+            name = self.identifier()?;
+        }
+        let package = &self.source[start..package_end];
+        let mut classes = vec![SimpleClassTypeSignature {
+            name,
+            type_arguments: self.type_arguments()?,
+        }];
+        while self.peek()? == '.' {
+            self.next()?;
+            classes.push(SimpleClassTypeSignature {
+                name: self.identifier()?,
+                type_arguments: self.type_arguments()?,
+            });
+        }
+        self.expect(';')?;
+        Ok(ClassTypeSignature { package, classes })
+    }
+
+    fn type_arguments(&mut self) -> Result<Vec<TypeArgument<'a>>, ClassLoadingError> {
+        let mut type_arguments = Vec::new();
+        if self.peek()? != '<' {
+            return Ok(type_arguments);
+        }
+        self.next()?;
+        loop {
+            type_arguments.push(match self.peek()? {
+                '*' => {
+                    self.next()?;
+                    TypeArgument::Any
+                }
+                '+' => {
+                    self.next()?;
+                    TypeArgument::Extends(self.reference_type()?)
+                }
+                '-' => {
+                    self.next()?;
+                    TypeArgument::Super(self.reference_type()?)
+                }
+                _ => TypeArgument::Exact(self.reference_type()?),
+            });
+            if self.peek()? == '>' {
+                self.next()?;
+                return Ok(type_arguments);
+            }
+        }
+    }
+}
+
+/// Writes items separated by commas, as in Java source
+fn write_list<T: fmt::Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl<'a> fmt::Display for TypeParameter<'a> {
+    /// Writes the type parameter in Java syntax, eg `T extends java.lang.Comparable<T>`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        let mut separator = " extends ";
+        for bound in self.class_bound.iter().chain(self.interface_bounds.iter()) {
+            write!(f, "{}{}", separator, bound)?;
+            separator = " & ";
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for JavaTypeSignature<'a> {
+    /// Writes the type in Java syntax, eg `java.util.List<? extends java.lang.Number>[]`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Byte => write!(f, "byte"),
+            Character => write!(f, "char"),
+            Double => write!(f, "double"),
+            Float => write!(f, "float"),
+            Integer => write!(f, "int"),
+            Long => write!(f, "long"),
+            Short => write!(f, "short"),
+            Boolean => write!(f, "boolean"),
+            Reference(reference) => write!(f, "{}", reference),
+        }
+    }
+}
+
+impl<'a> fmt::Display for ReferenceTypeSignature<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReferenceTypeSignature::Class(class) => write!(f, "{}", class),
+            ReferenceTypeSignature::TypeVariable(name) => write!(f, "{}", name),
+            ReferenceTypeSignature::Array(component) => write!(f, "{}[]", component),
+        }
+    }
+}
+
+impl<'a> fmt::Display for ClassTypeSignature<'a> {
+    /// Writes the class in Java syntax, with nested classes that have type arguments of their
+    /// own separated by dots, eg `java.util.Map$Entry<K, V>` or `p.Outer<T>.Inner`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.package.is_empty() {
+            write!(f, "{}.", self.package.replace('/', "."))?;
+        }
+        for (index, class) in self.classes.iter().enumerate() {
+            if index > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", class.name)?;
+            if !class.type_arguments.is_empty() {
+                write!(f, "<")?;
+                write_list(f, &class.type_arguments)?;
+                write!(f, ">")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for TypeArgument<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeArgument::Any => write!(f, "?"),
+            TypeArgument::Exact(reference) => write!(f, "{}", reference),
+            TypeArgument::Extends(reference) => write!(f, "? extends {}", reference),
+            TypeArgument::Super(reference) => write!(f, "? super {}", reference),
+        }
+    }
+}
+
+impl<'a> fmt::Display for ReturnSignature<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReturnSignature::Value(value) => write!(f, "{}", value),
+            ReturnSignature::Void => write!(f, "void"),
+        }
+    }
+}
+
+impl<'a> fmt::Display for ThrowsSignature<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThrowsSignature::Class(class) => write!(f, "{}", class),
+            ThrowsSignature::TypeVariable(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl<'a> fmt::Display for MethodSignature<'a> {
+    /// Writes the method in Java syntax, without its name, eg
+    /// `<T extends java.lang.Object> T (java.util.List<T>) throws java.io.IOException`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.type_parameters.is_empty() {
+            write!(f, "<")?;
+            write_list(f, &self.type_parameters)?;
+            write!(f, "> ")?;
+        }
+        write!(f, "{} (", self.return_type)?;
+        write_list(f, &self.parameters)?;
+        write!(f, ")")?;
+        if !self.throws.is_empty() {
+            write!(f, " throws ")?;
+            write_list(f, &self.throws)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for ClassSignature<'a> {
+    /// Writes the type parameters and supertypes of the class in Java syntax, eg
+    /// `<T extends java.lang.Object> extends java.lang.Object implements java.lang.Comparable<T>`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.type_parameters.is_empty() {
+            write!(f, "<")?;
+            write_list(f, &self.type_parameters)?;
+            write!(f, "> ")?;
+        }
+        write!(f, "extends {}", self.super_class)?;
+        if !self.interfaces.is_empty() {
+            write!(f, " implements ")?;
+            write_list(f, &self.interfaces)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_class_signature, parse_field_signature, parse_method_signature,
+        ReferenceTypeSignature, TypeArgument,
+    };
+    use assembler::assemble_class;
+    use class_file::ClassLoadingError::ClassFormatError;
+    use typed_arena::Arena;
+
+    #[test]
+    fn parses_class_signatures() {
+        let signature = parse_class_signature(
+            "<K:Ljava/lang/Object;V::Ljava/lang/Comparable<TV;>;:Ljava/io/Serializable;>\
+             Ljava/util/AbstractMap<TK;TV;>;Ljava/util/Map<TK;TV;>;",
+        ).unwrap();
+        let type_parameters = signature.get_type_parameters();
+        assert_eq!(type_parameters.len(), 2);
+        assert!(type_parameters[1].get_class_bound().is_none());
+        assert_eq!(type_parameters[1].get_interface_bounds().len(), 2);
+        assert_eq!(signature.get_super_class().get_binary_name(), "java/util/AbstractMap");
+        assert_eq!(
+            signature.to_string(),
+            "<K extends java.lang.Object, \
+             V extends java.lang.Comparable<V> & java.io.Serializable> \
+             extends java.util.AbstractMap<K, V> implements java.util.Map<K, V>"
+        );
+    }
+
+    #[test]
+    fn parses_method_signatures() {
+        let signature = parse_method_signature(
+            "<T:Ljava/lang/Object;X:Ljava/lang/Exception;>\
+             ([TT;Ljava/util/List<+Ljava/lang/Number;>;ILjava/util/Map<*-TT;>;)[[TT;\
+             ^TX;^Ljava/io/IOException;",
+        ).unwrap();
+        assert_eq!(signature.get_parameters().len(), 4);
+        assert_eq!(signature.get_throws().len(), 2);
+        assert_eq!(
+            signature.to_string(),
+            "<T extends java.lang.Object, X extends java.lang.Exception> T[][] (T[], \
+             java.util.List<? extends java.lang.Number>, int, java.util.Map<?, ? super T>) \
+             throws X, java.io.IOException"
+        );
+        let signature = parse_method_signature("()V").unwrap();
+        assert_eq!(signature.to_string(), "void ()");
+    }
+
+    #[test]
+    fn parses_type_arguments_of_nested_classes() {
+        let signature = parse_field_signature("Lp/Outer<TT;>.Inner$Deep<[I>;").unwrap();
+        let class = match &signature {
+            ReferenceTypeSignature::Class(class) => class,
+            signature => panic!("Expected a class type, found {:?}", signature),
+        };
+        assert_eq!(class.get_package(), "p");
+        assert_eq!(class.get_binary_name(), "p/Outer$Inner$Deep");
+        let type_arguments = class.get_classes()[1].get_type_arguments();
+        assert!(matches!(type_arguments, [TypeArgument::Exact(ReferenceTypeSignature::Array(_))]));
+        assert_eq!(signature.to_string(), "p.Outer<T>.Inner$Deep<int[]>");
+        assert_eq!(parse_field_signature("LTop;").unwrap().to_string(), "Top");
+    }
+
+    #[test]
+    fn signatures_are_read_from_classes_and_their_members() {
+        let strings = Arena::new();
+        let class = assemble_class("public abstract class p.A
+  minor version: 0
+  major version: 52
+{
+  java.util.List values;
+    descriptor: Ljava/util/List;
+    Signature: Ljava/util/List<TT;>;
+
+  public abstract java.lang.Object get(int);
+    descriptor: (I)Ljava/lang/Object;
+    Signature: (I)TT;
+
+  public abstract void clear();
+    descriptor: ()V
+}
+Signature: <T:Ljava/lang/Object;>Ljava/lang/Object;
+", &strings).unwrap();
+        let signature = class.get_signature().unwrap().unwrap();
+        assert_eq!(signature.to_string(), "<T extends java.lang.Object> extends java.lang.Object");
+        let signature = class.get_fields()[0].get_signature().unwrap().unwrap();
+        assert_eq!(signature.to_string(), "java.util.List<T>");
+        let methods = class.get_methods();
+        assert_eq!(methods[0].get_signature().unwrap().unwrap().to_string(), "T (int)");
+        assert!(methods[1].get_signature().unwrap().is_none());
+    }
+
+    #[test]
+    fn malformed_signatures_are_rejected() {
+        for source in &[
+            "", "I", "TT", "Ljava/util/List<>;", "Ljava/util/List<I>;", "LA;LB;", "[V",
+        ] {
+            assert!(
+                matches!(parse_field_signature(source), Err(ClassFormatError(_))),
+                "{:?}",
+                source
+            );
+        }
+        for source in &["", "()", "(V)V", "()V^[I", "<>()V", "<T>()V", "()VLA;"] {
+            assert!(
+                matches!(parse_method_signature(source), Err(ClassFormatError(_))),
+                "{:?}",
+                source
+            );
+        }
+        for source in &["", "<:LA;>LA;", "LA;I"] {
+            assert!(
+                matches!(parse_class_signature(source), Err(ClassFormatError(_))),
+                "{:?}",
+                source
+            );
+        }
+    }
+}
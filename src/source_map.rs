@@ -0,0 +1,440 @@
+use attribute::attribute_info;
+use attribute::attribute_info_Data::SourceDebugExtension;
+use cesu8::from_java_cesu8;
+use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+
+#[derive(Debug, Clone)]
+/// A resolved SMAP, which maps the lines of the class's Java stratum back to lines of the
+/// sources it was generated from, such as a JSP page or the Kotlin files of inlined functions
+///
+/// <https://jcp.org/en/jsr/detail?id=45>
+pub struct SourceMap {
+    output_file: String,
+    default_stratum: String,
+    strata: Vec<Stratum>,
+}
+
+#[derive(Debug, Clone)]
+/// One view of the source of a class, eg `Kotlin` or `JSP`, with its own files and lines
+pub struct Stratum {
+    id: String,
+    files: Vec<SourceFile>,
+    lines: Vec<LineMapping>,
+}
+
+#[derive(Debug, Clone)]
+/// An entry of a stratum's file section
+pub struct SourceFile {
+    id: u32,
+    name: String,
+    path: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+/// An entry of a stratum's line section
+///
+/// Maps `repeat_count` lines of a source file, starting at `input_start_line`, to
+/// `output_line_increment` lines each of the Java stratum, starting at `output_start_line`
+pub struct LineMapping {
+    input_start_line: u32,
+    file_id: u32,
+    repeat_count: u32,
+    output_start_line: u32,
+    output_line_increment: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A line of a source file
+pub struct SourceLocation {
+    file_name: String,
+    file_path: Option<String>,
+    line: u32,
+}
+
+impl SourceMap {
+    /// The name of the file the class was generated into, eg `Foo.java` for a JSP page
+    pub fn get_output_file(&self) -> &str {
+        &self.output_file
+    }
+
+    /// The id of the stratum a debugger shows unless asked for another one
+    pub fn get_default_stratum(&self) -> &str {
+        &self.default_stratum
+    }
+
+    pub fn get_strata(&self) -> &[Stratum] {
+        &self.strata
+    }
+
+    pub fn get_stratum(&self, id: &str) -> Option<&Stratum> {
+        self.strata.iter().find(|stratum| stratum.id == id)
+    }
+}
+
+impl Stratum {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    pub fn get_lines(&self) -> &[LineMapping] {
+        &self.lines
+    }
+
+    pub fn get_file(&self, id: u32) -> Option<&SourceFile> {
+        self.files.iter().find(|file| file.id == id)
+    }
+
+    /// The source line that a line of the Java stratum was generated from, using the first line
+    /// mapping that covers it
+    pub fn map_line(&self, line: u32) -> Option<SourceLocation> {
+        self.lines.iter().filter_map(|mapping| {
+            let input_line = mapping.map_line(line)?;
+            let file = self.get_file(mapping.file_id)?;
+            Some(SourceLocation {
+                file_name: file.name.clone(),
+                file_path: file.path.clone(),
+                line: input_line,
+            })
+        }).next()
+    }
+}
+
+impl SourceFile {
+    /// The id the file is referred to by in the line section
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path of the file relative to the source path, if the SMAP gives it
+    pub fn get_path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+}
+
+impl LineMapping {
+    pub fn get_input_start_line(&self) -> u32 {
+        self.input_start_line
+    }
+
+    pub fn get_file_id(&self) -> u32 {
+        self.file_id
+    }
+
+    pub fn get_repeat_count(&self) -> u32 {
+        self.repeat_count
+    }
+
+    pub fn get_output_start_line(&self) -> u32 {
+        self.output_start_line
+    }
+
+    pub fn get_output_line_increment(&self) -> u32 {
+        self.output_line_increment
+    }
+
+    /// The input line that an output line falls within, if it is in the range of the mapping
+    ///
+    /// A range whose input lines run past `u32::MAX` doesn't map the lines beyond it
+    pub fn map_line(&self, line: u32) -> Option<u32> {
+        // An increment of 0 maps the input lines to no output lines at all
+        if self.output_line_increment == 0 || line < self.output_start_line {
+            return None;
+        }
+        let index = (line - self.output_start_line) / self.output_line_increment;
+        if index < self.repeat_count {
+            self.input_start_line.checked_add(index)
+        } else {
+            None
+        }
+    }
+}
+
+impl SourceLocation {
+    pub fn new(file_name: String, file_path: Option<String>, line: u32) -> SourceLocation {
+        SourceLocation { file_name, file_path, line }
+    }
+
+    pub fn get_file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    pub fn get_file_path(&self) -> Option<&str> {
+        self.file_path.as_deref()
+    }
+
+    pub fn get_line(&self) -> u32 {
+        self.line
+    }
+}
+
+/// Parses the SMAP in a class's `SourceDebugExtension`, if it has one
+///
+/// The attribute isn't required to hold an SMAP, so one that doesn't start with the `SMAP`
+/// header is ignored
+pub fn read_source_map(
+    attributes: &[attribute_info],
+) -> Result<Option<SourceMap>, ClassLoadingError> {
+    for info in attributes {
+        if let SourceDebugExtension { debug_extension } = info.get_data() {
+            let source = from_java_cesu8(debug_extension)
+                .map_err(|error| ClassLoadingError::from(error).at_offset(info.get_offset()))?;
+            if !source.starts_with("SMAP") {
+                return Ok(None);
+            }
+            return parse_source_map(&source)
+                .map(Some)
+                .map_err(|error| error.at_offset(info.get_offset()));
+        }
+    }
+    Ok(None)
+}
+
+/// Parses a resolved SMAP
+///
+/// Embedded SMAPs are resolved by the compiler before it writes the class, so they are
+/// rejected here. Kotlin ends each of its strata with `*E` rather than only the last one, so
+/// sections after an end section are read as well.
+pub fn parse_source_map(source: &str) -> Result<SourceMap, ClassLoadingError> {
+    // Lines may end with CR, LF or CR LF
+    let mut lines = source.split(['\r', '\n'])
+        .filter(|line| !line.is_empty())
+        .peekable();
+    if lines.next() != Some("SMAP") {
+        return Err(ClassFormatError(String::from("SMAP doesn't start with its header")));
+    }
+    let output_file = lines.next()
+        .ok_or_else(|| ClassFormatError(String::from("SMAP is missing its output file")))?;
+    let default_stratum = lines.next()
+        .ok_or_else(|| ClassFormatError(String::from("SMAP is missing its default stratum")))?;
+    let mut strata: Vec<Stratum> = Vec::new();
+    while let Some(header) = lines.next() {
+        if !header.starts_with('*') {
+            return Err(ClassFormatError(format!("Expected an SMAP section, found {:?}", header)));
+        }
+        let (kind, argument) = match header.find(' ') {
+            Some(index) => (&header[..index], header[index + 1..].trim()),
+            None => (header, ""),
+        };
+        match kind {
+            "*S" => {
+                if argument.is_empty() {
+                    return Err(ClassFormatError(String::from("SMAP stratum has no id")));
+                }
+                strata.push(Stratum {
+                    id: String::from(argument),
+                    files: Vec::new(),
+                    lines: Vec::new(),
+                });
+                continue;
+            }
+            "*O" | "*C" => {
+                return Err(ClassFormatError(String::from(
+                    "SMAP has an embedded SMAP that wasn't resolved",
+                )));
+            }
+            "*F" | "*L" => {}
+            // Vendor sections, and any sections added by later versions, are skipped
+            _ => {
+                while lines.peek().is_some_and(|line| !line.starts_with('*')) {
+                    lines.next();
+                }
+                continue;
+            }
+        }
+        let stratum = strata.last_mut().ok_or_else(|| {
+            ClassFormatError(format!("SMAP section {} is outside of a stratum", kind))
+        })?;
+        if kind == "*F" {
+            while lines.peek().is_some_and(|line| !line.starts_with('*')) {
+                let line = lines.next().unwrap();
+                let has_path = line.starts_with('+');
+                let (id, name) = parse_file_info(line.trim_start_matches('+').trim())?;
+                let path = if has_path {
+                    let path = lines.next().ok_or_else(|| {
+                        ClassFormatError(format!("SMAP is missing the path of {}", name))
+                    })?;
+                    Some(String::from(path))
+                } else {
+                    None
+                };
+                stratum.files.push(SourceFile { id, name: String::from(name), path });
+            }
+        } else {
+            // A line that omits its file id is in the same file as the line before it
+            let mut file_id = 0;
+            while lines.peek().is_some_and(|line| !line.starts_with('*')) {
+                let mapping = parse_line_info(lines.next().unwrap(), file_id)?;
+                file_id = mapping.file_id;
+                stratum.lines.push(mapping);
+            }
+        }
+    }
+    Ok(SourceMap {
+        output_file: String::from(output_file),
+        default_stratum: String::from(default_stratum),
+        strata,
+    })
+}
+
+/// Parses `FileID FileName` from the file section
+fn parse_file_info(line: &str) -> Result<(u32, &str), ClassLoadingError> {
+    let error = || ClassFormatError(format!("Bad SMAP file info: {:?}", line));
+    let index = line.find(' ').ok_or_else(error)?;
+    let id = line[..index].parse().map_err(|_| error())?;
+    let name = line[index + 1..].trim();
+    if name.is_empty() {
+        return Err(error());
+    }
+    Ok((id, name))
+}
+
+/// Parses `InputStartLine[#LineFileID][,RepeatCount]:OutputStartLine[,OutputLineIncrement]`
+/// from the line section
+fn parse_line_info(line: &str, file_id: u32) -> Result<LineMapping, ClassLoadingError> {
+    let error = || ClassFormatError(format!("Bad SMAP line info: {:?}", line));
+    let number = |text: &str| text.trim().parse::<u32>().map_err(|_| error());
+    let index = line.find(':').ok_or_else(error)?;
+    let (input, output) = (&line[..index], &line[index + 1..]);
+    let (input, repeat_count) = match input.find(',') {
+        Some(index) => (&input[..index], number(&input[index + 1..])?),
+        None => (input, 1),
+    };
+    let (input_start_line, file_id) = match input.find('#') {
+        Some(index) => (number(&input[..index])?, number(&input[index + 1..])?),
+        None => (number(input)?, file_id),
+    };
+    let (output_start_line, output_line_increment) = match output.find(',') {
+        Some(index) => (number(&output[..index])?, number(&output[index + 1..])?),
+        None => (number(output)?, 1),
+    };
+    Ok(LineMapping {
+        input_start_line,
+        file_id,
+        repeat_count,
+        output_start_line,
+        output_line_increment,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_source_map, LineMapping, SourceLocation};
+    use assembler::assemble_class;
+    use class_file::ClassLoadingError::ClassFormatError;
+    use typed_arena::Arena;
+
+    const SMAP: &str = concat!(
+        "SMAP\r\nA.kt\r\nKotlin\r\n",
+        "*S Kotlin\r\n*F\r\n+ 1 A.kt\r\np/A.kt\r\n2 B.kt\r\n",
+        "*L\r\n1#1,5:1\r\n10#2,2:6,2\r\n20:10\r\n*E\r\n",
+        "*S KotlinDebug\r\n*F\r\n+ 1 A.kt\r\np/A.kt\r\n*L\r\n1#1,3:1\r\n*E\r\n",
+    );
+
+    fn location(file_name: &str, file_path: Option<&str>, line: u32) -> Option<SourceLocation> {
+        Some(SourceLocation::new(String::from(file_name), file_path.map(String::from), line))
+    }
+
+    #[test]
+    fn maps_lines_to_their_source_files() {
+        let source_map = parse_source_map(SMAP).unwrap();
+        assert_eq!(source_map.get_output_file(), "A.kt");
+        assert_eq!(source_map.get_default_stratum(), "Kotlin");
+        let ids: Vec<_> = source_map.get_strata().iter().map(|stratum| stratum.get_id()).collect();
+        assert_eq!(ids, ["Kotlin", "KotlinDebug"]);
+        let kotlin = source_map.get_stratum("Kotlin").unwrap();
+        assert_eq!(kotlin.get_file(1).unwrap().get_path(), Some("p/A.kt"));
+        assert_eq!(kotlin.get_file(2).unwrap().get_path(), None);
+        assert_eq!(kotlin.map_line(5), location("A.kt", Some("p/A.kt"), 5));
+        assert_eq!(kotlin.map_line(7), location("B.kt", None, 10));
+        assert_eq!(kotlin.map_line(9), location("B.kt", None, 11));
+        // The line without a file id stays in the file of the line before it
+        assert_eq!(kotlin.map_line(10), location("B.kt", None, 20));
+        assert_eq!(kotlin.map_line(11), None);
+    }
+
+    #[test]
+    fn lines_outside_of_a_mapping_are_unmapped() {
+        let mapping = |input_start_line, repeat_count, output_line_increment| LineMapping {
+            input_start_line,
+            file_id: 1,
+            repeat_count,
+            output_start_line: 10,
+            output_line_increment,
+        };
+        assert_eq!(mapping(1, 2, 1).map_line(9), None);
+        assert_eq!(mapping(1, 2, 1).map_line(11), Some(2));
+        assert_eq!(mapping(1, 2, 1).map_line(12), None);
+        assert_eq!(mapping(1, 2, 0).map_line(10), None);
+        assert_eq!(mapping(u32::MAX, 2, 1).map_line(10), Some(u32::MAX));
+        assert_eq!(mapping(u32::MAX, 2, 1).map_line(11), None);
+        assert_eq!(mapping(1, u32::MAX, 1).map_line(u32::MAX), Some(u32::MAX - 9));
+    }
+
+    #[test]
+    fn unresolved_and_malformed_smaps_are_rejected() {
+        for source in &[
+            "SMAP\nA.kt\nKotlin\n*O Kotlin\n*C Kotlin\n*E\n",
+            "SMAP\nA.kt\nKotlin\n*L\n1:1\n*E\n",
+            "SMAP\nA.kt\nKotlin\n*S Kotlin\n*L\n1,x:1\n*E\n",
+            "SMAP\nA.kt\nKotlin\n*S Kotlin\n*F\n+ 1 A.kt\n",
+            "SMAP\nA.kt\n",
+        ] {
+            assert!(matches!(parse_source_map(source), Err(ClassFormatError(_))), "{}", source);
+        }
+    }
+
+    #[test]
+    fn vendor_sections_are_skipped() {
+        let source = "SMAP\nA.kt\nKotlin\n*S Kotlin\n*V\nvendor data\n*F\n1 A.kt\n*L\n1#1:1\n*E\n";
+        let source_map = parse_source_map(source).unwrap();
+        let kotlin = source_map.get_stratum("Kotlin").unwrap();
+        assert_eq!(kotlin.map_line(1), location("A.kt", None, 1));
+    }
+
+    #[test]
+    fn source_locations_use_the_default_stratum() {
+        let source = |default_stratum| format!(
+            "public class p.A
+  minor version: 0
+  major version: 61
+{{
+  public static void m();
+    descriptor: ()V
+    Code:
+      stack=0, locals=0, args_size=0
+        0: return
+      LineNumberTable:
+        line 7: 0
+}}
+SourceFile: \"A.kt\"
+SourceDebugExtension:
+  SMAP
+  A.kt
+  {}
+  *S Kotlin
+  *F
+  1 B.kt
+  *L
+  1#1,10:1
+  *E
+",
+            default_stratum
+        );
+        let strings = Arena::new();
+        let class = assemble_class(&source("Kotlin"), &strings).unwrap();
+        let method = &class.get_methods()[0];
+        assert_eq!(class.get_source_location(method, 0).unwrap(), location("B.kt", None, 7));
+        let class = assemble_class(&source("Java"), &strings).unwrap();
+        let method = &class.get_methods()[0];
+        assert_eq!(class.get_source_location(method, 0).unwrap(), location("A.kt", None, 7));
+    }
+}
@@ -0,0 +1,352 @@
+use attribute::attribute_info;
+use attribute::attribute_info_Data::StackMapTable_attribute;
+use attribute::stack_map_frame_data::*;
+use attribute::verification_type_info;
+use attribute::verification_type_info_data::*;
+use class::ClassRef;
+use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::ClassFormatError;
+use constant_pool::ConstantPool;
+use field::FieldDescriptor;
+use method;
+use stack_map::VerificationType::*;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The type of a local variable or operand stack entry, as the verifier sees it
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1.2>
+pub enum VerificationType<'a> {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    /// `this` in an instance initializer, before the superclass's initializer has been called
+    UninitializedThis,
+    /// An instance of the class or array type, named as in a `CONSTANT_Class_info`
+    Object(&'a str),
+    /// An object created by the `new` instruction at this pc that hasn't been initialized yet
+    Uninitialized(u16),
+    /// The address a subroutine starting at this pc returns to, which only the type inference
+    /// verifier uses, as `jsr` and `ret` aren't allowed with a StackMapTable
+    ReturnAddress(u16),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The types of the local variables and operand stack at a pc
+///
+/// Locals are indexed by slot, so a long or double is followed by `Top` for its second slot, and
+/// any local past the end of `locals` is `Top` as well. The operand stack has one entry per
+/// value, from the bottom up.
+pub struct StackMapFrame<'a> {
+    pc: u32,
+    locals: Vec<VerificationType<'a>>,
+    stack: Vec<VerificationType<'a>>,
+}
+
+impl<'a> VerificationType<'a> {
+    /// Resolves the class named by an `Object_variable_info`
+    pub fn new(
+        info: &verification_type_info,
+        constant_pool: &ConstantPool<'a>,
+    ) -> Result<VerificationType<'a>, ClassLoadingError> {
+        Ok(match info.get_data() {
+            Top_variable_info => Top,
+            Integer_variable_info => Integer,
+            Float_variable_info => Float,
+            Long_variable_info => Long,
+            Double_variable_info => Double,
+            Null_variable_info => Null,
+            UninitializedThis_variable_info => UninitializedThis,
+            Object_variable_info { cpool_index } => {
+                Object(constant_pool.class_name(*cpool_index)?)
+            }
+            Uninitialized_variable_info { offset } => Uninitialized(*offset),
+        })
+    }
+
+    /// The type a value of a field type has on the operand stack or in a local
+    ///
+    /// `boolean`, `byte`, `char` and `short` are all `Integer`. Descriptors that are parsed from
+    /// the constant pool always name their classes, a resolved class is given as `Top`.
+    pub fn from_descriptor(descriptor: &FieldDescriptor<'a>) -> VerificationType<'a> {
+        match descriptor {
+            FieldDescriptor::Float => Float,
+            FieldDescriptor::Long => Long,
+            FieldDescriptor::Double => Double,
+            FieldDescriptor::Reference(ClassRef::Symbolic(name)) => Object(name),
+            FieldDescriptor::Reference(ClassRef::Static(_)) => Top,
+            _ => Integer,
+        }
+    }
+
+    /// The number of local variable slots, or operand stack words, the type takes up
+    pub fn get_size(&self) -> u16 {
+        match self {
+            Long | Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl<'a> fmt::Display for VerificationType<'a> {
+    /// Writes the type the way javap prints the entries of a StackMapTable, eg `int` or
+    /// `class java/lang/String`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Top => write!(f, "top"),
+            Integer => write!(f, "int"),
+            Float => write!(f, "float"),
+            Long => write!(f, "long"),
+            Double => write!(f, "double"),
+            Null => write!(f, "null"),
+            UninitializedThis => write!(f, "this"),
+            Object(name) if name.starts_with('[') => write!(f, "class \"{}\"", name),
+            Object(name) => write!(f, "class {}", name),
+            Uninitialized(offset) => write!(f, "uninitialized {}", offset),
+            ReturnAddress(_) => write!(f, "returnAddress"),
+        }
+    }
+}
+
+impl<'a> StackMapFrame<'a> {
+    /// The pc of the instruction the frame describes
+    pub fn get_pc(&self) -> u32 {
+        self.pc
+    }
+
+    pub fn get_locals(&self) -> &[VerificationType<'a>] {
+        &self.locals
+    }
+
+    pub fn get_stack(&self) -> &[VerificationType<'a>] {
+        &self.stack
+    }
+}
+
+/// The frame at the start of a method, with `this` followed by the parameters in its locals and
+/// an empty operand stack
+///
+/// `this` is `UninitializedThis` in an instance initializer, unless the class is
+/// `java/lang/Object`, which has no superclass initializer to call
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1.6>
+pub fn initial_frame<'a>(
+    class_name: &'a str,
+    method_name: &str,
+    is_static: bool,
+    descriptor: &'a str,
+) -> Result<StackMapFrame<'a>, ClassLoadingError> {
+    let descriptor = method::parse_method_descriptor(descriptor)?;
+    let mut locals = Vec::new();
+    if !is_static {
+        if method_name == "<init>" && class_name != "java/lang/Object" {
+            locals.push(UninitializedThis);
+        } else {
+            locals.push(Object(class_name));
+        }
+    }
+    locals.extend(descriptor.get_parameters().iter().map(VerificationType::from_descriptor));
+    Ok(StackMapFrame {
+        pc: 0,
+        locals: expand(&locals),
+        stack: Vec::new(),
+    })
+}
+
+/// Expands the StackMapTable among the attributes of a Code attribute into the frame at each pc
+/// it gives one for, starting from the initial frame of the method
+///
+/// Empty if there is no StackMapTable
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.4>
+pub fn read_stack_map_frames<'a>(
+    code_attributes: &[attribute_info],
+    initial_frame: &StackMapFrame<'a>,
+    constant_pool: &ConstantPool<'a>,
+) -> Result<Vec<StackMapFrame<'a>>, ClassLoadingError> {
+    let table = code_attributes.iter().filter_map(|info| {
+        if let StackMapTable_attribute { entries, .. } = info.get_data() {
+            Some((entries, info.get_offset()))
+        } else {
+            None
+        }
+    }).next();
+    let (entries, offset) = match table {
+        Some(table) => table,
+        None => return Ok(Vec::new()),
+    };
+    let resolve = |info: &verification_type_info| {
+        VerificationType::new(info, constant_pool).map_err(|error| error.at_offset(offset))
+    };
+    // The frames give each long or double local once, rather than once for each slot
+    let mut locals = compact(&initial_frame.locals);
+    let mut frames: Vec<StackMapFrame<'a>> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let frame_type = entry.get_frame_type();
+        let (offset_delta, stack) = match entry.get_frame_data() {
+            same_frame => (u16::from(frame_type), Vec::new()),
+            same_locals_1_stack_item_frame { stack } => {
+                (u16::from(frame_type) - 64, vec![resolve(stack)?])
+            }
+            same_locals_1_stack_item_frame_extended { offset_delta, stack } => {
+                (*offset_delta, vec![resolve(stack)?])
+            }
+            chop_frame { offset_delta } => {
+                let chopped = usize::from(251 - frame_type);
+                if chopped > locals.len() {
+                    return Err(ClassFormatError(format!(
+                        "chop_frame removes {} locals from a frame with {}",
+                        chopped,
+                        locals.len()
+                    )).at_offset(offset));
+                }
+                let length = locals.len() - chopped;
+                locals.truncate(length);
+                (*offset_delta, Vec::new())
+            }
+            same_frame_extended { offset_delta } => (*offset_delta, Vec::new()),
+            append_frame { offset_delta, locals: appended } => {
+                for local in appended {
+                    locals.push(resolve(local)?);
+                }
+                (*offset_delta, Vec::new())
+            }
+            full_frame { offset_delta, locals: full_locals, stack, .. } => {
+                locals = full_locals.iter().map(resolve).collect::<Result<_, _>>()?;
+                (*offset_delta, stack.iter().map(resolve).collect::<Result<_, _>>()?)
+            }
+        };
+        // Every frame after the first is at least one instruction after the one before it
+        let pc = match frames.last() {
+            Some(previous) => previous.pc + u32::from(offset_delta) + 1,
+            None => u32::from(offset_delta),
+        };
+        frames.push(StackMapFrame {
+            pc,
+            locals: expand(&locals),
+            stack,
+        });
+    }
+    Ok(frames)
+}
+
+/// Follows each long and double with the `Top` of its second slot
+fn expand<'a>(locals: &[VerificationType<'a>]) -> Vec<VerificationType<'a>> {
+    let mut expanded = Vec::with_capacity(locals.len());
+    for local in locals {
+        expanded.push(local.clone());
+        if local.get_size() == 2 {
+            expanded.push(Top);
+        }
+    }
+    expanded
+}
+
+/// Drops the second slot of each long and double
+fn compact<'a>(locals: &[VerificationType<'a>]) -> Vec<VerificationType<'a>> {
+    let mut compacted = Vec::with_capacity(locals.len());
+    let mut slot = 0;
+    while let Some(local) = locals.get(slot) {
+        compacted.push(local.clone());
+        slot += local.get_size() as usize;
+    }
+    compacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::initial_frame;
+    use super::VerificationType::*;
+    use assembler::assemble_class;
+    use class_file::ClassLoadingError::ClassFormatError;
+    use test_util::method_source;
+    use typed_arena::Arena;
+
+    /// A class with an instance method `m(long, String)` of the given flags, with the given
+    /// StackMapTable entries
+    fn frames_source(flags: &str, frames: &str) -> String {
+        let method = format!(
+            "  {} void m(long, java.lang.String);
+    descriptor: (JLjava/lang/String;)V
+    Code:
+      stack=2, locals=6, args_size=3
+        0: return
+      StackMapTable:
+{}",
+            flags, frames
+        );
+        method_source(61, &method)
+    }
+
+    #[test]
+    fn frames_are_given_at_absolute_pcs_with_every_slot() {
+        let frames = "        frame_type = 3 /* same */
+        frame_type = 65 /* same_locals_1_stack_item */
+          stack = [ int ]
+        frame_type = 252 /* append */
+          offset_delta = 2
+          locals = [ double ]
+        frame_type = 250 /* chop */
+          offset_delta = 0
+        frame_type = 255 /* full_frame */
+          offset_delta = 1
+          locals = [ class \"[I\", top, uninitialized 8 ]
+          stack = [ null, this ]
+        frame_type = 251 /* same_frame_extended */
+          offset_delta = 300";
+        let strings = Arena::new();
+        let class = assemble_class(&frames_source("public", frames), &strings).unwrap();
+        let frames = class.get_methods()[0].get_stack_map_frames(&class).unwrap();
+        let pcs: Vec<_> = frames.iter().map(|frame| frame.get_pc()).collect();
+        assert_eq!(pcs, [3, 5, 8, 9, 11, 312]);
+        let parameters = [Object("p/A"), Long, Top, Object("java/lang/String")];
+        assert_eq!(frames[0].get_locals(), parameters);
+        assert!(frames[0].get_stack().is_empty());
+        assert_eq!(frames[1].get_locals(), parameters);
+        assert_eq!(frames[1].get_stack(), [Integer]);
+        assert_eq!(frames[2].get_locals()[4..], [Double, Top]);
+        assert_eq!(frames[3].get_locals(), parameters);
+        assert_eq!(frames[4].get_locals(), [Object("[I"), Top, Uninitialized(8)]);
+        assert_eq!(frames[4].get_stack(), [Null, UninitializedThis]);
+        assert_eq!(frames[5].get_locals(), frames[4].get_locals());
+        assert!(frames[5].get_stack().is_empty());
+    }
+
+    #[test]
+    fn chopping_more_locals_than_the_frame_has_is_rejected() {
+        let frames = "        frame_type = 248 /* chop */
+          offset_delta = 0
+        frame_type = 250 /* chop */
+          offset_delta = 0";
+        let strings = Arena::new();
+        let class = assemble_class(&frames_source("public", frames), &strings).unwrap();
+        let method = &class.get_methods()[0];
+        assert!(matches!(method.get_stack_map_frames(&class), Err(ClassFormatError(_))));
+        // The locals of a static method start with its parameters, chopping the last of them
+        let frames = "        frame_type = 250 /* chop */\n          offset_delta = 0";
+        let class = assemble_class(&frames_source("public static", frames), &strings).unwrap();
+        let frames = class.get_methods()[0].get_stack_map_frames(&class).unwrap();
+        assert_eq!(frames[0].get_locals(), [Long, Top]);
+    }
+
+    #[test]
+    fn initializers_start_with_this_uninitialized() {
+        let frame = initial_frame("p/A", "<init>", false, "(D)V").unwrap();
+        assert_eq!(frame.get_locals(), [UninitializedThis, Double, Top]);
+        let frame = initial_frame("java/lang/Object", "<init>", false, "()V").unwrap();
+        assert_eq!(frame.get_locals(), [Object("java/lang/Object")]);
+        let frame = initial_frame("p/A", "m", true, "([ZC)V").unwrap();
+        assert_eq!(frame.get_locals(), [Object("[Z"), Integer]);
+        assert!(initial_frame("p/A", "m", true, "(X)V").is_err());
+    }
+
+    #[test]
+    fn types_are_written_as_javap_prints_them() {
+        let types = [Integer, Object("java/lang/String"), Object("[J"), Uninitialized(4)];
+        let text: Vec<_> = types.iter().map(ToString::to_string).collect();
+        assert_eq!(text, ["int", "class java/lang/String", "class \"[J\"", "uninitialized 4"]);
+    }
+}
@@ -0,0 +1,35 @@
+//! Helpers shared by the tests of several modules
+
+use assembler::assemble;
+use std::fs;
+use std::path::Path;
+
+/// The start of the source of a class, its declaration and version, which its constant pool,
+/// members and attributes follow
+pub fn class_header(declaration: &str, major_version: u16) -> String {
+    format!("{}\n  minor version: 0\n  major version: {}\n", declaration, major_version)
+}
+
+/// The source of a public class `p.A` of the given version that declares just the given method
+pub fn method_source(major_version: u16, method: &str) -> String {
+    format!("{}{{\n{}\n}}\n", class_header("public class p.A", major_version), method)
+}
+
+/// An empty directory for a test to write files to, named after the test and so unique to it
+pub fn temp_dir(test_name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("jvm-{}", test_name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.to_string_lossy().into_owned()
+}
+
+/// Assembles classes into a fresh directory to load them from
+pub fn write_classpath(test_name: &str, classes: &[(&str, &str)]) -> String {
+    let dir = temp_dir(test_name);
+    for (class_name, source) in classes {
+        let path = Path::new(&dir).join(format!("{}.class", class_name));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, assemble(source).unwrap()).unwrap();
+    }
+    dir
+}
@@ -0,0 +1,1958 @@
+use attribute::attribute_info;
+use attribute::attribute_info_Data::Code_attribute;
+use attribute::exception_info;
+use class::ClassAccessFlag;
+use class_file::ClassFile;
+use class_file::ClassLoadingError;
+use class_file::ClassLoadingError::{ClassCircularityError, ClassFormatError, VerifyError};
+use constant_pool::cp_info::*;
+use constant_pool::MemberRef;
+use field;
+use instruction::Instruction;
+use instruction::Instruction::*;
+use instruction::{InstructionIter, WideInstruction};
+use method;
+use method::{MethodAccessFlag, MethodDescriptor, MethodInfo, ReturnDescriptor};
+use stack_map;
+use stack_map::StackMapFrame;
+use stack_map::VerificationType;
+use stack_map::VerificationType::*;
+use std::collections::HashMap;
+use typed_arena::Arena;
+
+/// Answers the questions about other classes that the verifier needs to check that one type is
+/// assignable to another
+///
+/// Only the direct superclass of a class and whether it is an interface are needed, so they can
+/// be found without loading, and so verifying, the class
+pub trait ClassHierarchy<'a> {
+    /// The name of the direct superclass of a class, `None` for `java/lang/Object`
+    fn get_super_class(
+        &mut self,
+        class_name: &'a str,
+    ) -> Result<Option<&'a str>, ClassLoadingError>;
+
+    fn is_interface(&mut self, class_name: &'a str) -> Result<bool, ClassLoadingError>;
+}
+
+/// Checks the code of every method of a class, choosing the verifier by the class file version
+///
+/// Class files of version 50 and above are checked against their StackMapTables by type
+/// checking, older ones by type inference. A method of a version 50 class file that fails type
+/// checking is verified again by type inference, as the JVM does. Access to protected members
+/// isn't checked, as that needs the member to be resolved.
+///
+/// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10>
+pub fn verify_class<'a, H: ClassHierarchy<'a>>(
+    class: &ClassFile<'a>,
+    hierarchy: &mut H,
+    strings: &'a Arena<String>,
+) -> Result<(), ClassLoadingError> {
+    for method in class.get_methods() {
+        verify_method(class, method, hierarchy, strings)?;
+    }
+    Ok(())
+}
+
+fn verify_method<'a, H: ClassHierarchy<'a>>(
+    class: &ClassFile<'a>,
+    method: &MethodInfo<'a>,
+    hierarchy: &mut H,
+    strings: &'a Arena<String>,
+) -> Result<(), ClassLoadingError> {
+    let constant_pool = class.get_constant_pool();
+    // Only the Code attribute is read, a lazily parsed one that is malformed being an error, and
+    // without keeping it decoded once verified
+    let code_info = method.get_attributes().iter().find(|info| {
+        constant_pool.get_string_entry(info.get_name_index()).ok() == Some("Code")
+    });
+    let code_data = match code_info {
+        Some(info) => Some(info.read()?),
+        None => None,
+    };
+    let (code_offset, max_stack, max_locals, code, exception_table, code_attributes) =
+        match (code_info, code_data.as_deref()) {
+            (Some(info), Some(Code_attribute {
+                max_stack, max_locals, code, exception_table, attributes, ..
+            })) => (info.get_offset(), *max_stack, *max_locals, code, exception_table, attributes),
+            _ if method.get_access_flags()
+                .intersects(MethodAccessFlag::ACC_ABSTRACT | MethodAccessFlag::ACC_NATIVE) => {
+                return Ok(());
+            }
+            _ => {
+                return Err(ClassFormatError(format!(
+                    "Method {}.{}{} has no Code attribute",
+                    class.get_name(), method.get_name(), method.get_descriptor()
+                )));
+            }
+        };
+    let descriptor = constant_pool.get_string_entry(method.get_descriptor_index())?;
+    let return_type = match method::parse_method_descriptor(descriptor)?.get_return_type() {
+        ReturnDescriptor::Value(value) => Some(VerificationType::from_descriptor(value)),
+        ReturnDescriptor::Void => None,
+    };
+    // The code follows the attribute header, max_stack, max_locals and code_length
+    let instructions = InstructionIter::new_at(code, code_offset + 14)
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut is_instruction = vec![false; code.len()];
+    let mut news = HashMap::new();
+    for (pc, instruction) in instructions.iter() {
+        is_instruction[*pc as usize] = true;
+        if let New(index) = instruction {
+            news.insert(*pc, constant_pool.class_name(*index)?);
+        }
+    }
+    let mut verifier = MethodVerifier {
+        class,
+        hierarchy,
+        strings,
+        location: format!("{}.{}{}", class.get_name(), method.get_name(), descriptor),
+        is_initializer: method.get_name() == "<init>",
+        return_type,
+        max_stack,
+        max_locals,
+        is_instruction,
+        news,
+        infers_types: false,
+        pc: 0,
+    };
+    match class.get_major_version() {
+        0..=49 => verifier.infer_types(method, &instructions, exception_table),
+        50 => match verifier.check_types(method, &instructions, exception_table, code_attributes) {
+            Err(VerifyError { .. }) => {
+                verifier.infer_types(method, &instructions, exception_table)
+            }
+            result => result,
+        },
+        _ => verifier.check_types(method, &instructions, exception_table, code_attributes),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The types of the locals, one for each slot, and of the operand stack, one for each value
+struct Frame<'a> {
+    locals: Vec<VerificationType<'a>>,
+    stack: Vec<VerificationType<'a>>,
+    /// Whether `this` still has to be initialized by calling another instance initializer,
+    /// which stays set even if the local holding it is overwritten
+    this_uninitialized: bool,
+}
+
+impl<'a> Frame<'a> {
+    /// Whether any object created by `new` hasn't been initialized yet
+    fn has_uninitialized(&self) -> bool {
+        let is_uninitialized = |entry: &VerificationType| matches!(entry, Uninitialized(_));
+        self.locals.iter().chain(self.stack.iter()).any(is_uninitialized)
+    }
+
+    /// The number of words the operand stack takes up
+    fn stack_size(&self) -> usize {
+        self.stack.iter().map(|entry| usize::from(entry.get_size())).sum()
+    }
+
+    /// Replaces every copy of an object once its instance initializer has been called
+    fn initialize(&mut self, uninitialized: &VerificationType<'a>, object: VerificationType<'a>) {
+        if *uninitialized == UninitializedThis {
+            self.this_uninitialized = false;
+        }
+        for entry in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if entry == uninitialized {
+                *entry = object.clone();
+            }
+        }
+    }
+}
+
+/// Where execution can continue after an instruction, besides its exception handlers
+struct Successors {
+    targets: Vec<u32>,
+    falls_through: bool,
+}
+
+/// A subroutine, found by the `jsr` instructions that call it
+struct Subroutine {
+    /// The instructions that call the subroutine, by their index in the code
+    callers: Vec<usize>,
+    /// Whether the subroutine, or one it calls, loads or stores each local
+    accessed: Vec<bool>,
+}
+
+/// The state of data-flow analysis over the instructions of a method, by index in the code
+struct DataFlow<'a> {
+    /// The merged frame before each instruction that has been reached
+    frames: Vec<Option<Frame<'a>>>,
+    /// The instructions whose frame has changed since they were last checked
+    changed: Vec<usize>,
+    is_changed: Vec<bool>,
+}
+
+/// An entry of the exception table of a method
+struct Handler<'a> {
+    start_pc: u32,
+    end_pc: u32,
+    handler_pc: u32,
+    catch_type: VerificationType<'a>,
+}
+
+impl<'a> Handler<'a> {
+    /// The frame the handler is entered with from an instruction it covers, which keeps the
+    /// locals but has only the exception on the operand stack
+    fn get_frame(&self, frame: &Frame<'a>) -> Frame<'a> {
+        Frame {
+            locals: frame.locals.clone(),
+            stack: vec![self.catch_type.clone()],
+            this_uninitialized: frame.this_uninitialized,
+        }
+    }
+}
+
+struct MethodVerifier<'a, 'c, H: 'c> {
+    class: &'c ClassFile<'a>,
+    hierarchy: &'c mut H,
+    /// Holds the names of the array types `anewarray` and merging frames create
+    strings: &'a Arena<String>,
+    /// The class, name and descriptor of the method, for errors
+    location: String,
+    is_initializer: bool,
+    /// `None` for a void method
+    return_type: Option<VerificationType<'a>>,
+    max_stack: u16,
+    max_locals: u16,
+    /// Whether an instruction starts at each pc
+    is_instruction: Vec<bool>,
+    /// The class each `new` instruction creates an instance of, by pc
+    news: HashMap<u32, &'a str>,
+    /// Whether the types are being inferred rather than checked against a StackMapTable
+    infers_types: bool,
+    /// The pc of the instruction being verified
+    pc: u32,
+}
+
+impl<'a, 'c, H: ClassHierarchy<'a>> MethodVerifier<'a, 'c, H> {
+    /// Checks the types at each instruction against the frames of the StackMapTable in a single
+    /// pass over the code
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1>
+    fn check_types(
+        &mut self,
+        method: &MethodInfo<'a>,
+        instructions: &[(u32, Instruction)],
+        exception_table: &[exception_info],
+        code_attributes: &[attribute_info<'a>],
+    ) -> Result<(), ClassLoadingError> {
+        let initial_frame = method.get_initial_frame(self.class)?;
+        let mut frame = Some(self.read_frame(&initial_frame)?);
+        let mut stack_map = HashMap::new();
+        let constant_pool = self.class.get_constant_pool();
+        for stack_map_frame in
+            stack_map::read_stack_map_frames(code_attributes, &initial_frame, constant_pool)?
+        {
+            self.pc = stack_map_frame.get_pc();
+            if !self.is_instruction(self.pc) {
+                return Err(self.error(String::from(
+                    "StackMapTable frame isn't at the start of an instruction",
+                )));
+            }
+            stack_map.insert(self.pc, self.read_frame(&stack_map_frame)?);
+        }
+        let handlers = self.read_handlers(exception_table)?;
+
+        for (pc, instruction) in instructions {
+            self.pc = *pc;
+            if let Some(stack_map_frame) = stack_map.get(pc) {
+                if let Some(frame) = &frame {
+                    self.check_frame(frame, stack_map_frame, *pc)?;
+                }
+                frame = Some(stack_map_frame.clone());
+            }
+            let mut frame_after = match frame.take() {
+                Some(frame) => frame,
+                None => {
+                    return Err(self.error(String::from(
+                        "Expecting a stack map frame after an unconditional branch",
+                    )))
+                }
+            };
+            for handler in handlers.iter() {
+                if handler.start_pc <= *pc && *pc < handler.end_pc {
+                    let exception_frame = handler.get_frame(&frame_after);
+                    self.check_target(&stack_map, &exception_frame, handler.handler_pc)?;
+                }
+            }
+            let successors = self.get_successors(instruction)?;
+            self.execute(&mut frame_after, instruction)?;
+            for target in successors.targets {
+                self.check_target(&stack_map, &frame_after, target)?;
+            }
+            if successors.falls_through {
+                frame = Some(frame_after);
+            }
+        }
+        if frame.is_some() {
+            return Err(self.error(String::from("Falling off the end of the code")));
+        }
+        Ok(())
+    }
+
+    /// Pads the locals of a frame out to `max_locals`, checking that it fits the method
+    fn read_frame(&self, frame: &StackMapFrame<'a>) -> Result<Frame<'a>, ClassLoadingError> {
+        let max_locals = usize::from(self.max_locals);
+        if frame.get_locals().len() > max_locals {
+            return Err(self.error(format!(
+                "Frame has {} local variable slots, more than max_locals {}",
+                frame.get_locals().len(),
+                max_locals
+            )));
+        }
+        let mut locals = frame.get_locals().to_vec();
+        locals.resize(max_locals, Top);
+        let frame = Frame {
+            this_uninitialized: locals.contains(&UninitializedThis),
+            locals,
+            stack: frame.get_stack().to_vec(),
+        };
+        if frame.stack_size() > usize::from(self.max_stack) {
+            return Err(self.error(format!(
+                "Frame has {} operand stack words, more than max_stack {}",
+                frame.stack_size(),
+                self.max_stack
+            )));
+        }
+        for entry in frame.locals.iter().chain(frame.stack.iter()) {
+            if let Uninitialized(pc) = entry {
+                if !self.news.contains_key(&u32::from(*pc)) {
+                    return Err(self.error(format!(
+                        "Frame has {}, but there is no new instruction at pc {}",
+                        entry, pc
+                    )));
+                }
+            }
+        }
+        Ok(frame)
+    }
+
+    fn read_handlers(
+        &mut self,
+        exception_table: &[exception_info],
+    ) -> Result<Vec<Handler<'a>>, ClassLoadingError> {
+        let mut handlers = Vec::with_capacity(exception_table.len());
+        for entry in exception_table {
+            let handler = Handler {
+                start_pc: u32::from(entry.get_start_pc()),
+                end_pc: u32::from(entry.get_end_pc()),
+                handler_pc: u32::from(entry.get_handler_pc()),
+                catch_type: match entry.get_catch_type() {
+                    0 => Object("java/lang/Throwable"),
+                    index => Object(self.class.get_constant_pool().class_name(index)?),
+                },
+            };
+            self.pc = handler.handler_pc;
+            let code_length = self.is_instruction.len() as u32;
+            if handler.start_pc >= handler.end_pc
+                || !self.is_instruction(handler.start_pc)
+                || !(handler.end_pc == code_length || self.is_instruction(handler.end_pc))
+            {
+                return Err(self.error(format!(
+                    "Exception handler range {}..{} isn't a range of instructions",
+                    handler.start_pc, handler.end_pc
+                )));
+            }
+            if !self.is_instruction(handler.handler_pc) {
+                return Err(self.error(String::from(
+                    "Exception handler isn't at the start of an instruction",
+                )));
+            }
+            if !self.is_assignable(&handler.catch_type, &Object("java/lang/Throwable"))? {
+                return Err(self.error(format!(
+                    "Exception handler catches {}, which isn't a Throwable",
+                    handler.catch_type
+                )));
+            }
+            handlers.push(handler);
+        }
+        Ok(handlers)
+    }
+
+    /// Checks that execution can continue at a branch target or exception handler with a frame
+    fn check_target(
+        &mut self,
+        stack_map: &HashMap<u32, Frame<'a>>,
+        frame: &Frame<'a>,
+        target: u32,
+    ) -> Result<(), ClassLoadingError> {
+        match stack_map.get(&target) {
+            Some(target_frame) => self.check_frame(frame, target_frame, target),
+            None => Err(self.error(format!("Expecting a stack map frame at pc {}", target))),
+        }
+    }
+
+    /// Checks that a frame is assignable to the frame the StackMapTable gives at a pc
+    fn check_frame(
+        &mut self,
+        frame: &Frame<'a>,
+        target_frame: &Frame<'a>,
+        target: u32,
+    ) -> Result<(), ClassLoadingError> {
+        for (slot, (local, target_local)) in
+            frame.locals.iter().zip(target_frame.locals.iter()).enumerate()
+        {
+            if !self.is_assignable(local, target_local)? {
+                return Err(self.error(format!(
+                    "Local {} is {} where the frame at pc {} has {}",
+                    slot, local, target, target_local
+                )));
+            }
+        }
+        if frame.stack.len() != target_frame.stack.len() {
+            return Err(self.error(format!(
+                "Operand stack has {} values where the frame at pc {} has {}",
+                frame.stack.len(),
+                target,
+                target_frame.stack.len()
+            )));
+        }
+        for (entry, target_entry) in frame.stack.iter().zip(target_frame.stack.iter()) {
+            if !self.is_assignable(entry, target_entry)? {
+                return Err(self.error(format!(
+                    "Operand stack has {} where the frame at pc {} has {}",
+                    entry, target, target_entry
+                )));
+            }
+        }
+        if frame.this_uninitialized && !target_frame.this_uninitialized {
+            return Err(self.error(format!(
+                "this is uninitialized where the frame at pc {} has it initialized",
+                target
+            )));
+        }
+        Ok(())
+    }
+
+    /// Infers the types at each instruction by data-flow analysis, merging the frames that
+    /// execution reaches an instruction with until none of them change
+    ///
+    /// A subroutine called by `jsr` returns with the types it left in the locals it uses, and the
+    /// types from before the `jsr` in the others.
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.2>
+    fn infer_types(
+        &mut self,
+        method: &MethodInfo<'a>,
+        instructions: &[(u32, Instruction)],
+        exception_table: &[exception_info],
+    ) -> Result<(), ClassLoadingError> {
+        self.infers_types = true;
+        self.pc = 0;
+        if instructions.is_empty() {
+            return Err(self.error(String::from("Method has no code")));
+        }
+        let initial_frame = method.get_initial_frame(self.class)?;
+        let initial_frame = self.read_frame(&initial_frame)?;
+        let indices: HashMap<u32, usize> = instructions.iter()
+            .enumerate()
+            .map(|(index, (pc, _))| (*pc, index))
+            .collect();
+        let handlers = self.read_handlers(exception_table)?;
+        let subroutines = self.find_subroutines(instructions, &indices)?;
+        let returns: Vec<usize> = instructions.iter()
+            .enumerate()
+            .filter(|(_, (_, instruction))| {
+                matches!(instruction, Ret(_) | Wide(WideInstruction::Ret(_)))
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        let mut data_flow = DataFlow {
+            frames: vec![None; instructions.len()],
+            changed: Vec::new(),
+            is_changed: vec![false; instructions.len()],
+        };
+        self.merge(&mut data_flow, 0, initial_frame)?;
+        while let Some(index) = data_flow.changed.pop() {
+            data_flow.is_changed[index] = false;
+            let (pc, instruction) = &instructions[index];
+            self.pc = *pc;
+            let mut frame = data_flow.frames[index].clone().unwrap();
+            for handler in handlers.iter() {
+                if handler.start_pc <= *pc && *pc < handler.end_pc {
+                    if frame.locals.iter().any(|local| matches!(local, Uninitialized(_))) {
+                        return Err(self.error(String::from(
+                            "Uninitialized object in a local in code covered by an exception \
+                             handler",
+                        )));
+                    }
+                    let handler_index = indices[&handler.handler_pc];
+                    self.merge(&mut data_flow, handler_index, handler.get_frame(&frame))?;
+                }
+            }
+
+            if let Ret(_) | Wide(WideInstruction::Ret(_)) = instruction {
+                let entry = self.get_return_address(&frame, get_local_access(instruction).0)?;
+                for caller in subroutines[&entry].callers.iter() {
+                    let caller_frame = match &data_flow.frames[*caller] {
+                        Some(caller_frame) => caller_frame.clone(),
+                        None => continue,
+                    };
+                    if *caller + 1 == instructions.len() {
+                        return Err(self.error(String::from("Falling off the end of the code")));
+                    }
+                    let mut returned = frame.clone();
+                    for (slot, accessed) in subroutines[&entry].accessed.iter().enumerate() {
+                        if !accessed {
+                            returned.locals[slot] = caller_frame.locals[slot].clone();
+                        }
+                    }
+                    self.merge(&mut data_flow, *caller + 1, returned)?;
+                }
+                continue;
+            }
+
+            let successors = self.get_successors(instruction)?;
+            self.execute(&mut frame, instruction)?;
+            if let Jsr(_) | JsrW(_) = instruction {
+                // The subroutine returns here with the locals it doesn't use from before the jsr,
+                // which may have changed
+                for index in returns.iter() {
+                    if data_flow.frames[*index].is_some() && !data_flow.is_changed[*index] {
+                        data_flow.is_changed[*index] = true;
+                        data_flow.changed.push(*index);
+                    }
+                }
+            }
+            for target in successors.targets {
+                if target <= *pc && frame.has_uninitialized() {
+                    return Err(self.error(String::from(
+                        "Uninitialized object on a backward branch",
+                    )));
+                }
+                self.merge(&mut data_flow, indices[&target], frame.clone())?;
+            }
+            if successors.falls_through {
+                if index + 1 == instructions.len() {
+                    return Err(self.error(String::from("Falling off the end of the code")));
+                }
+                self.merge(&mut data_flow, index + 1, frame)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges a frame that execution reaches an instruction with into its frame, marking the
+    /// instruction as changed if that is different
+    fn merge(
+        &mut self,
+        data_flow: &mut DataFlow<'a>,
+        index: usize,
+        frame: Frame<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        let merged = match &data_flow.frames[index] {
+            Some(existing) => {
+                let merged = self.merge_frames(existing, &frame)?;
+                if merged == *existing {
+                    return Ok(());
+                }
+                merged
+            }
+            None => frame,
+        };
+        data_flow.frames[index] = Some(merged);
+        if !data_flow.is_changed[index] {
+            data_flow.is_changed[index] = true;
+            data_flow.changed.push(index);
+        }
+        Ok(())
+    }
+
+    /// Merges two frames, where a local that has different types in each becomes unusable
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.2.2>
+    fn merge_frames(
+        &mut self,
+        first: &Frame<'a>,
+        second: &Frame<'a>,
+    ) -> Result<Frame<'a>, ClassLoadingError> {
+        if first.stack.len() != second.stack.len() {
+            return Err(self.error(format!(
+                "Inconsistent operand stack height {} != {}",
+                first.stack.len(),
+                second.stack.len()
+            )));
+        }
+        let mut stack = Vec::with_capacity(first.stack.len());
+        for (first_entry, second_entry) in first.stack.iter().zip(second.stack.iter()) {
+            let entry = self.merge_types(first_entry, second_entry)?;
+            if entry == Top {
+                return Err(self.error(format!(
+                    "Mismatched operand stack types {} and {}",
+                    first_entry, second_entry
+                )));
+            }
+            stack.push(entry);
+        }
+        let mut locals = Vec::with_capacity(first.locals.len());
+        for (first_local, second_local) in first.locals.iter().zip(second.locals.iter()) {
+            locals.push(self.merge_types(first_local, second_local)?);
+        }
+        Ok(Frame {
+            locals,
+            stack,
+            this_uninitialized: first.this_uninitialized || second.this_uninitialized,
+        })
+    }
+
+    /// The most specific type both types are assignable to, `Top` if there is none
+    fn merge_types(
+        &mut self,
+        first: &VerificationType<'a>,
+        second: &VerificationType<'a>,
+    ) -> Result<VerificationType<'a>, ClassLoadingError> {
+        Ok(match (first, second) {
+            _ if first == second => first.clone(),
+            (Null, Object(_)) => second.clone(),
+            (Object(_), Null) => first.clone(),
+            (Object(first), Object(second)) => Object(self.merge_classes(first, second)?),
+            _ => Top,
+        })
+    }
+
+    /// The first common superclass of two classes, treating interfaces as `java/lang/Object`
+    ///
+    /// Arrays of references merge into an array of the merged component type, and any other
+    /// arrays into `java/lang/Object`
+    fn merge_classes(
+        &mut self,
+        first: &'a str,
+        second: &'a str,
+    ) -> Result<&'a str, ClassLoadingError> {
+        if first == second {
+            return Ok(first);
+        }
+        if first.starts_with('[') || second.starts_with('[') {
+            if first.starts_with('[') && second.starts_with('[') {
+                if let (Some(first), Some(second)) = (component(first), component(second)) {
+                    let component = self.merge_classes(first, second)?;
+                    return self.array_of(component);
+                }
+            }
+            return Ok("java/lang/Object");
+        }
+        if self.is_interface(first)? || self.is_interface(second)? {
+            return Ok("java/lang/Object");
+        }
+        let first_superclasses = self.get_superclasses(first)?;
+        for class in self.get_superclasses(second)? {
+            if first_superclasses.contains(&class) {
+                return Ok(class);
+            }
+        }
+        Ok("java/lang/Object")
+    }
+
+    /// Finds the subroutines the `jsr` instructions call, and the locals each one uses
+    ///
+    /// The code of a subroutine is everything reachable from its start without passing a
+    /// `ret`, stepping over the subroutines it calls in turn.
+    fn find_subroutines(
+        &mut self,
+        instructions: &[(u32, Instruction)],
+        indices: &HashMap<u32, usize>,
+    ) -> Result<HashMap<u32, Subroutine>, ClassLoadingError> {
+        let mut subroutines = HashMap::new();
+        for (index, (pc, instruction)) in instructions.iter().enumerate() {
+            if let Jsr(_) | JsrW(_) = instruction {
+                self.pc = *pc;
+                let entry = self.get_successors(instruction)?.targets[0];
+                subroutines.entry(entry).or_insert_with(|| Subroutine {
+                    callers: Vec::new(),
+                    accessed: vec![false; usize::from(self.max_locals)],
+                }).callers.push(index);
+            }
+        }
+
+        let mut calls: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (entry, subroutine) in subroutines.iter_mut() {
+            let mut is_visited = vec![false; instructions.len()];
+            let mut unvisited = vec![indices[entry]];
+            while let Some(index) = unvisited.pop() {
+                if is_visited[index] {
+                    continue;
+                }
+                is_visited[index] = true;
+                let (pc, instruction) = &instructions[index];
+                self.pc = *pc;
+                let (local, size) = get_local_access(instruction);
+                for slot in usize::from(local)..usize::from(local) + usize::from(size) {
+                    if let Some(accessed) = subroutine.accessed.get_mut(slot) {
+                        *accessed = true;
+                    }
+                }
+                let successors = self.get_successors(instruction)?;
+                if let Jsr(_) | JsrW(_) = instruction {
+                    calls.entry(*entry).or_default().push(successors.targets[0]);
+                } else {
+                    unvisited.extend(successors.targets.iter().map(|target| indices[target]));
+                }
+                let is_call = matches!(instruction, Jsr(_) | JsrW(_));
+                if (successors.falls_through || is_call) && index + 1 < instructions.len() {
+                    unvisited.push(index + 1);
+                }
+            }
+        }
+
+        for (entry, callees) in calls.iter() {
+            let mut unvisited = callees.clone();
+            let mut visited = Vec::new();
+            while let Some(callee) = unvisited.pop() {
+                if callee == *entry {
+                    self.pc = *entry;
+                    return Err(self.error(String::from("Recursive call to a subroutine")));
+                }
+                if !visited.contains(&callee) {
+                    visited.push(callee);
+                    unvisited.extend(calls.get(&callee).into_iter().flatten().cloned());
+                }
+            }
+        }
+        // A subroutine uses the locals the subroutines it calls use, however deeply nested
+        let mut is_changed = true;
+        while is_changed {
+            is_changed = false;
+            for (entry, callees) in calls.iter() {
+                for callee in callees {
+                    let accessed = subroutines[callee].accessed.clone();
+                    let subroutine = subroutines.get_mut(entry).unwrap();
+                    for (slot, is_accessed) in accessed.into_iter().enumerate() {
+                        if is_accessed && !subroutine.accessed[slot] {
+                            subroutine.accessed[slot] = true;
+                            is_changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(subroutines)
+    }
+
+    /// `jsr`, which calls the subroutine with the address to return to on the operand stack
+    fn call_subroutine(&self, frame: &mut Frame<'a>, offset: i32) -> Result<(), ClassLoadingError> {
+        let entry = self.get_target(offset)?;
+        self.push(frame, ReturnAddress(entry as u16))
+    }
+
+    /// The start of the subroutine that `ret` returns from, given by the address in its local
+    fn get_return_address(&self, frame: &Frame<'a>, index: u16) -> Result<u32, ClassLoadingError> {
+        self.check_index(index, 1)?;
+        match &frame.locals[usize::from(index)] {
+            ReturnAddress(entry) => Ok(u32::from(*entry)),
+            local => Err(self.error(format!(
+                "Local {} is {} where a returnAddress is expected",
+                index, local
+            ))),
+        }
+    }
+
+    /// Applies an instruction to the frame before it, giving the frame after it
+    fn execute(
+        &mut self,
+        frame: &mut Frame<'a>,
+        instruction: &Instruction,
+    ) -> Result<(), ClassLoadingError> {
+        let constant_pool = self.class.get_constant_pool();
+        match instruction {
+            Nop => {}
+            AconstNull => self.push(frame, Null)?,
+            IconstM1 | Iconst0 | Iconst1 | Iconst2 | Iconst3 | Iconst4 | Iconst5 | Bipush(_)
+            | Sipush(_) => self.push(frame, Integer)?,
+            Lconst0 | Lconst1 => self.push(frame, Long)?,
+            Fconst0 | Fconst1 | Fconst2 => self.push(frame, Float)?,
+            Dconst0 | Dconst1 => self.push(frame, Double)?,
+            Ldc(index) => self.load_constant(frame, u16::from(*index), false)?,
+            LdcW(index) => self.load_constant(frame, *index, false)?,
+            Ldc2W(index) => self.load_constant(frame, *index, true)?,
+
+            Iload(index) => self.load(frame, u16::from(*index), &Integer)?,
+            Lload(index) => self.load(frame, u16::from(*index), &Long)?,
+            Fload(index) => self.load(frame, u16::from(*index), &Float)?,
+            Dload(index) => self.load(frame, u16::from(*index), &Double)?,
+            Aload(index) => self.load_reference(frame, u16::from(*index))?,
+            Iload0 | Iload1 | Iload2 | Iload3 => {
+                self.load(frame, implicit_index(instruction), &Integer)?
+            }
+            Lload0 | Lload1 | Lload2 | Lload3 => {
+                self.load(frame, implicit_index(instruction), &Long)?
+            }
+            Fload0 | Fload1 | Fload2 | Fload3 => {
+                self.load(frame, implicit_index(instruction), &Float)?
+            }
+            Dload0 | Dload1 | Dload2 | Dload3 => {
+                self.load(frame, implicit_index(instruction), &Double)?
+            }
+            Aload0 | Aload1 | Aload2 | Aload3 => {
+                self.load_reference(frame, implicit_index(instruction))?
+            }
+            Iaload => self.load_element(frame, &["[I"], Integer)?,
+            Laload => self.load_element(frame, &["[J"], Long)?,
+            Faload => self.load_element(frame, &["[F"], Float)?,
+            Daload => self.load_element(frame, &["[D"], Double)?,
+            Baload => self.load_element(frame, &["[B", "[Z"], Integer)?,
+            Caload => self.load_element(frame, &["[C"], Integer)?,
+            Saload => self.load_element(frame, &["[S"], Integer)?,
+            Aaload => {
+                self.pop(frame, &Integer)?;
+                let element = match self.pop_any(frame)? {
+                    Null => Null,
+                    Object(name) if name.starts_with('[') && component(name).is_some() => {
+                        Object(component(name).unwrap())
+                    }
+                    entry => return Err(self.bad_type(&entry, "an array of references")),
+                };
+                self.push(frame, element)?;
+            }
+
+            Istore(index) => self.store_value(frame, u16::from(*index), Integer)?,
+            Lstore(index) => self.store_value(frame, u16::from(*index), Long)?,
+            Fstore(index) => self.store_value(frame, u16::from(*index), Float)?,
+            Dstore(index) => self.store_value(frame, u16::from(*index), Double)?,
+            Astore(index) => self.store_reference(frame, u16::from(*index))?,
+            Istore0 | Istore1 | Istore2 | Istore3 => {
+                self.store_value(frame, implicit_index(instruction), Integer)?
+            }
+            Lstore0 | Lstore1 | Lstore2 | Lstore3 => {
+                self.store_value(frame, implicit_index(instruction), Long)?
+            }
+            Fstore0 | Fstore1 | Fstore2 | Fstore3 => {
+                self.store_value(frame, implicit_index(instruction), Float)?
+            }
+            Dstore0 | Dstore1 | Dstore2 | Dstore3 => {
+                self.store_value(frame, implicit_index(instruction), Double)?
+            }
+            Astore0 | Astore1 | Astore2 | Astore3 => {
+                self.store_reference(frame, implicit_index(instruction))?
+            }
+            Iastore => self.store_element(frame, &["[I"], &Integer)?,
+            Lastore => self.store_element(frame, &["[J"], &Long)?,
+            Fastore => self.store_element(frame, &["[F"], &Float)?,
+            Dastore => self.store_element(frame, &["[D"], &Double)?,
+            Bastore => self.store_element(frame, &["[B", "[Z"], &Integer)?,
+            Castore => self.store_element(frame, &["[C"], &Integer)?,
+            Sastore => self.store_element(frame, &["[S"], &Integer)?,
+            Aastore => {
+                self.pop(frame, &Object("java/lang/Object"))?;
+                self.pop(frame, &Integer)?;
+                match self.pop_any(frame)? {
+                    Null => {}
+                    Object(name) if name.starts_with('[') && component(name).is_some() => {}
+                    entry => return Err(self.bad_type(&entry, "an array of references")),
+                }
+            }
+
+            Pop => {
+                self.pop_category1(frame)?;
+            }
+            Pop2 => {
+                if self.pop_value(frame)?.get_size() == 1 {
+                    self.pop_category1(frame)?;
+                }
+            }
+            Dup => {
+                let value1 = self.pop_category1(frame)?;
+                self.push_all(frame, vec![value1.clone(), value1])?;
+            }
+            DupX1 => {
+                let value1 = self.pop_category1(frame)?;
+                let value2 = self.pop_category1(frame)?;
+                self.push_all(frame, vec![value1.clone(), value2, value1])?;
+            }
+            DupX2 => {
+                let value1 = self.pop_category1(frame)?;
+                let value2 = self.pop_value(frame)?;
+                if value2.get_size() == 2 {
+                    self.push_all(frame, vec![value1.clone(), value2, value1])?;
+                } else {
+                    let value3 = self.pop_category1(frame)?;
+                    self.push_all(frame, vec![value1.clone(), value3, value2, value1])?;
+                }
+            }
+            Dup2 => {
+                let value1 = self.pop_value(frame)?;
+                if value1.get_size() == 2 {
+                    self.push_all(frame, vec![value1.clone(), value1])?;
+                } else {
+                    let value2 = self.pop_category1(frame)?;
+                    self.push_all(frame, vec![value2.clone(), value1.clone(), value2, value1])?;
+                }
+            }
+            Dup2X1 => {
+                let value1 = self.pop_value(frame)?;
+                let value2 = self.pop_category1(frame)?;
+                if value1.get_size() == 2 {
+                    self.push_all(frame, vec![value1.clone(), value2, value1])?;
+                } else {
+                    let value3 = self.pop_category1(frame)?;
+                    self.push_all(
+                        frame,
+                        vec![value2.clone(), value1.clone(), value3, value2, value1],
+                    )?;
+                }
+            }
+            Dup2X2 => {
+                let value1 = self.pop_value(frame)?;
+                if value1.get_size() == 2 {
+                    let value2 = self.pop_value(frame)?;
+                    if value2.get_size() == 2 {
+                        self.push_all(frame, vec![value1.clone(), value2, value1])?;
+                    } else {
+                        let value3 = self.pop_category1(frame)?;
+                        self.push_all(frame, vec![value1.clone(), value3, value2, value1])?;
+                    }
+                } else {
+                    let value2 = self.pop_category1(frame)?;
+                    let value3 = self.pop_value(frame)?;
+                    if value3.get_size() == 2 {
+                        self.push_all(
+                            frame,
+                            vec![value2.clone(), value1.clone(), value3, value2, value1],
+                        )?;
+                    } else {
+                        let value4 = self.pop_category1(frame)?;
+                        self.push_all(
+                            frame,
+                            vec![value2.clone(), value1.clone(), value4, value3, value2, value1],
+                        )?;
+                    }
+                }
+            }
+            Swap => {
+                let value1 = self.pop_category1(frame)?;
+                let value2 = self.pop_category1(frame)?;
+                self.push_all(frame, vec![value1, value2])?;
+            }
+
+            Iadd | Isub | Imul | Idiv | Irem | Ishl | Ishr | Iushr | Iand | Ior | Ixor => {
+                self.operate(frame, &[Integer, Integer], Integer)?
+            }
+            Ladd | Lsub | Lmul | Ldiv | Lrem | Land | Lor | Lxor => {
+                self.operate(frame, &[Long, Long], Long)?
+            }
+            Lshl | Lshr | Lushr => self.operate(frame, &[Long, Integer], Long)?,
+            Fadd | Fsub | Fmul | Fdiv | Frem => self.operate(frame, &[Float, Float], Float)?,
+            Dadd | Dsub | Dmul | Ddiv | Drem => self.operate(frame, &[Double, Double], Double)?,
+            Ineg | I2b | I2c | I2s => self.operate(frame, &[Integer], Integer)?,
+            Lneg => self.operate(frame, &[Long], Long)?,
+            Fneg => self.operate(frame, &[Float], Float)?,
+            Dneg => self.operate(frame, &[Double], Double)?,
+            Iinc { index, .. } => {
+                self.get_local(frame, u16::from(*index), &Integer)?;
+            }
+            I2l => self.operate(frame, &[Integer], Long)?,
+            I2f => self.operate(frame, &[Integer], Float)?,
+            I2d => self.operate(frame, &[Integer], Double)?,
+            L2i => self.operate(frame, &[Long], Integer)?,
+            L2f => self.operate(frame, &[Long], Float)?,
+            L2d => self.operate(frame, &[Long], Double)?,
+            F2i => self.operate(frame, &[Float], Integer)?,
+            F2l => self.operate(frame, &[Float], Long)?,
+            F2d => self.operate(frame, &[Float], Double)?,
+            D2i => self.operate(frame, &[Double], Integer)?,
+            D2l => self.operate(frame, &[Double], Long)?,
+            D2f => self.operate(frame, &[Double], Float)?,
+            Lcmp => self.operate(frame, &[Long, Long], Integer)?,
+            Fcmpl | Fcmpg => self.operate(frame, &[Float, Float], Integer)?,
+            Dcmpl | Dcmpg => self.operate(frame, &[Double, Double], Integer)?,
+
+            Ifeq(_) | Ifne(_) | Iflt(_) | Ifge(_) | Ifgt(_) | Ifle(_) => {
+                self.pop(frame, &Integer)?;
+            }
+            IfIcmpeq(_) | IfIcmpne(_) | IfIcmplt(_) | IfIcmpge(_) | IfIcmpgt(_) | IfIcmple(_) => {
+                self.pop(frame, &Integer)?;
+                self.pop(frame, &Integer)?;
+            }
+            IfAcmpeq(_) | IfAcmpne(_) => {
+                self.pop_reference(frame)?;
+                self.pop_reference(frame)?;
+            }
+            Ifnull(_) | Ifnonnull(_) => {
+                self.pop_reference(frame)?;
+            }
+            Goto(_) | GotoW(_) => {}
+            Jsr(_) | JsrW(_) | Ret(_) | Wide(WideInstruction::Ret(_)) if !self.infers_types => {
+                return Err(self.error(format!(
+                    "{} isn't allowed in a method verified by type checking",
+                    instruction.get_mnemonic()
+                )));
+            }
+            Jsr(offset) => self.call_subroutine(frame, i32::from(*offset))?,
+            JsrW(offset) => self.call_subroutine(frame, *offset)?,
+            Ret(index) => {
+                self.get_return_address(frame, u16::from(*index))?;
+            }
+            Tableswitch { .. } => {
+                self.pop(frame, &Integer)?;
+            }
+            Lookupswitch { pairs, .. } => {
+                if pairs.windows(2).any(|window| window[0].0 >= window[1].0) {
+                    return Err(self.error(String::from("lookupswitch keys aren't sorted")));
+                }
+                self.pop(frame, &Integer)?;
+            }
+
+            Ireturn | Lreturn | Freturn | Dreturn | Areturn => {
+                let return_type = match (instruction, &self.return_type) {
+                    (Ireturn, Some(return_type @ Integer))
+                    | (Lreturn, Some(return_type @ Long))
+                    | (Freturn, Some(return_type @ Float))
+                    | (Dreturn, Some(return_type @ Double))
+                    | (Areturn, Some(return_type @ Object(_))) => return_type.clone(),
+                    _ => {
+                        return Err(self.error(format!(
+                            "{} doesn't match the return type of the method",
+                            instruction.get_mnemonic()
+                        )))
+                    }
+                };
+                self.pop(frame, &return_type)?;
+            }
+            Return => {
+                if self.return_type.is_some() {
+                    return Err(self.error(String::from(
+                        "return doesn't match the return type of the method",
+                    )));
+                }
+                if self.is_initializer && frame.this_uninitialized {
+                    return Err(self.error(String::from(
+                        "Instance initializer returns before initializing this",
+                    )));
+                }
+            }
+
+            Getstatic(index) => {
+                let field = constant_pool.field_ref(*index)?;
+                self.push(frame, field_type(&field)?)?;
+            }
+            Putstatic(index) => {
+                let field = constant_pool.field_ref(*index)?;
+                self.pop(frame, &field_type(&field)?)?;
+            }
+            Getfield(index) => {
+                let field = constant_pool.field_ref(*index)?;
+                self.pop(frame, &Object(field.get_class_name()))?;
+                self.push(frame, field_type(&field)?)?;
+            }
+            Putfield(index) => {
+                let field = constant_pool.field_ref(*index)?;
+                self.pop(frame, &field_type(&field)?)?;
+                let object = self.pop_any(frame)?;
+                // An instance initializer may set the fields its class declares before `this`
+                // is initialized
+                if object == UninitializedThis {
+                    if !self.declares_field(&field)? {
+                        return Err(self.bad_type(&object, "an initialized object"));
+                    }
+                } else if !self.is_assignable(&object, &Object(field.get_class_name()))? {
+                    return Err(self.bad_type(&object, &Object(field.get_class_name())));
+                }
+            }
+            Invokevirtual(index) | Invokespecial(index) | Invokestatic(index) => {
+                self.invoke(frame, instruction, constant_pool.method_ref(*index)?)?
+            }
+            Invokeinterface { index, .. } => {
+                self.invoke(frame, instruction, constant_pool.interface_method_ref(*index)?)?
+            }
+            Invokedynamic(index) => {
+                let call_site = constant_pool.invoke_dynamic(*index)?;
+                let descriptor = method::parse_method_descriptor(call_site.get_descriptor())?;
+                self.pop_arguments(frame, &descriptor)?;
+                self.push_return(frame, &descriptor)?;
+            }
+
+            New(index) => {
+                let class_name = constant_pool.class_name(*index)?;
+                if class_name.starts_with('[') {
+                    return Err(self.error(format!("new of the array class {}", class_name)));
+                }
+                let object = Uninitialized(self.pc as u16);
+                if frame.locals.contains(&object) || frame.stack.contains(&object) {
+                    return Err(self.error(String::from(
+                        "new while the object it created before is still uninitialized",
+                    )));
+                }
+                self.push(frame, object)?;
+            }
+            Newarray(atype) => {
+                self.pop(frame, &Integer)?;
+                let array = match atype {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    _ => return Err(self.error(format!("newarray of unknown type {}", atype))),
+                };
+                self.push(frame, Object(array))?;
+            }
+            Anewarray(index) => {
+                self.pop(frame, &Integer)?;
+                let array = self.array_of(constant_pool.class_name(*index)?)?;
+                self.push(frame, Object(array))?;
+            }
+            Multianewarray { index, dimensions } => {
+                let class_name = constant_pool.class_name(*index)?;
+                let array_dimensions = class_name.len() - class_name.trim_start_matches('[').len();
+                if *dimensions == 0 || array_dimensions < usize::from(*dimensions) {
+                    return Err(self.error(format!(
+                        "multianewarray of {} with {} dimensions",
+                        class_name, dimensions
+                    )));
+                }
+                for _ in 0..*dimensions {
+                    self.pop(frame, &Integer)?;
+                }
+                self.push(frame, Object(class_name))?;
+            }
+            Arraylength => {
+                match self.pop_any(frame)? {
+                    Null => {}
+                    Object(name) if name.starts_with('[') => {}
+                    entry => return Err(self.bad_type(&entry, "an array")),
+                }
+                self.push(frame, Integer)?;
+            }
+            Athrow => {
+                self.pop(frame, &Object("java/lang/Throwable"))?;
+            }
+            Checkcast(index) => {
+                self.pop(frame, &Object("java/lang/Object"))?;
+                self.push(frame, Object(constant_pool.class_name(*index)?))?;
+            }
+            Instanceof(_) => {
+                self.pop(frame, &Object("java/lang/Object"))?;
+                self.push(frame, Integer)?;
+            }
+            Monitorenter | Monitorexit => {
+                self.pop_reference(frame)?;
+            }
+
+            Wide(wide) => {
+                let index = wide.get_index();
+                match wide {
+                    WideInstruction::Iload(_) => self.load(frame, index, &Integer)?,
+                    WideInstruction::Lload(_) => self.load(frame, index, &Long)?,
+                    WideInstruction::Fload(_) => self.load(frame, index, &Float)?,
+                    WideInstruction::Dload(_) => self.load(frame, index, &Double)?,
+                    WideInstruction::Aload(_) => self.load_reference(frame, index)?,
+                    WideInstruction::Istore(_) => self.store_value(frame, index, Integer)?,
+                    WideInstruction::Lstore(_) => self.store_value(frame, index, Long)?,
+                    WideInstruction::Fstore(_) => self.store_value(frame, index, Float)?,
+                    WideInstruction::Dstore(_) => self.store_value(frame, index, Double)?,
+                    WideInstruction::Astore(_) => self.store_reference(frame, index)?,
+                    WideInstruction::Iinc { .. } => {
+                        self.get_local(frame, index, &Integer)?;
+                    }
+                    WideInstruction::Ret(_) => {
+                        self.get_return_address(frame, index)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Where execution can continue after an instruction, besides its exception handlers
+    ///
+    /// `jsr` continues at the subroutine it calls, while where `ret` continues depends on the
+    /// types and so is left to the verifier
+    fn get_successors(&self, instruction: &Instruction) -> Result<Successors, ClassLoadingError> {
+        let (offsets, falls_through) = match instruction {
+            Ifeq(offset) | Ifne(offset) | Iflt(offset) | Ifge(offset) | Ifgt(offset)
+            | Ifle(offset) | IfIcmpeq(offset) | IfIcmpne(offset) | IfIcmplt(offset)
+            | IfIcmpge(offset) | IfIcmpgt(offset) | IfIcmple(offset) | IfAcmpeq(offset)
+            | IfAcmpne(offset) | Ifnull(offset) | Ifnonnull(offset) => {
+                (vec![i32::from(*offset)], true)
+            }
+            Goto(offset) | Jsr(offset) => (vec![i32::from(*offset)], false),
+            GotoW(offset) | JsrW(offset) => (vec![*offset], false),
+            Tableswitch { default, offsets, .. } => {
+                let mut targets = vec![*default];
+                targets.extend(offsets.iter().cloned());
+                (targets, false)
+            }
+            Lookupswitch { default, pairs } => {
+                let mut targets = vec![*default];
+                targets.extend(pairs.iter().map(|(_, offset)| *offset));
+                (targets, false)
+            }
+            Ireturn | Lreturn | Freturn | Dreturn | Areturn | Return | Athrow | Ret(_)
+            | Wide(WideInstruction::Ret(_)) => (Vec::new(), false),
+            _ => (Vec::new(), true),
+        };
+        let targets = offsets.into_iter()
+            .map(|offset| self.get_target(offset))
+            .collect::<Result<_, _>>()?;
+        Ok(Successors { targets, falls_through })
+    }
+
+    /// `ldc`, `ldc_w` or `ldc2_w`, which only loads longs and doubles
+    fn load_constant(
+        &mut self,
+        frame: &mut Frame<'a>,
+        index: u16,
+        is_wide: bool,
+    ) -> Result<(), ClassLoadingError> {
+        let constant_pool = self.class.get_constant_pool();
+        let constant = match constant_pool.get_entry(index)? {
+            CONSTANT_Integer_info { .. } => Integer,
+            CONSTANT_Float_info { .. } => Float,
+            CONSTANT_Long_info { .. } => Long,
+            CONSTANT_Double_info { .. } => Double,
+            CONSTANT_String_info { .. } => Object("java/lang/String"),
+            CONSTANT_Class_info { .. } => Object("java/lang/Class"),
+            CONSTANT_MethodType_info { .. } => Object("java/lang/invoke/MethodType"),
+            CONSTANT_MethodHandle_info { .. } => Object("java/lang/invoke/MethodHandle"),
+            CONSTANT_Dynamic_info { .. } => {
+                let descriptor = constant_pool.dynamic(index)?.get_descriptor();
+                VerificationType::from_descriptor(&field::parse_field_descriptor(descriptor)?)
+            }
+            _ => {
+                return Err(self.error(format!(
+                    "Constant pool index {} isn't a loadable constant",
+                    index
+                )))
+            }
+        };
+        if is_wide != (constant.get_size() == 2) {
+            return Err(self.error(format!(
+                "{} can't load a constant of type {}",
+                if is_wide { "ldc2_w" } else { "ldc" },
+                constant
+            )));
+        }
+        self.push(frame, constant)
+    }
+
+    /// Calls a method, or for `invokespecial` an instance initializer
+    fn invoke(
+        &mut self,
+        frame: &mut Frame<'a>,
+        instruction: &Instruction,
+        method: MemberRef<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        let is_initializer = method.get_name() == "<init>";
+        if method.get_name().starts_with('<')
+            && !(is_initializer && matches!(instruction, Invokespecial(_)))
+        {
+            return Err(self.error(format!(
+                "{} can't call {}",
+                instruction.get_mnemonic(),
+                method.get_name()
+            )));
+        }
+        let descriptor = method::parse_method_descriptor(method.get_descriptor())?;
+        if let Invokeinterface { count, .. } = instruction {
+            let size: u16 = descriptor.get_parameters().iter()
+                .map(|parameter| VerificationType::from_descriptor(parameter).get_size())
+                .sum();
+            if u16::from(*count) != size + 1 {
+                return Err(self.error(format!(
+                    "invokeinterface count {} doesn't match its {} argument words",
+                    count,
+                    size + 1
+                )));
+            }
+        }
+        self.pop_arguments(frame, &descriptor)?;
+        let class = Object(method.get_class_name());
+        match instruction {
+            Invokestatic(_) => {}
+            Invokespecial(_) if is_initializer => self.initialize(frame, method.get_class_name())?,
+            Invokespecial(_) => {
+                // The method must be one the current class inherits
+                let current_class = Object(self.class.get_name());
+                if !self.is_assignable(&current_class, &class)? {
+                    return Err(self.error(format!(
+                        "invokespecial of {}.{}, which {} doesn't inherit",
+                        method.get_class_name(),
+                        method.get_name(),
+                        self.class.get_name()
+                    )));
+                }
+                self.pop(frame, &current_class)?;
+            }
+            _ => {
+                self.pop(frame, &class)?;
+            }
+        }
+        self.push_return(frame, &descriptor)
+    }
+
+    /// Calls an instance initializer on an uninitialized object, which initializes every copy
+    /// of it in the frame
+    fn initialize(
+        &mut self,
+        frame: &mut Frame<'a>,
+        class_name: &'a str,
+    ) -> Result<(), ClassLoadingError> {
+        let object = self.pop_any(frame)?;
+        match object {
+            // An instance initializer must call another of its class or of its superclass
+            UninitializedThis => {
+                if class_name != self.class.get_name()
+                    && Some(class_name) != self.class.get_super_class_name()
+                {
+                    return Err(self.error(format!(
+                        "Instance initializer calls the initializer of {}, which isn't its \
+                         class or its superclass",
+                        class_name
+                    )));
+                }
+                frame.initialize(&object, Object(self.class.get_name()));
+            }
+            Uninitialized(pc) => {
+                if self.news.get(&u32::from(pc)) != Some(&class_name) {
+                    return Err(self.error(format!(
+                        "Initializer of {} called on {}, which is another class",
+                        class_name, object
+                    )));
+                }
+                frame.initialize(&object, Object(class_name));
+            }
+            _ => return Err(self.bad_type(&object, "an uninitialized object")),
+        }
+        Ok(())
+    }
+
+    /// Whether the current class declares a field
+    fn declares_field(&self, field: &MemberRef<'a>) -> Result<bool, ClassLoadingError> {
+        if field.get_class_name() != self.class.get_name() {
+            return Ok(false);
+        }
+        let constant_pool = self.class.get_constant_pool();
+        for declared in self.class.get_fields() {
+            let descriptor = constant_pool.get_string_entry(declared.get_descriptor_index())?;
+            if declared.get_name() == field.get_name() && descriptor == field.get_descriptor() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn pop_arguments(
+        &mut self,
+        frame: &mut Frame<'a>,
+        descriptor: &MethodDescriptor<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        for parameter in descriptor.get_parameters().iter().rev() {
+            self.pop(frame, &VerificationType::from_descriptor(parameter))?;
+        }
+        Ok(())
+    }
+
+    fn push_return(
+        &self,
+        frame: &mut Frame<'a>,
+        descriptor: &MethodDescriptor<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        match descriptor.get_return_type() {
+            ReturnDescriptor::Value(value) => {
+                self.push(frame, VerificationType::from_descriptor(value))
+            }
+            ReturnDescriptor::Void => Ok(()),
+        }
+    }
+
+    /// The pc an offset from the current pc branches to, which must start an instruction
+    fn get_target(&self, offset: i32) -> Result<u32, ClassLoadingError> {
+        let target = i64::from(self.pc) + i64::from(offset);
+        if target < 0 || target > i64::from(u32::MAX) || !self.is_instruction(target as u32) {
+            return Err(self.error(format!(
+                "Branch target {} isn't the start of an instruction",
+                target
+            )));
+        }
+        Ok(target as u32)
+    }
+
+    fn is_instruction(&self, pc: u32) -> bool {
+        self.is_instruction.get(pc as usize) == Some(&true)
+    }
+
+    /// Pops operands, listed from the bottom of the stack up, and pushes the result
+    fn operate(
+        &mut self,
+        frame: &mut Frame<'a>,
+        operands: &[VerificationType<'a>],
+        result: VerificationType<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        for operand in operands.iter().rev() {
+            self.pop(frame, operand)?;
+        }
+        self.push(frame, result)
+    }
+
+    fn load(
+        &mut self,
+        frame: &mut Frame<'a>,
+        index: u16,
+        expected: &VerificationType<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        let local = self.get_local(frame, index, expected)?;
+        self.push(frame, local)
+    }
+
+    /// `aload`, which may load an uninitialized object
+    fn load_reference(&self, frame: &mut Frame<'a>, index: u16) -> Result<(), ClassLoadingError> {
+        self.check_index(index, 1)?;
+        let local = frame.locals[usize::from(index)].clone();
+        if !is_reference(&local) {
+            return Err(self.error(format!(
+                "Local {} is {} where a reference is expected",
+                index, local
+            )));
+        }
+        self.push(frame, local)
+    }
+
+    /// The type of a local, which must be assignable to the type expected
+    fn get_local(
+        &mut self,
+        frame: &Frame<'a>,
+        index: u16,
+        expected: &VerificationType<'a>,
+    ) -> Result<VerificationType<'a>, ClassLoadingError> {
+        self.check_index(index, expected.get_size())?;
+        let local = frame.locals[usize::from(index)].clone();
+        if !self.is_assignable(&local, expected)? {
+            return Err(self.error(format!(
+                "Local {} is {} where {} is expected",
+                index, local, expected
+            )));
+        }
+        Ok(local)
+    }
+
+    fn store_value(
+        &mut self,
+        frame: &mut Frame<'a>,
+        index: u16,
+        value: VerificationType<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        self.pop(frame, &value)?;
+        self.store(frame, index, value)
+    }
+
+    /// `astore`, which may store an uninitialized object, or the return address a subroutine
+    /// is called with
+    fn store_reference(&self, frame: &mut Frame<'a>, index: u16) -> Result<(), ClassLoadingError> {
+        let value = self.pop_any(frame)?;
+        if !is_reference(&value) && !matches!(value, ReturnAddress(_)) {
+            return Err(self.bad_type(&value, "a reference or returnAddress"));
+        }
+        self.store(frame, index, value)
+    }
+
+    /// Sets a local, and the second slot of a long or double, overwriting any long or double
+    /// that was using either slot
+    fn store(
+        &self,
+        frame: &mut Frame<'a>,
+        index: u16,
+        value: VerificationType<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        self.check_index(index, value.get_size())?;
+        let index = usize::from(index);
+        if index > 0 && frame.locals[index - 1].get_size() == 2 {
+            frame.locals[index - 1] = Top;
+        }
+        if value.get_size() == 2 {
+            frame.locals[index + 1] = Top;
+        }
+        frame.locals[index] = value;
+        Ok(())
+    }
+
+    fn check_index(&self, index: u16, size: u16) -> Result<(), ClassLoadingError> {
+        if u32::from(index) + u32::from(size) > u32::from(self.max_locals) {
+            return Err(self.error(format!(
+                "Local {} is out of range, max_locals is {}",
+                index, self.max_locals
+            )));
+        }
+        Ok(())
+    }
+
+    /// `<x>aload`, with the array types it accepts
+    fn load_element(
+        &mut self,
+        frame: &mut Frame<'a>,
+        arrays: &[&str],
+        element: VerificationType<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        self.pop(frame, &Integer)?;
+        self.pop_array(frame, arrays)?;
+        self.push(frame, element)
+    }
+
+    /// `<x>astore`, with the array types it accepts
+    fn store_element(
+        &mut self,
+        frame: &mut Frame<'a>,
+        arrays: &[&str],
+        element: &VerificationType<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        self.pop(frame, element)?;
+        self.pop(frame, &Integer)?;
+        self.pop_array(frame, arrays)
+    }
+
+    fn pop_array(&self, frame: &mut Frame<'a>, arrays: &[&str]) -> Result<(), ClassLoadingError> {
+        match self.pop_any(frame)? {
+            Null => Ok(()),
+            Object(name) if arrays.contains(&name) => Ok(()),
+            entry => Err(self.bad_type(&entry, &arrays.join(" or "))),
+        }
+    }
+
+    fn push(
+        &self,
+        frame: &mut Frame<'a>,
+        entry: VerificationType<'a>,
+    ) -> Result<(), ClassLoadingError> {
+        frame.stack.push(entry);
+        if frame.stack_size() > usize::from(self.max_stack) {
+            return Err(self.error(format!(
+                "Operand stack overflow, max_stack is {}",
+                self.max_stack
+            )));
+        }
+        Ok(())
+    }
+
+    /// Pushes entries, from the bottom of the stack up
+    fn push_all(
+        &self,
+        frame: &mut Frame<'a>,
+        entries: Vec<VerificationType<'a>>,
+    ) -> Result<(), ClassLoadingError> {
+        for entry in entries {
+            self.push(frame, entry)?;
+        }
+        Ok(())
+    }
+
+    fn pop_any(&self, frame: &mut Frame<'a>) -> Result<VerificationType<'a>, ClassLoadingError> {
+        frame.stack.pop().ok_or_else(|| self.error(String::from("Operand stack underflow")))
+    }
+
+    /// Pops an entry that must be assignable to the type expected
+    fn pop(
+        &mut self,
+        frame: &mut Frame<'a>,
+        expected: &VerificationType<'a>,
+    ) -> Result<VerificationType<'a>, ClassLoadingError> {
+        let entry = self.pop_any(frame)?;
+        if !self.is_assignable(&entry, expected)? {
+            return Err(self.bad_type(&entry, expected));
+        }
+        Ok(entry)
+    }
+
+    /// Pops any entry but `top`, for the instructions that move values around the stack
+    fn pop_value(&self, frame: &mut Frame<'a>) -> Result<VerificationType<'a>, ClassLoadingError> {
+        let entry = self.pop_any(frame)?;
+        if entry == Top {
+            return Err(self.bad_type(&entry, "a value"));
+        }
+        Ok(entry)
+    }
+
+    /// Pops a value that takes up one word
+    fn pop_category1(
+        &self,
+        frame: &mut Frame<'a>,
+    ) -> Result<VerificationType<'a>, ClassLoadingError> {
+        let entry = self.pop_value(frame)?;
+        if entry.get_size() != 1 {
+            return Err(self.bad_type(&entry, "a category 1 value"));
+        }
+        Ok(entry)
+    }
+
+    /// Pops a reference, which may be an uninitialized object
+    fn pop_reference(
+        &self,
+        frame: &mut Frame<'a>,
+    ) -> Result<VerificationType<'a>, ClassLoadingError> {
+        let entry = self.pop_any(frame)?;
+        if !is_reference(&entry) {
+            return Err(self.bad_type(&entry, "a reference"));
+        }
+        Ok(entry)
+    }
+
+    /// Whether a value of one type can be used where another is expected
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.10.1.2>
+    fn is_assignable(
+        &mut self,
+        from: &VerificationType<'a>,
+        to: &VerificationType<'a>,
+    ) -> Result<bool, ClassLoadingError> {
+        Ok(match (from, to) {
+            _ if from == to => true,
+            (_, Top) => true,
+            (Null, Object(_)) => true,
+            (Object(from), Object(to)) => self.is_class_assignable(from, to)?,
+            _ => false,
+        })
+    }
+
+    /// The array type with elements of a class or array type
+    fn array_of(&self, component: &'a str) -> Result<&'a str, ClassLoadingError> {
+        if component.len() - component.trim_start_matches('[').len() >= 255 {
+            return Err(self.error(format!(
+                "An array of {} would have more than 255 dimensions",
+                component
+            )));
+        }
+        let array = if component.starts_with('[') {
+            format!("[{}", component)
+        } else {
+            format!("[L{};", component)
+        };
+        Ok(self.strings.alloc(array))
+    }
+
+    /// A class followed by its superclasses, up to `java/lang/Object`
+    fn get_superclasses(&mut self, class_name: &'a str) -> Result<Vec<&'a str>, ClassLoadingError> {
+        let mut superclasses = vec![class_name];
+        let mut class_name = class_name;
+        while let Some(super_class) = self.get_super_class(class_name)? {
+            if superclasses.contains(&super_class) {
+                return Err(ClassCircularityError);
+            }
+            superclasses.push(super_class);
+            class_name = super_class;
+        }
+        Ok(superclasses)
+    }
+
+    /// Whether a class or array type is assignable to another
+    ///
+    /// Any class type is assignable to an interface, as the verifier leaves checking that a
+    /// class implements it until the interface's methods are called
+    fn is_class_assignable(
+        &mut self,
+        from: &'a str,
+        to: &'a str,
+    ) -> Result<bool, ClassLoadingError> {
+        if from == to || to == "java/lang/Object" {
+            return Ok(true);
+        }
+        if to.starts_with('[') {
+            if !from.starts_with('[') {
+                return Ok(false);
+            }
+            // Arrays of different primitive types aren't assignable to each other
+            return match (component(from), component(to)) {
+                (Some(from), Some(to)) => self.is_class_assignable(from, to),
+                _ => Ok(false),
+            };
+        }
+        if from.starts_with('[') {
+            return Ok(to == "java/lang/Cloneable" || to == "java/io/Serializable");
+        }
+        if self.is_interface(to)? {
+            return Ok(true);
+        }
+        Ok(self.get_superclasses(from)?.contains(&to))
+    }
+
+    /// The superclass of a class, which may be the class being verified
+    fn get_super_class(
+        &mut self,
+        class_name: &'a str,
+    ) -> Result<Option<&'a str>, ClassLoadingError> {
+        if class_name == self.class.get_name() {
+            Ok(self.class.get_super_class_name())
+        } else {
+            self.hierarchy.get_super_class(class_name)
+        }
+    }
+
+    /// Whether a class, which may be the class being verified, is an interface
+    fn is_interface(&mut self, class_name: &'a str) -> Result<bool, ClassLoadingError> {
+        if class_name == self.class.get_name() {
+            Ok(self.class.get_access_flags().contains(ClassAccessFlag::ACC_INTERFACE))
+        } else {
+            self.hierarchy.is_interface(class_name)
+        }
+    }
+
+    fn bad_type<T: ToString + ?Sized>(
+        &self,
+        entry: &VerificationType<'a>,
+        expected: &T,
+    ) -> ClassLoadingError {
+        self.error(format!(
+            "Bad type on operand stack: {} where {} is expected",
+            entry,
+            expected.to_string()
+        ))
+    }
+
+    fn error(&self, message: String) -> ClassLoadingError {
+        VerifyError {
+            method: self.location.clone(),
+            pc: self.pc,
+            message,
+        }
+    }
+}
+
+/// The local the `<x>load_<n>` and `<x>store_<n>` instructions use, which their opcode gives
+fn implicit_index(instruction: &Instruction) -> u16 {
+    let opcode = instruction.get_opcode();
+    // iload_0 is 0x1a and istore_0 is 0x3b, each followed by the other types in groups of four
+    if opcode < 0x3b {
+        u16::from((opcode - 0x1a) % 4)
+    } else {
+        u16::from((opcode - 0x3b) % 4)
+    }
+}
+
+/// The first local an instruction loads, stores or increments, and the number of slots it uses
+///
+/// No slots for an instruction that doesn't use the locals
+fn get_local_access(instruction: &Instruction) -> (u16, u16) {
+    match instruction {
+        Iload(index) | Fload(index) | Aload(index) | Istore(index) | Fstore(index)
+        | Astore(index) | Ret(index) | Iinc { index, .. } => (u16::from(*index), 1),
+        Lload(index) | Dload(index) | Lstore(index) | Dstore(index) => (u16::from(*index), 2),
+        Iload0 | Iload1 | Iload2 | Iload3 | Fload0 | Fload1 | Fload2 | Fload3 | Aload0
+        | Aload1 | Aload2 | Aload3 | Istore0 | Istore1 | Istore2 | Istore3 | Fstore0 | Fstore1
+        | Fstore2 | Fstore3 | Astore0 | Astore1 | Astore2 | Astore3 => {
+            (implicit_index(instruction), 1)
+        }
+        Lload0 | Lload1 | Lload2 | Lload3 | Dload0 | Dload1 | Dload2 | Dload3 | Lstore0
+        | Lstore1 | Lstore2 | Lstore3 | Dstore0 | Dstore1 | Dstore2 | Dstore3 => {
+            (implicit_index(instruction), 2)
+        }
+        Wide(wide) => match wide {
+            WideInstruction::Lload(_)
+            | WideInstruction::Dload(_)
+            | WideInstruction::Lstore(_)
+            | WideInstruction::Dstore(_) => (wide.get_index(), 2),
+            _ => (wide.get_index(), 1),
+        },
+        _ => (0, 0),
+    }
+}
+
+/// The class or array type of the elements of an array type, `None` if they are primitives
+fn component(array: &str) -> Option<&str> {
+    let component = &array[1..];
+    if component.starts_with('[') {
+        Some(component)
+    } else if component.starts_with('L') && component.ends_with(';') {
+        Some(&component[1..component.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Whether a type is a reference, including `null` and uninitialized objects
+fn is_reference(entry: &VerificationType) -> bool {
+    matches!(entry, Null | Object(_) | Uninitialized(_) | UninitializedThis)
+}
+
+fn field_type<'a>(field: &MemberRef<'a>) -> Result<VerificationType<'a>, ClassLoadingError> {
+    let descriptor = field::parse_field_descriptor(field.get_descriptor())?;
+    Ok(VerificationType::from_descriptor(&descriptor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_class, ClassHierarchy};
+    use assembler::{assemble, assemble_class};
+    use class_file::ClassFile;
+    use class_file::ClassLoadingError;
+    use class_file::ClassLoadingError::{ClassFormatError, VerifyError};
+    use test_util::{class_header, method_source};
+    use typed_arena::Arena;
+
+    /// A hierarchy of classes that all extend `java/lang/Object`
+    struct Flat;
+
+    impl<'a> ClassHierarchy<'a> for Flat {
+        fn get_super_class(
+            &mut self,
+            class_name: &'a str,
+        ) -> Result<Option<&'a str>, ClassLoadingError> {
+            Ok(match class_name {
+                "java/lang/Object" => None,
+                _ => Some("java/lang/Object"),
+            })
+        }
+
+        fn is_interface(&mut self, _class_name: &'a str) -> Result<bool, ClassLoadingError> {
+            Ok(false)
+        }
+    }
+
+    /// A class with a static method `m()V` of the given code and attributes
+    fn code_source(major_version: u16, code: &str) -> String {
+        let method = "  public static void m();
+    descriptor: ()V
+    flags: (0x0009) ACC_PUBLIC, ACC_STATIC
+    Code:
+      stack=2, locals=3, args_size=0
+";
+        method_source(major_version, &(method.to_owned() + code))
+    }
+
+    fn verify(source: &str) -> Result<(), ClassLoadingError> {
+        let strings = Arena::new();
+        let class = assemble_class(source, &strings).unwrap();
+        verify_class(&class, &mut Flat, &strings)
+    }
+
+    fn assert_verify_error(source: &str, expected_pc: u32) {
+        match verify(source) {
+            Err(VerifyError { pc, .. }) => assert_eq!(pc, expected_pc),
+            result => panic!("Expected a VerifyError, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn branches_to_their_frames_are_verified() {
+        let source = code_source(
+            61,
+            "        0: iconst_0
+        1: ifeq 0
+        4: return
+      StackMapTable: number_of_entries = 1
+        frame_type = 0 /* same */",
+        );
+        verify(&source).unwrap();
+    }
+
+    #[test]
+    fn stack_underflows_are_rejected() {
+        assert_verify_error(&code_source(61, "        0: pop\n        1: return"), 0);
+    }
+
+    #[test]
+    fn mismatched_operands_are_rejected() {
+        let source = code_source(
+            61,
+            "        0: aconst_null
+        1: iconst_1
+        2: iadd
+        3: return",
+        );
+        assert_verify_error(&source, 2);
+    }
+
+    #[test]
+    fn branches_without_frames_are_rejected() {
+        let source = code_source(
+            61,
+            "        0: iconst_0
+        1: ifeq 0
+        4: return",
+        );
+        assert_verify_error(&source, 1);
+    }
+
+    #[test]
+    fn methods_without_code_are_rejected_unless_abstract_or_native() {
+        let source = |flags| {
+            class_header("public abstract class p.A", 61)
+                + &format!("{{\n  public {} void m();\n    descriptor: ()V\n}}\n", flags)
+        };
+        verify(&source("abstract")).unwrap();
+        verify(&source("native")).unwrap();
+        assert!(matches!(verify(&source("static")), Err(ClassFormatError(_))));
+    }
+
+    #[test]
+    fn malformed_lazy_code_is_rejected() {
+        let mut bytes = assemble(&code_source(61, "        0: return")).unwrap();
+        let strings = Arena::new();
+        let offset = {
+            let class = ClassFile::from_bytes(&bytes, &strings).unwrap();
+            verify_class(&class, &mut Flat, &strings).unwrap();
+            class.get_methods()[0].get_attributes()[0].get_offset()
+        };
+        // Runs code_length past the end of the attribute
+        bytes[offset + 13] = 0xff;
+        let class = ClassFile::from_bytes_lazy(&bytes, &strings).unwrap();
+        assert!(matches!(
+            verify_class(&class, &mut Flat, &strings),
+            Err(ClassFormatError(_))
+        ));
+    }
+
+    /// Calls a subroutine that stores its return address in local 2 and then runs the given
+    /// code, before loading the int in local 1
+    fn subroutine_source(major_version: u16, subroutine: &str) -> String {
+        code_source(
+            major_version,
+            &format!(
+                "        0: iconst_1
+        1: istore_1
+        2: jsr 8
+        5: iload_1
+        6: pop
+        7: return
+        8: astore_2
+{}",
+                subroutine
+            ),
+        )
+    }
+
+    #[test]
+    fn subroutines_are_inferred_before_version_51() {
+        verify(&subroutine_source(49, "        9: ret 2")).unwrap();
+        // Version 50 falls back to type inference when type checking fails
+        verify(&subroutine_source(50, "        9: ret 2")).unwrap();
+        assert_verify_error(&subroutine_source(51, "        9: ret 2"), 2);
+    }
+
+    #[test]
+    fn returns_to_non_addresses_are_rejected() {
+        assert_verify_error(&subroutine_source(49, "        9: ret 1"), 9);
+    }
+
+    #[test]
+    fn locals_changed_by_subroutines_are_seen_by_their_callers() {
+        let source = subroutine_source(
+            49,
+            "        9: aconst_null
+       10: astore_1
+       11: ret 2",
+        );
+        assert_verify_error(&source, 5);
+    }
+
+    #[test]
+    fn recursive_subroutines_are_rejected() {
+        let source = code_source(
+            49,
+            "        0: jsr 4
+        3: return
+        4: astore_2
+        5: jsr 4
+        8: ret 2",
+        );
+        assert!(matches!(verify(&source), Err(VerifyError { .. })));
+    }
+
+    #[test]
+    fn stack_underflows_are_rejected_by_type_inference() {
+        assert_verify_error(&code_source(49, "        0: pop\n        1: return"), 0);
+    }
+}